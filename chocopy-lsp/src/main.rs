@@ -0,0 +1,24 @@
+// A language server for ChocoPy, speaking the Language Server Protocol over
+// stdin and stdout
+
+mod protocol;
+mod server;
+
+use server::Server;
+use std::io;
+
+fn main() -> io::Result<()> {
+    let mut input = io::stdin().lock();
+    let mut output = io::stdout().lock();
+    let mut server = Server::new();
+    while let Some(message) = protocol::read_message(&mut input)? {
+        for reply in server.handle(&message) {
+            protocol::write_message(&mut output, &reply)?;
+        }
+        if let Some(code) = server.exit_code() {
+            std::process::exit(code);
+        }
+    }
+    // The client went away without asking the server to exit
+    std::process::exit(1)
+}
@@ -0,0 +1,138 @@
+// JSON-RPC messages framed by `Content-Length` headers, and conversions between
+// the positions of the compiler and of the protocol. The compiler counts rows
+// and columns from 1 and includes the end of a location, while the protocol
+// counts lines and characters from 0 and excludes the end of a range. Columns
+// count chars, and characters count UTF-16 code units, so the conversions
+// need the text of the document.
+
+use chocopy::common::location::{Location, Position};
+use serde_json::{Value, json};
+use std::io::{self, BufRead, Write};
+
+// Read the next message, or `None` at the end of the input
+pub fn read_message(reader: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("Content-Length")
+        {
+            length = value.trim().parse::<usize>().ok();
+        }
+    }
+    let length = length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut content = vec![0; length];
+    reader.read_exact(&mut content)?;
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_message(writer: &mut impl Write, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", content.len(), content)?;
+    writer.flush()
+}
+
+// The line of the text at a row of the compiler, empty past the end
+fn line(text: &str, row: u32) -> &str {
+    text.lines().nth(row.saturating_sub(1) as usize).unwrap_or_default()
+}
+
+// The character of the protocol after the first `col` chars of the line
+fn character(line: &str, col: u32) -> usize {
+    line.chars().take(col as usize).map(char::len_utf16).sum()
+}
+
+pub fn range(text: &str, location: Location) -> Value {
+    let (start, end) = (location.start, location.end);
+    json!({
+        "start": {
+            "line": start.row.saturating_sub(1),
+            "character": character(line(text, start.row), start.col.saturating_sub(1)),
+        },
+        "end": {
+            "line": end.row.saturating_sub(1),
+            "character": character(line(text, end.row), end.col),
+        },
+    })
+}
+
+// The position of the character after the cursor
+pub fn position(text: &str, params: &Value) -> Option<Position> {
+    let position = &params["position"];
+    let row = position["line"].as_u64()? as u32 + 1;
+    let character = position["character"].as_u64()? as usize;
+    let mut units = 0;
+    let col = line(text, row)
+        .chars()
+        .take_while(|c| {
+            units += c.len_utf16();
+            units <= character
+        })
+        .count();
+    Some(Position {
+        row,
+        col: col as u32 + 1,
+    })
+}
+
+pub fn contains(location: Location, position: Position) -> bool {
+    location.start <= position && position <= location.end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framing() {
+        let mut output = vec![];
+        write_message(&mut output, &json!({"id": 1})).unwrap();
+        assert_eq!(output, b"Content-Length: 8\r\n\r\n{\"id\":1}");
+        let mut input = &output[..];
+        assert_eq!(read_message(&mut input).unwrap(), Some(json!({"id": 1})));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn positions() {
+        let text = "\nx = abc\n";
+        let location = Location::new(2, 5, 2, 7);
+        assert_eq!(
+            range(text, location),
+            json!({
+                "start": {"line": 1, "character": 4},
+                "end": {"line": 1, "character": 7},
+            })
+        );
+        let cursor = |character| {
+            let params = json!({"position": {"line": 1, "character": character}});
+            position(text, &params).unwrap()
+        };
+        assert!(!contains(location, cursor(3)));
+        assert!(contains(location, cursor(4)));
+        assert!(contains(location, cursor(6)));
+        assert!(!contains(location, cursor(7)));
+
+        // The emoji is one char, and two UTF-16 code units
+        let text = "s = \"\u{1f600}\" + t";
+        assert_eq!(
+            range(text, Location::new(1, 11, 1, 11)),
+            json!({
+                "start": {"line": 0, "character": 11},
+                "end": {"line": 0, "character": 12},
+            })
+        );
+        let params = json!({"position": {"line": 0, "character": 11}});
+        assert_eq!(position(text, &params), Some(Position { row: 1, col: 11 }));
+    }
+}
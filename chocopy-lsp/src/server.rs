@@ -0,0 +1,399 @@
+// The state of the language server: the open documents with their latest
+// analysis, and the handlers of the requests and notifications it supports.
+
+use crate::protocol::{contains, position, range};
+use chocopy::common::diagnostic;
+use chocopy::common::location::{Location, Position};
+use chocopy::common::node::{CompilerError, FuncType, Severity, ValueType};
+use chocopy::core::frontend;
+use chocopy::core::typecheck::{self, Analysis, SymbolKind, SymbolType, WarningConfig};
+use serde_json::{Value, json};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_REQUEST: i64 = -32600;
+
+struct Document {
+    text: String,
+    // The last analysis of the document without syntax errors, so that
+    // navigation and completion keep working while an edit is incomplete
+    analysis: Option<Analysis>,
+    types: Vec<(Location, ValueType)>,
+}
+
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, Document>,
+    shutdown: bool,
+    exit: bool,
+}
+
+fn response(id: &Value, result: Value) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "result": result})
+}
+
+fn error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({"jsonrpc": "2.0", "method": method, "params": params})
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            // Full document sync
+            "textDocumentSync": 1,
+            "hoverProvider": true,
+            "definitionProvider": true,
+            "referencesProvider": true,
+            "completionProvider": {"triggerCharacters": ["."]},
+            "documentSymbolProvider": true,
+        },
+        "serverInfo": {"name": "chocopy-lsp", "version": env!("CARGO_PKG_VERSION")},
+    })
+}
+
+fn func_string(f: &FuncType, skip_self: bool) -> String {
+    let parameters: Vec<String> = f
+        .parameters
+        .iter()
+        .skip(skip_self as usize)
+        .map(ValueType::to_string)
+        .collect();
+    format!("({}) -> {}", parameters.join(", "), f.return_type)
+}
+
+fn type_string(t: &SymbolType, kind: SymbolKind) -> String {
+    match t {
+        SymbolType::Value(t) => t.to_string(),
+        SymbolType::Func(f) => func_string(f, kind == SymbolKind::Method),
+    }
+}
+
+fn diagnostic(uri: &str, text: &str, error: &CompilerError, notes: &[CompilerError]) -> Value {
+    let severity = match error.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+        Severity::Note => 3,
+    };
+    let related: Vec<Value> = notes
+        .iter()
+        .map(|note| {
+            json!({
+                "location": {"uri": uri, "range": range(text, note.base.location)},
+                "message": note.message,
+            })
+        })
+        .collect();
+    let mut diagnostic = json!({
+        "range": range(text, error.base.location),
+        "severity": severity,
+        "source": "chocopy",
+        "message": error.message,
+    });
+    if let Some(code) = &error.code {
+        diagnostic["code"] = json!(code);
+    }
+    if !related.is_empty() {
+        diagnostic["relatedInformation"] = json!(related);
+    }
+    diagnostic
+}
+
+// Parse and check the text. Syntax errors are reported alone, and leave no
+// analysis.
+fn check(text: &str) -> (Vec<CompilerError>, Option<Analysis>) {
+    let ast = frontend::process_source(text);
+    if !ast.errors.errors.is_empty() {
+        return (ast.errors.errors, None);
+    }
+    let analysis = typecheck::analyze(ast, false);
+    let mut diagnostics = analysis.ast.errors.clone();
+    if diagnostics.errors.is_empty() {
+        diagnostics.errors = typecheck::warnings(&analysis.ast, &WarningConfig::new());
+        diagnostics.sort();
+    }
+    (diagnostics.errors, Some(analysis))
+}
+
+impl Document {
+    // The symbol declared or referred to at the position, with the location of
+    // the name there
+    fn symbol_at(&self, position: Position) -> Option<(usize, Location)> {
+        let symbols = &self.analysis.as_ref()?.symbols;
+        let declaration = symbols
+            .declarations
+            .iter()
+            .find(|symbol| contains(symbol.location, position))
+            .map(|symbol| (symbol.id, symbol.location));
+        declaration.or_else(|| {
+            symbols
+                .references
+                .iter()
+                .find(|reference| contains(reference.location, position))
+                .and_then(|reference| Some((reference.declaration?, reference.location)))
+        })
+    }
+
+    // The innermost expression at the position
+    fn expression_at(&self, position: Position) -> Option<&(Location, ValueType)> {
+        self.types
+            .iter()
+            .filter(|(location, _)| contains(*location, position))
+            .min_by_key(|(location, _)| (Reverse(location.start), location.end))
+    }
+
+    // A variable of another scope declared by `global` or `nonlocal` stands
+    // for that variable
+    fn original(&self, mut id: usize) -> usize {
+        let symbols = &self.analysis.as_ref().unwrap().symbols;
+        while let Some(target) = symbols.declarations[id].alias_of {
+            id = target;
+        }
+        id
+    }
+}
+
+impl Server {
+    pub fn new() -> Server {
+        Server::default()
+    }
+
+    // After an `exit` notification, the code the server should exit with
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit.then_some(if self.shutdown { 0 } else { 1 })
+    }
+
+    // Handle a message from the client, and return the messages to send back
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            return self.notify(method, params).into_iter().collect();
+        };
+        if self.shutdown {
+            return vec![error_response(id, INVALID_REQUEST, "The server is shut down")];
+        }
+        let result = match method {
+            "initialize" => capabilities(),
+            "shutdown" => {
+                self.shutdown = true;
+                Value::Null
+            }
+            "textDocument/hover" => self.hover(params).unwrap_or_default(),
+            "textDocument/definition" => self.definition(params).unwrap_or_default(),
+            "textDocument/references" => self.references(params).unwrap_or_default(),
+            "textDocument/completion" => self.completion(params).unwrap_or(json!([])),
+            "textDocument/documentSymbol" => self.document_symbols(params).unwrap_or_default(),
+            _ => {
+                let message = format!("Unsupported method: {}", method);
+                return vec![error_response(id, METHOD_NOT_FOUND, &message)];
+            }
+        };
+        vec![response(id, result)]
+    }
+
+    fn notify(&mut self, method: &str, params: &Value) -> Option<Value> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match method {
+            "exit" => {
+                self.exit = true;
+                None
+            }
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str()?;
+                Some(self.update(uri, text.to_owned()))
+            }
+            "textDocument/didChange" => {
+                let text = params["contentChanges"].as_array()?.last()?["text"].as_str()?;
+                Some(self.update(uri, text.to_owned()))
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                let params = json!({"uri": uri, "diagnostics": []});
+                Some(notification("textDocument/publishDiagnostics", params))
+            }
+            _ => None,
+        }
+    }
+
+    // Check the new text of a document, and publish its diagnostics
+    fn update(&mut self, uri: &str, text: String) -> Value {
+        let (errors, analysis) = check(&text);
+        let diagnostics: Vec<Value> = diagnostic::with_notes(&errors)
+            .into_iter()
+            .map(|(error, notes)| diagnostic(uri, &text, error, notes))
+            .collect();
+
+        let previous = self.documents.remove(uri);
+        let document = match analysis {
+            Some(analysis) => Document {
+                text,
+                types: typecheck::expression_types(&analysis.ast),
+                analysis: Some(analysis),
+            },
+            None => match previous {
+                Some(previous) => Document { text, ..previous },
+                None => Document {
+                    text,
+                    analysis: None,
+                    types: vec![],
+                },
+            },
+        };
+        self.documents.insert(uri.to_owned(), document);
+        let params = json!({"uri": uri, "diagnostics": diagnostics});
+        notification("textDocument/publishDiagnostics", params)
+    }
+
+    fn document<'a>(&'a self, params: &'a Value) -> Option<(&'a str, &'a Document)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        Some((uri, self.documents.get(uri)?))
+    }
+
+    // The type of the expression under the cursor. For a name, the declared
+    // type of its symbol, unless the name is an expression of its own.
+    fn hover(&self, params: &Value) -> Option<Value> {
+        let (_, document) = self.document(params)?;
+        let position = position(&document.text, params)?;
+        let symbols = &document.analysis.as_ref()?.symbols;
+        let expression = document.expression_at(position);
+        let (location, text) = match (document.symbol_at(position), expression) {
+            (Some((id, location)), Some((e, t))) if *e == location => {
+                (location, format!("{}: {}", symbols.declarations[id].name, t))
+            }
+            (Some((id, location)), expression)
+                if expression.is_none_or(|(e, _)| e.start <= location.start) =>
+            {
+                let symbol = &symbols.declarations[id];
+                let type_ = symbol
+                    .type_
+                    .as_ref()
+                    .map_or("type".to_owned(), |t| type_string(t, symbol.kind));
+                (location, format!("{}: {}", symbol.name, type_))
+            }
+            (_, expression) => {
+                let (location, t) = expression?;
+                (*location, t.to_string())
+            }
+        };
+        Some(json!({
+            "contents": {"kind": "markdown", "value": format!("```python\n{}\n```", text)},
+            "range": range(&document.text, location),
+        }))
+    }
+
+    fn definition(&self, params: &Value) -> Option<Value> {
+        let (uri, document) = self.document(params)?;
+        let (id, _) = document.symbol_at(position(&document.text, params)?)?;
+        let symbol = &document.analysis.as_ref()?.symbols.declarations[id];
+        Some(json!({"uri": uri, "range": range(&document.text, symbol.location)}))
+    }
+
+    // The uses of the symbol under the cursor, including through `global` and
+    // `nonlocal` declarations
+    fn references(&self, params: &Value) -> Option<Value> {
+        let (uri, document) = self.document(params)?;
+        let (id, _) = document.symbol_at(position(&document.text, params)?)?;
+        let symbols = &document.analysis.as_ref()?.symbols;
+        let original = document.original(id);
+        let group = |id: usize| document.original(id) == original;
+
+        let mut locations = vec![];
+        if params["context"]["includeDeclaration"].as_bool().unwrap_or(true) {
+            let declarations = symbols.declarations.iter().filter(|symbol| group(symbol.id));
+            locations.extend(declarations.map(|symbol| symbol.location));
+        }
+        let references = symbols.references.iter();
+        locations.extend(
+            references
+                .filter(|reference| reference.declaration.is_some_and(group))
+                .map(|reference| reference.location),
+        );
+        locations.sort();
+        let locations: Vec<Value> = locations
+            .into_iter()
+            .map(|location| json!({"uri": uri, "range": range(&document.text, location)}))
+            .collect();
+        Some(json!(locations))
+    }
+
+    // The attributes and methods of the object before the `.` at the cursor
+    fn completion(&self, params: &Value) -> Option<Value> {
+        let (_, document) = self.document(params)?;
+        let cursor = position(&document.text, params)?;
+        let line = document.text.lines().nth(cursor.row as usize - 1)?;
+        let before: String = line.chars().take(cursor.col as usize - 1).collect();
+        let object = before.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_');
+        let object = object.strip_suffix('.')?;
+        let end = Position {
+            row: cursor.row,
+            col: object.chars().count() as u32,
+        };
+        let (_, t) = document
+            .types
+            .iter()
+            .filter(|(location, _)| location.end == end)
+            .max_by_key(|(location, _)| location.start)?;
+        let ValueType::ClassValueType(class) = t.non_optional() else {
+            return Some(json!([]));
+        };
+
+        let classes = &document.analysis.as_ref()?.classes;
+        let mut items = vec![];
+        for name in classes.attribute_names(&class.class_name) {
+            let t = classes.get_attribute(&class.class_name, name)?;
+            let t = classes.instantiate(t, class);
+            items.push(json!({"label": name, "kind": 5, "detail": t.to_string()}));
+        }
+        for name in classes.method_names(&class.class_name) {
+            let method = classes.get_method(&class.class_name, name)?;
+            let method = classes.instantiate_method(method, class);
+            items.push(json!({"label": name, "kind": 2, "detail": func_string(&method, true)}));
+        }
+        items.sort_by(|a, b| a["label"].as_str().cmp(&b["label"].as_str()));
+        Some(json!(items))
+    }
+
+    // The classes, functions and variables of the document, nested by scope
+    fn document_symbols(&self, params: &Value) -> Option<Value> {
+        let (_, document) = self.document(params)?;
+        let symbols = &document.analysis.as_ref()?.symbols;
+        fn children(text: &str, symbols: &[typecheck::Symbol], scope: Option<usize>) -> Vec<Value> {
+            symbols
+                .iter()
+                .filter(|symbol| symbol.scope == scope)
+                .filter_map(|symbol| {
+                    let kind = match symbol.kind {
+                        SymbolKind::Global | SymbolKind::Local => 13,
+                        SymbolKind::Class => 5,
+                        SymbolKind::TypeParameter => 26,
+                        SymbolKind::Attribute => 8,
+                        SymbolKind::Method => 6,
+                        SymbolKind::Function => 12,
+                        // Part of their function, or of another scope
+                        SymbolKind::Parameter
+                        | SymbolKind::GlobalAlias
+                        | SymbolKind::NonlocalAlias
+                        | SymbolKind::ComprehensionVariable => return None,
+                    };
+                    let mut value = json!({
+                        "name": symbol.name,
+                        "kind": kind,
+                        "range": range(text, symbol.location),
+                        "selectionRange": range(text, symbol.location),
+                        "children": children(text, symbols, Some(symbol.id)),
+                    });
+                    if let Some(t) = &symbol.type_ {
+                        value["detail"] = json!(type_string(t, symbol.kind));
+                    }
+                    Some(value)
+                })
+                .collect()
+        }
+        Some(json!(children(&document.text, &symbols.declarations, None)))
+    }
+}
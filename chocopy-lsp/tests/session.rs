@@ -0,0 +1,200 @@
+// A scripted client session with the language server binary
+
+use serde_json::{Value, json};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const URI: &str = "file:///counter.py";
+
+const SOURCE: &str = "\
+class Counter(object):
+    count: int = 0
+
+    def inc(self: \"Counter\", by: int) -> int:
+        self.count = self.count + by
+        return self.count
+
+c: Counter = None
+n: int = 0
+c = Counter()
+n = c.inc(1)
+n = c
+";
+
+struct Client {
+    server: Child,
+    input: ChildStdin,
+    output: BufReader<ChildStdout>,
+    next_id: u64,
+}
+
+impl Client {
+    fn start() -> Client {
+        let mut server = Command::new(env!("CARGO_BIN_EXE_chocopy-lsp"))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        Client {
+            input: server.stdin.take().unwrap(),
+            output: BufReader::new(server.stdout.take().unwrap()),
+            server,
+            next_id: 1,
+        }
+    }
+
+    fn send(&mut self, message: Value) {
+        let content = message.to_string();
+        write!(self.input, "Content-Length: {}\r\n\r\n{}", content.len(), content).unwrap();
+        self.input.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Value {
+        let mut length = 0;
+        loop {
+            let mut line = String::new();
+            self.output.read_line(&mut line).unwrap();
+            match line.trim_end().split_once(": ") {
+                Some(("Content-Length", value)) => length = value.parse().unwrap(),
+                _ if line.trim_end().is_empty() => break,
+                _ => (),
+            }
+        }
+        let mut content = vec![0; length];
+        self.output.read_exact(&mut content).unwrap();
+        serde_json::from_slice(&content).unwrap()
+    }
+
+    fn request(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}));
+        let response = self.receive();
+        assert_eq!(response["id"], id);
+        response["result"].clone()
+    }
+
+    fn notify(&mut self, method: &str, params: Value) {
+        self.send(json!({"jsonrpc": "2.0", "method": method, "params": params}));
+    }
+
+    // Send a new text, and return the diagnostics published for it
+    fn change(&mut self, text: &str) -> Value {
+        let params = json!({
+            "textDocument": {"uri": URI, "version": 2},
+            "contentChanges": [{"text": text}],
+        });
+        self.notify("textDocument/didChange", params);
+        let published = self.receive();
+        assert_eq!(published["method"], "textDocument/publishDiagnostics");
+        published["params"]["diagnostics"].clone()
+    }
+
+    fn at(&mut self, method: &str, line: u32, character: u32) -> Value {
+        let params = json!({
+            "textDocument": {"uri": URI},
+            "position": {"line": line, "character": character},
+            "context": {"includeDeclaration": true},
+        });
+        self.request(method, params)
+    }
+}
+
+fn range(line: u32, start: u32, end: u32) -> Value {
+    json!({
+        "start": {"line": line, "character": start},
+        "end": {"line": line, "character": end},
+    })
+}
+
+#[test]
+fn session() {
+    let mut client = Client::start();
+    let initialized = client.request("initialize", json!({"capabilities": {}}));
+    let capabilities = &initialized["capabilities"];
+    assert_eq!(capabilities["textDocumentSync"], 1);
+    assert_eq!(capabilities["completionProvider"]["triggerCharacters"], json!(["."]));
+    client.notify("initialized", json!({}));
+
+    let params = json!({
+        "textDocument": {"uri": URI, "languageId": "python", "version": 1, "text": SOURCE},
+    });
+    client.notify("textDocument/didOpen", params);
+    let published = client.receive();
+    assert_eq!(published["params"]["uri"], URI);
+    assert_eq!(
+        published["params"]["diagnostics"],
+        json!([{
+            "range": range(11, 0, 5),
+            "severity": 1,
+            "code": "E0202",
+            "source": "chocopy",
+            "message": "Expected type `int`; got type `Counter`",
+        }])
+    );
+
+    // The type of a call, and of names with their declared types
+    let hover = client.at("textDocument/hover", 10, 9);
+    assert_eq!(hover["contents"]["value"], "```python\nint\n```");
+    assert_eq!(hover["range"], range(10, 4, 12));
+    let hover = client.at("textDocument/hover", 10, 0);
+    assert_eq!(hover["contents"]["value"], "```python\nn: int\n```");
+    let hover = client.at("textDocument/hover", 10, 6);
+    assert_eq!(hover["contents"]["value"], "```python\ninc: (int) -> int\n```");
+
+    let definition = client.at("textDocument/definition", 10, 7);
+    assert_eq!(definition, json!({"uri": URI, "range": range(3, 8, 11)}));
+
+    let references = client.at("textDocument/references", 1, 4);
+    let ranges: Vec<Value> = references
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|location| location["range"].clone())
+        .collect();
+    assert_eq!(ranges, [range(1, 4, 9), range(4, 13, 18), range(4, 26, 31), range(5, 20, 25)]);
+
+    let params = json!({"textDocument": {"uri": URI}});
+    let symbols = client.request("textDocument/documentSymbol", params);
+    let names: Vec<&str> = symbols
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|symbol| symbol["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["Counter", "c", "n"]);
+    let members = &symbols[0]["children"];
+    assert_eq!(members[0]["name"], "count");
+    assert_eq!(members[1]["name"], "inc");
+    assert_eq!(members[1]["detail"], "(int) -> int");
+
+    // Completion after a `.` uses the last analysis without syntax errors
+    client.change(&SOURCE.replace("n = c\n", "c\n"));
+    let diagnostics = client.change(&SOURCE.replace("n = c\n", "c.\n"));
+    assert_eq!(diagnostics[0]["code"], "E0001");
+    let completion = client.at("textDocument/completion", 11, 2);
+    let labels: Vec<&str> = completion
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item["label"].as_str().unwrap())
+        .collect();
+    assert_eq!(labels, ["__init__", "count", "inc"]);
+    assert_eq!(completion[2]["detail"], "(int) -> int");
+
+    let diagnostics = client.change(&SOURCE.replace("n = c\n", "print(c.count)\n"));
+    assert_eq!(diagnostics, json!([]));
+
+    // Characters count UTF-16 code units, two for the emoji, and the text
+    // before the cursor may not be ASCII
+    client.change(&SOURCE.replace("n = c\n", "print(\"a\", c)\n"));
+    client.change(&SOURCE.replace("n = c\n", "print(\"\u{1f600}\", c.)\n"));
+    let completion = client.at("textDocument/completion", 11, 14);
+    assert_eq!(completion.as_array().unwrap().len(), 3);
+    client.change(&SOURCE.replace("n = c\n", "x: str = \"\u{e9}a\"\n"));
+    assert_eq!(client.at("textDocument/completion", 11, 12), json!([]));
+
+    assert_eq!(client.request("shutdown", Value::Null), Value::Null);
+    client.notify("exit", Value::Null);
+    assert!(client.server.wait().unwrap().success());
+}
@@ -61,6 +61,11 @@ unsafe fn mark_reachable_from(var_ptr: *const u64) {
                     mark_reachable_from((list_ptr.add(1) as *const u64).add(i as usize));
                 }
             }
+            Type::Set => {
+                // A set only references its hash table, which is a list.
+                let set_ptr = object_ptr as *mut SetObject;
+                mark_reachable_from(addr_of!((*set_ptr).table) as *const u64);
+            }
             _ => (), // Other types do not contain references.
         }
     }
@@ -131,11 +136,9 @@ pub unsafe fn perform_mark_and_sweep_gc(stack_frame_base: *const u64, stack_poin
                 // Compute size of object in allocation units.
                 let size_units = calculate_size((*object_ptr).prototype, || (*(object_ptr as *mut ArrayObject)).len);
 
-                // Reclaim memory by dropping the boxed slice.
-                drop(Box::from_raw(std::slice::from_raw_parts_mut(
-                    object_ptr as *mut AllocUnit,
-                    size_units,
-                )));
+                // Reclaim memory. This does not depend on the size, which may be
+                // smaller than the allocation for lists shrunk by `del`.
+                free(object_ptr as *mut AllocUnit);
 
                 reclaimed_units += size_units;
             }
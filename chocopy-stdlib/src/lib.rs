@@ -2,11 +2,13 @@ mod object;
 
 use object::*;
 use std::cell::*;
+use std::cmp::Ordering;
 use std::mem::*;
 use std::process::{abort, exit};
 use std::ptr::*;
 
 mod gc;
+mod set;
 
 /// Allocation unit used to measure memory usage in the mark-and-sweep GC.
 #[repr(transparent)]
@@ -25,6 +27,14 @@ thread_local! {
     static THRESHOLD_SPACE: Cell<usize> = const { Cell::new(1024) };
 }
 
+// Objects are allocated through the C allocator so that `free` does not need the
+// allocation size: arrays can shrink in place (see `list_del`) without the GC
+// having to remember their original length.
+unsafe extern "C" {
+    fn calloc(count: usize, size: usize) -> *mut AllocUnit;
+    pub(crate) fn free(pointer: *mut AllocUnit);
+}
+
 /// Helper to round up memory allocation to nearest unit.
 fn divide_up(value: usize) -> usize {
     let align = size_of::<AllocUnit>();
@@ -84,8 +94,10 @@ pub unsafe extern "C" fn alloc_obj(
         let size = calculate_size(prototype, || len);
 
         // Allocate raw memory for the object
-        let pointer = Box::into_raw(vec![AllocUnit(0); size].into_boxed_slice())
-            as *mut AllocUnit as *mut Object;
+        let pointer = calloc(size, size_of::<AllocUnit>()) as *mut Object;
+        if pointer.is_null() {
+            fatal("Out of memory");
+        }
 
         // Update GC memory tracking
         CURRENT_SPACE.with(|current_space| current_space.set(current_space.get() + size));
@@ -127,7 +139,7 @@ pub unsafe extern "C" fn len(pointer: *mut Object) -> i32 {
         let prototype = (*object).object.prototype;
         if !matches!(
             (*prototype).type_tag,
-            Type::Str | Type::ValueList | Type::ObjList
+            Type::Str | Type::ValueList | Type::ObjList | Type::Set
         ) {
             invalid_arg();
         }
@@ -135,13 +147,185 @@ pub unsafe extern "C" fn len(pointer: *mut Object) -> i32 {
     }
 }
 
-/// Prints a ChocoPy object to standard output.
-/// Supports int, bool, and str types.
+/// Removes the elements `start..end` from a list, shifting the tail down and
+/// shrinking `len` in place. Bounds must satisfy `0 <= start, end <= len`;
+/// an empty or reversed range removes nothing.
 ///
 /// # Safety
-/// - `pointer` must be valid and initialized.
-#[unsafe(export_name = "$print")]
-pub unsafe extern "C" fn print(pointer: *mut Object) -> *mut u8 {
+/// - `pointer` must be a valid, non-null list object.
+#[unsafe(export_name = "$list_del")]
+pub unsafe extern "C" fn list_del(pointer: *mut Object, start: i32, end: i32) {
+    unsafe {
+        if pointer.is_null() {
+            invalid_arg();
+        }
+        let object = pointer as *mut ArrayObject;
+        let prototype = (*object).object.prototype;
+        if !matches!((*prototype).type_tag, Type::ValueList | Type::ObjList) {
+            invalid_arg();
+        }
+        let len = (*object).len;
+        if start < 0 || end < 0 || start as u64 > len || end as u64 > len {
+            out_of_bound();
+        }
+        if start >= end {
+            return;
+        }
+        let (start, end) = (start as u64, end as u64);
+
+        // Shift the tail down and clear the vacated slots, so that the GC never
+        // sees stale references past the new length.
+        let element_size = -(*prototype).size as usize;
+        let elements = object.offset(1) as *mut u8;
+        std::ptr::copy(
+            elements.add(end as usize * element_size),
+            elements.add(start as usize * element_size),
+            (len - end) as usize * element_size,
+        );
+        let new_len = len - (end - start);
+        std::ptr::write_bytes(
+            elements.add(new_len as usize * element_size),
+            0,
+            (end - start) as usize * element_size,
+        );
+
+        // The allocation keeps its size, but the GC accounts for objects by
+        // their current length, so release the difference here.
+        let old_size = calculate_size(prototype, || len);
+        let new_size = calculate_size(prototype, || new_len);
+        CURRENT_SPACE.with(|current_space| current_space.set(current_space.get() - (old_size - new_size)));
+        (*object).len = new_len;
+    }
+}
+
+/// Copies a list into a new one of length `len`, truncating the elements or
+/// padding them with zeros. List comprehensions use this to grow their buffer
+/// and to build the final list out of it.
+///
+/// # Safety
+/// - `pointer` must be a valid, non-null list object, reachable by the GC
+///   from the caller's frame.
+/// - `rbp` and `rsp` must be the frame of the caller, as for `alloc_obj`.
+#[unsafe(export_name = "$list_resize")]
+pub unsafe extern "C" fn list_resize(
+    pointer: *mut Object,
+    len: u64,
+    rbp: *const u64,
+    rsp: *const u64,
+) -> *mut Object {
+    unsafe {
+        let prototype = (*pointer).prototype;
+        let result = alloc_obj(prototype, len, rbp, rsp);
+        let element_size = -(*prototype).size as usize;
+        let copied = std::cmp::min((*(pointer as *mut ArrayObject)).len, len);
+        std::ptr::copy_nonoverlapping(
+            (pointer as *mut ArrayObject).offset(1) as *const u8,
+            (result as *mut ArrayObject).offset(1) as *mut u8,
+            copied as usize * element_size,
+        );
+        result
+    }
+}
+
+/// The elements of a non-empty array object.
+unsafe fn elements<'a, T>(pointer: *mut Object) -> &'a [T] {
+    unsafe {
+        let object = pointer as *mut ArrayObject;
+        std::slice::from_raw_parts(object.offset(1) as *const T, (*object).len as usize)
+    }
+}
+
+/// Three-way comparison of two str or list objects of the same type.
+/// Lists compare element-wise, and the shorter list goes first if it is a prefix.
+unsafe fn compare_objects(left: *mut Object, right: *mut Object) -> Ordering {
+    unsafe {
+        if left.is_null() || right.is_null() {
+            none_op();
+        }
+        let left_len = (*(left as *mut ArrayObject)).len;
+        let right_len = (*(right as *mut ArrayObject)).len;
+        // An empty list literal doesn't have the prototype of the element type
+        if left_len == 0 || right_len == 0 {
+            return left_len.cmp(&right_len);
+        }
+        let prototype = (*left).prototype;
+        match (&(*prototype).type_tag, (*prototype).size) {
+            (Type::Str, _) => str_bytes(left).cmp(str_bytes(right)),
+            (Type::ValueList, -4) => elements::<i32>(left).cmp(elements::<i32>(right)),
+            (Type::ValueList, _) => elements::<bool>(left).cmp(elements::<bool>(right)),
+            (Type::ObjList, _) => {
+                let left = elements::<*mut Object>(left);
+                let right = elements::<*mut Object>(right);
+                for (&l, &r) in left.iter().zip(right) {
+                    let ordering = compare_objects(l, r);
+                    if ordering != Ordering::Equal {
+                        return ordering;
+                    }
+                }
+                left.len().cmp(&right.len())
+            }
+            _ => invalid_arg(),
+        }
+    }
+}
+
+/// Structural equality of two objects. Objects other than str and lists are
+/// only equal to themselves.
+unsafe fn equal_objects(left: *mut Object, right: *mut Object) -> bool {
+    unsafe {
+        if left == right {
+            return true;
+        }
+        if left.is_null() || right.is_null() {
+            return false;
+        }
+        let prototype = (*left).prototype;
+        if !matches!((*prototype).type_tag, Type::Str | Type::ValueList | Type::ObjList) {
+            return false;
+        }
+        let left_len = (*(left as *mut ArrayObject)).len;
+        let right_len = (*(right as *mut ArrayObject)).len;
+        if left_len != right_len {
+            return false;
+        }
+        if left_len == 0 {
+            return true;
+        }
+        match (&(*prototype).type_tag, (*prototype).size) {
+            (Type::Str, _) => str_bytes(left) == str_bytes(right),
+            (Type::ValueList, -4) => elements::<i32>(left) == elements::<i32>(right),
+            (Type::ValueList, _) => elements::<bool>(left) == elements::<bool>(right),
+            _ => {
+                let left = elements::<*mut Object>(left);
+                let right = elements::<*mut Object>(right);
+                left.iter().zip(right).all(|(&l, &r)| equal_objects(l, r))
+            }
+        }
+    }
+}
+
+/// Compares two str objects or two lists lexicographically, returning a
+/// negative, zero or positive number. Comparing None exits with an error.
+///
+/// # Safety
+/// - `left` and `right` must be null or valid objects of the same type.
+#[unsafe(export_name = "$compare")]
+pub unsafe extern "C" fn compare(left: *mut Object, right: *mut Object) -> i32 {
+    unsafe { compare_objects(left, right) as i32 }
+}
+
+/// Checks whether two str objects or two lists have equal contents.
+/// None is only equal to None.
+///
+/// # Safety
+/// - `left` and `right` must be null or valid objects of the same type.
+#[unsafe(export_name = "$equal")]
+pub unsafe extern "C" fn equal(left: *mut Object, right: *mut Object) -> bool {
+    unsafe { equal_objects(left, right) }
+}
+
+/// Appends the text of an int, bool or str object to the output.
+unsafe fn write_object(output: &mut String, pointer: *mut Object) {
     unsafe {
         if pointer.is_null() {
             invalid_arg();
@@ -149,36 +333,96 @@ pub unsafe extern "C" fn print(pointer: *mut Object) -> *mut u8 {
         let prototype = (*pointer).prototype;
         match (*prototype).type_tag {
             Type::Int => {
-                println!("{}", *(pointer.offset(1) as *const i32));
+                output.push_str(&(*(pointer.offset(1) as *const i32)).to_string());
             }
             Type::Bool => {
-                println!(
-                    "{}",
-                    if *(pointer.offset(1) as *const bool) {
-                        "True"
-                    } else {
-                        "False"
-                    }
-                );
+                output.push_str(if *(pointer.offset(1) as *const bool) {
+                    "True"
+                } else {
+                    "False"
+                });
             }
             Type::Str => {
-                let object = pointer as *mut ArrayObject;
-                let slice = std::str::from_utf8(std::slice::from_raw_parts(
-                    object.offset(1) as *const u8,
-                    (*object).len as usize,
-                ))
-                .unwrap_or_else(|e| fatal(&e.to_string()));
-                println!("{}", slice);
+                // A `str` is a sequence of UTF-8 bytes, so indexing may split a character
+                output.push_str(&String::from_utf8_lossy(str_bytes(pointer)));
             }
             _ => {
                 invalid_arg();
             }
         }
+    }
+}
+
+/// The bytes of a str object.
+pub(crate) unsafe fn str_bytes<'a>(pointer: *mut Object) -> &'a [u8] {
+    unsafe {
+        let object = pointer as *mut ArrayObject;
+        std::slice::from_raw_parts(object.offset(1) as *const u8, (*object).len as usize)
+    }
+}
+
+/// Prints the objects of the list `args`, separated by `sep` and followed by
+/// `end`, which default to a space and a newline when null. `flags` is a
+/// combination of `PRINT_STDERR` and `PRINT_FLUSH`.
+/// Only int, bool and str objects can be printed.
+///
+/// # Safety
+/// - `args` must be a valid, non-null list of objects.
+/// - `sep` and `end` must be null or valid str objects.
+#[unsafe(export_name = "$print")]
+pub unsafe extern "C" fn print(
+    args: *mut Object,
+    sep: *mut Object,
+    end: *mut Object,
+    flags: u64,
+) -> *mut u8 {
+    unsafe {
+        let text = |pointer: *mut Object, default: &'static str| {
+            if pointer.is_null() {
+                std::borrow::Cow::Borrowed(default)
+            } else {
+                String::from_utf8_lossy(str_bytes(pointer))
+            }
+        };
+
+        // Nothing is written if any object can't be printed
+        let mut output = String::new();
+        let list = args as *mut ArrayObject;
+        let elements = list.offset(1) as *const *mut Object;
+        for i in 0..(*list).len as usize {
+            if i != 0 {
+                output.push_str(&text(sep, " "));
+            }
+            write_object(&mut output, *elements.add(i));
+        }
+        output.push_str(&text(end, "\n"));
+
+        let flush = flags & PRINT_FLUSH != 0;
+        let result = if flags & PRINT_STDERR != 0 {
+            write_output(&mut std::io::stderr().lock(), &output, flush)
+        } else {
+            write_output(&mut std::io::stdout().lock(), &output, flush)
+        };
+        if result.is_err() {
+            fatal("Cannot write output");
+        }
 
         std::ptr::null_mut()
     }
 }
 
+fn write_output(
+    stream: &mut impl std::io::Write,
+    output: &str,
+    flush: bool,
+) -> std::io::Result<()> {
+    stream.write_all(output.as_bytes())?;
+    if flush {
+        stream.flush()?;
+    }
+    Ok(())
+}
+
 /// Reads a line from stdin into a new str object.
 ///
 /// # Safety
@@ -222,9 +466,18 @@ pub(crate) fn fatal(message: &str) -> ! {
     abort();
 }
 
+/// Flushes stdout and stderr, which the C runtime doesn't know about, so that
+/// output without a trailing newline isn't lost when the program ends.
+fn flush_output() {
+    use std::io::Write;
+    let _ = std::io::stdout().flush();
+    let _ = std::io::stderr().flush();
+}
+
 /// Terminates the program with a given exit code.
 fn exit_code(code: i32) -> ! {
     println!("Exited with error code {}", code);
+    flush_output();
     exit(code);
 }
 
@@ -255,6 +508,33 @@ pub extern "C" fn none_op() -> ! {
     exit_code(4)
 }
 
+/// Runtime trap: `next` on an exhausted generator.
+#[unsafe(export_name = "$stop_iteration")]
+pub extern "C" fn stop_iteration() -> ! {
+    println!("Generator exhausted");
+    exit_code(5)
+}
+
+/// Runtime trap: shift by a negative count.
+#[unsafe(export_name = "$negative_shift")]
+pub extern "C" fn negative_shift() -> ! {
+    println!("Negative shift count");
+    exit_code(6)
+}
+
+/// Runtime trap: `**` with a negative exponent.
+#[unsafe(export_name = "$negative_exponent")]
+pub extern "C" fn negative_exponent() -> ! {
+    println!("Negative exponent");
+    exit_code(7)
+}
+
+/// Runtime trap: `remove` of an element missing from a set.
+fn missing_element() -> ! {
+    println!("Element not in set");
+    exit_code(8)
+}
+
 #[cfg(not(test))]
 pub mod crt0_glue {
     unsafe extern "C" {
@@ -269,6 +549,7 @@ pub mod crt0_glue {
     #[unsafe(export_name = "main")]
     pub unsafe extern "C" fn entry_point() -> i32 {
         unsafe { chocopy_main(); }
+        super::flush_output();
         0
     }
 }
@@ -6,6 +6,7 @@ pub enum Type {
     Int = 1,
     Bool = 2,
     Str = 3,
+    Set = 4,
     ValueList = -1, // Represents list of primitives (i.e ints, bools).
     ObjList = -2,   // Represents list of object references (i.e strings, custom objects).
 }
@@ -21,6 +22,9 @@ pub struct Prototype {
     // This is a pointer to a bitmap representing the member variables of the object,
     // if the nth position is 1, then the nth member variable is a reference to another object.
     pub reference_bitmap: *const u8,
+
+    // The prototype of the super class, or null for `object`.
+    pub super_prototype: *const Prototype,
     // ... Object method pointers (right after header in memory).
 }
 
@@ -41,6 +45,14 @@ pub struct ArrayObject {
     // ... Array elements (right after header in memory).
 }
 
+#[repr(C)] // Makes sure the struct is not reordered by the Rust compiler.
+pub struct SetObject {
+    pub object: Object,
+    pub len: u64, // Number of elements, at the same offset as in arrays for `len`.
+    pub table: *mut Object, // The hash table, a list object allocated on the first insertion.
+    pub table_prototype: *const Prototype, // Prototype of the table, `[int]` or `[object]`.
+}
+
 #[repr(C)] // Makes sure the struct is not reordered by the Rust compiler.
 pub struct InitParam {
     pub bottom_frame: *const u64, // Stack base pointer, used for stack walking.
@@ -49,3 +61,7 @@ pub struct InitParam {
     pub global_map: *const u8, // Bitmap of which globals are GC roots.
     pub str_prototype: *const Prototype, // Metadata for allocating string objects.
 }
+
+// Flags of `$print`
+pub const PRINT_STDERR: u64 = 1; // Write to standard error instead of standard output
+pub const PRINT_FLUSH: u64 = 2; // Flush the stream after writing
@@ -0,0 +1,319 @@
+use super::*;
+
+// Hash sets of int, bool or str elements.
+//
+// The elements live in an open addressing table with linear probing, which is
+// allocated on the first insertion and doubled when it is 3/4 full. The table
+// is a list object, so that the GC only needs to follow one pointer from the set:
+// - A `[object]` table holds str elements, with null for empty slots.
+// - A `[int]` table holds int and bool elements as (occupied, value) pairs.
+// Elements are passed around as u64: the value of an int or bool, or a pointer.
+
+const INITIAL_CAPACITY: u64 = 8;
+
+// Whether the elements of the set are references
+unsafe fn is_ref(set: *mut SetObject) -> bool {
+    unsafe { matches!((*(*set).table_prototype).type_tag, Type::ObjList) }
+}
+
+unsafe fn capacity(set: *mut SetObject) -> u64 {
+    unsafe {
+        let table = (*set).table as *mut ArrayObject;
+        if table.is_null() {
+            0
+        } else if is_ref(set) {
+            (*table).len
+        } else {
+            (*table).len / 2
+        }
+    }
+}
+
+unsafe fn get_slot(set: *mut SetObject, index: u64) -> Option<u64> {
+    unsafe {
+        let table = (*set).table as *mut ArrayObject;
+        if is_ref(set) {
+            let slot = *(table.offset(1) as *const u64).add(index as usize);
+            (slot != 0).then_some(slot)
+        } else {
+            let slot = (table.offset(1) as *const i32).add(index as usize * 2);
+            (*slot != 0).then(|| *slot.add(1) as u32 as u64)
+        }
+    }
+}
+
+unsafe fn set_slot(set: *mut SetObject, index: u64, element: Option<u64>) {
+    unsafe {
+        let table = (*set).table as *mut ArrayObject;
+        if is_ref(set) {
+            *(table.offset(1) as *mut u64).add(index as usize) = element.unwrap_or(0);
+        } else {
+            let slot = (table.offset(1) as *mut i32).add(index as usize * 2);
+            *slot = element.is_some() as i32;
+            *slot.add(1) = element.unwrap_or(0) as i32;
+        }
+    }
+}
+
+unsafe fn hash(set: *mut SetObject, element: u64) -> u64 {
+    unsafe {
+        if is_ref(set) {
+            // FNV-1a
+            str_bytes(element as *mut Object)
+                .iter()
+                .fold(0xcbf29ce484222325, |hash, &byte| {
+                    (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+                })
+        } else {
+            (element as u32 as u64).wrapping_mul(0x9E3779B97F4A7C15) >> 32
+        }
+    }
+}
+
+unsafe fn equal(set: *mut SetObject, a: u64, b: u64) -> bool {
+    unsafe {
+        if is_ref(set) {
+            str_bytes(a as *mut Object) == str_bytes(b as *mut Object)
+        } else {
+            a == b
+        }
+    }
+}
+
+// Plain elements are passed in the low 32 bits, and str elements must not be None
+unsafe fn normalize(set: *mut SetObject, element: u64) -> u64 {
+    unsafe {
+        if is_ref(set) {
+            if element == 0 {
+                none_op();
+            }
+            element
+        } else {
+            element as u32 as u64
+        }
+    }
+}
+
+// The slot holding the element, or the empty slot where it would be inserted
+unsafe fn find(set: *mut SetObject, element: u64) -> (u64, bool) {
+    unsafe {
+        let mask = capacity(set) - 1;
+        let mut index = hash(set, element) & mask;
+        loop {
+            match get_slot(set, index) {
+                None => return (index, false),
+                Some(slot) if equal(set, slot, element) => return (index, true),
+                Some(_) => index = (index + 1) & mask,
+            }
+        }
+    }
+}
+
+unsafe fn contains_element(set: *mut SetObject, element: u64) -> bool {
+    unsafe { (*set).len != 0 && find(set, element).1 }
+}
+
+unsafe fn add_element(set: *mut SetObject, element: u64, rbp: *const u64, rsp: *const u64) {
+    unsafe {
+        if ((*set).len + 1) * 4 > capacity(set) * 3 {
+            grow(set, rbp, rsp);
+        }
+        let (index, found) = find(set, element);
+        if !found {
+            set_slot(set, index, Some(element));
+            (*set).len += 1;
+        }
+    }
+}
+
+unsafe fn grow(set: *mut SetObject, rbp: *const u64, rsp: *const u64) {
+    unsafe {
+        let old_capacity = capacity(set);
+        let new_capacity = std::cmp::max(INITIAL_CAPACITY, old_capacity * 2);
+        let slots = if is_ref(set) {
+            new_capacity
+        } else {
+            new_capacity * 2
+        };
+        // The set is reachable from the caller, and the old table from the set
+        let table = alloc_obj((*set).table_prototype, slots, rbp, rsp);
+
+        let old_elements = elements_of(set);
+        (*set).table = table;
+        for element in old_elements {
+            let (index, _) = find(set, element);
+            set_slot(set, index, Some(element));
+        }
+    }
+}
+
+// Remove the element, shifting back the following elements of its probe sequence
+unsafe fn remove_element(set: *mut SetObject, element: u64) -> bool {
+    unsafe {
+        if !contains_element(set, element) {
+            return false;
+        }
+        let mask = capacity(set) - 1;
+        let (mut hole, _) = find(set, element);
+        let mut index = hole;
+        loop {
+            index = (index + 1) & mask;
+            let Some(slot) = get_slot(set, index) else {
+                break;
+            };
+            // The element can fill the hole unless its home is cyclically in (hole, index]
+            let home = hash(set, slot) & mask;
+            let stays = if hole <= index {
+                hole < home && home <= index
+            } else {
+                hole < home || home <= index
+            };
+            if !stays {
+                set_slot(set, hole, Some(slot));
+                hole = index;
+            }
+        }
+        set_slot(set, hole, None);
+        (*set).len -= 1;
+        true
+    }
+}
+
+unsafe fn elements_of(set: *mut SetObject) -> Vec<u64> {
+    unsafe { (0..capacity(set)).filter_map(|i| get_slot(set, i)).collect() }
+}
+
+unsafe fn as_set(pointer: *mut Object) -> *mut SetObject {
+    if pointer.is_null() {
+        none_op();
+    }
+    pointer as *mut SetObject
+}
+
+/// Adds an element to a set.
+///
+/// # Safety
+/// - `set` must be null or a valid set object, reachable by the GC from the
+///   caller's frame, as must a str `element`.
+/// - `rbp` and `rsp` must be the frame of the caller, as for `alloc_obj`.
+#[unsafe(export_name = "$set_add")]
+pub unsafe extern "C" fn set_add(
+    set: *mut Object,
+    element: u64,
+    rbp: *const u64,
+    rsp: *const u64,
+) {
+    unsafe {
+        let set = as_set(set);
+        add_element(set, normalize(set, element), rbp, rsp);
+    }
+}
+
+/// Removes an element from a set, exiting with an error if it is missing.
+///
+/// # Safety
+/// - `set` must be null or a valid set object, and `element` one of its type.
+#[unsafe(export_name = "$set_remove")]
+pub unsafe extern "C" fn set_remove(set: *mut Object, element: u64) {
+    unsafe {
+        let set = as_set(set);
+        if !remove_element(set, normalize(set, element)) {
+            missing_element();
+        }
+    }
+}
+
+/// Removes an element from a set if it is present.
+///
+/// # Safety
+/// - `set` must be null or a valid set object, and `element` one of its type.
+#[unsafe(export_name = "$set_discard")]
+pub unsafe extern "C" fn set_discard(set: *mut Object, element: u64) {
+    unsafe {
+        let set = as_set(set);
+        remove_element(set, normalize(set, element));
+    }
+}
+
+/// Checks whether an element is in a set.
+///
+/// # Safety
+/// - `set` must be null or a valid set object, and `element` one of its type.
+#[unsafe(export_name = "$set_contains")]
+pub unsafe extern "C" fn set_contains(set: *mut Object, element: u64) -> bool {
+    unsafe {
+        let set = as_set(set);
+        contains_element(set, normalize(set, element))
+    }
+}
+
+/// Adds all elements of `source` to `set`. Unions are built by updating an
+/// empty set with both operands.
+///
+/// # Safety
+/// - `set` must be a valid set object and `source` null or a valid set object
+///   of the same type, both reachable by the GC from the caller's frame.
+/// - `rbp` and `rsp` must be the frame of the caller, as for `alloc_obj`.
+#[unsafe(export_name = "$set_update")]
+pub unsafe extern "C" fn set_update(
+    set: *mut Object,
+    source: *mut Object,
+    rbp: *const u64,
+    rsp: *const u64,
+) {
+    unsafe {
+        let set = as_set(set);
+        for element in elements_of(as_set(source)) {
+            add_element(set, element, rbp, rsp);
+        }
+    }
+}
+
+/// Removes the elements of `set` that are not in `other`. Intersections are
+/// built by updating an empty set with the left operand and retaining the right one.
+///
+/// # Safety
+/// - `set` must be a valid set object and `other` null or a valid set object
+///   of the same type.
+#[unsafe(export_name = "$set_retain")]
+pub unsafe extern "C" fn set_retain(set: *mut Object, other: *mut Object) {
+    unsafe {
+        let set = as_set(set);
+        let other = as_set(other);
+        for element in elements_of(set) {
+            if !contains_element(other, element) {
+                remove_element(set, element);
+            }
+        }
+    }
+}
+
+/// Copies the elements of a set into a new list with the given prototype,
+/// which `for` loops iterate over.
+///
+/// # Safety
+/// - `set` must be null or a valid set object, reachable by the GC from the
+///   caller's frame.
+/// - `prototype` must be the list prototype for the element type.
+/// - `rbp` and `rsp` must be the frame of the caller, as for `alloc_obj`.
+#[unsafe(export_name = "$set_list")]
+pub unsafe extern "C" fn set_list(
+    set: *mut Object,
+    prototype: *const Prototype,
+    rbp: *const u64,
+    rsp: *const u64,
+) -> *mut Object {
+    unsafe {
+        let set = as_set(set);
+        let list = alloc_obj(prototype, (*set).len, rbp, rsp);
+        let data = (list as *mut ArrayObject).offset(1);
+        for (i, element) in elements_of(set).into_iter().enumerate() {
+            match -(*prototype).size {
+                1 => *(data as *mut u8).add(i) = element as u8,
+                4 => *(data as *mut i32).add(i) = element as i32,
+                _ => *(data as *mut u64).add(i) = element,
+            }
+        }
+        list
+    }
+}
@@ -0,0 +1,249 @@
+// Stable codes of diagnostics, and their machine-readable output formats.
+// Codes never change once assigned, so tools can match on them instead of
+// on the wording of messages.
+
+use crate::common::location::Location;
+use crate::common::node::{CompilerError, Severity};
+use serde_json::{Value, json};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Code {
+    pub id: &'static str,
+    pub name: &'static str,
+}
+
+macro_rules! codes {
+    ($($constant:ident = $id:literal $name:literal,)*) => {
+        $(pub const $constant: Code = Code { id: $id, name: $name };)*
+
+        pub const CODES: &[Code] = &[$($constant),*];
+    };
+}
+
+codes! {
+    // Syntax errors
+    UNEXPECTED_TOKEN = "E0001" "unexpected-token",
+    INVALID_INTEGER = "E0002" "invalid-integer",
+    INTEGER_OUT_OF_RANGE = "E0003" "integer-out-of-range",
+    POSITIONAL_AFTER_KEYWORD = "E0004" "positional-after-keyword",
+    BREAK_OUTSIDE_LOOP = "E0006" "break-outside-loop",
+
+    // Declaration errors
+    DUPLICATE_DECLARATION = "E0101" "duplicate-declaration",
+    UNDEFINED_SUPERCLASS = "E0102" "undefined-superclass",
+    SUPERCLASS_NOT_CLASS = "E0103" "superclass-not-class",
+    SPECIAL_SUPERCLASS = "E0104" "special-superclass",
+    GENERIC_SUPERCLASS = "E0105" "generic-superclass",
+    METHOD_SELF = "E0106" "method-self",
+    METHOD_OVERRIDE = "E0107" "method-override",
+    ATTRIBUTE_REDEFINITION = "E0108" "attribute-redefinition",
+    INVALID_TYPE = "E0109" "invalid-type",
+    CLASS_NAME_SHADOWED = "E0110" "class-name-shadowed",
+    NOT_NONLOCAL = "E0111" "not-nonlocal",
+    NOT_GLOBAL = "E0112" "not-global",
+    MISSING_RETURN = "E0113" "missing-return",
+    FINAL_LOCAL = "E0114" "final-local",
+    OPTIONAL_TYPE = "E0115" "optional-type",
+    TYPE_ARGUMENT_COUNT = "E0116" "type-argument-count",
+    GENERATOR_RETURN_TYPE = "E0117" "generator-return-type",
+    GENERATOR_NESTED = "E0118" "generator-nested",
+    PLAIN_TYPE_ARGUMENT = "E0119" "plain-type-argument",
+
+    // Type errors
+    UNDEFINED_VARIABLE = "E0201" "undefined-variable",
+    TYPE_MISMATCH = "E0202" "type-mismatch",
+    ASSIGN_UNDECLARED = "E0203" "assign-undeclared",
+    FINAL_ASSIGN = "E0204" "final-assign",
+    UNARY_OPERAND = "E0205" "unary-operand",
+    BINARY_OPERANDS = "E0206" "binary-operands",
+    CONDITION_TYPE = "E0207" "condition-type",
+    MEMBER_NON_CLASS = "E0208" "member-non-class",
+    ARGUMENT_COUNT = "E0209" "argument-count",
+    ARGUMENT_TYPE = "E0210" "argument-type",
+    NOT_INDEXABLE = "E0211" "not-indexable",
+    INDEX_NOT_INT = "E0212" "index-not-int",
+    UNDEFINED_ATTRIBUTE = "E0213" "undefined-attribute",
+    NOT_CALLABLE = "E0214" "not-callable",
+    UNDEFINED_METHOD = "E0215" "undefined-method",
+    NONE_RETURN = "E0216" "none-return",
+    NOT_ITERABLE = "E0217" "not-iterable",
+    MULTI_ASSIGN_NONE = "E0218" "multi-assign-none",
+    TOP_LEVEL_RETURN = "E0219" "top-level-return",
+    STR_INDEX_ASSIGN = "E0220" "str-index-assign",
+    ISINSTANCE_CLASS = "E0221" "isinstance-class",
+    MAYBE_NONE = "E0222" "maybe-none",
+    GENERATOR_RETURN_VALUE = "E0223" "generator-return-value",
+    TOP_LEVEL_YIELD = "E0224" "top-level-yield",
+    NEXT_NON_ITERATOR = "E0225" "next-non-iterator",
+    SLICE_OUTSIDE_DEL = "E0226" "slice-outside-del",
+    DEL_NON_LOCAL = "E0227" "del-non-local",
+    DEL_CAPTURED = "E0228" "del-captured",
+    POSSIBLY_UNBOUND = "E0229" "possibly-unbound",
+    SUPER_OUTSIDE_METHOD = "E0230" "super-outside-method",
+    KEYWORD_UNSUPPORTED = "E0231" "keyword-unsupported",
+    KEYWORD_UNKNOWN = "E0232" "keyword-unknown",
+    KEYWORD_REPEATED = "E0233" "keyword-repeated",
+    KEYWORD_TYPE = "E0234" "keyword-type",
+    PRINT_FILE = "E0235" "print-file",
+    SET_ELEMENT_TYPE = "E0236" "set-element-type",
+    SET_MIXED_ELEMENTS = "E0237" "set-mixed-elements",
+    MISSING_TYPE = "E0238" "missing-type",
+
+    // Warnings, named like their `-W` flags
+    UNUSED_VARIABLE = "W0001" "unused-variable",
+    UNUSED_PARAMETER = "W0002" "unused-parameter",
+    UNUSED_FUNCTION = "W0003" "unused-function",
+    UNREACHABLE = "W0004" "unreachable",
+    SHADOW = "W0005" "shadow",
+    ALWAYS_TRUE = "W0006" "always-true",
+    POSSIBLY_NONE = "W0007" "possibly-none",
+}
+
+impl Code {
+    pub fn from_id(id: &str) -> Option<Code> {
+        CODES.iter().copied().find(|code| code.id == id)
+    }
+}
+
+// The text of a diagnostic with its code
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Message {
+    pub code: Code,
+    pub text: String,
+}
+
+impl Message {
+    pub fn new(code: Code, text: String) -> Message {
+        Message { code, text }
+    }
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note => "note",
+    }
+}
+
+// Split sorted diagnostics into each diagnostic and the notes following it
+pub fn with_notes(errors: &[CompilerError]) -> Vec<(&CompilerError, &[CompilerError])> {
+    let mut groups = vec![];
+    let mut i = 0;
+    while i < errors.len() {
+        let notes = errors[i + 1..]
+            .iter()
+            .take_while(|e| e.severity == Severity::Note)
+            .count();
+        groups.push((&errors[i], &errors[i + 1..i + 1 + notes]));
+        i += 1 + notes;
+    }
+    groups
+}
+
+fn json_location(location: Location) -> Value {
+    json!({
+        "start": { "row": location.start.row, "col": location.start.col },
+        "end": { "row": location.end.row, "col": location.end.col },
+    })
+}
+
+// Diagnostics as a JSON array. Locations are inclusive ranges of 1-based rows
+// and columns, and notes are attached to the diagnostic they belong to.
+pub fn to_json(errors: &[CompilerError]) -> Value {
+    let diagnostics: Vec<Value> = with_notes(errors)
+        .into_iter()
+        .map(|(error, notes)| {
+            let code = error.code.as_deref().and_then(Code::from_id);
+            json!({
+                "code": error.code,
+                "name": code.map(|code| code.name),
+                "severity": severity_name(error.severity),
+                "message": error.message,
+                "location": json_location(error.base.location),
+                "flag": error.flag,
+                "syntax": error.syntax,
+                "notes": notes.iter().map(|note| json!({
+                    "message": note.message,
+                    "location": json_location(note.base.location),
+                })).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    Value::Array(diagnostics)
+}
+
+fn sarif_location(file: &str, location: Location) -> Value {
+    // SARIF end columns are exclusive
+    json!({
+        "physicalLocation": {
+            "artifactLocation": { "uri": file },
+            "region": {
+                "startLine": location.start.row,
+                "startColumn": location.start.col,
+                "endLine": location.end.row,
+                "endColumn": location.end.col + 1,
+            },
+        },
+    })
+}
+
+// Diagnostics as a SARIF 2.1.0 log of one run, with every code as a rule
+pub fn to_sarif(file: &str, errors: &[CompilerError]) -> Value {
+    let rules: Vec<Value> = CODES
+        .iter()
+        .map(|code| json!({ "id": code.id, "name": code.name }))
+        .collect();
+    let results: Vec<Value> = with_notes(errors)
+        .into_iter()
+        .map(|(error, notes)| {
+            let mut result = json!({
+                "level": severity_name(error.severity),
+                "message": { "text": error.message },
+                "locations": [sarif_location(file, error.base.location)],
+            });
+            if let Some(index) = CODES.iter().position(|c| Some(c.id) == error.code.as_deref()) {
+                result["ruleId"] = json!(CODES[index].id);
+                result["ruleIndex"] = json!(index);
+            }
+            if !notes.is_empty() {
+                let related = notes.iter().enumerate().map(|(id, note)| {
+                    let mut location = sarif_location(file, note.base.location);
+                    location["id"] = json!(id);
+                    location["message"] = json!({ "text": note.message });
+                    location
+                });
+                result["relatedLocations"] = Value::Array(related.collect());
+            }
+            result
+        })
+        .collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "chocopy",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn unique_codes() {
+        let ids: HashSet<_> = CODES.iter().map(|code| code.id).collect();
+        let names: HashSet<_> = CODES.iter().map(|code| code.name).collect();
+        assert_eq!(ids.len(), CODES.len());
+        assert_eq!(names.len(), CODES.len());
+    }
+}
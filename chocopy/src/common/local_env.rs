@@ -1,9 +1,40 @@
-use std::collections::HashMap;
+use crate::common::location::Location;
+use std::collections::{HashMap, HashSet};
 
-pub struct LocalEnv<F, V>(Vec<HashMap<String, LocalSlot<F, V>>>);
+// A name resolved to where it is declared, or to nothing for built-in names
+pub struct Resolution {
+    pub name: String,
+    pub location: Location,
+    pub declaration: Option<Location>,
+}
+
+pub struct LocalEnv<F, V> {
+    frames: Vec<HashMap<String, LocalSlot<F, V>>>,
+    // Where the names of each frame are declared, including `global` and
+    // `nonlocal` declarations
+    declared: Vec<HashMap<String, Location>>,
+    // Names resolved so far, for the symbol table
+    resolutions: Vec<Resolution>,
+    // Names in each frame that must never be refined (e.g. captured by `nonlocal`)
+    pinned: Vec<HashSet<String>>,
+    // Flow-sensitive refinements of local variables, tagged with the frame depth.
+    // `None` restores the declared type.
+    refinements: Vec<(usize, String, Option<V>)>,
+    // Variables of the base frame that cannot be assigned
+    constants: HashSet<String>,
+    // Type parameters of the generic class whose methods are in scope
+    type_params: Vec<String>,
+}
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 pub struct Assignable(pub bool);
 pub struct FrameHandle<'a, F, V>(&'a mut LocalEnv<F, V>);
+pub struct RefineHandle<'a, F, V>(&'a mut LocalEnv<F, V>, usize);
+pub struct ScopeHandle<'a, F, V>(
+    &'a mut LocalEnv<F, V>,
+    String,
+    Option<(LocalSlot<F, V>, Option<Location>)>,
+    usize,
+);
 
 impl<'a, F, V> FrameHandle<'a, F, V> {
     pub fn inner(&mut self) -> &mut LocalEnv<F, V> {
@@ -14,7 +45,47 @@ impl<'a, F, V> FrameHandle<'a, F, V> {
 // The Drop trait defines the destructor functionality for the type.
 impl<'a, F, V> Drop for FrameHandle<'a, F, V> {
     fn drop(&mut self) {
-        (self.0).0.pop();
+        self.0.frames.pop();
+        self.0.declared.pop();
+        self.0.pinned.pop();
+    }
+}
+
+impl<'a, F, V> RefineHandle<'a, F, V> {
+    pub fn inner(&mut self) -> &mut LocalEnv<F, V> {
+        self.0
+    }
+}
+
+// Leaving the refined region restores the declared types
+impl<'a, F, V> Drop for RefineHandle<'a, F, V> {
+    fn drop(&mut self) {
+        self.0.refinements.truncate(self.1);
+    }
+}
+
+impl<'a, F, V> ScopeHandle<'a, F, V> {
+    pub fn inner(&mut self) -> &mut LocalEnv<F, V> {
+        self.0
+    }
+}
+
+// Leaving the scope uncovers the shadowed variable
+impl<'a, F, V> Drop for ScopeHandle<'a, F, V> {
+    fn drop(&mut self) {
+        let frame = self.0.frames.last_mut().unwrap();
+        let declared = self.0.declared.last_mut().unwrap();
+        if let Some((slot, location)) = self.2.take() {
+            frame.insert(self.1.clone(), slot);
+            match location {
+                Some(location) => declared.insert(self.1.clone(), location),
+                None => declared.remove(&self.1),
+            };
+        } else {
+            frame.remove(&self.1);
+            declared.remove(&self.1);
+        }
+        self.0.refinements.truncate(self.3);
     }
 }
 
@@ -32,28 +103,58 @@ pub enum LocalSlot<F, V> {
 
 impl<F, V> LocalEnv<F, V> {
     pub fn new(base: HashMap<String, LocalSlot<F, V>>) -> LocalEnv<F, V> {
-        LocalEnv(vec![base])
+        LocalEnv {
+            frames: vec![base],
+            declared: vec![HashMap::new()],
+            resolutions: vec![],
+            pinned: vec![HashSet::new()],
+            refinements: vec![],
+            constants: HashSet::new(),
+            type_params: vec![],
+        }
+    }
+
+    pub fn set_constants(&mut self, names: HashSet<String>) {
+        self.constants = names;
+    }
+
+    pub fn set_type_params(&mut self, names: &[String]) {
+        self.type_params = names.to_vec();
+    }
+
+    pub fn type_params(&self) -> &[String] {
+        &self.type_params
     }
 
-    pub fn get(&self, name: &str) -> Option<EnvSlot<F, V>> {
-        match self.0.last().unwrap().get(name) {
-            Some(LocalSlot::Var(t)) => Some(EnvSlot::Var(t, Assignable(true))),
+    // Whether the name refers to a constant of the base frame
+    pub fn is_constant(&self, name: &str) -> bool {
+        self.constants.contains(name)
+            && (self.frames.len() == 1
+                || matches!(self.frames.last().unwrap().get(name), Some(LocalSlot::Global)))
+    }
+
+    pub fn get(&self, name: &str) -> Option<EnvSlot<'_, F, V>> {
+        match self.frames.last().unwrap().get(name) {
+            Some(LocalSlot::Var(t)) => {
+                let t = self.get_refinement(name).unwrap_or(t);
+                Some(EnvSlot::Var(t, Assignable(!self.is_constant(name))))
+            }
             Some(LocalSlot::Func(t)) => Some(EnvSlot::Func(t)),
             Some(LocalSlot::Global) => {
-                let t = if let Some(LocalSlot::Var(t)) = &self.0[0].get(name) {
+                let t = if let Some(LocalSlot::Var(t)) = &self.frames[0].get(name) {
                     t
                 } else {
                     panic!()
                 };
-                Some(EnvSlot::Var(t, Assignable(true)))
+                Some(EnvSlot::Var(t, Assignable(!self.is_constant(name))))
             }
             s @ Some(LocalSlot::NonLocal) | s @ None => {
-                for frame in self.0[0..self.0.len() - 1].iter().rev() {
+                for frame in self.frames[0..self.frames.len() - 1].iter().rev() {
                     match frame.get(name) {
                         Some(LocalSlot::NonLocal) | None => (),
                         Some(LocalSlot::Global) => {
                             assert!(s.is_none());
-                            let t = if let Some(LocalSlot::Var(t)) = &self.0[0].get(name) {
+                            let t = if let Some(LocalSlot::Var(t)) = &self.frames[0].get(name) {
                                 t
                             } else {
                                 panic!()
@@ -74,8 +175,148 @@ impl<F, V> LocalEnv<F, V> {
         }
     }
 
-    pub fn push(&mut self, frame: HashMap<String, LocalSlot<F, V>>) -> FrameHandle<F, V> {
-        self.0.push(frame);
+    // Names of the variables visible from the current frame
+    pub fn variable_names(&self) -> Vec<&str> {
+        self.names(|slot| matches!(slot, EnvSlot::Var(..)))
+    }
+
+    // Names of the functions and classes visible from the current frame
+    pub fn function_names(&self) -> Vec<&str> {
+        self.names(|slot| matches!(slot, EnvSlot::Func(_)))
+    }
+
+    fn names(&self, filter: fn(&EnvSlot<F, V>) -> bool) -> Vec<&str> {
+        let mut names: HashSet<&str> = HashSet::new();
+        for frame in &self.frames {
+            names.extend(frame.keys().map(String::as_str));
+        }
+        names.retain(|name| self.get(name).is_some_and(|slot| filter(&slot)));
+        names.into_iter().collect()
+    }
+
+    // A variable of the current frame only, not looked up in enclosing scopes
+    pub fn get_local(&self, name: &str) -> Option<&V> {
+        match self.frames.last().unwrap().get(name) {
+            Some(LocalSlot::Var(t)) => Some(t),
+            _ => None,
+        }
+    }
+
+    // Bind a variable declared at `location` in a nested scope of the current
+    // frame, such as the variable of a comprehension, until the handle is
+    // dropped. Refinements of the variable it shadows don't apply.
+    pub fn bind(&mut self, name: String, t: V, location: Location) -> ScopeHandle<'_, F, V> {
+        let len = self.refinements.len();
+        let declared = self.declared.last_mut().unwrap().insert(name.clone(), location);
+        let shadowed = self
+            .frames
+            .last_mut()
+            .unwrap()
+            .insert(name.clone(), LocalSlot::Var(t))
+            .map(|slot| (slot, declared));
+        if self.get_refinement(&name).is_some() {
+            self.refinements.push((self.frames.len(), name.clone(), None));
+        }
+        ScopeHandle(self, name, shadowed, len)
+    }
+
+    // Whether the name is redefined by any frame above the base
+    pub fn is_shadowed(&self, name: &str) -> bool {
+        self.frames[1..].iter().any(|frame| frame.contains_key(name))
+    }
+
+    pub fn push(&mut self, frame: HashMap<String, LocalSlot<F, V>>) -> FrameHandle<'_, F, V> {
+        self.frames.push(frame);
+        self.declared.push(HashMap::new());
+        self.pinned.push(HashSet::new());
         FrameHandle(self)
     }
+
+    // Record where a name of the current frame is declared
+    pub fn declare(&mut self, name: String, location: Location) {
+        self.declared.last_mut().unwrap().insert(name, location);
+    }
+
+    // Where the name visible from the current frame is declared. A name of an
+    // enclosing frame resolves to its `nonlocal` or `global` declaration there,
+    // if any.
+    pub fn declaration(&self, name: &str) -> Option<Location> {
+        self.declared.iter().rev().find_map(|frame| frame.get(name).copied())
+    }
+
+    // Record a use of a name at `location`, resolved in the current frame
+    pub fn resolve(&mut self, name: &str, location: Location) {
+        let declaration = self.declaration(name);
+        self.resolve_to(name, location, declaration);
+    }
+
+    // Record a use of a name resolved elsewhere, such as a class member
+    pub fn resolve_to(&mut self, name: &str, location: Location, declaration: Option<Location>) {
+        self.resolutions.push(Resolution {
+            name: name.to_owned(),
+            location,
+            declaration,
+        });
+    }
+
+    pub fn take_resolutions(&mut self) -> Vec<Resolution> {
+        std::mem::take(&mut self.resolutions)
+    }
+
+    // Exclude names of the current frame from refinement
+    pub fn pin(&mut self, names: impl IntoIterator<Item = String>) {
+        self.pinned.last_mut().unwrap().extend(names);
+    }
+
+    // Whether a variable can be refined: it must be a variable of the current
+    // frame, a local variable or parameter of a function or a global variable in
+    // top-level code, and not pinned
+    pub fn is_refinable(&self, name: &str) -> bool {
+        matches!(self.frames.last().unwrap().get(name), Some(LocalSlot::Var(_)))
+            && !self.pinned.last().unwrap().contains(name)
+    }
+
+    // Drop the refinements of global variables in top-level code, e.g. after a
+    // call to a function that may assign them. Unlike `reset`, this outlasts
+    // the handles of the refinements made since.
+    pub fn reset_globals(&mut self) {
+        if self.frames.len() == 1 {
+            for (_, _, t) in &mut self.refinements {
+                *t = None;
+            }
+        }
+    }
+
+    // Refine the types of local variables until the handle is dropped.
+    // Facts about non-refinable variables are ignored.
+    pub fn refine(&mut self, facts: Vec<(String, V)>) -> RefineHandle<'_, F, V> {
+        let len = self.refinements.len();
+        let depth = self.frames.len();
+        for (name, t) in facts {
+            if self.is_refinable(&name) {
+                self.refinements.push((depth, name, Some(t)));
+            }
+        }
+        RefineHandle(self, len)
+    }
+
+    // Drop refinements of the variables, e.g. after they are reassigned.
+    // This lasts until the handle of the refinement is dropped.
+    pub fn reset(&mut self, names: impl IntoIterator<Item = String>) {
+        let depth = self.frames.len();
+        for name in names {
+            if self.get_refinement(&name).is_some() {
+                self.refinements.push((depth, name, None));
+            }
+        }
+    }
+
+    fn get_refinement(&self, name: &str) -> Option<&V> {
+        let depth = self.frames.len();
+        self.refinements
+            .iter()
+            .rev()
+            .find(|(d, n, _)| *d == depth && n == name)
+            .and_then(|(_, _, t)| t.as_ref())
+    }
 }
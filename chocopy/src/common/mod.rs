@@ -1,4 +1,8 @@
+pub mod diagnostic;
 pub mod local_env;
 pub mod location;
 pub mod node;
 pub mod object;
+pub mod render;
+#[cfg(test)]
+pub mod test_files;
@@ -1,7 +1,8 @@
+use crate::common::diagnostic::Message;
 use crate::common::location::*;
 use enum_dispatch::*;
 use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 use std::fmt::{self, Display, Formatter};
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
@@ -40,13 +41,16 @@ pub trait Node {
     fn base(&self) -> &NodeBase;
     fn base_mut(&mut self) -> &mut NodeBase;
 
-    fn add_error(&mut self, errors: &mut Vec<CompilerError>, message: String) {
+    fn add_error(&mut self, errors: &mut Vec<CompilerError>, message: Message) {
         let base = self.base_mut();
-        base.error_msg = Some(message.clone());
+        base.error_msg = Some(message.text.clone());
         errors.push(CompilerError {
             base: NodeBase::from_location(base.location),
-            message,
+            message: message.text,
             syntax: false,
+            severity: Severity::Error,
+            flag: None,
+            code: Some(message.code.id.to_owned()),
         })
     }
 }
@@ -105,6 +109,18 @@ pub enum BinaryOp {
     Div,
     #[serde(rename = "%")]
     Mod,
+    #[serde(rename = "**")]
+    Pow,
+    #[serde(rename = "&")]
+    BitAnd,
+    #[serde(rename = "|")]
+    BitOr,
+    #[serde(rename = "^")]
+    BitXor,
+    #[serde(rename = "<<")]
+    Shl,
+    #[serde(rename = ">>")]
+    Shr,
     #[serde(rename = "==")]
     Eq,
     #[serde(rename = "!=")]
@@ -119,6 +135,8 @@ pub enum BinaryOp {
     Ge,
     #[serde(rename = "is")]
     Is,
+    #[serde(rename = "in")]
+    In,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
@@ -143,6 +161,15 @@ pub struct BooleanLiteral {
 
 impl_node!(BooleanLiteral);
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BreakStmt {
+    #[serde(flatten)]
+    pub base: NodeBase,
+}
+
+impl_node!(BreakStmt);
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct CallExpr {
@@ -150,6 +177,12 @@ pub struct CallExpr {
     pub base: NodeBase,
     pub function: Function,
     pub args: Vec<Expr>,
+    // Explicit type arguments of a generic class constructor: `Box[int]()`
+    #[serde(rename = "typeArgs", default, skip_serializing_if = "Vec::is_empty")]
+    pub type_args: Vec<TypeAnnotation>,
+    // `name=value` arguments following the positional ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<KeywordArg>,
 }
 
 impl_node!(CallExpr);
@@ -162,6 +195,9 @@ pub struct ClassDef {
     pub name: Identifier,
     #[serde(rename = "superClass")]
     pub super_class: Identifier,
+    // Type parameters declared by `Generic[T, ...]`
+    #[serde(rename = "typeParams", default, skip_serializing_if = "Vec::is_empty")]
+    pub type_params: Vec<Identifier>,
     pub declarations: Vec<Declaration>,
 }
 
@@ -174,6 +210,8 @@ pub struct ClassType {
     pub base: NodeBase,
     #[serde(rename = "className")]
     pub class_name: String,
+    #[serde(rename = "typeArgs", default, skip_serializing_if = "Vec::is_empty")]
+    pub type_args: Vec<TypeAnnotation>,
 }
 
 impl_node!(ClassType);
@@ -183,11 +221,24 @@ impl_node!(ClassType);
 pub struct ClassValueType {
     #[serde(rename = "className")]
     pub class_name: String,
+    #[serde(rename = "typeArgs", default, skip_serializing_if = "Vec::is_empty")]
+    pub type_args: Vec<ValueType>,
 }
 
 impl Display for ClassValueType {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "{}", &self.class_name)
+        write!(f, "{}", &self.class_name)?;
+        if !self.type_args.is_empty() {
+            write!(f, "[")?;
+            for (i, arg) in self.type_args.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", arg)?;
+            }
+            write!(f, "]")?;
+        }
+        Ok(())
     }
 }
 
@@ -196,6 +247,21 @@ fn is_not(b: &bool) -> bool {
     !*b
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+    // Additional information attached to the preceding diagnostic
+    Note,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)] // Function signature is required by serde
+fn is_error(severity: &Severity) -> bool {
+    *severity == Severity::Error
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(tag = "kind")]
 pub struct CompilerError {
@@ -204,10 +270,47 @@ pub struct CompilerError {
     pub message: String,
     #[serde(default, skip_serializing_if = "is_not")]
     pub syntax: bool,
+    #[serde(default, skip_serializing_if = "is_error")]
+    pub severity: Severity,
+    // The `-W` name of the warning that produced this diagnostic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flag: Option<String>,
+    // The stable code of the diagnostic, such as `E0101`; notes have none. It
+    // is reported by the renderer and the JSON and SARIF diagnostics, and is not
+    // part of the AST.
+    #[serde(skip)]
+    pub code: Option<String>,
 }
 
 impl_node!(CompilerError);
 
+impl CompilerError {
+    // A note pointing at a related location, attached to the diagnostic before it
+    pub fn note(location: Location, message: String) -> CompilerError {
+        CompilerError {
+            base: NodeBase::from_location(location),
+            message,
+            syntax: false,
+            severity: Severity::Note,
+            flag: None,
+            code: None,
+        }
+    }
+}
+
+// One "for identifier in iterable if condition ..." part of a list comprehension
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(tag = "kind")]
+pub struct ComprehensionClause {
+    #[serde(flatten)]
+    pub base: NodeBase,
+    pub identifier: ForTarget,
+    pub iterable: Expr,
+    pub conditions: Vec<Expr>,
+}
+
+impl_node!(ComprehensionClause);
+
 #[allow(clippy::large_enum_variant)]
 #[enum_dispatch(Node)]
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
@@ -221,6 +324,19 @@ pub enum Declaration {
 }
 
 impl Declaration {
+    pub fn name(&self) -> &Identifier {
+        match self {
+            Declaration::ClassDef(ClassDef { name, .. }) => name,
+            Declaration::FuncDef(FuncDef { name, .. }) => name,
+            Declaration::GlobalDecl(GlobalDecl { variable, .. }) => variable,
+            Declaration::NonLocalDecl(NonLocalDecl { variable, .. }) => variable,
+            Declaration::VarDef(VarDef {
+                var: TypedVar { identifier, .. },
+                ..
+            }) => identifier,
+        }
+    }
+
     pub fn name_mut(&mut self) -> &mut Identifier {
         match self {
             Declaration::ClassDef(ClassDef { name, .. }) => name,
@@ -235,19 +351,48 @@ impl Declaration {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DelStmt {
+    #[serde(flatten)]
+    pub base: NodeBase,
+    pub targets: Vec<Expr>,
+}
+
+impl_node!(DelStmt);
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(tag = "kind")]
 pub struct Errors {
     #[serde(flatten)]
     pub base: NodeBase,
+    // Notes are reported with the diagnostic before them by the renderer and the
+    // diagnostics formats, and are not part of the AST
+    #[serde(serialize_with = "serialize_without_notes")]
     pub errors: Vec<CompilerError>,
 }
 
+fn serialize_without_notes<S>(errors: &[CompilerError], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    s.collect_seq(errors.iter().filter(|error| error.severity != Severity::Note))
+}
+
 impl_node!(Errors);
 
 impl Errors {
     pub fn sort(&mut self) {
-        self.errors.sort_by_key(|error| error.base().location);
+        // Notes stay after the diagnostic they belong to
+        let mut groups: Vec<Vec<CompilerError>> = vec![];
+        for error in self.errors.drain(..) {
+            match groups.last_mut() {
+                Some(group) if error.severity == Severity::Note => group.push(error),
+                _ => groups.push(vec![error]),
+            }
+        }
+        groups.sort_by_key(|group| group[0].base().location);
+        self.errors = groups.into_iter().flatten().collect();
     }
 }
 
@@ -298,10 +443,13 @@ impl Expr {
     expr_init!(Variable, Variable);
     expr_init!(IfExpr, Box<IfExpr>);
     expr_init!(IndexExpr, Box<IndexExpr>);
+    expr_init!(ListComprehension, Box<ListComprehension>);
     expr_init!(ListExpr, ListExpr);
     expr_init!(MemberExpr, Box<MemberExpr>);
     expr_init!(MethodCallExpr, Box<MethodCallExpr>);
     expr_init!(NoneLiteral, NoneLiteral);
+    expr_init!(SetExpr, SetExpr);
+    expr_init!(SliceExpr, Box<SliceExpr>);
     expr_init!(StringLiteral, StringLiteral);
     expr_init!(UnaryExpr, Box<UnaryExpr>);
 }
@@ -318,10 +466,13 @@ pub enum ExprContent {
     Variable(Variable),
     IfExpr(Box<IfExpr>),
     IndexExpr(Box<IndexExpr>),
+    ListComprehension(Box<ListComprehension>),
     ListExpr(ListExpr),
     MemberExpr(Box<MemberExpr>),
     MethodCallExpr(Box<MethodCallExpr>),
     NoneLiteral(NoneLiteral),
+    SetExpr(SetExpr),
+    SliceExpr(Box<SliceExpr>),
     StringLiteral(StringLiteral),
     UnaryExpr(Box<UnaryExpr>),
 }
@@ -344,10 +495,20 @@ pub struct ForStmt {
     pub identifier: ForTarget,
     pub iterable: Expr,
     pub body: Vec<Stmt>,
+    // Runs when the loop finishes
+    #[serde(rename = "elseBody", default, skip_serializing_if = "Option::is_none")]
+    pub else_body: Option<Vec<Stmt>>,
 }
 
 impl_node!(ForStmt);
 
+impl ForStmt {
+    /// The statements of the `else` clause, if any
+    pub fn else_body(&self) -> &[Stmt] {
+        self.else_body.as_deref().unwrap_or_default()
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct FuncDef {
@@ -363,6 +524,22 @@ pub struct FuncDef {
 
 impl_node!(FuncDef);
 
+impl FuncDef {
+    /// Whether the function body contains a `yield` statement
+    pub fn is_generator(&self) -> bool {
+        fn contains_yield(statements: &[Stmt]) -> bool {
+            statements.iter().any(|statement| match statement {
+                Stmt::YieldStmt(_) => true,
+                Stmt::ForStmt(s) => contains_yield(&s.body) || contains_yield(s.else_body()),
+                Stmt::WhileStmt(s) => contains_yield(&s.body) || contains_yield(s.else_body()),
+                Stmt::IfStmt(s) => contains_yield(&s.then_body) || contains_yield(&s.else_body),
+                _ => false,
+            })
+        }
+        contains_yield(&self.statements)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(tag = "kind")]
 pub struct FuncType {
@@ -462,6 +639,28 @@ pub struct IntegerLiteral {
 
 impl_node!(IntegerLiteral);
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(tag = "kind")]
+pub struct KeywordArg {
+    #[serde(flatten)]
+    pub base: NodeBase,
+    pub name: Identifier,
+    pub value: Expr,
+}
+
+impl_node!(KeywordArg);
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ListComprehension {
+    #[serde(flatten)]
+    pub base: NodeBase,
+    pub element: Expr,
+    pub clauses: Vec<ComprehensionClause>,
+}
+
+impl_node!(ListComprehension);
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct ListExpr {
@@ -607,6 +806,30 @@ pub struct NonLocalDecl {
 
 impl_node!(NonLocalDecl);
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct OptionalType {
+    #[serde(flatten)]
+    pub base: NodeBase,
+    #[serde(rename = "elementType")]
+    pub element_type: TypeAnnotation,
+}
+
+impl_node!(OptionalType);
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct OptionalValueType {
+    #[serde(rename = "elementType")]
+    pub element_type: Box<ValueType>,
+}
+
+impl Display for OptionalValueType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Optional[{}]", self.element_type)
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(tag = "kind")]
 pub struct Program {
@@ -629,6 +852,28 @@ pub struct ReturnStmt {
 
 impl_node!(ReturnStmt);
 
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SetExpr {
+    #[serde(flatten)]
+    pub base: NodeBase,
+    pub elements: Vec<Expr>,
+}
+
+impl_node!(SetExpr);
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SliceExpr {
+    #[serde(flatten)]
+    pub base: NodeBase,
+    pub list: Expr,
+    pub start: Option<Expr>,
+    pub end: Option<Expr>,
+}
+
+impl_node!(SliceExpr);
+
 #[allow(clippy::enum_variant_names)]
 #[enum_dispatch(Node)]
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
@@ -640,6 +885,9 @@ pub enum Stmt {
     IfStmt(IfStmt),
     ReturnStmt(ReturnStmt),
     WhileStmt(WhileStmt),
+    YieldStmt(YieldStmt),
+    DelStmt(DelStmt),
+    BreakStmt(BreakStmt),
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
@@ -655,18 +903,11 @@ impl_node!(StringLiteral);
 #[enum_dispatch(Node)]
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(tag = "kind", deny_unknown_fields)]
+#[allow(clippy::enum_variant_names)] // Names are the serialized `kind` tags
 pub enum TypeAnnotation {
     ClassType(ClassType),
     ListType(Box<ListType>),
-}
-
-impl TypeAnnotation {
-    pub fn core_type_mut(&mut self) -> &mut ClassType {
-        match self {
-            TypeAnnotation::ClassType(c) => c,
-            TypeAnnotation::ListType(l) => l.element_type.core_type_mut(),
-        }
-    }
+    OptionalType(Box<OptionalType>),
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
@@ -708,6 +949,8 @@ pub enum UnaryOp {
     Negative,
     #[serde(rename = "not")]
     Not,
+    #[serde(rename = "~")]
+    Invert,
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
@@ -723,9 +966,11 @@ impl_node!(UnaryExpr);
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(tag = "kind", deny_unknown_fields)]
+#[allow(clippy::enum_variant_names)] // Names are the serialized `kind` tags
 pub enum ValueType {
     ClassValueType(ClassValueType),
     ListValueType(ListValueType),
+    OptionalValueType(OptionalValueType),
 }
 
 impl Display for ValueType {
@@ -733,6 +978,7 @@ impl Display for ValueType {
         match self {
             ValueType::ClassValueType(v) => v.fmt(f),
             ValueType::ListValueType(v) => v.fmt(f),
+            ValueType::OptionalValueType(v) => v.fmt(f),
         }
     }
 }
@@ -742,10 +988,22 @@ impl ValueType {
         match t {
             TypeAnnotation::ClassType(c) => ValueType::ClassValueType(ClassValueType {
                 class_name: c.class_name.clone(),
+                type_args: c.type_args.iter().map(ValueType::from_annotation).collect(),
             }),
             TypeAnnotation::ListType(c) => ValueType::ListValueType(ListValueType {
                 element_type: Box::new(ValueType::from_annotation(&c.element_type)),
             }),
+            TypeAnnotation::OptionalType(o) => ValueType::OptionalValueType(OptionalValueType {
+                element_type: Box::new(ValueType::from_annotation(&o.element_type)),
+            }),
+        }
+    }
+
+    // The type without the `Optional` wrapper, if any
+    pub fn non_optional(&self) -> &ValueType {
+        match self {
+            ValueType::OptionalValueType(o) => &o.element_type,
+            t => t,
         }
     }
 }
@@ -757,6 +1015,9 @@ pub struct VarDef {
     pub base: NodeBase,
     pub var: TypedVar,
     pub value: Literal,
+    // Declared as `Final[type]`, which cannot be reassigned
+    #[serde(rename = "final", default, skip_serializing_if = "is_not")]
+    pub is_final: bool,
 }
 
 impl_node!(VarDef);
@@ -768,38 +1029,64 @@ pub struct WhileStmt {
     pub base: NodeBase,
     pub condition: Expr,
     pub body: Vec<Stmt>,
+    // Runs when the loop finishes
+    #[serde(rename = "elseBody", default, skip_serializing_if = "Option::is_none")]
+    pub else_body: Option<Vec<Stmt>>,
 }
 
 impl_node!(WhileStmt);
 
+impl WhileStmt {
+    /// The statements of the `else` clause, if any
+    pub fn else_body(&self) -> &[Stmt] {
+        self.else_body.as_deref().unwrap_or_default()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct YieldStmt {
+    #[serde(flatten)]
+    pub base: NodeBase,
+    pub value: Option<Expr>,
+}
+
+impl_node!(YieldStmt);
+
 pub static TYPE_OBJECT: Lazy<ValueType> = Lazy::new(|| {
     ValueType::ClassValueType(ClassValueType {
         class_name: "object".to_owned(),
+        type_args: vec![],
     })
 });
 pub static TYPE_NONE: Lazy<ValueType> = Lazy::new(|| {
     ValueType::ClassValueType(ClassValueType {
         class_name: "<None>".to_owned(),
+        type_args: vec![],
     })
 });
 pub static TYPE_EMPTY: Lazy<ValueType> = Lazy::new(|| {
     ValueType::ClassValueType(ClassValueType {
         class_name: "<Empty>".to_owned(),
+        type_args: vec![],
     })
 });
 pub static TYPE_STR: Lazy<ValueType> = Lazy::new(|| {
     ValueType::ClassValueType(ClassValueType {
         class_name: "str".to_owned(),
+        type_args: vec![],
     })
 });
 pub static TYPE_INT: Lazy<ValueType> = Lazy::new(|| {
     ValueType::ClassValueType(ClassValueType {
         class_name: "int".to_owned(),
+        type_args: vec![],
     })
 });
 pub static TYPE_BOOL: Lazy<ValueType> = Lazy::new(|| {
     ValueType::ClassValueType(ClassValueType {
         class_name: "bool".to_owned(),
+        type_args: vec![],
     })
 });
 pub static TYPE_NONE_LIST: Lazy<ValueType> = Lazy::new(|| {
@@ -826,12 +1113,14 @@ mod tests {
                     type_: TypeAnnotation::ClassType(ClassType {
                         base: NodeBase::new(0, 0, 0, 0),
                         class_name: "a".to_owned(),
+                        type_args: vec![],
                     }),
                 },
                 value: Literal::BooleanLiteral(BooleanLiteral {
                     base: NodeBase::new(0, 0, 0, 0),
                     value: true,
                 }),
+                is_final: false,
             })],
             statements: vec![Stmt::ExprStmt(ExprStmt {
                 base: NodeBase::new(1, 1, 1, 9),
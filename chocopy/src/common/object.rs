@@ -7,6 +7,7 @@ pub enum Type {
     Int = 1,
     Bool = 2,
     Str = 3,
+    Set = 4,
     ValueList = -1, // Represents list of primitives (i.e ints, bools).
     ObjList = -2,   // Represents list of object references (i.e strings, custom objects).
 }
@@ -22,14 +23,18 @@ pub struct Prototype {
     // This is a pointer to a bitmap representing the member variables of the object,
     // if the nth position is 1, then the nth member variable is a reference to another object.
     pub reference_bitmap: *const u8,
+
+    // The prototype of the super class, or null for `object`.
+    pub super_prototype: *const Prototype,
     // ... Object method pointers (right after header in memory).
 }
 pub const PROTOTYPE_SIZE_OFFSET: u32 = 0;
 pub const PROTOTYPE_TAG_OFFSET: u32 = PROTOTYPE_SIZE_OFFSET + 4;
 pub const PROTOTYPE_MAP_OFFSET: u32 = PROTOTYPE_TAG_OFFSET + 4;
-pub const PROTOTYPE_INIT_OFFSET: u32 = PROTOTYPE_MAP_OFFSET + FUNCTION_POINTER_SIZE;
+pub const PROTOTYPE_SUPER_OFFSET: u32 = PROTOTYPE_MAP_OFFSET + POINTER_SIZE;
+pub const PROTOTYPE_INIT_OFFSET: u32 = PROTOTYPE_SUPER_OFFSET + POINTER_SIZE;
 pub const OBJECT_PROTOTYPE_SIZE: u32 = PROTOTYPE_INIT_OFFSET + FUNCTION_POINTER_SIZE;
-pub const NUM_PROTOTYPE_HEADERS: u32 = 3;
+pub const NUM_PROTOTYPE_HEADERS: u32 = 4;
 
 pub const OBJECT_PROTOTYPE_OFFSET: u32 = 0;
 pub const OBJECT_GC_COUNT_OFFSET: u32 = OBJECT_PROTOTYPE_OFFSET + 8;
@@ -40,6 +45,22 @@ pub const NUM_OBJECT_HEADERS: u32 = 3;
 pub const ARRAY_LEN_OFFSET: u32 = OBJECT_ATTRIBUTE_OFFSET;
 pub const ARRAY_ELEMENT_OFFSET: u32 = ARRAY_LEN_OFFSET + 8;
 
+// Set objects hold their length where arrays do, followed by the hash table
+// and the prototype to allocate the table with.
+pub const SET_LEN_OFFSET: u32 = ARRAY_LEN_OFFSET;
+pub const SET_TABLE_OFFSET: u32 = SET_LEN_OFFSET + 8;
+pub const SET_TABLE_PROTOTYPE_OFFSET: u32 = SET_TABLE_OFFSET + POINTER_SIZE;
+pub const SET_SIZE: u32 = SET_TABLE_PROTOTYPE_OFFSET + POINTER_SIZE - OBJECT_ATTRIBUTE_OFFSET;
+
+// Generator objects hold the resume state followed by the saved stack frame slots.
+// Each slot is saved as a pair of (reference, plain value), so that only the
+// reference half is marked in the prototype bitmap.
+pub const GENERATOR_STATE_OFFSET: u32 = OBJECT_ATTRIBUTE_OFFSET;
+pub const GENERATOR_SLOT_OFFSET: u32 = GENERATOR_STATE_OFFSET + 8;
+pub const GENERATOR_SLOT_SIZE: u32 = 16;
+// The resume function is stored after `__init__` in the prototype
+pub const GENERATOR_RESUME_OFFSET: u32 = PROTOTYPE_INIT_OFFSET + FUNCTION_POINTER_SIZE;
+
 #[repr(C)] // Makes sure the struct is not reordered by the Rust compiler.
 pub struct InitParam {
     pub bottom_frame: *const u64, // Stack base pointer, used for stack walking.
@@ -55,3 +76,7 @@ pub const GLOBAL_SIZE_OFFSET: u32 = GLOBAL_SECTION_OFFSET + POINTER_SIZE;
 pub const GLOBAL_MAP_OFFSET: u32 = GLOBAL_SIZE_OFFSET + 8;
 pub const STR_PROTOTYPE_OFFSET: u32 = GLOBAL_MAP_OFFSET + POINTER_SIZE;
 pub const INIT_PARAM_SIZE: u32 = std::mem::size_of::<InitParam>() as u32;
+
+// Flags of `$print`
+pub const PRINT_STDERR: u64 = 1; // Write to standard error instead of standard output
+pub const PRINT_FLUSH: u64 = 2; // Flush the stream after writing
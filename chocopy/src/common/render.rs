@@ -0,0 +1,270 @@
+// Human-readable rendering of diagnostics, in the style of rustc: a header with
+// the severity and code, then the source lines of the diagnostic and its notes
+// with their spans underlined.
+
+use crate::common::diagnostic::with_notes;
+use crate::common::location::Location;
+use crate::common::node::{CompilerError, Severity};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const GREEN: &str = "\x1b[1;32m";
+const BLUE: &str = "\x1b[1;34m";
+
+// Spans of more lines than this only show their first and last lines
+const MAX_SPAN_LINES: u32 = 4;
+
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("{}{}{}", color, text, RESET)
+        } else {
+            text.to_owned()
+        }
+    }
+}
+
+// One underlined part of a source line
+struct Label<'a> {
+    start: usize,
+    end: usize,
+    primary: bool,
+    text: Option<&'a str>,
+}
+
+fn severity_style(severity: Severity) -> (&'static str, &'static str) {
+    match severity {
+        Severity::Error => ("error", RED),
+        Severity::Warning => ("warning", YELLOW),
+        Severity::Note => ("note", GREEN),
+    }
+}
+
+// Add the labels of a span to the rows it covers. Columns are 1-based and
+// inclusive, and a multi-line span underlines the text of its middle lines.
+fn add_span<'a>(
+    rows: &mut BTreeMap<u32, Vec<Label<'a>>>,
+    lines: &[&str],
+    location: Location,
+    primary: bool,
+    text: Option<&'a str>,
+) {
+    let Location { start, end } = location;
+    if start.row == 0 || start.row as usize > lines.len() {
+        return;
+    }
+    // A compound statement ends at the first column of the statement after it,
+    // so its span stops at the end of the line before
+    let end_row = if end.col == 1 && end.row > start.row {
+        end.row - 1
+    } else {
+        end.row
+    };
+    let end_row = end_row.clamp(start.row, lines.len() as u32);
+    for row in start.row..=end_row {
+        if row > start.row + 1 && row < end_row && end_row - start.row >= MAX_SPAN_LINES {
+            continue;
+        }
+        let line = lines[row as usize - 1];
+        let length = line.chars().count();
+        let first = if row == start.row {
+            start.col as usize
+        } else {
+            line.chars().take_while(|c| *c == ' ').count() + 1
+        };
+        let last = if row == end_row && end.row == end_row {
+            end.col as usize
+        } else {
+            length
+        };
+        rows.entry(row).or_default().push(Label {
+            start: first.max(1),
+            end: last.max(first).max(1),
+            primary,
+            text: if row == end_row { text } else { None },
+        });
+    }
+}
+
+fn render_one(
+    out: &mut String,
+    file: &str,
+    lines: &[&str],
+    error: &CompilerError,
+    notes: &[CompilerError],
+    style: &Style,
+) {
+    let (severity, color) = severity_style(error.severity);
+    let header = match &error.code {
+        Some(code) => format!("{}[{}]", severity, code),
+        None => severity.to_owned(),
+    };
+    writeln!(
+        out,
+        "{}{}",
+        style.paint(color, &header),
+        style.paint(BOLD, &format!(": {}", error.message))
+    )
+    .unwrap();
+
+    // Notes about the span itself are shown below the excerpt, and so are
+    // those whose lines are not available, with their position
+    let (footers, labels): (Vec<_>, Vec<_>) = notes.iter().partition(|note| {
+        note.base.location == error.base.location
+            || note.base.location.start.row as usize > lines.len()
+    });
+    let mut rows = BTreeMap::new();
+    add_span(&mut rows, lines, error.base.location, true, None);
+    for note in labels {
+        add_span(&mut rows, lines, note.base.location, false, Some(&note.message));
+    }
+    let width = rows.keys().last().map_or(1, |row| row.to_string().len());
+    let gutter = style.paint(BLUE, &format!("{} |", " ".repeat(width)));
+
+    let start = error.base.location.start;
+    writeln!(
+        out,
+        "{}{} {}:{}:{}",
+        " ".repeat(width),
+        style.paint(BLUE, "-->"),
+        file,
+        start.row,
+        start.col
+    )
+    .unwrap();
+    if !rows.is_empty() {
+        writeln!(out, "{}", gutter).unwrap();
+    }
+    let mut previous = None;
+    for (row, labels) in &mut rows {
+        labels.sort_by_key(|label| label.start);
+        if previous.is_some_and(|previous| row - previous > 1) {
+            writeln!(out, "{}", style.paint(BLUE, "...")).unwrap();
+        }
+        previous = Some(*row);
+        let number = style.paint(BLUE, &format!("{:>width$} |", row, width = width));
+        writeln!(out, "{} {}", number, lines[*row as usize - 1]).unwrap();
+        for label in labels {
+            let (mark, color) = if label.primary { ("^", color) } else { ("-", BLUE) };
+            let mut underline = mark.repeat(label.end - label.start + 1);
+            if let Some(text) = label.text {
+                underline = format!("{} {}", underline, text);
+            }
+            let padding = " ".repeat(label.start - 1);
+            writeln!(out, "{} {}{}", gutter, padding, style.paint(color, &underline)).unwrap();
+        }
+    }
+    let equals = style.paint(BLUE, "=");
+    for note in footers {
+        let mut message = note.message.clone();
+        if note.base.location != error.base.location {
+            let start = note.base.location.start;
+            write!(message, " ({}:{}:{})", file, start.row, start.col).unwrap();
+        }
+        writeln!(out, "{} {} note: {}", " ".repeat(width), equals, message).unwrap();
+    }
+    if let Some(flag) = &error.flag {
+        writeln!(out, "{} {} note: `-W{}` is enabled", " ".repeat(width), equals, flag).unwrap();
+    }
+    writeln!(out).unwrap();
+}
+
+// Render the diagnostics of a file with the given source. `color` adds ANSI
+// escape codes for terminals.
+pub fn render(file: &str, source: &str, errors: &[CompilerError], color: bool) -> String {
+    let lines: Vec<String> = source.lines().map(|line| line.replace('\t', " ")).collect();
+    let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+    let style = Style { color };
+    let mut out = String::new();
+    for (error, notes) in with_notes(errors) {
+        render_one(&mut out, file, &lines, error, notes, &style);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::node::NodeBase;
+
+    fn diagnostic(location: Location, message: &str, severity: Severity) -> CompilerError {
+        CompilerError {
+            base: NodeBase::from_location(location),
+            message: message.to_owned(),
+            syntax: false,
+            severity,
+            flag: None,
+            code: (severity != Severity::Note).then(|| "E0101".to_owned()),
+        }
+    }
+
+    #[test]
+    fn spans_and_notes() {
+        let source = "x:int = 1\ny:int = 2\nx:int = 3\nprint(x +\n      y)\n";
+        let errors = [
+            diagnostic(Location::new(3, 1, 3, 1), "Duplicate: x", Severity::Error),
+            diagnostic(Location::new(1, 1, 1, 1), "First declared here", Severity::Note),
+            diagnostic(Location::new(4, 7, 5, 7), "Multi-line", Severity::Error),
+        ];
+        let expected = "\
+error[E0101]: Duplicate: x
+ --> test.py:3:1
+  |
+1 | x:int = 1
+  | - First declared here
+...
+3 | x:int = 3
+  | ^
+
+error[E0101]: Multi-line
+ --> test.py:4:7
+  |
+4 | print(x +
+  |       ^^^
+5 |       y)
+  |       ^
+
+";
+        assert_eq!(render("test.py", source, &errors, false), expected);
+    }
+
+    #[test]
+    fn span_ending_at_dedent() {
+        let source = "if True:\n    x = 1\nprint(x)\n";
+        let errors = [diagnostic(Location::new(1, 1, 3, 1), "Compound", Severity::Error)];
+        let expected = "\
+error[E0101]: Compound
+ --> test.py:1:1
+  |
+1 | if True:
+  | ^^^^^^^^
+2 |     x = 1
+  |     ^^^^^
+
+";
+        assert_eq!(render("test.py", source, &errors, false), expected);
+    }
+
+    #[test]
+    fn missing_source() {
+        let errors = [
+            diagnostic(Location::new(3, 1, 3, 1), "Duplicate: x", Severity::Error),
+            diagnostic(Location::new(1, 1, 1, 1), "First declared here", Severity::Note),
+        ];
+        let expected = "\
+error[E0101]: Duplicate: x
+ --> test.ast:3:1
+  = note: First declared here (test.ast:1:1)
+
+";
+        assert_eq!(render("test.ast", "", &errors, false), expected);
+    }
+}
@@ -0,0 +1,58 @@
+// The programs of the `test` directory at the workspace root, shared by the
+// tests that check their output against the files next to them
+
+use super::node::Program;
+use serde::{Serialize, de::DeserializeOwned};
+use std::path::Path;
+
+// The contents of the files the results are compared with
+pub trait Expected: Serialize + DeserializeOwned {
+    // Put the contents in the order of the results
+    fn normalize(&mut self) {}
+}
+
+impl Expected for serde_json::Value {}
+
+impl Expected for Program {
+    // The errors of the typed ASTs in test/pa2 are not always sorted
+    fn normalize(&mut self) {
+        self.errors.sort();
+    }
+}
+
+// Check each file of a test directory whose name ends in `input` against the
+// file of the same name ending in `expected` instead. `f` gets the AST read from
+// the input and its path, and returns the result compared as JSON with the
+// expected file. Missing directories are skipped, as the original suite is
+// optional.
+pub fn check_files<E: Expected>(
+    dir: &str,
+    input: &str,
+    expected: &str,
+    mut f: impl FnMut(Program, &Path) -> E,
+) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../test").join(dir);
+    if !dir.is_dir() {
+        return;
+    }
+    let mut files = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|f| f.unwrap().path())
+        .filter(|f| f.file_name().unwrap().to_str().unwrap().ends_with(input))
+        .collect::<Vec<_>>();
+    files.sort();
+    for input_file in files {
+        let name = input_file.file_name().unwrap().to_str().unwrap();
+        let stem = name.strip_suffix(input).unwrap();
+        let expected_file = input_file.with_file_name(format!("{}{}", stem, expected));
+        let input_string = std::fs::read_to_string(&input_file).unwrap();
+        let expected_string = std::fs::read_to_string(expected_file).unwrap();
+        let ast = serde_json::from_str::<Program>(&input_string).unwrap();
+        let mut expected = serde_json::from_str::<E>(&expected_string).unwrap();
+        expected.normalize();
+        // Compare the serialized results, which leave out the codes of errors
+        let result = serde_json::to_value(f(ast, &input_file)).unwrap();
+        let expected = serde_json::to_value(expected).unwrap();
+        assert_eq!(result, expected, "{}", input_file.display());
+    }
+}
@@ -80,7 +80,7 @@ impl VecWriter for Vec<u8> {
         self.push(value)
     }
     fn align4(&mut self) {
-        while self.len() % 4 != 0 {
+        while !self.len().is_multiple_of(4) {
             self.push(0)
         }
     }
@@ -360,6 +360,12 @@ impl DebugWriter for Codeview {
         proto_fields.write_u16(PROTOTYPE_MAP_OFFSET as u16);
         proto_fields.write_str("$map");
 
+        proto_fields.write_u16(MEMBER);
+        proto_fields.write_u16(1); // private
+        proto_fields.write_u32(0x0603); // void* (64-bit)
+        proto_fields.write_u16(PROTOTYPE_SUPER_OFFSET as u16);
+        proto_fields.write_str("$super");
+
         for (&offset, (name, method)) in &class_debug.methods {
             let mut arg_list = vec![];
             arg_list.write_u32(method.params.len() as u32);
@@ -346,6 +346,14 @@ impl DebugWriter for Dwarf {
             PROTOTYPE_MAP_OFFSET as u64,
         );
 
+        dwarf_add_member(
+            &mut self.dwarf,
+            prototype_id,
+            "$super",
+            self.int_t_id,
+            PROTOTYPE_SUPER_OFFSET as u64,
+        );
+
         for (offset, (method, method_type)) in class_debug.methods {
             let method_type = self.add_method_type(method_type);
             dwarf_add_member(
@@ -24,13 +24,29 @@ const STR_PROTOTYPE: &str = "str.$proto";
 const BOOL_LIST_PROTOTYPE: &str = "[bool].$proto";
 const INT_LIST_PROTOTYPE: &str = "[int].$proto";
 const OBJECT_LIST_PROTOTYPE: &str = "[object].$proto";
+const ITERATOR_PROTOTYPE: &str = "Iterator.$proto";
+const SET_PROTOTYPE: &str = "set.$proto";
 
 // Standard library function symboles
 const BUILTIN_ALLOC_OBJ: &str = "$alloc_obj";
 const BUILTIN_DIV_ZERO: &str = "$div_zero";
 const BUILTIN_OUT_OF_BOUND: &str = "$out_of_bound";
 const BUILTIN_NONE_OP: &str = "$none_op";
+const BUILTIN_STOP_ITERATION: &str = "$stop_iteration";
+const BUILTIN_NEGATIVE_SHIFT: &str = "$negative_shift";
+const BUILTIN_NEGATIVE_EXPONENT: &str = "$negative_exponent";
 const BUILTIN_LEN: &str = "$len";
+const BUILTIN_LIST_DEL: &str = "$list_del";
+const BUILTIN_LIST_RESIZE: &str = "$list_resize";
+const BUILTIN_COMPARE: &str = "$compare";
+const BUILTIN_EQUAL: &str = "$equal";
+const BUILTIN_SET_ADD: &str = "$set_add";
+const BUILTIN_SET_REMOVE: &str = "$set_remove";
+const BUILTIN_SET_DISCARD: &str = "$set_discard";
+const BUILTIN_SET_CONTAINS: &str = "$set_contains";
+const BUILTIN_SET_UPDATE: &str = "$set_update";
+const BUILTIN_SET_RETAIN: &str = "$set_retain";
+const BUILTIN_SET_LIST: &str = "$set_list";
 const BUILTIN_INPUT: &str = "$input";
 const BUILTIN_PRINT: &str = "$print";
 const BUILTIN_INIT: &str = "$init";
@@ -43,6 +59,7 @@ const GLOBAL_SECTION: &str = "$global";
 const INIT_PARAM: &str = "$init_param";
 
 #[derive(PartialEq, Eq, Clone, Copy)]
+#[allow(dead_code)] // Only the host platform is constructed
 pub enum Platform {
     Windows,
     Linux,
@@ -86,6 +103,8 @@ impl TypeDebug {
                 type_debug.array_level += 1;
                 type_debug
             }
+            // Optional values share the representation of the wrapped type
+            TypeAnnotation::OptionalType(o) => TypeDebug::from_annotation(&o.element_type),
         }
     }
 }
@@ -240,14 +259,14 @@ impl CodeSet {
             .chain(self.globals_debug.iter().map(|global| &global.var_type))
             .chain(
                 self.classes_debug
-                    .iter()
-                    .flat_map(|(_, class)| class.used_types()),
+                    .values()
+                    .flat_map(|class| class.used_types()),
             )
     }
 
     // Collects all types appeared in the program, and returns representives
     // that have the highest array level.
-    fn used_types_representive(&self) -> impl Iterator<Item = TypeDebugRepresentive> {
+    fn used_types_representive(&self) -> impl Iterator<Item = TypeDebugRepresentive<'_>> {
         let mut array_level_map = HashMap::<&str, u32>::new();
         for type_used in self.used_types() {
             if let Some(array_level) = array_level_map.get_mut(type_used.core_name.as_str()) {
@@ -317,6 +336,7 @@ pub fn gen_object(
     source_path: &str,
     ast: Program,
     obj_path: &Path,
+    strict_none: bool,
     platform: Platform,
 ) -> std::result::Result<(), Box<dyn std::error::Error>> {
     let current_dir_buf = std::env::current_dir();
@@ -373,13 +393,27 @@ pub fn gen_object(
     import_function(&mut obj, BUILTIN_DIV_ZERO);
     import_function(&mut obj, BUILTIN_OUT_OF_BOUND);
     import_function(&mut obj, BUILTIN_NONE_OP);
+    import_function(&mut obj, BUILTIN_STOP_ITERATION);
+    import_function(&mut obj, BUILTIN_NEGATIVE_SHIFT);
+    import_function(&mut obj, BUILTIN_NEGATIVE_EXPONENT);
     import_function(&mut obj, BUILTIN_LEN);
+    import_function(&mut obj, BUILTIN_LIST_DEL);
+    import_function(&mut obj, BUILTIN_LIST_RESIZE);
+    import_function(&mut obj, BUILTIN_COMPARE);
+    import_function(&mut obj, BUILTIN_EQUAL);
+    import_function(&mut obj, BUILTIN_SET_ADD);
+    import_function(&mut obj, BUILTIN_SET_REMOVE);
+    import_function(&mut obj, BUILTIN_SET_DISCARD);
+    import_function(&mut obj, BUILTIN_SET_CONTAINS);
+    import_function(&mut obj, BUILTIN_SET_UPDATE);
+    import_function(&mut obj, BUILTIN_SET_RETAIN);
+    import_function(&mut obj, BUILTIN_SET_LIST);
     import_function(&mut obj, BUILTIN_PRINT);
     import_function(&mut obj, BUILTIN_INPUT);
     import_function(&mut obj, BUILTIN_INIT);
 
     // Generate machine code and debug info
-    let code_set = x64::gen_code_set(ast, platform);
+    let code_set = x64::gen_code_set(ast, strict_none, platform);
 
     // Feed type/class debug info to debug section generator
     for t in code_set.used_types_representive() {
@@ -655,9 +689,10 @@ pub fn link(
         }
         Platform::Linux | Platform::Macos => {
             let mut command = std::process::Command::new("cc");
+            if platform == Platform::Macos {
+                command.args(["-arch", "x86_64"]);
+            }
             command.args([
-                OsStr::new("-arch"),
-                OsStr::new("x86_64"),
                 OsStr::new("-o"),
                 OsStr::new(path),
                 obj_path.as_os_str(),
@@ -694,6 +729,7 @@ pub fn codegen(
     path: &str,
     no_link: bool,
     static_lib: bool,
+    strict_none: bool, // values of non-optional types are never None
     platform: Platform,
 ) -> std::result::Result<(), Box<dyn std::error::Error>> {
     let obj_path = if no_link {
@@ -706,7 +742,7 @@ pub fn codegen(
         obj_path
     };
 
-    gen_object(source_path, ast, &obj_path, platform)?;
+    gen_object(source_path, ast, &obj_path, strict_none, platform)?;
 
     if no_link {
         return Ok(());
@@ -718,3 +754,65 @@ pub fn codegen(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_files::check_files;
+    use crate::core::{frontend, typecheck};
+
+    // The calls to `$none_op` in the compiled program, leaving out the `next`
+    // builtin, which always checks its argument
+    fn none_checks(ast: &Program, strict_none: bool) -> usize {
+        let code_set = x64::gen_code_set(ast.clone(), strict_none, Platform::Linux);
+        code_set
+            .chunks
+            .iter()
+            .filter(|chunk| chunk.name != "next")
+            .flat_map(|chunk| &chunk.links)
+            .filter(|link| match &link.to {
+                ChunkLinkTarget::Symbol(name, _) => name == BUILTIN_NONE_OP,
+                ChunkLinkTarget::Data(_) => false,
+            })
+            .count()
+    }
+
+    // Only optional values are checked for None with `--strict-none`, and this
+    // program only uses them after narrowing
+    #[test]
+    fn strict_none() {
+        let typed_file = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../test/pa3/strict_none.py.ast.typed");
+        let typed_string = std::fs::read_to_string(typed_file).unwrap();
+        let ast = serde_json::from_str::<Program>(&typed_string).unwrap();
+        assert!(none_checks(&ast, false) > 0);
+        assert_eq!(none_checks(&ast, true), 0);
+    }
+
+    // The typed ASTs in test/pa3 compile as read by `--from-typed`. Checking
+    // them again gives the same AST, and `--no-recheck` finds no missing types.
+    #[test]
+    fn from_typed() {
+        check_files("pa3", ".ast.typed", ".ast.typed", |ast, path| {
+            assert!(typecheck::validate_typed(&ast).is_empty(), "{}", path.display());
+            let checked = typecheck::check(ast, false);
+            let result = serde_json::to_value(&checked).unwrap();
+            x64::gen_code_set(checked, false, Platform::Linux);
+            result
+        });
+    }
+
+    // A typed AST using a variable it doesn't declare has a type on every
+    // expression, and is rejected by checking it again before code generation
+    #[test]
+    fn from_typed_undeclared() {
+        let typed_file = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../test/pa3/loop_break.py.ast.typed");
+        let mut ast = frontend::read_json(typed_file.to_str().unwrap()).unwrap();
+        ast.declarations.retain(|declaration| declaration.name().name != "x");
+        assert!(typecheck::validate_typed(&ast).is_empty());
+        let errors = typecheck::check(ast, false).errors.errors;
+        let messages: Vec<_> = errors.iter().map(|e| e.message.as_str()).collect();
+        assert!(messages.contains(&"Not a variable: x"), "{:?}", messages);
+    }
+}
@@ -11,6 +11,7 @@ struct FuncSlot {
 struct VarSlot {
     offset: i32, // relative to global seciton or rbp
     level: u32,  // 0 = global variable
+    value: Option<i32>, // `Final` int or bool global, inlined without a slot
 }
 
 type StorageEnv = LocalEnv<FuncSlot, VarSlot>;
@@ -35,6 +36,7 @@ struct ClassSlot {
     object_size: u32, // excluding the object header
     methods: BTreeMap<String, MethodSlot>,
     prototype_size: u32,
+    super_class: Option<String>,
 }
 
 struct Emitter<'a> {
@@ -49,6 +51,24 @@ struct Emitter<'a> {
     code: Vec<u8>,
     links: Vec<ChunkLink>,
     platform: Platform,
+    strict_none: bool, // values of non-optional types are never None
+    generator: Option<GeneratorFrame>,
+    receiver: Option<String>, // `self` parameter of a method, the object of `super()` calls
+    scoped_vars: Vec<(String, i32)>, // comprehension variables and their offsets relative to rbp
+    breaks: Vec<Vec<ForwardJumper>>, // jumps of `break` statements out of each enclosing loop
+}
+
+// How a call reaches its callee
+enum Callee<'b> {
+    Function(&'b str), // global or nested function, by name
+    Virtual(&'b str),  // method looked up in the prototype of the first argument
+    Direct(&'b str),   // method of a known class, by link name
+}
+
+// Resume points of a generator function being emitted
+struct GeneratorFrame {
+    resume_points: Vec<BackwardJumper>, // state k resumes at resume_points[k]
+    slot_count: u32,                    // stack frame slots saved in the generator object
 }
 
 impl Platform {
@@ -81,7 +101,10 @@ struct StackTicket {
 
 impl Drop for StackTicket {
     fn drop(&mut self) {
-        panic!()
+        // Don't turn a panic while emitting code into an abort
+        if !std::thread::panicking() {
+            panic!()
+        }
     }
 }
 
@@ -114,6 +137,58 @@ impl ValueType {
     }
 }
 
+// Prototype of lists holding elements of the type
+fn list_prototype(element_type: &ValueType) -> &'static str {
+    if element_type == &*TYPE_INT {
+        INT_LIST_PROTOTYPE
+    } else if element_type == &*TYPE_BOOL {
+        BOOL_LIST_PROTOTYPE
+    } else {
+        OBJECT_LIST_PROTOTYPE
+    }
+}
+
+// The element type of `set[T]`
+fn set_element(t: &ValueType) -> Option<&ValueType> {
+    match t {
+        ValueType::ClassValueType(c) if c.class_name == "set" => c.type_args.first(),
+        _ => None,
+    }
+}
+
+// Prototype of the hash table of sets holding elements of the type
+fn set_table_prototype(element_type: &ValueType) -> &'static str {
+    if element_type.is_plain() {
+        INT_LIST_PROTOTYPE
+    } else {
+        OBJECT_LIST_PROTOTYPE
+    }
+}
+
+// The value of a `Final` int or bool global, as an immediate
+fn constant_value(decl: &VarDef) -> Option<i32> {
+    let target_type = ValueType::from_annotation(&decl.var.type_);
+    if !decl.is_final || !target_type.is_plain() {
+        return None;
+    }
+    match &decl.value.content {
+        LiteralContent::IntegerLiteral(i) => Some(i.value),
+        LiteralContent::BooleanLiteral(b) => Some(b.value as i32),
+        _ => None,
+    }
+}
+
+// Initial length of the buffer of a list comprehension
+const COMPREHENSION_CAPACITY: u32 = 8;
+
+// Where a list comprehension collects its elements
+struct ComprehensionBuffer<'b> {
+    element_type: &'b ValueType,
+    list: StackTicket,
+    count: StackTicket,
+    growable: bool, // the list is a buffer which may be full, instead of having the final length
+}
+
 impl<'a> Emitter<'a> {
     // Construct a simple machine code emitter for auto-generated functions
     pub fn new_simple(name: &str, platform: Platform) -> Emitter<'a> {
@@ -145,6 +220,11 @@ impl<'a> Emitter<'a> {
             code: vec![0x55, 0x48, 0x89, 0xe5, 0x48, 0x81, 0xEC, 0, 0, 0, 0],
             links: vec![],
             platform,
+            strict_none: false,
+            generator: None,
+            receiver: None,
+            scoped_vars: vec![],
+            breaks: vec![],
         }
     }
 
@@ -331,8 +411,76 @@ impl<'a> Emitter<'a> {
         self.emit_ref_map();
     }
 
-    // Ensure rax is not None
-    pub fn emit_check_none(&mut self) {
+    // Call into standard library to copy the list in rdi into a new one of length rsi
+    pub fn call_builtin_list_resize(&mut self) {
+        match self.platform {
+            Platform::Windows => {
+                // mov r8,rbp
+                self.emit(&[0x49, 0x89, 0xE8]);
+                // mov r9,rsp
+                self.emit(&[0x49, 0x89, 0xE1]);
+                // mov rdx,rsi
+                self.emit(&[0x48, 0x89, 0xF2]);
+                // mov rcx,rdi
+                self.emit(&[0x48, 0x89, 0xF9]);
+            }
+            Platform::Linux | Platform::Macos => {
+                // mov rdx,rbp
+                self.emit(&[0x48, 0x89, 0xEA]);
+                // mov rcx,rsp
+                self.emit(&[0x48, 0x89, 0xE1]);
+            }
+        }
+        self.prepare_call(self.platform.stack_reserve());
+        self.call(BUILTIN_LIST_RESIZE);
+        self.emit_ref_map();
+    }
+
+    // Call a set procedure of the standard library with the leading arguments
+    // loaded from the stack. Procedures that may allocate also get the frame.
+    fn call_builtin_set(&mut self, name: &str, args: &[&StackTicket], allocates: bool) {
+        let loads: [&[u8]; 2] = match self.platform {
+            // mov rcx,[rbp+{}]
+            // mov rdx,[rbp+{}]
+            Platform::Windows => [&[0x48, 0x8B, 0x8D], &[0x48, 0x8B, 0x95]],
+            // mov rdi,[rbp+{}]
+            // mov rsi,[rbp+{}]
+            Platform::Linux | Platform::Macos => [&[0x48, 0x8B, 0xBD], &[0x48, 0x8B, 0xB5]],
+        };
+        for (arg, load) in args.iter().zip(loads) {
+            self.emit_with_stack(load, arg);
+        }
+        if allocates {
+            match self.platform {
+                Platform::Windows => {
+                    // mov r8,rbp
+                    self.emit(&[0x49, 0x89, 0xE8]);
+                    // mov r9,rsp
+                    self.emit(&[0x49, 0x89, 0xE1]);
+                }
+                Platform::Linux | Platform::Macos => {
+                    // mov rdx,rbp
+                    self.emit(&[0x48, 0x89, 0xEA]);
+                    // mov rcx,rsp
+                    self.emit(&[0x48, 0x89, 0xE1]);
+                }
+            }
+        }
+        self.prepare_call(self.platform.stack_reserve());
+        self.call(name);
+        if allocates {
+            self.emit_ref_map();
+        }
+    }
+
+    // Ensure rax is not None, unless it is proven by its type
+    pub fn emit_check_none(&mut self, value_type: &ValueType) {
+        if self.strict_none
+            && *value_type != *TYPE_NONE
+            && !matches!(value_type, ValueType::OptionalValueType(_))
+        {
+            return;
+        }
         // test rax,rax
         self.emit(&[0x48, 0x85, 0xC0]);
         // jne
@@ -584,7 +732,7 @@ impl<'a> Emitter<'a> {
         };
 
         self.emit_expression(&expr.left);
-        self.emit_check_none();
+        self.emit_check_none(expr.left.get_type());
         // mov rsi,QWORD PTR [rax+ARRAY_LEN_OFFSET]
         self.emit(&[0x48, 0x8B, 0x70, ARRAY_LEN_OFFSET as u8]);
         let left = self.alloc_stack(TicketType::Reference);
@@ -594,7 +742,7 @@ impl<'a> Emitter<'a> {
         // mov [rbp+{}],rsi
         self.emit_with_stack(&[0x48, 0x89, 0xB5], &left_size);
         self.emit_expression(&expr.right);
-        self.emit_check_none();
+        self.emit_check_none(expr.right.get_type());
         // mov rsi,[rbp+{}]
         self.emit_with_stack(&[0x48, 0x8B, 0xB5], &left_size);
         self.free_stack(left_size);
@@ -635,65 +783,63 @@ impl<'a> Emitter<'a> {
         self.free_stack(left);
     }
 
-    pub fn emit_str_compare(&mut self, expr: &BinaryExpr) {
+    // Compare str or list operands by content through the runtime
+    pub fn emit_object_compare(&mut self, expr: &BinaryExpr) {
         self.emit_expression(&expr.left);
         let left = self.alloc_stack(TicketType::Reference);
         // mov [rbp+{}],rax
         self.emit_with_stack(&[0x48, 0x89, 0x85], &left);
         self.emit_expression(&expr.right);
-        // mov r11,[rbp+{}]
-        self.emit_with_stack(&[0x4C, 0x8B, 0x9D], &left);
+        match self.platform {
+            Platform::Windows => {
+                // mov rdx,rax
+                self.emit(&[0x48, 0x89, 0xC2]);
+                // mov rcx,[rbp+{}]
+                self.emit_with_stack(&[0x48, 0x8B, 0x8D], &left);
+            }
+            Platform::Linux | Platform::Macos => {
+                // mov rsi,rax
+                self.emit(&[0x48, 0x89, 0xC6]);
+                // mov rdi,[rbp+{}]
+                self.emit_with_stack(&[0x48, 0x8B, 0xBD], &left);
+            }
+        }
         self.free_stack(left);
+        self.prepare_call(self.platform.stack_reserve());
 
-        /*
-        mov rcx,[rax+ARRAY_LEN_OFFSET]
-        mov rdx,[r11+ARRAY_LEN_OFFSET]
-        cmp rcx,rdx
-        jne not_equal
-        test rcx,rcx
-        je equal
-        lea rdi,[rax+ARRAY_ELEMENT_OFFSET]
-        lea rsi,[r11+ARRAY_ELEMENT_OFFSET]
-        lo:
-        mov dl,[rdi]
-        cmp dl,[rsi]
-        jne not_equal
-        inc rdi
-        inc rsi
-        loop lo
-        equal:
-        mov rdx,1
-        jmp finish
-        not_equal:
-        xor rdx,rdx
-        finish:
-        */
-        #[rustfmt::skip]
-        self.emit(&[
-            0x48, 0x8B, 0x48, ARRAY_LEN_OFFSET as u8,
-            0x49, 0x8B, 0x53, ARRAY_LEN_OFFSET as u8,
-            0x48, 0x39, 0xD1, 0x75, 0x24, 0x48, 0x85, 0xC9, 0x74, 0x16,
-            0x48, 0x8D, 0x78, ARRAY_ELEMENT_OFFSET as u8,
-            0x49, 0x8D, 0x73, ARRAY_ELEMENT_OFFSET as u8,
-            0x8A, 0x17, 0x3A, 0x16, 0x75, 0x11, 0x48, 0xFF, 0xC7, 0x48,
-            0xFF, 0xC6, 0xE2, 0xF2, 0x48, 0xC7, 0xC2, 0x01, 0x00, 0x00, 0x00,
-            0xEB, 0x03, 0x48, 0x31, 0xD2
-        ]);
-
-        if expr.operator == BinaryOp::Ne {
-            // test rdx,rdx
-            self.emit(&[0x48, 0x85, 0xD2]);
-            // sete dl
-            self.emit(&[0x0F, 0x94, 0xC2]);
+        match expr.operator {
+            BinaryOp::Eq => self.call(BUILTIN_EQUAL),
+            BinaryOp::Ne => {
+                self.call(BUILTIN_EQUAL);
+                // xor al,1
+                self.emit(&[0x34, 0x01]);
+            }
+            _ => {
+                let code = match expr.operator {
+                    BinaryOp::Lt => 0xc,
+                    BinaryOp::Ge => 0xd,
+                    BinaryOp::Le => 0xe,
+                    BinaryOp::Gt => 0xf,
+                    _ => panic!(),
+                };
+                self.call(BUILTIN_COMPARE);
+                // test eax,eax
+                self.emit(&[0x85, 0xC0]);
+                // set* al
+                self.emit(&[0x0f, 0x90 + code, 0xc0]);
+            }
         }
-
-        // mov rax,rdx
-        self.emit(&[0x48, 0x89, 0xD0]);
     }
 
     pub fn emit_binary_expr(&mut self, expr: &BinaryExpr, target_type: &ValueType) {
         let left_type = expr.left.get_type();
-        if expr.operator == BinaryOp::Add && left_type == &*TYPE_STR {
+        if expr.operator == BinaryOp::In {
+            self.emit_set_contains(expr);
+        } else if let (BinaryOp::BitOr | BinaryOp::BitAnd, Some(element_type)) =
+            (&expr.operator, set_element(left_type))
+        {
+            self.emit_set_operation(expr, element_type);
+        } else if expr.operator == BinaryOp::Add && left_type == &*TYPE_STR {
             self.emit_string_add(expr);
         } else if expr.operator == BinaryOp::Add && left_type != &*TYPE_INT {
             let target_element = if let ValueType::ListValueType(l) = &target_type {
@@ -702,10 +848,14 @@ impl<'a> Emitter<'a> {
                 panic!()
             };
             self.emit_list_add(expr, target_element);
-        } else if (expr.operator == BinaryOp::Eq || expr.operator == BinaryOp::Ne)
-            && left_type == &*TYPE_STR
+        } else if matches!(
+            expr.operator,
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge
+        ) && (left_type == &*TYPE_STR
+            || left_type == &*TYPE_EMPTY
+            || matches!(left_type, ValueType::ListValueType(_)))
         {
-            self.emit_str_compare(expr);
+            self.emit_object_compare(expr);
         } else if expr.operator == BinaryOp::Or || expr.operator == BinaryOp::And {
             self.emit_expression(&expr.left);
             // test al,al
@@ -788,6 +938,75 @@ impl<'a> Emitter<'a> {
                         self.emit(&[0x29, 0xC8]);
                     }
                 }
+                BinaryOp::BitAnd => {
+                    // and eax,r11d
+                    self.emit(&[0x44, 0x21, 0xD8]);
+                }
+                BinaryOp::BitOr => {
+                    // or eax,r11d
+                    self.emit(&[0x44, 0x09, 0xD8]);
+                }
+                BinaryOp::BitXor => {
+                    // xor eax,r11d
+                    self.emit(&[0x44, 0x31, 0xD8]);
+                }
+                BinaryOp::Shl | BinaryOp::Shr => {
+                    self.emit_check_negative(BUILTIN_NEGATIVE_SHIFT);
+                    // mov ecx,eax
+                    self.emit(&[0x89, 0xC1]);
+                    // Shifting by 32 or more bits is well-defined for 32-bit `int`s,
+                    // unlike the `shl` and `sar` instructions that mask the count
+                    if expr.operator == BinaryOp::Shl {
+                        // mov eax,r11d
+                        self.emit(&[0x44, 0x89, 0xD8]);
+                        // shl eax,cl
+                        self.emit(&[0xD3, 0xE0]);
+                        // xor edx,edx
+                        self.emit(&[0x31, 0xD2]);
+                        // cmp ecx,31
+                        self.emit(&[0x83, 0xF9, 0x1F]);
+                        // cmova eax,edx
+                        self.emit(&[0x0F, 0x47, 0xC2]);
+                    } else {
+                        // mov edx,31
+                        self.emit(&[0xBA, 0x1F, 0x00, 0x00, 0x00]);
+                        // cmp ecx,edx
+                        self.emit(&[0x39, 0xD1]);
+                        // cmova ecx,edx
+                        self.emit(&[0x0F, 0x47, 0xCA]);
+                        // mov eax,r11d
+                        self.emit(&[0x44, 0x89, 0xD8]);
+                        // sar eax,cl
+                        self.emit(&[0xD3, 0xF8]);
+                    }
+                }
+                BinaryOp::Pow => {
+                    self.emit_check_negative(BUILTIN_NEGATIVE_EXPONENT);
+                    // Exponentiation by squaring, wrapping around like `*`
+                    // mov ecx,eax
+                    self.emit(&[0x89, 0xC1]);
+                    // mov eax,1
+                    self.emit(&[0xB8, 0x01, 0x00, 0x00, 0x00]);
+                    // loop:
+                    // test ecx,ecx
+                    self.emit(&[0x85, 0xC9]);
+                    // je done
+                    self.emit(&[0x74, 0x11]);
+                    // test cl,1
+                    self.emit(&[0xF6, 0xC1, 0x01]);
+                    // je skip
+                    self.emit(&[0x74, 0x04]);
+                    // imul eax,r11d
+                    self.emit(&[0x41, 0x0F, 0xAF, 0xC3]);
+                    // skip:
+                    // imul r11d,r11d
+                    self.emit(&[0x45, 0x0F, 0xAF, 0xDB]);
+                    // shr ecx,1
+                    self.emit(&[0xD1, 0xE9]);
+                    // jmp loop
+                    self.emit(&[0xEB, 0xEB]);
+                    // done:
+                }
                 BinaryOp::Is => {
                     // cmp r11,rax
                     self.emit(&[0x49, 0x39, 0xC3]);
@@ -825,14 +1044,137 @@ impl<'a> Emitter<'a> {
         }
     }
 
-    // Coerce the valie in rax from one type to another
+    // Allocate an empty set in rax
+    pub fn emit_new_set(&mut self, element_type: &ValueType) {
+        // xor rsi,rsi
+        self.emit(&[0x48, 0x31, 0xF6]);
+        self.call_builtin_alloc(SET_PROTOTYPE);
+        // lea rcx,[rip+{}]
+        self.emit(&[0x48, 0x8D, 0x0D]);
+        self.emit_link(set_table_prototype(element_type), 0);
+        // mov [rax+SET_TABLE_PROTOTYPE_OFFSET],rcx
+        self.emit(&[0x48, 0x89, 0x48, SET_TABLE_PROTOTYPE_OFFSET as u8]);
+    }
+
+    // Evaluate a set element into a stack slot, as passed to the runtime
+    fn emit_set_element(&mut self, element: &Expr) -> StackTicket {
+        self.emit_expression(element);
+        if element.get_type() == &*TYPE_BOOL {
+            // movzx eax,al
+            self.emit(&[0x0F, 0xB6, 0xC0]);
+        }
+        let ticket = self.alloc_stack(element.get_type().ticket_type());
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &ticket);
+        ticket
+    }
+
+    pub fn emit_set_expr(&mut self, expr: &SetExpr, target_type: &ValueType) {
+        self.emit_new_set(set_element(target_type).unwrap());
+        let set = self.alloc_stack(TicketType::Reference);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &set);
+
+        for element in &expr.elements {
+            let element = self.emit_set_element(element);
+            self.call_builtin_set(BUILTIN_SET_ADD, &[&set, &element], true);
+            self.free_stack(element);
+        }
+
+        // mov rax,[rbp+{}]
+        self.emit_with_stack(&[0x48, 0x8B, 0x85], &set);
+        self.free_stack(set);
+    }
+
+    pub fn emit_set_method(&mut self, expr: &MethodCallExpr) {
+        self.emit_expression(&expr.method.object);
+        let set = self.alloc_stack(TicketType::Reference);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &set);
+        let element = self.emit_set_element(&expr.args[0]);
+
+        match expr.method.member.name.as_str() {
+            "add" => self.call_builtin_set(BUILTIN_SET_ADD, &[&set, &element], true),
+            "remove" => self.call_builtin_set(BUILTIN_SET_REMOVE, &[&set, &element], false),
+            "discard" => self.call_builtin_set(BUILTIN_SET_DISCARD, &[&set, &element], false),
+            _ => panic!(),
+        }
+        self.free_stack(element);
+        self.free_stack(set);
+        self.emit_none_literal();
+    }
+
+    pub fn emit_set_contains(&mut self, expr: &BinaryExpr) {
+        let element = self.emit_set_element(&expr.left);
+        self.emit_expression(&expr.right);
+        let set = self.alloc_stack(TicketType::Reference);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &set);
+        self.call_builtin_set(BUILTIN_SET_CONTAINS, &[&set, &element], false);
+        self.free_stack(set);
+        self.free_stack(element);
+    }
+
+    // Union and intersection build a new set from the left operand
+    pub fn emit_set_operation(&mut self, expr: &BinaryExpr, element_type: &ValueType) {
+        self.emit_expression(&expr.left);
+        let left = self.alloc_stack(TicketType::Reference);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &left);
+        self.emit_expression(&expr.right);
+        let right = self.alloc_stack(TicketType::Reference);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &right);
+        self.emit_new_set(element_type);
+        let result = self.alloc_stack(TicketType::Reference);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &result);
+
+        self.call_builtin_set(BUILTIN_SET_UPDATE, &[&result, &left], true);
+        if expr.operator == BinaryOp::BitOr {
+            self.call_builtin_set(BUILTIN_SET_UPDATE, &[&result, &right], true);
+        } else {
+            self.call_builtin_set(BUILTIN_SET_RETAIN, &[&result, &right], false);
+        }
+
+        // mov rax,[rbp+{}]
+        self.emit_with_stack(&[0x48, 0x8B, 0x85], &result);
+        self.free_stack(result);
+        self.free_stack(right);
+        self.free_stack(left);
+    }
+
+    // Call the runtime trap `error` if the int in eax is negative
+    fn emit_check_negative(&mut self, error: &str) {
+        // test eax,eax
+        self.emit(&[0x85, 0xC0]);
+        // jns
+        self.emit(&[0x0F, 0x89]);
+        let ok = self.jump_from();
+        self.prepare_call(self.platform.stack_reserve());
+        self.call(error);
+        self.to_here(ok);
+    }
+
+    // Coerce the valie in rax from one type to another.
+    // Besides upcasting to object, values whose type is a type parameter of a
+    // generic class are boxed, and unboxed again when read as a plain type.
     pub fn emit_coerce(&mut self, from: &ValueType, to: &ValueType) {
-        if to == &*TYPE_OBJECT {
+        if !to.is_plain() {
             if from == &*TYPE_INT {
                 self.emit_box_int();
             } else if from == &*TYPE_BOOL {
                 self.emit_box_bool();
             }
+        } else if !from.is_plain() {
+            self.emit_check_none(from);
+            if to == &*TYPE_INT {
+                // mov eax,DWORD PTR [rax+OBJECT_ATTRIBUTE_OFFSET]
+                self.emit(&[0x8B, 0x40, OBJECT_ATTRIBUTE_OFFSET as u8]);
+            } else {
+                // mov al,BYTE PTR [rax+OBJECT_ATTRIBUTE_OFFSET]
+                self.emit(&[0x8A, 0x40, OBJECT_ATTRIBUTE_OFFSET as u8]);
+            }
         }
     }
 
@@ -840,8 +1182,7 @@ impl<'a> Emitter<'a> {
         &mut self,
         args: &[Expr],
         func_type: &Option<FuncType>,
-        name: &str,
-        virtual_call: bool,
+        callee: Callee,
     ) {
         let mut args_stack = vec![];
 
@@ -853,8 +1194,8 @@ impl<'a> Emitter<'a> {
 
             self.emit_coerce(arg.get_type(), param_type);
 
-            if i == 0 && virtual_call {
-                self.emit_check_none();
+            if i == 0 && !matches!(callee, Callee::Function(_)) {
+                self.emit_check_none(arg.get_type());
             }
 
             let arg_stack = self.alloc_stack(param_type.ticket_type());
@@ -876,44 +1217,200 @@ impl<'a> Emitter<'a> {
         }
 
         // Call the function
-        if virtual_call {
-            let offset = if let ValueType::ClassValueType(c) = args[0].get_type() {
-                if matches!(
-                    c.class_name.as_str(),
-                    "int" | "bool" | "str" | "<None>" | "<Empty>"
-                ) {
-                    assert!(name == "__init__");
-                    PROTOTYPE_INIT_OFFSET
+        match callee {
+            Callee::Virtual(name) => {
+                let offset = if let ValueType::ClassValueType(c) = args[0].get_type() {
+                    if matches!(
+                        c.class_name.as_str(),
+                        "int" | "bool" | "str" | "<None>" | "<Empty>"
+                    ) {
+                        assert!(name == "__init__");
+                        PROTOTYPE_INIT_OFFSET
+                    } else {
+                        self.classes()[&c.class_name].methods[name].offset
+                    }
+                } else {
+                    panic!()
+                };
+                self.call_virtual(offset);
+            }
+            Callee::Direct(link_name) => self.call(link_name),
+            Callee::Function(name) => {
+                let slot = if let Some(EnvSlot::Func(f)) = self.storage_env().get(name) {
+                    f
                 } else {
-                    self.classes()[&c.class_name].methods[name].offset
+                    panic!()
+                };
+
+                let link_name = slot.link_name.clone();
+                let call_level = slot.level;
+
+                // Pass static link
+                if call_level != 0 {
+                    // mov r10,rbp
+                    self.emit(&[0x49, 0x89, 0xEA]);
+                    for _ in 0..self.level + 1 - call_level {
+                        // mov r10,[r10-8]
+                        self.emit(&[0x4D, 0x8B, 0x52, 0xF8]);
+                    }
                 }
-            } else {
-                panic!()
-            };
-            self.call_virtual(offset);
-        } else {
-            let slot = if let Some(EnvSlot::Func(f)) = self.storage_env().get(name) {
-                f
-            } else {
-                panic!()
-            };
 
-            let link_name = slot.link_name.clone();
-            let call_level = slot.level;
+                self.call(&link_name);
+            }
+        }
+        self.emit_ref_map();
+    }
+
+    // `print(*args, sep=, end=, file=, flush=)` passes the arguments as a list of objects
+    pub fn emit_print(&mut self, expr: &CallExpr) {
+        let args = ListExpr {
+            base: expr.base.clone(),
+            elements: expr.args.clone(),
+        };
+        let args_type = ValueType::ListValueType(ListValueType {
+            element_type: Box::new(TYPE_OBJECT.clone()),
+        });
+        self.emit_list_expr(&args, &args_type);
+        let args = self.alloc_stack(TicketType::Reference);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &args);
+
+        // Null `sep` and `end` select the defaults
+        let sep = self.alloc_stack(TicketType::Reference);
+        let end = self.alloc_stack(TicketType::Reference);
+        let flush = self.alloc_stack(TicketType::Plain);
+        for ticket in [&sep, &end, &flush] {
+            // mov QWORD PTR [rbp+{}],0
+            self.emit_with_stack(&[0x48, 0xC7, 0x85], ticket);
+            self.emit(&0u32.to_le_bytes());
+        }
 
-            // Pass static link
-            if call_level != 0 {
-                // mov r10,rbp
-                self.emit(&[0x49, 0x89, 0xEA]);
-                for _ in 0..self.level + 1 - call_level {
-                    // mov r10,[r10-8]
-                    self.emit(&[0x4D, 0x8B, 0x52, 0xF8]);
+        let mut flags = 0;
+        for keyword in &expr.keywords {
+            match keyword.name.name.as_str() {
+                "sep" | "end" => {
+                    self.emit_expression(&keyword.value);
+                    let ticket = if keyword.name.name == "sep" { &sep } else { &end };
+                    // mov [rbp+{}],rax
+                    self.emit_with_stack(&[0x48, 0x89, 0x85], ticket);
+                }
+                "flush" => {
+                    self.emit_expression(&keyword.value);
+                    // movzx eax,al
+                    self.emit(&[0x0F, 0xB6, 0xC0]);
+                    // neg eax
+                    self.emit(&[0xF7, 0xD8]);
+                    // and eax,PRINT_FLUSH
+                    self.emit(&[0x25]);
+                    self.emit(&(PRINT_FLUSH as u32).to_le_bytes());
+                    // mov [rbp+{}],rax
+                    self.emit_with_stack(&[0x48, 0x89, 0x85], &flush);
+                }
+                // The stream is a name known statically
+                "file" => {
+                    if matches!(&keyword.value.content,
+                        ExprContent::Variable(v) if v.name == "stderr")
+                    {
+                        flags |= PRINT_STDERR;
+                    }
                 }
+                _ => panic!(),
+            }
+        }
+
+        // mov rax,[rbp+{}]
+        self.emit_with_stack(&[0x48, 0x8B, 0x85], &flush);
+        // or eax,{flags}
+        self.emit(&[0x0D]);
+        self.emit(&(flags as u32).to_le_bytes());
+        match self.platform {
+            Platform::Windows => {
+                // mov r9,rax
+                self.emit(&[0x49, 0x89, 0xC1]);
+                // mov r8,[rbp+{}]
+                self.emit_with_stack(&[0x4C, 0x8B, 0x85], &end);
+                // mov rdx,[rbp+{}]
+                self.emit_with_stack(&[0x48, 0x8B, 0x95], &sep);
+                // mov rcx,[rbp+{}]
+                self.emit_with_stack(&[0x48, 0x8B, 0x8D], &args);
             }
+            Platform::Linux | Platform::Macos => {
+                // mov rcx,rax
+                self.emit(&[0x48, 0x89, 0xC1]);
+                // mov rdx,[rbp+{}]
+                self.emit_with_stack(&[0x48, 0x8B, 0x95], &end);
+                // mov rsi,[rbp+{}]
+                self.emit_with_stack(&[0x48, 0x8B, 0xB5], &sep);
+                // mov rdi,[rbp+{}]
+                self.emit_with_stack(&[0x48, 0x8B, 0xBD], &args);
+            }
+        }
+        self.free_stack(flush);
+        self.free_stack(end);
+        self.free_stack(sep);
+        self.free_stack(args);
+
+        self.prepare_call(self.platform.stack_reserve());
+        self.call(BUILTIN_PRINT);
+        self.emit_none_literal();
+    }
+
+    // Walk up the prototype chain of the object looking for the class prototype
+    pub fn emit_isinstance(&mut self, expr: &CallExpr) {
+        let object = &expr.args[0];
+        let class_name = if let ExprContent::Variable(c) = &expr.args[1].content {
+            &c.name
+        } else {
+            panic!()
+        };
+
+        self.emit_expression(object);
 
-            self.call(&link_name);
+        let object_type = object.get_type();
+        if object_type.is_plain() {
+            // Unboxed values have no prototype, but their class is known statically
+            let result = class_name == "object"
+                || matches!(object_type, ValueType::ClassValueType(c) if &c.class_name == class_name);
+            self.emit_bool_literal(result);
+            return;
         }
-        self.emit_ref_map();
+
+        // test rax,rax
+        self.emit(&[0x48, 0x85, 0xC0]);
+        // je
+        self.emit(&[0x0F, 0x84]);
+        let label_none = self.jump_from();
+        // mov rax,[rax], assumed OBJECT_PROTOTYPE_OFFSET = 0
+        self.emit(&[0x48, 0x8B, 0x00]);
+        // lea rcx,[rip+{class_name}.$proto]
+        self.emit(&[0x48, 0x8D, 0x0D]);
+        self.emit_link(class_name.clone() + ".$proto", 0);
+
+        let label_loop = self.jump_to();
+        // cmp rax,rcx
+        self.emit(&[0x48, 0x39, 0xC8]);
+        // je
+        self.emit(&[0x0F, 0x84]);
+        let label_true = self.jump_from();
+        // mov rax,[rax+PROTOTYPE_SUPER_OFFSET]
+        self.emit(&[0x48, 0x8B, 0x40, PROTOTYPE_SUPER_OFFSET as u8]);
+        // test rax,rax
+        self.emit(&[0x48, 0x85, 0xC0]);
+        // jne
+        self.emit(&[0x0F, 0x85]);
+        self.from_here(label_loop);
+
+        self.to_here(label_none);
+        // xor eax,eax
+        self.emit(&[0x31, 0xC0]);
+        // jmp
+        self.emit(&[0xE9]);
+        let label_end = self.jump_from();
+
+        self.to_here(label_true);
+        // mov eax,1
+        self.emit(&[0xB8, 0x01, 0x00, 0x00, 0x00]);
+        self.to_here(label_end);
     }
 
     pub fn emit_str_index(&mut self, expr: &IndexExpr) {
@@ -953,7 +1450,7 @@ impl<'a> Emitter<'a> {
 
     pub fn emit_list_index(&mut self, expr: &IndexExpr) {
         self.emit_expression(&expr.list);
-        self.emit_check_none();
+        self.emit_check_none(expr.list.get_type());
         let list = self.alloc_stack(TicketType::Reference);
         // mov [rbp+{}],rax
         self.emit_with_stack(&[0x48, 0x89, 0x85], &list);
@@ -990,9 +1487,9 @@ impl<'a> Emitter<'a> {
         }
     }
 
-    pub fn emit_member_expr(&mut self, expr: &MemberExpr) {
+    pub fn emit_member_expr(&mut self, expr: &MemberExpr, target_type: &ValueType) {
         self.emit_expression(&expr.object);
-        self.emit_check_none();
+        self.emit_check_none(expr.object.get_type());
         // mov rsi,rax
         self.emit(&[0x48, 0x89, 0xC6]);
 
@@ -1015,6 +1512,9 @@ impl<'a> Emitter<'a> {
             self.emit(&[0x48, 0x8B, 0x86]);
             self.emit(&slot.offset.to_le_bytes());
         }
+
+        let declared_type = slot.target_type.clone();
+        self.emit_coerce(&declared_type, target_type);
     }
 
     pub fn emit_if_expr(&mut self, expr: &IfExpr, target_type: &ValueType) {
@@ -1078,13 +1578,7 @@ impl<'a> Emitter<'a> {
             panic!()
         };
 
-        let prototype = if element_type == &*TYPE_INT {
-            INT_LIST_PROTOTYPE
-        } else if element_type == &*TYPE_BOOL {
-            BOOL_LIST_PROTOTYPE
-        } else {
-            OBJECT_LIST_PROTOTYPE
-        };
+        let prototype = list_prototype(element_type);
 
         // mov rsi,{len}
         self.emit(&[0x48, 0xc7, 0xc6]);
@@ -1119,13 +1613,193 @@ impl<'a> Emitter<'a> {
         self.free_stack(result);
     }
 
+    // Offset and level of a variable, as in its `VarSlot`
+    fn var_slot(&self, name: &str) -> (i32, u32) {
+        if let Some((_, offset)) = self.scoped_vars.iter().rev().find(|(n, _)| n == name) {
+            (*offset, self.level + 1)
+        } else if let Some(EnvSlot::Var(v, _)) = self.storage_env().get(name) {
+            (v.offset, v.level)
+        } else {
+            panic!()
+        }
+    }
+
+    // Build a list from a comprehension. With a single unfiltered clause over a
+    // list or str the length is known, and the list is allocated up front.
+    // Otherwise elements go to a buffer that doubles when full, and the final
+    // list is copied out of it.
+    pub fn emit_list_comprehension(&mut self, expr: &ListComprehension, target_type: &ValueType) {
+        let element_type = if let ValueType::ListValueType(l) = target_type {
+            &*l.element_type
+        } else {
+            panic!()
+        };
+
+        let first = &expr.clauses[0];
+        let is_iterator = matches!(
+            first.iterable.get_type(),
+            ValueType::ClassValueType(c) if c.class_name == "Iterator"
+        );
+        let known_len = expr.clauses.len() == 1 && first.conditions.is_empty() && !is_iterator;
+
+        let iterable = if known_len {
+            self.emit_expression(&first.iterable);
+            self.emit_check_none(first.iterable.get_type());
+            let iterable = self.alloc_stack(TicketType::Reference);
+            // mov [rbp+{}],rax
+            self.emit_with_stack(&[0x48, 0x89, 0x85], &iterable);
+            // mov rsi,[rax+ARRAY_LEN_OFFSET]
+            self.emit(&[0x48, 0x8B, 0x70, ARRAY_LEN_OFFSET as u8]);
+            Some(iterable)
+        } else {
+            // mov rsi,{COMPREHENSION_CAPACITY}
+            self.emit(&[0x48, 0xc7, 0xc6]);
+            self.emit(&COMPREHENSION_CAPACITY.to_le_bytes());
+            None
+        };
+        self.call_builtin_alloc(list_prototype(element_type));
+        let list = self.alloc_stack(TicketType::Reference);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &list);
+        let count = self.alloc_stack(TicketType::Plain);
+        // mov QWORD PTR [rbp+{}],0
+        self.emit_with_stack(&[0x48, 0xC7, 0x85], &count);
+        self.emit(&[0, 0, 0, 0]);
+
+        let buffer = ComprehensionBuffer {
+            element_type,
+            list,
+            count,
+            growable: !known_len,
+        };
+        if let Some(iterable) = &iterable {
+            // mov rax,[rbp+{}]
+            self.emit_with_stack(&[0x48, 0x8B, 0x85], iterable);
+            self.emit_comprehension_loop(expr, 0, &buffer);
+        } else {
+            self.emit_comprehension(expr, 0, &buffer);
+        }
+
+        if buffer.growable {
+            // mov rdi,[rbp+{}]
+            self.emit_with_stack(&[0x48, 0x8B, 0xBD], &buffer.list);
+            // mov rsi,[rbp+{}]
+            self.emit_with_stack(&[0x48, 0x8B, 0xB5], &buffer.count);
+            self.call_builtin_list_resize();
+        } else {
+            // mov rax,[rbp+{}]
+            self.emit_with_stack(&[0x48, 0x8B, 0x85], &buffer.list);
+        }
+
+        self.free_stack(buffer.count);
+        self.free_stack(buffer.list);
+        if let Some(iterable) = iterable {
+            self.free_stack(iterable);
+        }
+    }
+
+    // Emit the comprehension from clause `i` on. Past the last clause, this
+    // appends the element to the buffer.
+    fn emit_comprehension(
+        &mut self,
+        expr: &ListComprehension,
+        i: usize,
+        buffer: &ComprehensionBuffer,
+    ) {
+        if let Some(clause) = expr.clauses.get(i) {
+            self.emit_expression(&clause.iterable);
+            self.emit_check_none(clause.iterable.get_type());
+            self.emit_comprehension_loop(expr, i, buffer);
+            return;
+        }
+
+        self.emit_expression(&expr.element);
+        self.emit_coerce(expr.element.get_type(), buffer.element_type);
+        let value = self.alloc_stack(buffer.element_type.ticket_type());
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &value);
+
+        if buffer.growable {
+            // mov rdi,[rbp+{}]
+            self.emit_with_stack(&[0x48, 0x8B, 0xBD], &buffer.list);
+            // mov rsi,[rbp+{}]
+            self.emit_with_stack(&[0x48, 0x8B, 0xB5], &buffer.count);
+            // cmp rsi,[rdi+ARRAY_LEN_OFFSET]
+            self.emit(&[0x48, 0x3B, 0x77, ARRAY_LEN_OFFSET as u8]);
+            // jb
+            self.emit(&[0x0F, 0x82]);
+            let has_room = self.jump_from();
+            // add rsi,rsi
+            self.emit(&[0x48, 0x01, 0xF6]);
+            self.call_builtin_list_resize();
+            // mov [rbp+{}],rax
+            self.emit_with_stack(&[0x48, 0x89, 0x85], &buffer.list);
+            self.to_here(has_room);
+        }
+
+        // mov rdi,[rbp+{}]
+        self.emit_with_stack(&[0x48, 0x8B, 0xBD], &buffer.list);
+        // mov rsi,[rbp+{}]
+        self.emit_with_stack(&[0x48, 0x8B, 0xB5], &buffer.count);
+        // mov rax,[rbp+{}]
+        self.emit_with_stack(&[0x48, 0x8B, 0x85], &value);
+        if buffer.element_type == &*TYPE_INT {
+            // mov [rdi+rsi*4+ARRAY_ELEMENT_OFFSET],eax
+            self.emit(&[0x89, 0x44, 0xB7, ARRAY_ELEMENT_OFFSET as u8]);
+        } else if buffer.element_type == &*TYPE_BOOL {
+            // mov [rdi+rsi+ARRAY_ELEMENT_OFFSET],al
+            self.emit(&[0x88, 0x44, 0x37, ARRAY_ELEMENT_OFFSET as u8]);
+        } else {
+            // mov [rdi+rsi*8+ARRAY_ELEMENT_OFFSET],rax
+            self.emit(&[0x48, 0x89, 0x44, 0xF7, ARRAY_ELEMENT_OFFSET as u8]);
+        }
+        // inc QWORD PTR [rbp+{}]
+        self.emit_with_stack(&[0x48, 0xFF, 0x85], &buffer.count);
+        self.free_stack(value);
+    }
+
+    // Emit the loop of clause `i` over the iterable in rax
+    fn emit_comprehension_loop(
+        &mut self,
+        expr: &ListComprehension,
+        i: usize,
+        buffer: &ComprehensionBuffer,
+    ) {
+        let clause = &expr.clauses[i];
+        let name = &clause.identifier.name;
+        let target_type = clause.identifier.get_type();
+        self.emit_loop(clause.iterable.get_type(), &mut |this, source_type| {
+            let variable = this.alloc_stack(target_type.ticket_type());
+            this.scoped_vars.push((name.clone(), variable.offset));
+            this.emit_assign_identifier(name, source_type, target_type);
+
+            // Skip the rest of the iteration unless all conditions hold
+            let mut skips = vec![];
+            for condition in &clause.conditions {
+                this.emit_expression(condition);
+                // test al,al
+                this.emit(&[0x84, 0xC0]);
+                // je
+                this.emit(&[0x0F, 0x84]);
+                skips.push(this.jump_from());
+            }
+            this.emit_comprehension(expr, i + 1, buffer);
+            for skip in skips {
+                this.to_here(skip);
+            }
+
+            this.scoped_vars.pop();
+            this.free_stack(variable);
+        });
+    }
+
     pub fn emit_load_var(&mut self, identifier: &Variable, target_type: &ValueType) {
-        let (offset, level) =
-            if let Some(EnvSlot::Var(v, _)) = self.storage_env().get(&identifier.name) {
-                (v.offset, v.level)
-            } else {
-                panic!()
-            };
+        if let Some(value) = self.constant(&identifier.name) {
+            self.emit_constant(value, target_type);
+            return;
+        }
+
+        let (offset, level) = self.var_slot(&identifier.name);
 
         if level == 0 {
             // Global variable
@@ -1164,7 +1838,93 @@ impl<'a> Emitter<'a> {
         }
     }
 
+    // The inlined value of a `Final` global, unless a local variable shadows it
+    fn constant(&self, name: &str) -> Option<i32> {
+        if self.scoped_vars.iter().any(|(n, _)| n == name) {
+            return None;
+        }
+        match self.storage_env().get(name) {
+            Some(EnvSlot::Var(v, _)) => v.value,
+            _ => None,
+        }
+    }
+
+    fn emit_constant(&mut self, value: i32, target_type: &ValueType) {
+        if target_type == &*TYPE_BOOL {
+            self.emit_bool_literal(value != 0);
+        } else {
+            self.emit_int_literal(value);
+        }
+    }
+
+    // Evaluate an int or bool expression of literals and `Final` constants at
+    // compile time, with the same result as the emitted code. Operations that
+    // fail at run time are left to it. Bools are 0 or 1.
+    fn fold(&self, expr: &Expr) -> Option<i32> {
+        match &expr.content {
+            ExprContent::IntegerLiteral(i) => Some(i.value),
+            ExprContent::BooleanLiteral(b) => Some(b.value as i32),
+            ExprContent::Variable(v) => self.constant(&v.name),
+            ExprContent::UnaryExpr(e) => {
+                let operand = self.fold(&e.operand)?;
+                Some(match e.operator {
+                    UnaryOp::Negative => operand.wrapping_neg(),
+                    UnaryOp::Not => (operand == 0) as i32,
+                    UnaryOp::Invert => !operand,
+                })
+            }
+            ExprContent::BinaryExpr(e) if e.left.get_type().is_plain() => {
+                let left = self.fold(&e.left)?;
+                let right = self.fold(&e.right)?;
+                let value = match e.operator {
+                    BinaryOp::Add => left.wrapping_add(right),
+                    BinaryOp::Sub => left.wrapping_sub(right),
+                    BinaryOp::Mul => left.wrapping_mul(right),
+                    BinaryOp::Div | BinaryOp::Mod => {
+                        if right == 0 || (left == i32::MIN && right == -1) {
+                            return None;
+                        }
+                        // Round toward negative infinity
+                        let (quotient, remainder) = (left / right, left % right);
+                        let adjust = remainder != 0 && (remainder < 0) != (right < 0);
+                        match (e.operator == BinaryOp::Div, adjust) {
+                            (true, false) => quotient,
+                            (true, true) => quotient - 1,
+                            (false, false) => remainder,
+                            (false, true) => remainder + right,
+                        }
+                    }
+                    BinaryOp::BitAnd => left & right,
+                    BinaryOp::BitOr => left | right,
+                    BinaryOp::BitXor => left ^ right,
+                    BinaryOp::Shl | BinaryOp::Shr | BinaryOp::Pow if right < 0 => return None,
+                    BinaryOp::Shl => left.checked_shl(right as u32).unwrap_or(0),
+                    BinaryOp::Shr => left >> right.min(31),
+                    BinaryOp::Pow => left.wrapping_pow(right as u32),
+                    BinaryOp::Eq => (left == right) as i32,
+                    BinaryOp::Ne => (left != right) as i32,
+                    BinaryOp::Lt => (left < right) as i32,
+                    BinaryOp::Le => (left <= right) as i32,
+                    BinaryOp::Gt => (left > right) as i32,
+                    BinaryOp::Ge => (left >= right) as i32,
+                    BinaryOp::And => left & right,
+                    BinaryOp::Or => left | right,
+                    BinaryOp::Is | BinaryOp::In => return None,
+                };
+                Some(value)
+            }
+            _ => None,
+        }
+    }
+
     pub fn emit_expression(&mut self, expression: &Expr) {
+        if let ExprContent::UnaryExpr(_) | ExprContent::BinaryExpr(_) = &expression.content
+            && let Some(value) = self.fold(expression)
+        {
+            self.emit_constant(value, expression.get_type());
+            return;
+        }
+
         match &expression.content {
             ExprContent::Variable(identifier) => {
                 self.emit_load_var(identifier, expression.get_type());
@@ -1194,25 +1954,82 @@ impl<'a> Emitter<'a> {
                         // sete al
                         self.emit(&[0x0F, 0x94, 0xC0]);
                     }
+                    UnaryOp::Invert => {
+                        // not rax
+                        self.emit(&[0x48, 0xF7, 0xD0]);
+                    }
                 }
             }
             ExprContent::BinaryExpr(expr) => {
                 self.emit_binary_expr(expr, expression.get_type());
             }
+            ExprContent::CallExpr(expr)
+                if expr.function.name == "isinstance"
+                    && self.storage_env().get("isinstance").is_none() =>
+            {
+                self.emit_isinstance(expr);
+            }
+            ExprContent::CallExpr(expr)
+                if expr.function.name == "print"
+                    && matches!(self.storage_env().get("print"),
+                        Some(EnvSlot::Func(f)) if f.link_name == "print") =>
+            {
+                self.emit_print(expr);
+            }
+            ExprContent::CallExpr(expr)
+                if expr.function.name == "super" && self.storage_env().get("super").is_none() =>
+            {
+                // The receiver of a parent class method call
+                let receiver = Variable {
+                    base: expr.base.clone(),
+                    name: self.receiver.clone().unwrap(),
+                };
+                self.emit_load_var(&receiver, expression.get_type());
+            }
+            ExprContent::CallExpr(expr) if expr.function.name == "set" => {
+                self.emit_new_set(set_element(expression.get_type()).unwrap());
+            }
             ExprContent::CallExpr(expr) => {
                 self.emit_call_expr(
                     &expr.args,
                     &expr.function.inferred_type,
-                    &expr.function.name,
-                    false,
+                    Callee::Function(&expr.function.name),
                 );
+                // `next` returns the yielded value boxed
+                if let Some(function) = &expr.function.inferred_type {
+                    self.emit_coerce(&function.return_type, expression.get_type());
+                }
+            }
+            ExprContent::MethodCallExpr(expr)
+                if set_element(expr.method.object.get_type()).is_some() =>
+            {
+                self.emit_set_method(expr);
             }
             ExprContent::MethodCallExpr(expr) => {
                 let method = &expr.method;
                 let args: Vec<Expr> = std::iter::once(method.object.clone())
                     .chain(expr.args.iter().cloned())
                     .collect();
-                self.emit_call_expr(&args, &method.inferred_type, &method.member.name, true);
+                let name = &method.member.name;
+                let callee = match &method.object.content {
+                    // `super().method()` bypasses the prototype of the object
+                    ExprContent::CallExpr(c)
+                        if c.function.name == "super"
+                            && self.storage_env().get("super").is_none() =>
+                    {
+                        let class = if let ValueType::ClassValueType(c) = method.object.get_type() {
+                            &c.class_name
+                        } else {
+                            panic!()
+                        };
+                        Callee::Direct(&self.classes()[class].methods[name].link_name)
+                    }
+                    _ => Callee::Virtual(name),
+                };
+                self.emit_call_expr(&args, &method.inferred_type, callee);
+                // Methods of generic classes return type parameters boxed
+                let return_type = &method.inferred_type.as_ref().unwrap().return_type;
+                self.emit_coerce(return_type, expression.get_type());
             }
             ExprContent::IndexExpr(expr) => {
                 if expr.list.get_type() == &*TYPE_STR {
@@ -1225,13 +2042,21 @@ impl<'a> Emitter<'a> {
             ExprContent::ListExpr(expr) => {
                 self.emit_list_expr(expr, expression.get_type());
             }
+            ExprContent::ListComprehension(expr) => {
+                self.emit_list_comprehension(expr, expression.get_type());
+            }
+            ExprContent::SetExpr(expr) => {
+                self.emit_set_expr(expr, expression.get_type());
+            }
             ExprContent::MemberExpr(expr) => {
-                self.emit_member_expr(expr);
+                self.emit_member_expr(expr, expression.get_type());
             }
+            ExprContent::SliceExpr(_) => panic!("Slices are only allowed in `del`"),
         }
     }
 
     pub fn emit_while_stmt(&mut self, stmt: &WhileStmt, lines: &mut Vec<LineMap>) {
+        self.breaks.push(vec![]);
         let start = self.jump_to();
         self.emit_expression(&stmt.condition);
         // test al,al
@@ -1248,6 +2073,28 @@ impl<'a> Emitter<'a> {
         self.emit(&[0xe9]);
         self.from_here(start);
         self.to_here(end);
+
+        let breaks = self.breaks.pop().unwrap();
+        for stmt in stmt.else_body() {
+            self.emit_statement(stmt, lines);
+        }
+        self.emit_break_targets(breaks);
+    }
+
+    // Exit the innermost loop, skipping its `else` clause
+    pub fn emit_break_stmt(&mut self) {
+        // jmp
+        self.emit(&[0xe9]);
+        let jump = self.jump_from();
+        self.breaks.last_mut().unwrap().push(jump);
+    }
+
+    // Make the `break` statements of a loop land here, after its `else` clause.
+    // A `break` in the `else` clause belongs to the enclosing loop.
+    fn emit_break_targets(&mut self, breaks: Vec<ForwardJumper>) {
+        for jump in breaks {
+            self.to_here(jump);
+        }
     }
 
     pub fn emit_assign_identifier(
@@ -1258,11 +2105,7 @@ impl<'a> Emitter<'a> {
     ) {
         // rax: value to assign
 
-        let (offset, level) = if let Some(EnvSlot::Var(v, _)) = self.storage_env().get(name) {
-            (v.offset, v.level)
-        } else {
-            panic!()
-        };
+        let (offset, level) = self.var_slot(name);
 
         self.emit_coerce(source_type, target_type);
         if level == 0 {
@@ -1322,7 +2165,7 @@ impl<'a> Emitter<'a> {
                 }
                 ExprContent::IndexExpr(expr) => {
                     self.emit_expression(&expr.list);
-                    self.emit_check_none();
+                    self.emit_check_none(expr.list.get_type());
                     let list = self.alloc_stack(TicketType::Reference);
                     // mov [rbp+{}],rax
                     self.emit_with_stack(&[0x48, 0x89, 0x85], &list);
@@ -1378,7 +2221,7 @@ impl<'a> Emitter<'a> {
                 }
                 ExprContent::MemberExpr(expr) => {
                     self.emit_expression(&expr.object);
-                    self.emit_check_none();
+                    self.emit_check_none(expr.object.get_type());
                     let object = self.alloc_stack(TicketType::Reference);
                     // mov [rbp+{}],rax
                     self.emit_with_stack(&[0x48, 0x89, 0x85], &object);
@@ -1416,11 +2259,121 @@ impl<'a> Emitter<'a> {
         self.free_stack(value);
     }
 
-    #[allow(clippy::useless_let_if_seq)] // Tell me which is more readable
+    // Resume the generator stored at the rbp offset. The yielded value is put in rax,
+    // and ZF is set if the generator is exhausted instead
+    pub fn emit_resume(&mut self, offset: i32) {
+        // mov rax,[rbp+{}]
+        self.emit(&[0x48, 0x8B, 0x85]);
+        self.emit(&offset.to_le_bytes());
+        self.prepare_call(1);
+        // mov [rsp],rax
+        self.emit(&[0x48, 0x89, 0x04, 0x24]);
+        self.call_virtual(GENERATOR_RESUME_OFFSET);
+        self.emit_ref_map();
+        // mov rsi,[rbp+{}]
+        self.emit(&[0x48, 0x8B, 0xB5]);
+        self.emit(&offset.to_le_bytes());
+        // cmp QWORD PTR [rsi+GENERATOR_STATE_OFFSET],-1
+        self.emit(&[0x48, 0x83, 0x7E, GENERATOR_STATE_OFFSET as u8, 0xFF]);
+    }
+
     pub fn emit_for_stmt(&mut self, stmt: &ForStmt, lines: &mut Vec<LineMap>) {
         //// Compute the iterable
         self.emit_expression(&stmt.iterable);
-        self.emit_check_none();
+        self.emit_check_none(stmt.iterable.get_type());
+
+        let target_type = stmt.identifier.get_type();
+        self.breaks.push(vec![]);
+        self.emit_loop(stmt.iterable.get_type(), &mut |this, source_type| {
+            //// Assign the element
+            this.emit_assign_identifier(&stmt.identifier.name, source_type, target_type);
+
+            //// Execute the loop body
+            for stmt in &stmt.body {
+                this.emit_statement(stmt, lines);
+            }
+        });
+
+        let breaks = self.breaks.pop().unwrap();
+        for stmt in stmt.else_body() {
+            self.emit_statement(stmt, lines);
+        }
+        self.emit_break_targets(breaks);
+    }
+
+    // Emit a loop over the list, str, set or iterator in rax. `body` is emitted once,
+    // with the current element in rax of the type passed to it.
+    pub fn emit_loop(
+        &mut self,
+        iterable_type: &ValueType,
+        body: &mut dyn FnMut(&mut Emitter<'a>, &ValueType),
+    ) {
+        if matches!(iterable_type, ValueType::ClassValueType(c) if c.class_name == "Iterator") {
+            self.emit_loop_iterator(body);
+        } else if let Some(element_type) = set_element(iterable_type) {
+            self.emit_loop_set(element_type, body);
+        } else {
+            self.emit_loop_list(iterable_type, body);
+        }
+    }
+
+    fn emit_loop_iterator(&mut self, body: &mut dyn FnMut(&mut Emitter<'a>, &ValueType)) {
+        let iterator = self.alloc_stack(TicketType::Reference);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &iterator);
+
+        let start = self.jump_to();
+        self.emit_resume(iterator.offset);
+        // je
+        self.emit(&[0x0f, 0x84]);
+        let end = self.jump_from();
+
+        // The element is boxed by the generator
+        body(self, &TYPE_OBJECT);
+
+        // jmp
+        self.emit(&[0xe9]);
+        self.from_here(start);
+        self.to_here(end);
+
+        self.free_stack(iterator);
+    }
+
+    // Sets are iterated over a list of their elements, so they can be modified in the loop
+    fn emit_loop_set(
+        &mut self,
+        element_type: &ValueType,
+        body: &mut dyn FnMut(&mut Emitter<'a>, &ValueType),
+    ) {
+        let set = self.alloc_stack(TicketType::Reference);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &set);
+        match self.platform {
+            Platform::Windows => {
+                // lea rdx,[rip+{}]
+                self.emit(&[0x48, 0x8D, 0x15]);
+            }
+            Platform::Linux | Platform::Macos => {
+                // lea rsi,[rip+{}]
+                self.emit(&[0x48, 0x8D, 0x35]);
+            }
+        }
+        self.emit_link(list_prototype(element_type), 0);
+        self.call_builtin_set(BUILTIN_SET_LIST, &[&set], true);
+        self.free_stack(set);
+
+        let list_type = ValueType::ListValueType(ListValueType {
+            element_type: Box::new(element_type.clone()),
+        });
+        self.emit_loop_list(&list_type, body);
+    }
+
+    #[allow(clippy::useless_let_if_seq)] // Tell me which is more readable
+    fn emit_loop_list(
+        &mut self,
+        iterable_type: &ValueType,
+        body: &mut dyn FnMut(&mut Emitter<'a>, &ValueType),
+    ) {
         let list = self.alloc_stack(TicketType::Reference);
         // mov [rbp+{}],rax
         self.emit_with_stack(&[0x48, 0x89, 0x85], &list);
@@ -1442,7 +2395,6 @@ impl<'a> Emitter<'a> {
         self.emit_with_stack(&[0x48, 0x89, 0x85], &counter);
 
         //// Compute the element
-        let iterable_type = stmt.iterable.get_type();
         #[allow(clippy::needless_late_init)]
         let source_type;
         if iterable_type == &*TYPE_STR {
@@ -1480,14 +2432,7 @@ impl<'a> Emitter<'a> {
             source_type = element_type;
         }
 
-        //// Assign the element
-        let target_type = stmt.identifier.get_type();
-        self.emit_assign_identifier(&stmt.identifier.name, source_type, target_type);
-
-        //// Execute the loop body
-        for stmt in &stmt.body {
-            self.emit_statement(stmt, lines);
-        }
+        body(self, source_type);
 
         //// Increase the index and loop back
         // mov rax,[rbp+{}]
@@ -1503,6 +2448,88 @@ impl<'a> Emitter<'a> {
         self.free_stack(list);
     }
 
+    pub fn emit_del_stmt(&mut self, stmt: &DelStmt) {
+        for target in &stmt.targets {
+            match &target.content {
+                ExprContent::Variable(identifier) => {
+                    // Drop the reference so that the object can be collected.
+                    // The type checker rejects any later use of the variable.
+                    let target_type = target.get_type();
+                    if target_type.ticket_type() == TicketType::Reference {
+                        self.emit_none_literal();
+                        self.emit_assign_identifier(&identifier.name, target_type, target_type);
+                    }
+                }
+                ExprContent::IndexExpr(expr) => {
+                    self.emit_list_del(&expr.list, Some(&expr.index), None, true);
+                }
+                ExprContent::SliceExpr(expr) => {
+                    let (start, end) = (expr.start.as_ref(), expr.end.as_ref());
+                    self.emit_list_del(&expr.list, start, end, false);
+                }
+                _ => panic!(),
+            }
+        }
+    }
+
+    // Call into standard library to remove `list[start:end]`, or `list[start]`
+    // if `single` is set. Missing bounds default to the whole list.
+    fn emit_list_del(&mut self, list: &Expr, start: Option<&Expr>, end: Option<&Expr>, single: bool) {
+        self.emit_expression(list);
+        self.emit_check_none(list.get_type());
+        let list_ticket = self.alloc_stack(TicketType::Reference);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &list_ticket);
+
+        if let Some(start) = start {
+            self.emit_expression(start);
+        } else {
+            self.emit_int_literal(0);
+        }
+        let start_ticket = self.alloc_stack(TicketType::Plain);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &start_ticket);
+
+        if single {
+            // inc eax
+            self.emit(&[0xFF, 0xC0]);
+        } else if let Some(end) = end {
+            self.emit_expression(end);
+        } else {
+            // mov rsi,[rbp+{}]
+            self.emit_with_stack(&[0x48, 0x8B, 0xB5], &list_ticket);
+            // mov rax,[rsi+ARRAY_LEN_OFFSET]
+            self.emit(&[0x48, 0x8B, 0x46, ARRAY_LEN_OFFSET as u8]);
+        }
+        let end_ticket = self.alloc_stack(TicketType::Plain);
+        // mov [rbp+{}],rax
+        self.emit_with_stack(&[0x48, 0x89, 0x85], &end_ticket);
+
+        match self.platform {
+            Platform::Windows => {
+                // mov rcx,[rbp+{}]
+                self.emit_with_stack(&[0x48, 0x8B, 0x8D], &list_ticket);
+                // mov rdx,[rbp+{}]
+                self.emit_with_stack(&[0x48, 0x8B, 0x95], &start_ticket);
+                // mov r8,[rbp+{}]
+                self.emit_with_stack(&[0x4C, 0x8B, 0x85], &end_ticket);
+            }
+            Platform::Linux | Platform::Macos => {
+                // mov rdi,[rbp+{}]
+                self.emit_with_stack(&[0x48, 0x8B, 0xBD], &list_ticket);
+                // mov rsi,[rbp+{}]
+                self.emit_with_stack(&[0x48, 0x8B, 0xB5], &start_ticket);
+                // mov rdx,[rbp+{}]
+                self.emit_with_stack(&[0x48, 0x8B, 0x95], &end_ticket);
+            }
+        }
+        self.free_stack(end_ticket);
+        self.free_stack(start_ticket);
+        self.free_stack(list_ticket);
+        self.prepare_call(self.platform.stack_reserve());
+        self.call(BUILTIN_LIST_DEL);
+    }
+
     pub fn emit_statement(&mut self, statement: &Stmt, lines: &mut Vec<LineMap>) {
         lines.push(LineMap {
             code_pos: self.pos(),
@@ -1524,6 +2551,18 @@ impl<'a> Emitter<'a> {
             Stmt::ForStmt(stmt) => {
                 self.emit_for_stmt(stmt, lines);
             }
+            Stmt::ReturnStmt(_) if self.generator.is_some() => {
+                self.emit_generator_exit();
+            }
+            Stmt::YieldStmt(stmt) => {
+                self.emit_yield_stmt(stmt);
+            }
+            Stmt::DelStmt(stmt) => {
+                self.emit_del_stmt(stmt);
+            }
+            Stmt::BreakStmt(_) => {
+                self.emit_break_stmt();
+            }
             Stmt::ReturnStmt(stmt) => {
                 if let Some(value) = &stmt.value {
                     self.emit_expression(value);
@@ -1536,6 +2575,117 @@ impl<'a> Emitter<'a> {
         }
     }
 
+    // Offset into the generator object of the slot saving the rbp offset
+    fn generator_slot(offset: i32) -> u32 {
+        GENERATOR_SLOT_OFFSET + (-offset / 8 - 1) as u32 * GENERATOR_SLOT_SIZE
+    }
+
+    // Save all occupied stack frame slots into the generator object
+    pub fn emit_save_frame(&mut self) {
+        // mov rsi,[rbp+16]
+        self.emit(&[0x48, 0x8B, 0x75, 0x10]);
+        for offset in (self.current_stack_top..0).step_by(8) {
+            let slot = Emitter::generator_slot(offset);
+            // mov rdi,[rbp+{}]
+            self.emit(&[0x48, 0x8B, 0xBD]);
+            self.emit(&offset.to_le_bytes());
+            if self.ref_list.contains(&offset) {
+                // mov [rsi+{}],rdi
+                self.emit(&[0x48, 0x89, 0xBE]);
+                self.emit(&slot.to_le_bytes());
+            } else {
+                // mov [rsi+{}],rdi
+                self.emit(&[0x48, 0x89, 0xBE]);
+                self.emit(&(slot + 8).to_le_bytes());
+                // Clear the reference half so that GC doesn't keep stale objects alive
+                // mov QWORD PTR [rsi+{}],0
+                self.emit(&[0x48, 0xC7, 0x86]);
+                self.emit(&slot.to_le_bytes());
+                self.emit(&0i32.to_le_bytes());
+            }
+        }
+
+        let generator = self.generator.as_mut().unwrap();
+        generator.slot_count = std::cmp::max(
+            generator.slot_count,
+            (-self.current_stack_top / 8) as u32,
+        );
+    }
+
+    // Restore all occupied stack frame slots from the generator object
+    pub fn emit_restore_frame(&mut self) {
+        // mov rsi,[rbp+16]
+        self.emit(&[0x48, 0x8B, 0x75, 0x10]);
+        for offset in (self.current_stack_top..0).step_by(8) {
+            let mut slot = Emitter::generator_slot(offset);
+            if !self.ref_list.contains(&offset) {
+                slot += 8;
+            }
+            // mov rdi,[rsi+{}]
+            self.emit(&[0x48, 0x8B, 0xBE]);
+            self.emit(&slot.to_le_bytes());
+            // mov [rbp+{}],rdi
+            self.emit(&[0x48, 0x89, 0xBD]);
+            self.emit(&offset.to_le_bytes());
+        }
+    }
+
+    // Suspend the generator, and continue from here on the next resume
+    pub fn emit_yield_stmt(&mut self, stmt: &YieldStmt) {
+        if let Some(value) = &stmt.value {
+            self.emit_expression(value);
+            self.emit_coerce(value.get_type(), &TYPE_OBJECT);
+        } else {
+            self.emit_none_literal();
+        }
+
+        self.emit_save_frame();
+        let state = self.generator.as_ref().unwrap().resume_points.len() as i32;
+        // mov QWORD PTR [rsi+GENERATOR_STATE_OFFSET],{state}
+        self.emit(&[0x48, 0xC7, 0x46, GENERATOR_STATE_OFFSET as u8]);
+        self.emit(&state.to_le_bytes());
+        self.end_proc();
+
+        let resume_point = self.jump_to();
+        self.generator.as_mut().unwrap().resume_points.push(resume_point);
+        self.emit_restore_frame();
+    }
+
+    // Mark the generator as exhausted and return
+    pub fn emit_generator_exit(&mut self) {
+        // mov rsi,[rbp+16]
+        self.emit(&[0x48, 0x8B, 0x75, 0x10]);
+        // mov QWORD PTR [rsi+GENERATOR_STATE_OFFSET],-1
+        self.emit(&[0x48, 0xC7, 0x46, GENERATOR_STATE_OFFSET as u8]);
+        self.emit(&(-1i32).to_le_bytes());
+        // xor eax,eax
+        self.emit(&[0x31, 0xC0]);
+        self.end_proc();
+    }
+
+    // Jump to the resume point of the current state.
+    // Returns the number of stack frame slots the generator object needs.
+    pub fn emit_generator_dispatch(&mut self) -> u32 {
+        // mov rsi,[rbp+16]
+        self.emit(&[0x48, 0x8B, 0x75, 0x10]);
+        // mov rax,[rsi+GENERATOR_STATE_OFFSET]
+        self.emit(&[0x48, 0x8B, 0x46, GENERATOR_STATE_OFFSET as u8]);
+        let generator = self.generator.take().unwrap();
+        for (state, resume_point) in generator.resume_points.into_iter().enumerate() {
+            // cmp rax,{state}
+            self.emit(&[0x48, 0x3D]);
+            self.emit(&(state as i32).to_le_bytes());
+            // je
+            self.emit(&[0x0F, 0x84]);
+            self.from_here(resume_point);
+        }
+        // Exhausted generator
+        // xor eax,eax
+        self.emit(&[0x31, 0xC0]);
+        self.end_proc();
+        generator.slot_count
+    }
+
     pub fn emit_local_var_init(&mut self, decl: &VarDef) {
         match &decl.value.content {
             LiteralContent::NoneLiteral(_) => {
@@ -1612,6 +2762,7 @@ fn gen_function(
     classes: &BTreeMap<String, ClassSlot>,
     level: u32,
     parent: Option<&str>,
+    strict_none: bool,
     platform: Platform,
 ) -> Vec<Chunk> {
     let link_name = if let Some(parent) = parent {
@@ -1622,21 +2773,30 @@ fn gen_function(
 
     let mut locals = HashMap::new();
 
+    // The body of a generator runs in a resume function taking the generator object,
+    // with parameters and local variables restored from it into the stack frame
+    let generator = function.is_generator();
+
     // Collects slot and debug info for parameters
-    let mut ref_list = vec![];
+    let mut ref_list = if generator { vec![16] } else { vec![] };
     let mut params_debug = vec![];
     for (i, param) in function.params.iter().enumerate() {
-        let offset = i as i32 * 8 + 16;
+        let offset = if generator {
+            -8 - i as i32 * 8
+        } else {
+            i as i32 * 8 + 16
+        };
         let name = &param.identifier.name;
         locals.insert(
             name.clone(),
             LocalSlot::Var(VarSlot {
                 offset,
                 level: level + 1,
+                value: None,
             }),
         );
         let param_type = ValueType::from_annotation(&param.type_);
-        if !param_type.is_plain() {
+        if !param_type.is_plain() && !generator {
             ref_list.push(offset);
         }
 
@@ -1650,7 +2810,13 @@ fn gen_function(
 
     // Collect infos for local variables and nested functions
     let mut locals_debug = vec![];
-    let mut local_offset = if level == 0 { -8 } else { -16 };
+    let mut local_offset = if generator {
+        -8 - function.params.len() as i32 * 8
+    } else if level == 0 {
+        -8
+    } else {
+        -16
+    };
     for declaration in &function.declarations {
         match declaration {
             Declaration::VarDef(v) => {
@@ -1662,6 +2828,7 @@ fn gen_function(
                     LocalSlot::Var(VarSlot {
                         offset,
                         level: level + 1,
+                        value: None,
                     }),
                 );
 
@@ -1689,8 +2856,13 @@ fn gen_function(
     let mut handle = storage_env.push(locals);
     let return_type = ValueType::from_annotation(&function.return_type);
 
+    let code_name = if generator {
+        link_name.clone() + ".$resume"
+    } else {
+        link_name.clone()
+    };
     let mut code = Emitter::new(
-        &link_name,
+        &code_name,
         Some(&return_type),
         Some(handle.inner()),
         Some(classes),
@@ -1698,6 +2870,10 @@ fn gen_function(
         level,
         platform,
     );
+    code.strict_none = strict_none;
+    if level == 0 && parent.is_some() {
+        code.receiver = Some(function.params[0].identifier.name.clone());
+    }
 
     if level != 0 {
         // Save static link
@@ -1707,6 +2883,25 @@ fn gen_function(
         static_link.free_on_exit();
     }
 
+    let dispatch = if generator {
+        for param in &function.params {
+            let param_type = ValueType::from_annotation(&param.type_);
+            code.alloc_stack(param_type.ticket_type()).free_on_exit();
+        }
+        // jmp
+        code.emit(&[0xE9]);
+        let dispatch = code.jump_from();
+        let start = code.jump_to();
+        code.generator = Some(GeneratorFrame {
+            resume_points: vec![start],
+            slot_count: function.params.len() as u32,
+        });
+        code.emit_restore_frame();
+        Some(dispatch)
+    } else {
+        None
+    };
+
     // Initialize local variables
     for declaration in &function.declarations {
         if let Declaration::VarDef(v) = declaration {
@@ -1724,12 +2919,22 @@ fn gen_function(
         code.emit_statement(statement, &mut lines);
     }
 
-    // Implicit `return None`
-    code.emit_none_literal();
-    code.end_proc();
+    let mut chunks = vec![];
+    if let Some(dispatch) = dispatch {
+        code.emit_generator_exit();
+        code.to_here(dispatch);
+        let slot_count = code.emit_generator_dispatch();
+
+        chunks.push(gen_generator_start(function, &link_name, platform));
+        chunks.push(gen_generator_proto(&link_name, slot_count));
+    } else {
+        // Implicit `return None`
+        code.emit_none_literal();
+        code.end_proc();
+    }
 
     // Package code into a chunk
-    let mut chunks = vec![code.finalize(ProcedureDebug {
+    chunks.push(code.finalize(ProcedureDebug {
         decl_line: function.statements[0].base().location.start.row,
         artificial: false,
         parent: if level == 0 {
@@ -1742,7 +2947,7 @@ fn gen_function(
         params: params_debug,
         locals: locals_debug,
         frame_size: 0,
-    })];
+    }));
 
     // Recursively generate codes for nested functions
     // Note: put children functions after the parent one
@@ -1755,6 +2960,7 @@ fn gen_function(
                 classes,
                 level + 1,
                 Some(&link_name),
+                strict_none,
                 platform,
             ));
         }
@@ -1763,6 +2969,98 @@ fn gen_function(
     chunks
 }
 
+// Generate machine code that creates a generator object holding the arguments
+fn gen_generator_start(function: &FuncDef, link_name: &str, platform: Platform) -> Chunk {
+    let mut ref_list = vec![];
+    let mut params_debug = vec![];
+    for (i, param) in function.params.iter().enumerate() {
+        let offset = i as i32 * 8 + 16;
+        if !ValueType::from_annotation(&param.type_).is_plain() {
+            ref_list.push(offset);
+        }
+        params_debug.push(VarDebug {
+            offset,
+            line: param.base().location.start.row,
+            name: param.identifier.name.clone(),
+            var_type: TypeDebug::from_annotation(&param.type_),
+        })
+    }
+
+    let mut code = Emitter::new(link_name, None, None, None, ref_list, 0, platform);
+
+    // xor rsi,rsi
+    code.emit(&[0x48, 0x31, 0xF6]);
+    code.call_builtin_alloc(&(link_name.to_owned() + ".$proto"));
+
+    // The state is zeroed as the initial state.
+    // Arguments are saved as the first stack frame slots of the resume function.
+    for (i, param) in function.params.iter().enumerate() {
+        let mut slot = Emitter::generator_slot(-8 - i as i32 * 8);
+        if ValueType::from_annotation(&param.type_).is_plain() {
+            slot += 8;
+        }
+        // mov rdi,[rbp+{}]
+        code.emit(&[0x48, 0x8B, 0xBD]);
+        code.emit(&(i as i32 * 8 + 16).to_le_bytes());
+        // mov [rax+{}],rdi
+        code.emit(&[0x48, 0x89, 0xB8]);
+        code.emit(&slot.to_le_bytes());
+    }
+    code.end_proc();
+
+    code.finalize(ProcedureDebug {
+        decl_line: function.base().location.start.row,
+        artificial: true,
+        parent: None,
+        lines: vec![],
+        return_type: TypeDebug::from_annotation(&function.return_type),
+        params: params_debug,
+        locals: vec![],
+        frame_size: 0,
+    })
+}
+
+// Generate prototype for generator objects created by a function
+fn gen_generator_proto(link_name: &str, slot_count: u32) -> Chunk {
+    let size = GENERATOR_SLOT_OFFSET - OBJECT_ATTRIBUTE_OFFSET + slot_count * GENERATOR_SLOT_SIZE;
+    let mut code = vec![0; (GENERATOR_RESUME_OFFSET + FUNCTION_POINTER_SIZE) as usize];
+    code[PROTOTYPE_SIZE_OFFSET as usize..][..4].copy_from_slice(&size.to_le_bytes());
+    code[PROTOTYPE_TAG_OFFSET as usize..][..4].copy_from_slice(&(Type::Other as i32).to_le_bytes());
+
+    // Only the reference half of each slot is marked
+    let mut ref_map = vec![0u8; (size as usize / 8).div_ceil(8)];
+    for slot in 0..slot_count {
+        let offset = GENERATOR_SLOT_OFFSET + slot * GENERATOR_SLOT_SIZE;
+        let index = (offset - OBJECT_ATTRIBUTE_OFFSET) as usize / 8;
+        ref_map[index / 8] |= 1 << (index % 8);
+    }
+
+    let links = vec![
+        ChunkLink {
+            pos: PROTOTYPE_MAP_OFFSET as usize,
+            to: ChunkLinkTarget::Data(ref_map),
+        },
+        ChunkLink {
+            pos: PROTOTYPE_SUPER_OFFSET as usize,
+            to: ChunkLinkTarget::Symbol(ITERATOR_PROTOTYPE.to_owned(), 0),
+        },
+        ChunkLink {
+            pos: PROTOTYPE_INIT_OFFSET as usize,
+            to: ChunkLinkTarget::Symbol("object.__init__".to_owned(), 0),
+        },
+        ChunkLink {
+            pos: GENERATOR_RESUME_OFFSET as usize,
+            to: ChunkLinkTarget::Symbol(link_name.to_owned() + ".$resume", 0),
+        },
+    ];
+    Chunk {
+        name: link_name.to_owned() + ".$proto",
+        code,
+        links,
+        extra: ChunkExtra::Data { writable: false },
+    }
+}
+
 // Generate machine code for constructor
 fn gen_ctor(class_name: &str, class_slot: &ClassSlot, platform: Platform) -> Chunk {
     let mut code = Emitter::new(class_name, None, None, None, vec![], 0, platform);
@@ -1961,6 +3259,37 @@ fn gen_len(platform: Platform) -> Chunk {
     })
 }
 
+// Generate machine code for `next`
+fn gen_next(platform: Platform) -> Chunk {
+    let mut code = Emitter::new("next", None, None, None, vec![16], 0, platform);
+    // mov rax,[rbp+16]
+    code.emit(&[0x48, 0x8B, 0x45, 0x10]);
+    code.emit_check_none(&TYPE_OBJECT);
+    code.emit_resume(16);
+    // jne
+    code.emit(&[0x0F, 0x85]);
+    let ok = code.jump_from();
+    code.prepare_call(platform.stack_reserve());
+    code.call(BUILTIN_STOP_ITERATION);
+    code.to_here(ok);
+    code.end_proc();
+    code.finalize(ProcedureDebug {
+        decl_line: 0,
+        artificial: true,
+        parent: None,
+        lines: vec![],
+        return_type: TypeDebug::class_type("object"),
+        params: vec![VarDebug {
+            offset: 16,
+            line: 0,
+            name: "iterator".to_owned(),
+            var_type: TypeDebug::class_type("object"),
+        }],
+        locals: vec![],
+        frame_size: 0,
+    })
+}
+
 // Generate machine code for `input`
 fn gen_input(platform: Platform) -> Chunk {
     let mut code = Emitter::new_simple("input", platform);
@@ -1994,38 +3323,12 @@ fn gen_input(platform: Platform) -> Chunk {
     })
 }
 
-// Generate machine code for `print`
-fn gen_print(platform: Platform) -> Chunk {
-    let mut code = Emitter::new_simple("print", platform);
-    match platform {
-        Platform::Windows => code.emit(&[0x48, 0x8B, 0x4D, 0x10]), //  mov rcx,[rbp+16]
-        Platform::Linux | Platform::Macos => code.emit(&[0x48, 0x8B, 0x7D, 0x10]), // mov rdi,[rbp+16]
-    }
-    code.prepare_call(platform.stack_reserve());
-    code.call(BUILTIN_PRINT);
-    code.end_proc();
-    code.finalize(ProcedureDebug {
-        decl_line: 0,
-        artificial: true,
-        parent: None,
-        lines: vec![],
-        return_type: TypeDebug::class_type("<None>"),
-        params: vec![VarDebug {
-            offset: 16,
-            line: 0,
-            name: "object".to_owned(),
-            var_type: TypeDebug::class_type("object"),
-        }],
-        locals: vec![],
-        frame_size: 0,
-    })
-}
-
 // Generate machine code for main procedure
 fn gen_main(
     ast: &Program,
     storage_env: &mut StorageEnv,
     classes: &BTreeMap<String, ClassSlot>,
+    strict_none: bool,
     platform: Platform,
 ) -> Chunk {
     let mut main_code = Emitter::new(
@@ -2037,6 +3340,7 @@ fn gen_main(
         0,
         platform,
     );
+    main_code.strict_none = strict_none;
 
     // Save rdi/rsi according to Windows ABI. Shadow space is used here
     if platform == Platform::Windows {
@@ -2068,7 +3372,9 @@ fn gen_main(
 
     // Initialize global variables
     for declaration in &ast.declarations {
-        if let Declaration::VarDef(v) = declaration {
+        if let Declaration::VarDef(v) = declaration
+            && constant_value(v).is_none()
+        {
             main_code.emit_global_var_init(v);
         }
     }
@@ -2145,6 +3451,7 @@ fn add_class(
     let class_name = &c.name.name;
     let super_name = &c.super_class.name;
     let mut class_slot = classes.get(super_name).unwrap().clone();
+    class_slot.super_class = Some(super_name.clone());
     let mut class_debug = classes_debug.get(super_name).unwrap().clone();
     // Add constructor function as global function
     globals.insert(
@@ -2244,10 +3551,16 @@ fn gen_special_proto(name: &str, size: i32, tag: Type) -> Chunk {
     code[PROTOTYPE_SIZE_OFFSET as usize..][..4].copy_from_slice(&size.to_le_bytes());
     code[PROTOTYPE_TAG_OFFSET as usize..][..4].copy_from_slice(&(tag as i32).to_le_bytes());
     code[PROTOTYPE_MAP_OFFSET as usize..][..8].copy_from_slice(&(0u64).to_le_bytes());
-    let links = vec![ChunkLink {
-        pos: PROTOTYPE_INIT_OFFSET as usize,
-        to: ChunkLinkTarget::Symbol("object.__init__".to_owned(), 0),
-    }];
+    let links = vec![
+        ChunkLink {
+            pos: PROTOTYPE_SUPER_OFFSET as usize,
+            to: ChunkLinkTarget::Symbol("object.$proto".to_owned(), 0),
+        },
+        ChunkLink {
+            pos: PROTOTYPE_INIT_OFFSET as usize,
+            to: ChunkLinkTarget::Symbol("object.__init__".to_owned(), 0),
+        },
+    ];
     Chunk {
         name: name.to_owned(),
         code,
@@ -2257,7 +3570,7 @@ fn gen_special_proto(name: &str, size: i32, tag: Type) -> Chunk {
 }
 
 // Generate the ChocoPy machine code
-pub(super) fn gen_code_set(ast: Program, platform: Platform) -> CodeSet {
+pub(super) fn gen_code_set(ast: Program, strict_none: bool, platform: Platform) -> CodeSet {
     let mut globals = HashMap::new();
     let mut global_ref_indexs = vec![];
     let mut classes = BTreeMap::new();
@@ -2278,6 +3591,7 @@ pub(super) fn gen_code_set(ast: Program, platform: Platform) -> CodeSet {
             object_size: 0,
             methods: base_methods,
             prototype_size: OBJECT_PROTOTYPE_SIZE,
+            super_class: None,
         },
     );
     let mut global_offset = 0;
@@ -2309,6 +3623,17 @@ pub(super) fn gen_code_set(ast: Program, platform: Platform) -> CodeSet {
                 // Allocate global variable
                 let name = &v.var.identifier.name;
                 let target_type = ValueType::from_annotation(&v.var.type_);
+                if let Some(value) = constant_value(v) {
+                    globals.insert(
+                        name.clone(),
+                        LocalSlot::Var(VarSlot {
+                            offset: 0,
+                            level: 0,
+                            value: Some(value),
+                        }),
+                    );
+                    continue;
+                }
                 let size = if target_type == *TYPE_INT {
                     4
                 } else if target_type == *TYPE_BOOL {
@@ -2322,6 +3647,7 @@ pub(super) fn gen_code_set(ast: Program, platform: Platform) -> CodeSet {
                     LocalSlot::Var(VarSlot {
                         offset: global_offset,
                         level: 0,
+                        value: None,
                     }),
                 );
 
@@ -2368,6 +3694,7 @@ pub(super) fn gen_code_set(ast: Program, platform: Platform) -> CodeSet {
     };
 
     insert_builtin(&mut globals, "len");
+    // `print` has no procedure of its own; calls are emitted inline
     insert_builtin(&mut globals, "print");
     insert_builtin(&mut globals, "input");
     insert_builtin(&mut globals, "str");
@@ -2375,10 +3702,16 @@ pub(super) fn gen_code_set(ast: Program, platform: Platform) -> CodeSet {
     insert_builtin(&mut globals, "bool");
     insert_builtin(&mut globals, "object");
 
+    // `next` can be replaced by a global declaration of the program
+    let builtin_next = !globals.contains_key("next");
+    if builtin_next {
+        insert_builtin(&mut globals, "next");
+    }
+
     let mut storage_env = StorageEnv::new(globals);
 
     // Generate machine code for main procedure
-    let mut chunks = vec![gen_main(&ast, &mut storage_env, &classes, platform)];
+    let mut chunks = vec![gen_main(&ast, &mut storage_env, &classes, strict_none, platform)];
 
     // Generate machine code for all functions and methods
     for declaration in &ast.declarations {
@@ -2390,6 +3723,7 @@ pub(super) fn gen_code_set(ast: Program, platform: Platform) -> CodeSet {
                     &classes,
                     0,
                     None,
+                    strict_none,
                     platform,
                 ));
             }
@@ -2402,6 +3736,7 @@ pub(super) fn gen_code_set(ast: Program, platform: Platform) -> CodeSet {
                             &classes,
                             0,
                             Some(&c.name.name),
+                            strict_none,
                             platform,
                         ));
                     }
@@ -2440,6 +3775,12 @@ pub(super) fn gen_code_set(ast: Program, platform: Platform) -> CodeSet {
             pos: PROTOTYPE_MAP_OFFSET as usize,
             to: ChunkLinkTarget::Data(ref_map),
         });
+        if let Some(super_class) = &class_slot.super_class {
+            links.push(ChunkLink {
+                pos: PROTOTYPE_SUPER_OFFSET as usize,
+                to: ChunkLinkTarget::Symbol(super_class.clone() + ".$proto", 0),
+            });
+        }
         chunks.push(Chunk {
             name: class_name.clone() + ".$proto",
             code: prototype,
@@ -2454,8 +3795,10 @@ pub(super) fn gen_code_set(ast: Program, platform: Platform) -> CodeSet {
     chunks.push(gen_str(platform));
     chunks.push(gen_object_init(platform));
     chunks.push(gen_len(platform));
+    if builtin_next {
+        chunks.push(gen_next(platform));
+    }
     chunks.push(gen_input(platform));
-    chunks.push(gen_print(platform));
 
     // Generate prototypes for primitive types
     chunks.push(gen_special_proto(INT_PROTOTYPE, 4, Type::Int));
@@ -2464,6 +3807,8 @@ pub(super) fn gen_code_set(ast: Program, platform: Platform) -> CodeSet {
     chunks.push(gen_special_proto(INT_LIST_PROTOTYPE, -4, Type::ValueList));
     chunks.push(gen_special_proto(BOOL_LIST_PROTOTYPE, -1, Type::ValueList));
     chunks.push(gen_special_proto(OBJECT_LIST_PROTOTYPE, -8, Type::ObjList));
+    chunks.push(gen_special_proto(ITERATOR_PROTOTYPE, 0, Type::Other));
+    chunks.push(gen_special_proto(SET_PROTOTYPE, SET_SIZE as i32, Type::Set));
 
     // Generate configuration data for initialization
     chunks.push(gen_init_param(global_offset as u64, &global_ref_indexs));
@@ -0,0 +1,874 @@
+// A canonical pretty-printer from a parsed program back to source code, keeping
+// the comments and (at most single) blank lines of the source. Expressions are
+// parenthesized only where the precedence levels of the parser require it.
+
+use super::token::*;
+use crate::common::location::{Location, Position};
+use crate::common::node::*;
+use std::collections::HashSet;
+
+const INDENT: &str = "    ";
+
+// Precedence levels, numbered like the `parse_exprN` functions of the parser
+const IF_EXPR: u8 = 1;
+const OR: u8 = 2;
+const AND: u8 = 3;
+const NOT: u8 = 4;
+const COMPARISON: u8 = 5;
+const BIT_OR: u8 = 6;
+const BIT_XOR: u8 = 7;
+const BIT_AND: u8 = 8;
+const SHIFT: u8 = 9;
+const SUM: u8 = 10;
+const PRODUCT: u8 = 11;
+const UNARY: u8 = 12;
+const POWER: u8 = 13;
+const POSTFIX: u8 = 14;
+const ATOM: u8 = 15;
+
+fn binary_level(operator: &BinaryOp) -> u8 {
+    match operator {
+        BinaryOp::Or => OR,
+        BinaryOp::And => AND,
+        BinaryOp::Eq
+        | BinaryOp::Ne
+        | BinaryOp::Lt
+        | BinaryOp::Gt
+        | BinaryOp::Le
+        | BinaryOp::Ge
+        | BinaryOp::Is
+        | BinaryOp::In => COMPARISON,
+        BinaryOp::BitOr => BIT_OR,
+        BinaryOp::BitXor => BIT_XOR,
+        BinaryOp::BitAnd => BIT_AND,
+        BinaryOp::Shl | BinaryOp::Shr => SHIFT,
+        BinaryOp::Add | BinaryOp::Sub => SUM,
+        BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => PRODUCT,
+        BinaryOp::Pow => POWER,
+    }
+}
+
+fn binary_operator(operator: &BinaryOp) -> &'static str {
+    match operator {
+        BinaryOp::Or => "or",
+        BinaryOp::And => "and",
+        BinaryOp::Add => "+",
+        BinaryOp::Sub => "-",
+        BinaryOp::Mul => "*",
+        BinaryOp::Div => "//",
+        BinaryOp::Mod => "%",
+        BinaryOp::Pow => "**",
+        BinaryOp::BitAnd => "&",
+        BinaryOp::BitOr => "|",
+        BinaryOp::BitXor => "^",
+        BinaryOp::Shl => "<<",
+        BinaryOp::Shr => ">>",
+        BinaryOp::Eq => "==",
+        BinaryOp::Ne => "!=",
+        BinaryOp::Lt => "<",
+        BinaryOp::Gt => ">",
+        BinaryOp::Le => "<=",
+        BinaryOp::Ge => ">=",
+        BinaryOp::Is => "is",
+        BinaryOp::In => "in",
+    }
+}
+
+// `a is not b` and `a not in b`, which the parser desugars into `not (a is b)`
+// and `not (a in b)`
+fn negated_comparison(e: &UnaryExpr) -> Option<(&BinaryExpr, &'static str)> {
+    match (&e.operator, &e.operand.content) {
+        (UnaryOp::Not, ExprContent::BinaryExpr(b)) => match b.operator {
+            BinaryOp::Is => Some((b, "is not")),
+            BinaryOp::In => Some((b, "not in")),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn level(e: &Expr) -> u8 {
+    match &e.content {
+        ExprContent::IfExpr(_) => IF_EXPR,
+        ExprContent::BinaryExpr(b) => binary_level(&b.operator),
+        ExprContent::UnaryExpr(u) if negated_comparison(u).is_some() => COMPARISON,
+        ExprContent::UnaryExpr(u) if u.operator == UnaryOp::Not => NOT,
+        ExprContent::UnaryExpr(_) => UNARY,
+        ExprContent::CallExpr(_)
+        | ExprContent::IndexExpr(_)
+        | ExprContent::MemberExpr(_)
+        | ExprContent::MethodCallExpr(_)
+        | ExprContent::SliceExpr(_) => POSTFIX,
+        _ => ATOM,
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains_key(name)
+}
+
+// A string literal in double quotes, or in single quotes if that saves escapes.
+// Multi-line strings without other special characters are triple-quoted.
+fn string_literal(value: &str) -> String {
+    let printable = |c: char| matches!(c, ' '..='~');
+    // Text spanning several lines, but not a single line ending with `\n`
+    if value.trim_end_matches('\n').contains('\n')
+        && value.chars().all(|c| c == '\n' || (printable(c) && c != '\\'))
+        && !value.contains("\"\"\"")
+        && !value.ends_with('"')
+    {
+        return format!("\"\"\"{}\"\"\"", value);
+    }
+
+    let quote = if value.contains('"') && !value.contains('\'') {
+        '\''
+    } else {
+        '"'
+    };
+    let mut s = quote.to_string();
+    for c in value.chars() {
+        match c {
+            '\\' => s.push_str("\\\\"),
+            '\n' => s.push_str("\\n"),
+            '\t' => s.push_str("\\t"),
+            '\r' => s.push_str("\\r"),
+            c if c == quote => {
+                s.push('\\');
+                s.push(c);
+            }
+            c if printable(c) => s.push(c),
+            c if (c as u32) < 0x100 => s.push_str(&format!("\\x{:02x}", c as u32)),
+            c if (c as u32) < 0x10000 => s.push_str(&format!("\\u{:04x}", c as u32)),
+            c => s.push_str(&format!("\\U{:08x}", c as u32)),
+        }
+    }
+    s.push(quote);
+    s
+}
+
+struct Comment {
+    text: String,
+    location: Location,
+    own_line: bool,
+}
+
+struct Printer {
+    out: String,
+    indent: usize,
+    comments: Vec<Comment>,
+    // The index of the first comment not printed yet
+    next_comment: usize,
+    blank_lines: HashSet<u32>,
+    // Classes of the program that are not fully declared yet, whose names are
+    // quoted in annotations as Python requires
+    pending_classes: HashSet<String>,
+    // The `else` and `pass` keywords, which the AST does not locate
+    else_keywords: Vec<Location>,
+    pass_keywords: Vec<Location>,
+    // The lines of the source, for the text of raw strings
+    lines: Vec<String>,
+}
+
+impl Printer {
+    fn line_start(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    fn blank_line_before(&self, row: u32) -> bool {
+        self.blank_lines.contains(&(row - 1))
+    }
+
+    fn pending_comment(&self, before: u32) -> Option<&Comment> {
+        self.comments.get(self.next_comment).filter(|c| c.location.start.row < before)
+    }
+
+    // End the line printed for source row `row`, with the comment after it
+    fn line_end(&mut self, row: u32) {
+        if let Some(comment) = self.comments.get(self.next_comment)
+            && !comment.own_line
+            && comment.location.start.row == row
+        {
+            self.out.push_str("  ");
+            self.out.push_str(&comment.text);
+            self.next_comment += 1;
+        }
+        self.out.push('\n');
+    }
+
+    fn comment_line(&mut self) {
+        let comment = &self.comments[self.next_comment];
+        let text = comment.text.clone();
+        self.next_comment += 1;
+        self.line_start();
+        self.out.push_str(&text);
+        self.out.push('\n');
+    }
+
+    // Print the comments before source row `row`, and the blank lines before
+    // them and the line at `row`. `blank_lines` is the number of blank lines
+    // required before the first of them, or `None` at the start of a block.
+    fn leading(&mut self, row: u32, blank_lines: Option<usize>) {
+        let mut required = blank_lines;
+        while let Some(comment) = self.pending_comment(row) {
+            let row = comment.location.start.row;
+            self.blank_lines_before(row, required);
+            self.comment_line();
+            required = Some(0);
+        }
+        self.blank_lines_before(row, required);
+    }
+
+    fn blank_lines_before(&mut self, row: u32, required: Option<usize>) {
+        if let Some(required) = required {
+            let count = required.max(self.blank_line_before(row) as usize);
+            for _ in 0..count {
+                self.out.push('\n');
+            }
+        }
+    }
+
+    // Print the comments at the end of a block, which are indented deeper than
+    // the statement `column` opening it and come before source row `before`
+    fn trailing(&mut self, column: u32, before: u32) {
+        while let Some(comment) = self.pending_comment(before) {
+            if comment.location.start.col <= column {
+                break;
+            }
+            let row = comment.location.start.row;
+            self.blank_lines_before(row, Some(0));
+            self.comment_line();
+        }
+    }
+
+    // Whether there are comments in the block from source row `row` to `before`
+    // indented deeper than the statement at `column`, or after code at `row`
+    fn has_comments(&self, column: u32, row: u32, before: u32) -> bool {
+        self.comments[self.next_comment..]
+            .iter()
+            .take_while(|c| c.location.start.row < before)
+            .any(|c| {
+                let start = c.location.start;
+                start.row >= row && (start.row == row || start.col > column)
+            })
+    }
+
+    // The row of the `else` keyword of the compound statement starting at
+    // `start`, if there is one before source row `before`
+    fn else_row(&self, start: Position, before: u32) -> Option<u32> {
+        self.else_keywords
+            .iter()
+            .map(|keyword| keyword.start)
+            .find(|k| k.col == start.col && k.row > start.row && k.row < before)
+            .map(|k| k.row)
+    }
+
+    // A raw string literal as written, since its backslashes are part of the
+    // value, or a string literal in canonical form
+    fn string(&self, s: &StringLiteral) -> String {
+        let Location { start, end } = s.base.location;
+        let rows = &self.lines[start.row as usize - 1..end.row as usize];
+        let text = if let [line] = rows {
+            line[start.col as usize - 1..end.col as usize].to_owned()
+        } else {
+            let first = &rows[0][start.col as usize - 1..];
+            let last = &rows[rows.len() - 1][..end.col as usize];
+            let middle = rows[1..rows.len() - 1].iter().map(String::as_str);
+            let rows: Vec<&str> = std::iter::once(first).chain(middle).chain([last]).collect();
+            rows.join("\n")
+        };
+        if text.starts_with(['r', 'R']) { text } else { string_literal(&s.value) }
+    }
+
+    // Print `pass` for an empty block, after the comments before the last
+    // `pass` of the block and with the comment after it if any
+    fn pass(&mut self, before: u32) {
+        let pass = self.pass_keywords.iter().rev().find(|k| k.start.row < before);
+        if let Some(pass) = pass {
+            self.leading(pass.start.row, None);
+        }
+        self.line_start();
+        self.out.push_str("pass");
+        match self.pending_comment(before) {
+            Some(comment) if !comment.own_line => {
+                let row = comment.location.start.row;
+                self.line_end(row);
+            }
+            _ => self.out.push('\n'),
+        }
+    }
+
+    fn annotation(&self, t: &TypeAnnotation) -> String {
+        match t {
+            TypeAnnotation::ClassType(c) => {
+                // The parser reads no type arguments after a quoted name
+                let quoted = (self.pending_classes.contains(&c.class_name)
+                    || !is_identifier(&c.class_name))
+                    && c.type_args.is_empty();
+                let mut s = if quoted {
+                    string_literal(&c.class_name)
+                } else {
+                    c.class_name.clone()
+                };
+                if !c.type_args.is_empty() {
+                    s.push_str(&format!("[{}]", self.annotations(&c.type_args)));
+                }
+                s
+            }
+            TypeAnnotation::ListType(l) => format!("[{}]", self.annotation(&l.element_type)),
+            TypeAnnotation::OptionalType(o) => {
+                format!("Optional[{}]", self.annotation(&o.element_type))
+            }
+        }
+    }
+
+    fn annotations(&self, types: &[TypeAnnotation]) -> String {
+        types.iter().map(|t| self.annotation(t)).collect::<Vec<_>>().join(", ")
+    }
+
+    fn var_def(&mut self, v: &VarDef) {
+        self.line_start();
+        let value = match &v.value.content {
+            LiteralContent::IntegerLiteral(i) => i.value.to_string(),
+            LiteralContent::BooleanLiteral(b) => if b.value { "True" } else { "False" }.to_owned(),
+            LiteralContent::NoneLiteral(_) => "None".to_owned(),
+            LiteralContent::StringLiteral(s) => self.string(s),
+        };
+        let t = self.annotation(&v.var.type_);
+        let t = if v.is_final { format!("Final[{}]", t) } else { t };
+        let line = format!("{}: {} = {}", v.var.identifier.name, t, value);
+        self.out.push_str(&line);
+        self.line_end(v.base.location.end.row);
+    }
+
+    fn func_def(&mut self, f: &FuncDef, before: u32) {
+        self.line_start();
+        let params: Vec<String> = f
+            .params
+            .iter()
+            .map(|p| format!("{}: {}", p.identifier.name, self.annotation(&p.type_)))
+            .collect();
+        let header = format!("def {}({})", f.name.name, params.join(", "));
+        self.out.push_str(&header);
+        if !matches!(&f.return_type, TypeAnnotation::ClassType(c) if c.class_name == "<None>") {
+            let return_type = format!(" -> {}", self.annotation(&f.return_type));
+            self.out.push_str(&return_type);
+        }
+        self.out.push(':');
+        self.line_end(f.name.base.location.start.row);
+
+        self.indent += 1;
+        let items: Vec<Item> = f
+            .declarations
+            .iter()
+            .map(Item::Declaration)
+            .chain(f.statements.iter().map(Item::Statement))
+            .collect();
+        self.items(&items, f.base.location.start.col, before);
+        self.indent -= 1;
+    }
+
+    fn class_def(&mut self, c: &ClassDef, before: u32) {
+        self.line_start();
+        let generic = format!("Generic[{}]", {
+            let params: Vec<&str> = c.type_params.iter().map(|p| p.name.as_str()).collect();
+            params.join(", ")
+        });
+        let bases = match (c.super_class.name.as_str(), c.type_params.is_empty()) {
+            (name, true) => name.to_owned(),
+            ("object", false) => generic,
+            (name, false) => format!("{}, {}", name, generic),
+        };
+        let header = format!("class {}({}):", c.name.name, bases);
+        self.out.push_str(&header);
+        self.line_end(c.name.base.location.start.row);
+
+        self.indent += 1;
+        let items: Vec<Item> = c.declarations.iter().map(Item::Declaration).collect();
+        self.items(&items, c.base.location.start.col, before);
+        self.indent -= 1;
+        self.pending_classes.remove(&c.name.name);
+    }
+
+    // Print an indented block of statements, and `else` blocks following it
+    fn block(&mut self, statements: &[Stmt], column: u32, before: u32) {
+        self.indent += 1;
+        let items: Vec<Item> = statements.iter().map(Item::Statement).collect();
+        self.items(&items, column, before);
+        self.indent -= 1;
+    }
+
+    // The row of the first statement of a block, or of the next item for an
+    // empty one
+    fn block_row(statements: &[Stmt], before: u32) -> u32 {
+        statements.first().map_or(before, |s| s.base().location.start.row)
+    }
+
+    // Print an `else` block, whose keyword is at source row `row`
+    fn else_block(&mut self, statements: &[Stmt], column: u32, row: u32, before: u32) {
+        self.line_start();
+        self.out.push_str("else:");
+        self.line_end(row);
+        self.block(statements, column, before);
+    }
+
+    // Print the body and `else` block of a loop
+    fn loop_blocks(
+        &mut self,
+        body: &[Stmt],
+        else_body: Option<&[Stmt]>,
+        start: Position,
+        before: u32,
+    ) {
+        let else_start = else_body.map_or(before, |b| Self::block_row(b, before));
+        let else_row = else_body.map(|_| self.else_row(start, else_start).unwrap_or(else_start));
+        self.block(body, start.col, else_row.unwrap_or(before));
+        if let (Some(else_body), Some(row)) = (else_body, else_row) {
+            self.else_block(else_body, start.col, row, before);
+        }
+    }
+
+    fn statement(&mut self, s: &Stmt, before: u32) {
+        let location = s.base().location;
+        self.line_start();
+        match s {
+            Stmt::ExprStmt(s) => {
+                let line = self.expr(&s.expr, IF_EXPR);
+                self.out.push_str(&line);
+            }
+            Stmt::AssignStmt(s) => {
+                for target in &s.targets {
+                    let target = self.expr(target, IF_EXPR);
+                    self.out.push_str(&target);
+                    self.out.push_str(" = ");
+                }
+                let value = self.expr(&s.value, IF_EXPR);
+                self.out.push_str(&value);
+            }
+            Stmt::ReturnStmt(ReturnStmt { value, .. })
+            | Stmt::YieldStmt(YieldStmt { value, .. }) => {
+                let keyword = if matches!(s, Stmt::ReturnStmt(_)) { "return" } else { "yield" };
+                self.out.push_str(keyword);
+                if let Some(value) = value {
+                    let value = self.expr(value, IF_EXPR);
+                    self.out.push(' ');
+                    self.out.push_str(&value);
+                }
+            }
+            Stmt::DelStmt(s) => {
+                let targets = self.exprs(&s.targets);
+                self.out.push_str(&format!("del {}", targets));
+            }
+            Stmt::BreakStmt(_) => self.out.push_str("break"),
+            Stmt::IfStmt(s) => {
+                self.if_stmt(s, before);
+                return;
+            }
+            Stmt::WhileStmt(s) => {
+                let header = format!("while {}:", self.expr(&s.condition, IF_EXPR));
+                self.out.push_str(&header);
+                self.line_end(location.start.row);
+                self.loop_blocks(&s.body, s.else_body.as_deref(), location.start, before);
+                return;
+            }
+            Stmt::ForStmt(s) => {
+                let iterable = self.expr(&s.iterable, IF_EXPR);
+                let header = format!("for {} in {}:", s.identifier.name, iterable);
+                self.out.push_str(&header);
+                self.line_end(location.start.row);
+                self.loop_blocks(&s.body, s.else_body.as_deref(), location.start, before);
+                return;
+            }
+        }
+        self.line_end(location.end.row);
+    }
+
+    // Print an `if` statement, whose `else` block with a single `if` statement
+    // is an `elif`. An `else` block of only `pass` is left out, unless it holds
+    // comments.
+    fn if_stmt(&mut self, s: &IfStmt, before: u32) {
+        let start = s.base.location.start;
+        let column = start.col;
+        let header = format!("if {}:", self.expr(&s.condition, IF_EXPR));
+        self.out.push_str(&header);
+        self.line_end(start.row);
+        let else_start = Self::block_row(&s.else_body, before);
+        let else_row = self.else_row(start, else_start);
+        self.block(&s.then_body, column, else_row.unwrap_or(else_start));
+        match (&s.else_body[..], else_row) {
+            ([], Some(row)) if self.has_comments(column, row, before) => {
+                self.else_block(&[], column, row, before);
+            }
+            ([], _) => (),
+            ([Stmt::IfStmt(elif)], row)
+                if row.is_none_or(|row| !self.has_comments(column, row, else_start)) =>
+            {
+                self.leading(elif.base.location.start.row, Some(0));
+                self.line_start();
+                self.out.push_str("el");
+                self.if_stmt(elif, before);
+            }
+            (else_body, row) => {
+                self.else_block(else_body, column, row.unwrap_or(else_start), before);
+            }
+        }
+    }
+
+    fn exprs(&self, exprs: &[Expr]) -> String {
+        exprs.iter().map(|e| self.expr(e, IF_EXPR)).collect::<Vec<_>>().join(", ")
+    }
+
+    // Print an expression where the parser expects level `min_level`
+    fn expr(&self, e: &Expr, min_level: u8) -> String {
+        let s = match &e.content {
+            ExprContent::IfExpr(e) => format!(
+                "{} if {} else {}",
+                self.expr(&e.then_expr, OR),
+                self.expr(&e.condition, IF_EXPR),
+                self.expr(&e.else_expr, IF_EXPR)
+            ),
+            ExprContent::BinaryExpr(b) => {
+                let level = binary_level(&b.operator);
+                let (left, right) = match level {
+                    COMPARISON => (BIT_OR, BIT_OR),
+                    POWER => (POSTFIX, UNARY),
+                    _ => (level, level + 1),
+                };
+                format!(
+                    "{} {} {}",
+                    self.expr(&b.left, left),
+                    binary_operator(&b.operator),
+                    self.expr(&b.right, right)
+                )
+            }
+            ExprContent::UnaryExpr(u) => {
+                if let Some((b, operator)) = negated_comparison(u) {
+                    let left = self.expr(&b.left, BIT_OR);
+                    format!("{} {} {}", left, operator, self.expr(&b.right, BIT_OR))
+                } else {
+                    match u.operator {
+                        UnaryOp::Not => format!("not {}", self.expr(&u.operand, COMPARISON)),
+                        UnaryOp::Negative => format!("-{}", self.expr(&u.operand, UNARY)),
+                        UnaryOp::Invert => format!("~{}", self.expr(&u.operand, UNARY)),
+                    }
+                }
+            }
+            ExprContent::IntegerLiteral(i) => i.value.to_string(),
+            ExprContent::BooleanLiteral(b) => if b.value { "True" } else { "False" }.to_owned(),
+            ExprContent::NoneLiteral(_) => "None".to_owned(),
+            ExprContent::StringLiteral(s) => self.string(s),
+            ExprContent::Variable(v) => v.name.clone(),
+            ExprContent::CallExpr(c) => {
+                let mut args: Vec<String> = c.args.iter().map(|a| self.expr(a, IF_EXPR)).collect();
+                args.extend(
+                    c.keywords
+                        .iter()
+                        .map(|k| format!("{}={}", k.name.name, self.expr(&k.value, IF_EXPR))),
+                );
+                let type_args = if c.type_args.is_empty() {
+                    String::new()
+                } else {
+                    format!("[{}]", self.annotations(&c.type_args))
+                };
+                format!("{}{}({})", c.function.name, type_args, args.join(", "))
+            }
+            ExprContent::MethodCallExpr(m) => format!(
+                "{}.{}({})",
+                self.expr(&m.method.object, POSTFIX),
+                m.method.member.name,
+                self.exprs(&m.args)
+            ),
+            ExprContent::MemberExpr(m) => {
+                format!("{}.{}", self.expr(&m.object, POSTFIX), m.member.name)
+            }
+            ExprContent::IndexExpr(i) => {
+                format!("{}[{}]", self.expr(&i.list, POSTFIX), self.expr(&i.index, IF_EXPR))
+            }
+            ExprContent::SliceExpr(s) => {
+                let bound = |e: &Option<Expr>| {
+                    e.as_ref().map_or(String::new(), |e| self.expr(e, IF_EXPR))
+                };
+                let list = self.expr(&s.list, POSTFIX);
+                format!("{}[{}:{}]", list, bound(&s.start), bound(&s.end))
+            }
+            ExprContent::ListExpr(l) => format!("[{}]", self.exprs(&l.elements)),
+            ExprContent::SetExpr(s) => format!("{{{}}}", self.exprs(&s.elements)),
+            ExprContent::ListComprehension(c) => {
+                let mut s = format!("[{}", self.expr(&c.element, IF_EXPR));
+                for clause in &c.clauses {
+                    let iterable = self.expr(&clause.iterable, OR);
+                    s.push_str(&format!(" for {} in {}", clause.identifier.name, iterable));
+                    for condition in &clause.conditions {
+                        s.push_str(&format!(" if {}", self.expr(condition, OR)));
+                    }
+                }
+                s.push(']');
+                s
+            }
+        };
+        if level(e) < min_level { format!("({})", s) } else { s }
+    }
+
+    // Print the declarations and statements of a block, separating functions
+    // and classes by blank lines. At the end of a block, print the comments
+    // indented deeper than the statement at `column` opening it.
+    fn items(&mut self, items: &[Item], column: u32, before: u32) {
+        if items.is_empty() {
+            self.pass(before);
+        }
+        let top_level = self.indent == 0;
+        for (i, item) in items.iter().enumerate() {
+            let next = items.get(i + 1).map_or(before, |next| next.row());
+            let blank_lines = if i == 0 {
+                None
+            } else if item.is_definition() || items[i - 1].is_definition() {
+                Some(if top_level { 2 } else { 1 })
+            } else {
+                Some(0)
+            };
+            self.leading(item.row(), blank_lines);
+            match item {
+                Item::Declaration(Declaration::VarDef(v)) => self.var_def(v),
+                Item::Declaration(Declaration::FuncDef(f)) => self.func_def(f, next),
+                Item::Declaration(Declaration::ClassDef(c)) => self.class_def(c, next),
+                Item::Declaration(Declaration::GlobalDecl(GlobalDecl { base, variable }))
+                | Item::Declaration(Declaration::NonLocalDecl(NonLocalDecl { base, variable })) => {
+                    let keyword = match item {
+                        Item::Declaration(Declaration::GlobalDecl(_)) => "global",
+                        _ => "nonlocal",
+                    };
+                    self.line_start();
+                    self.out.push_str(&format!("{} {}", keyword, variable.name));
+                    self.line_end(base.location.end.row);
+                }
+                Item::Statement(s) => self.statement(s, next),
+            }
+        }
+        if !top_level {
+            self.trailing(column, before);
+        }
+    }
+}
+
+enum Item<'a> {
+    Declaration(&'a Declaration),
+    Statement(&'a Stmt),
+}
+
+impl Item<'_> {
+    fn row(&self) -> u32 {
+        match self {
+            Item::Declaration(d) => d.base().location.start.row,
+            Item::Statement(s) => s.base().location.start.row,
+        }
+    }
+
+    fn is_definition(&self) -> bool {
+        matches!(self, Item::Declaration(Declaration::FuncDef(_) | Declaration::ClassDef(_)))
+    }
+}
+
+// Format a program without syntax errors, given its source and tokens, which
+// carry the comments and blank lines
+pub fn format(ast: &Program, source: &str, tokens: impl Iterator<Item = ComplexToken>) -> String {
+    let mut comments = vec![];
+    let mut blank_lines = HashSet::new();
+    let mut else_keywords = vec![];
+    let mut pass_keywords = vec![];
+    let mut trivia = vec![];
+    for token in tokens {
+        match token.token {
+            Token::Else => else_keywords.push(token.location),
+            Token::Pass => pass_keywords.push(token.location),
+            _ => (),
+        }
+        trivia.extend(token.trivia);
+    }
+    for trivia in trivia {
+        match trivia {
+            Trivia::Comment {
+                text,
+                location,
+                own_line,
+            } => comments.push(Comment {
+                text,
+                location,
+                own_line,
+            }),
+            Trivia::BlankLine(row) => {
+                blank_lines.insert(row);
+            }
+        }
+    }
+    let pending_classes = ast
+        .declarations
+        .iter()
+        .filter_map(|d| match d {
+            Declaration::ClassDef(c) => Some(c.name.name.clone()),
+            _ => None,
+        })
+        .collect();
+    let mut printer = Printer {
+        out: String::new(),
+        indent: 0,
+        comments,
+        next_comment: 0,
+        blank_lines,
+        pending_classes,
+        else_keywords,
+        pass_keywords,
+        lines: source.lines().map(str::to_owned).collect(),
+    };
+
+    let items: Vec<Item> = ast
+        .declarations
+        .iter()
+        .map(Item::Declaration)
+        .chain(ast.statements.iter().map(Item::Statement))
+        .collect();
+    if !items.is_empty() {
+        printer.items(&items, 0, u32::MAX);
+    }
+    // Comments after the last statement
+    let mut required = Some(0);
+    while printer.next_comment < printer.comments.len() {
+        let row = printer.comments[printer.next_comment].location.start.row;
+        printer.blank_lines_before(row, required.filter(|_| !printer.out.is_empty()));
+        printer.comment_line();
+        required = Some(0);
+    }
+    printer.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{format_source, process_source};
+    use crate::common::node::Program;
+    use serde_json::Value;
+
+    // The JSON of a program without locations
+    fn without_locations(ast: &Program) -> Value {
+        fn strip(value: &mut Value) {
+            match value {
+                Value::Object(map) => {
+                    map.remove("location");
+                    map.values_mut().for_each(strip);
+                }
+                Value::Array(values) => values.iter_mut().for_each(strip),
+                _ => (),
+            }
+        }
+        let mut value = serde_json::to_value(ast).unwrap();
+        strip(&mut value);
+        value
+    }
+
+    #[test]
+    fn canonical() {
+        let source = "\
+# Counters
+class Counter(object):
+    count:int=0 # how many
+    def inc(self:\"Counter\", by:int)->int:
+
+        self.count=self.count+by
+        return self.count
+c:Counter=None
+x:int=0
+x=(1+2)*3
+if x>3 :
+    x=-(x-1)
+elif not x is c:
+    pass
+else:
+    x=x**2**3
+print('a'+\"b\\n\")
+";
+        let expected = "\
+# Counters
+class Counter(object):
+    count: int = 0  # how many
+
+    def inc(self: \"Counter\", by: int) -> int:
+        self.count = self.count + by
+        return self.count
+
+
+c: Counter = None
+x: int = 0
+x = (1 + 2) * 3
+if x > 3:
+    x = -(x - 1)
+elif x is not c:
+    pass
+else:
+    x = x ** 2 ** 3
+print(\"a\" + \"b\\n\")
+";
+        assert_eq!(format_source(source).unwrap(), expected);
+    }
+
+    // Comments stay in their blocks, including `else` blocks of only `pass`,
+    // which are otherwise left out, and raw strings keep their text
+    #[test]
+    fn layout() {
+        let source = "\
+x: int = 0
+if x:
+    pass
+else:
+    # important
+    pass
+if x:
+    x = 1
+else:  # why
+    # first
+    x = 2
+while x:
+    x = 1
+else:
+    # loop
+    x = 3
+print(r\"a\\nb\" + R'c\"')
+";
+        assert_eq!(format_source(source).unwrap(), source);
+        let without_comment = source.replace("    # important\n", "");
+        let expected = source.replace("else:\n    # important\n    pass\n", "");
+        assert_eq!(format_source(&without_comment).unwrap(), expected);
+    }
+
+    #[test]
+    fn round_trip() {
+        let dirs = ["pa1", "pa2", "pa3", "strict", "symbols", "warnings"];
+        for dir in dirs {
+            let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../test").join(dir);
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().is_none_or(|extension| extension != "py") {
+                    continue;
+                }
+                let source = std::fs::read_to_string(&path).unwrap();
+                let ast = process_source(&source);
+                if !ast.errors.errors.is_empty() || !source.is_ascii() {
+                    continue;
+                }
+
+                let formatted = format_source(&source).unwrap();
+                let formatted_ast = process_source(&formatted);
+                let path = path.display();
+                assert!(formatted_ast.errors.errors.is_empty(), "{}:\n{}", path, formatted);
+                assert_eq!(
+                    without_locations(&ast),
+                    without_locations(&formatted_ast),
+                    "{}:\n{}",
+                    path,
+                    formatted
+                );
+                assert_eq!(format_source(&formatted).unwrap(), formatted, "{}", path);
+            }
+        }
+    }
+}
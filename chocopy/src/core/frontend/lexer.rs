@@ -1,5 +1,6 @@
 use super::token::*;
 use crate::common::location::*;
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::future::*;
 
@@ -83,6 +84,21 @@ impl<GetChar: Iterator<Item = char>> TextReader<GetChar> {
     }
 }
 
+// Read exactly `digits` hex digits after `\x`, `\u` or `\U`
+fn read_hex<GetChar: Iterator<Item = char>>(
+    reader: &mut TextReader<GetChar>,
+    digits: usize,
+) -> Option<char> {
+    let mut value = 0;
+    for _ in 0..digits {
+        reader.next();
+        value = value * 16 + reader.current_char()?.to_digit(16)?;
+    }
+    char::from_u32(value)
+}
+
+// Lex a string literal starting at its opening quote `quote`, or at the prefix
+// of a raw string if `raw` is set. Triple-quoted strings may span lines.
 async fn lex_string<
     GetChar: Iterator<Item = char>,
     PutTokenFuture: Future<Output = ()>,
@@ -91,30 +107,78 @@ async fn lex_string<
     reader: &mut TextReader<GetChar>,
     put_token: &mut PutToken,
     start: Position,
+    quote: char,
+    raw: bool,
 ) {
     reader.next();
     let mut s = "".to_owned();
     let mut is_id = true;
+
+    // `""` is an empty string, and `"""` opens a triple-quoted string
+    let mut triple = false;
+    if reader.current_char() == Some(quote) {
+        reader.next();
+        if reader.current_char() == Some(quote) {
+            reader.next();
+            triple = true;
+        } else {
+            put_token(Token::StringLiteral(s), start, reader.previous_position()).await;
+            return;
+        }
+    }
+
+    // Number of consecutive unescaped quotes read in a triple-quoted string
+    let mut quotes = 0;
     loop {
-        match reader.current_char().unwrap() {
+        match reader.current_char() {
             // end quote
-            '\"' => {
+            Some(c) if c == quote => {
+                if !triple {
+                    reader.next();
+                    break;
+                }
+                quotes += 1;
+                s.push(c);
+                if quotes == 3 {
+                    s.truncate(s.len() - 3);
+                    reader.next();
+                    break;
+                }
                 reader.next();
-                break;
+                continue;
             }
             // escape
-            '\\' => {
+            Some('\\') => {
                 is_id = false;
                 reader.next();
-                match reader.current_char().unwrap() {
-                    'n' => s.push('\n'),
-                    't' => s.push('\t'),
-                    '\\' => s.push('\\'),
-                    '\"' => s.push('\"'),
-                    c => {
+                // `Some(None)` for a line continuation, `None` for a bad escape
+                let escaped = match reader.current_char() {
+                    // A raw string keeps the backslash, which still prevents the
+                    // next character from ending the string
+                    Some(c @ (' '..='~' | '\n')) if raw => {
+                        s.push('\\');
+                        Some(Some(c))
+                    }
+                    Some('n') => Some(Some('\n')),
+                    Some('t') => Some(Some('\t')),
+                    Some('r') => Some(Some('\r')),
+                    Some('\\') => Some(Some('\\')),
+                    Some('\"') => Some(Some('\"')),
+                    Some('\'') => Some(Some('\'')),
+                    Some('x') => read_hex(reader, 2).map(Some),
+                    Some('u') => read_hex(reader, 4).map(Some),
+                    Some('U') => read_hex(reader, 8).map(Some),
+                    Some('\n') => Some(None),
+                    _ => None,
+                };
+                match escaped {
+                    Some(Some(c)) => s.push(c),
+                    Some(None) => (),
+                    None => {
+                        let c = reader.current_char().map(|c| c.to_string()).unwrap_or_default();
                         reader.next();
                         put_token(
-                            Token::Unrecognized(c.to_string()),
+                            Token::Unrecognized(c),
                             start,
                             reader.previous_position(),
                         )
@@ -124,17 +188,24 @@ async fn lex_string<
                 }
             }
             // normal char
-            c @ ' '..='~' => {
+            Some(c @ ' '..='~') => {
                 if !matches!(c, 'a'..='z' | 'A'..='Z' | '_' | '0'..='9') {
                     is_id = false;
                 }
                 s.push(c);
             }
-            // unrecognized
+            Some('\n') if triple => {
+                is_id = false;
+                s.push('\n');
+            }
+            // unrecognized, keeping the line break of an unterminated string
             c => {
-                reader.next();
+                let c = c.map(|c| c.to_string()).unwrap_or_default();
+                if c != "\n" {
+                    reader.next();
+                }
                 put_token(
-                    Token::Unrecognized(c.to_string()),
+                    Token::Unrecognized(c),
                     start,
                     reader.previous_position(),
                 )
@@ -142,6 +213,7 @@ async fn lex_string<
                 break;
             }
         }
+        quotes = 0;
         reader.next();
     }
     let end = reader.previous_position();
@@ -160,6 +232,64 @@ async fn lex_string<
     .await;
 }
 
+// Parse an integer literal such as `42`, `0x2A`, `0o52`, `0b101010` or `1_000`,
+// negated if `negative` is set
+pub fn lex_number(s: String, negative: bool) -> Token {
+    let (radix, digits) = match s.get(..2).map(|prefix| prefix.to_ascii_lowercase()).as_deref() {
+        Some("0x") => (16, &s[2..]),
+        Some("0o") => (8, &s[2..]),
+        Some("0b") => (2, &s[2..]),
+        _ => (10, &s[..]),
+    };
+
+    // Separators must be between digits, or right after a prefix
+    let digits = match digits.strip_prefix('_') {
+        Some(rest) if radix != 10 => rest,
+        _ => digits,
+    };
+    if digits.is_empty()
+        || digits.starts_with('_')
+        || digits.ends_with('_')
+        || digits.contains("__")
+        || !digits.chars().all(|c| c == '_' || c.is_digit(radix))
+    {
+        return Token::BadNumber(s);
+    }
+
+    let sign = if negative { "-" } else { "" };
+    let digits: String = sign.chars().chain(digits.chars().filter(|&c| c != '_')).collect();
+    match i32::from_str_radix(&digits, radix) {
+        Ok(n) => Token::Number(n),
+        Err(_) => Token::NumberOutOfRange(s),
+    }
+}
+
+// Read a comment up to the end of the line
+fn lex_comment<GetChar: Iterator<Item = char>>(
+    reader: &mut TextReader<GetChar>,
+    own_line: bool,
+) -> Trivia {
+    let start = reader.current_position();
+    let mut text = "".to_owned();
+    while let Some(c) = reader.current_char().filter(|&c| c != '\n') {
+        text.push(c);
+        reader.next();
+    }
+    let text = text.trim_end().to_owned();
+    Trivia::Comment {
+        location: Location {
+            start,
+            end: Position {
+                row: start.row,
+                col: start.col + text.len() as u32 - 1,
+            },
+        },
+        text,
+        own_line,
+    }
+}
+
+// Lex the tokens of a line, and return the comment ending it, if any
 async fn lex_line<
     GetChar: Iterator<Item = char>,
     PutTokenFuture: Future<Output = ()>,
@@ -167,7 +297,7 @@ async fn lex_line<
 >(
     reader: &mut TextReader<GetChar>,
     put_token: &mut PutToken,
-) {
+) -> Option<Trivia> {
     while reader.current_char() != Some('\n') {
         let start = reader.current_position();
         match reader.current_char().unwrap() {
@@ -178,25 +308,19 @@ async fn lex_line<
                 }
             }
 
-            // Skip comments
-            '#' => {
-                while reader.current_char() != Some('\n') {
-                    reader.next();
-                }
-            }
+            '#' => return Some(lex_comment(reader, false)),
 
             // Numbers
             '0'..='9' => {
                 let mut s = "".to_owned();
-                while let c @ '0'..='9' = reader.current_char().unwrap() {
+                while let c @ 'a'..='z' | c @ 'A'..='Z' | c @ '_' | c @ '0'..='9' =
+                    reader.current_char().unwrap()
+                {
                     s.push(c);
                     reader.next();
                 }
                 let end = reader.previous_position();
-                match s.parse() {
-                    Ok(n) => put_token(Token::Number(n), start, end).await,
-                    Err(_) => put_token(Token::BadNumber, start, end).await,
-                }
+                put_token(lex_number(s, false), start, end).await;
             }
 
             // Words
@@ -208,6 +332,11 @@ async fn lex_line<
                     s.push(c);
                     reader.next();
                 }
+                // Raw string prefix
+                if let ("r" | "R", Some(quote @ ('\"' | '\''))) = (&s[..], reader.current_char()) {
+                    lex_string(reader, put_token, start, quote, true).await;
+                    continue;
+                }
                 let end = reader.previous_position();
                 put_token(
                     KEYWORDS
@@ -221,8 +350,8 @@ async fn lex_line<
             }
 
             // Strings
-            '\"' => {
-                lex_string(reader, put_token, start).await;
+            quote @ ('\"' | '\'') => {
+                lex_string(reader, put_token, start, quote, false).await;
             }
 
             // Operators
@@ -246,6 +375,7 @@ async fn lex_line<
             }
         }
     }
+    None
 }
 
 pub async fn lex(
@@ -253,10 +383,13 @@ pub async fn lex(
     put_token: super::generator::Sender<ComplexToken>,
 ) {
     let mut reader = TextReader::new(get_char);
+    // Trivia to attach to the next token
+    let trivia = RefCell::new(vec![]);
     let mut put_token = |token, start, end| {
         put_token.send(ComplexToken {
             token,
             location: Location { start, end },
+            trivia: trivia.take(),
         })
     };
     let mut indentation_stack = vec![0];
@@ -278,11 +411,11 @@ pub async fn lex(
         let mut indentation_end = reader.current_position();
         indentation_end.col -= 1;
 
-        // Found comment immediately, skip to line break
+        // Found comment immediately, read to line break
         if reader.current_char() == Some('#') {
-            while reader.current_char() != Some('\n') {
-                reader.next();
-            }
+            trivia.borrow_mut().push(lex_comment(&mut reader, true));
+        } else if reader.current_char() == Some('\n') && !reader.early_eof {
+            trivia.borrow_mut().push(Trivia::BlankLine(indentation_begin.row));
         }
 
         // Found line break immediately. This is an empty line
@@ -315,7 +448,9 @@ pub async fn lex(
         }
 
         // Lex normal tokens
-        lex_line(&mut reader, &mut put_token).await;
+        if let Some(comment) = lex_line(&mut reader, &mut put_token).await {
+            trivia.borrow_mut().push(comment);
+        }
 
         // Finish the line
         let new_line_begin = reader.current_position();
@@ -450,4 +585,67 @@ a _b \t x2
     Token::Dedent, Token::Dedent, Token::Eof
         ]);
     }
+
+    #[test]
+    fn lex_string_forms() {
+        lex_case(
+            r#"'a' "b\x41\u00e9" r'c\n\'' R"" ''"#,
+            &[
+                Token::IdString("a".to_owned()),
+                Token::StringLiteral("bA\u{e9}".to_owned()),
+                Token::StringLiteral("c\\n\\'".to_owned()),
+                Token::StringLiteral("".to_owned()),
+                Token::StringLiteral("".to_owned()),
+                Token::NewLine,
+                Token::Eof,
+            ],
+        );
+        lex_case(
+            "'\\x4g",
+            &[
+                Token::Unrecognized("g".to_owned()),
+                Token::StringLiteral("".to_owned()),
+                Token::NewLine,
+                Token::Eof,
+            ],
+        );
+
+        // Triple-quoted strings span lines without producing line breaks or indentation
+        let result: Vec<_> =
+            generator(|put_token| lex("x = \"\"\"a\n  \"b\"\"\\\n\"\"\" 1\ny".chars(), put_token)).collect();
+        assert_eq!(
+            result[2],
+            ComplexToken {
+                token: Token::StringLiteral("a\n  \"b\"\"".to_owned()),
+                location: Location {
+                    start: Position { row: 1, col: 5 },
+                    end: Position { row: 3, col: 3 },
+                },
+                trivia: vec![],
+            }
+        );
+        assert_eq!(result[3].token, Token::Number(1));
+        assert_eq!(result[4].token, Token::NewLine);
+        assert_eq!(result[5].token, Token::Identifier("y".to_owned()));
+    }
+
+    #[test]
+    fn lex_number_forms() {
+        lex_case(
+            "0x7fff_ffff 0o17 0B101 1_000 0x_1F 2147483648 0x1__F 0b2 12ab",
+            &[
+                Token::Number(i32::MAX),
+                Token::Number(15),
+                Token::Number(5),
+                Token::Number(1000),
+                Token::Number(31),
+                Token::NumberOutOfRange("2147483648".to_owned()),
+                Token::BadNumber("0x1__F".to_owned()),
+                Token::BadNumber("0b2".to_owned()),
+                Token::BadNumber("12ab".to_owned()),
+                Token::NewLine,
+                Token::Eof,
+            ],
+        );
+    }
 }
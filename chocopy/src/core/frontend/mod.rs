@@ -1,28 +1,70 @@
+mod format;
 mod generator;
 mod lexer;
 mod parser;
 mod token;
+use crate::common::diagnostic::UNEXPECTED_TOKEN;
+use crate::common::location::Location;
 use crate::common::node::*;
 
-pub fn process(path: &str) -> Result<Program, Box<dyn std::error::Error>> {
-    use std::fs::*;
-    use std::io::*;
+// The characters of source code, which ends at the first non-ASCII byte
+fn chars(bytes: impl Iterator<Item = u8>) -> impl Iterator<Item = char> {
+    bytes.take_while(|c| *c < 0x80).map(|c| c as char)
+}
 
-    let get_char = BufReader::new(File::open(path)?)
-        .bytes()
-        .map(|c| match c {
-            Ok(c) if c < 0x80 => Some(c as char),
-            _ => None,
-        })
-        .take_while(|c| c.is_some())
-        .map(|c| c.unwrap());
+// Parse source code
+fn parse(bytes: impl Iterator<Item = u8>) -> Program {
+    let get_char = chars(bytes);
 
     let get_token = generator::generator(|put_token| lexer::lex(get_char, put_token));
     let mut ast = parser::parse(get_token);
 
     ast.errors.sort();
 
-    Ok(ast)
+    ast
+}
+
+pub fn process(path: &str) -> Result<Program, Box<dyn std::error::Error>> {
+    use std::fs::*;
+    use std::io::*;
+
+    let bytes = BufReader::new(File::open(path)?).bytes().map_while(Result::ok);
+    Ok(parse(bytes))
+}
+
+// Parse source code held in memory, such as an unsaved document of an editor
+pub fn process_source(source: &str) -> Program {
+    parse(source.bytes())
+}
+
+// Format source code canonically, keeping its comments, or return its syntax
+// errors. Non-ASCII characters are an error, as parsing stops at them.
+pub fn format_source(source: &str) -> Result<String, Vec<CompilerError>> {
+    if let Some((row, line)) = source.lines().enumerate().find(|(_, line)| !line.is_ascii()) {
+        let col = line.find(|c: char| !c.is_ascii()).unwrap() as u32 + 1;
+        let row = row as u32 + 1;
+        return Err(vec![CompilerError {
+            base: NodeBase::from_location(Location::new(row, col, row, col)),
+            message: "Non-ASCII character".to_owned(),
+            syntax: true,
+            severity: Severity::Error,
+            flag: None,
+            code: Some(UNEXPECTED_TOKEN.id.to_owned()),
+        }]);
+    }
+
+    let ast = process_source(source);
+    if !ast.errors.errors.is_empty() {
+        return Err(ast.errors.errors);
+    }
+    let tokens = generator::generator(|put_token| lexer::lex(chars(source.bytes()), put_token));
+    Ok(format::format(&ast, source, tokens))
+}
+
+// Read a program serialized as JSON, such as the output of `--ast` or `--typed`
+pub fn read_json(path: &str) -> Result<Program, Box<dyn std::error::Error>> {
+    let reader = std::io::BufReader::new(std::fs::File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
 }
 
 #[cfg(test)]
@@ -68,7 +110,14 @@ mod tests {
         ];
 
         for dir in &test_dirs {
-            println!("Testing Directory {}", dir);
+            // Test data lives at the workspace root; the original suite is optional
+            let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("..")
+                .join(dir);
+            if !dir.is_dir() {
+                continue;
+            }
+            println!("Testing Directory {}", dir.display());
             let mut files = std::fs::read_dir(dir)
                 .unwrap()
                 .map(|f| f.unwrap())
@@ -93,7 +142,8 @@ mod tests {
                 std::thread::Builder::new()
                     .stack_size(16_000_000)
                     .spawn(move || {
-                        sender.send(process(source_file.as_os_str().to_str().unwrap()).unwrap())
+                        let _ = sender
+                            .send(process(source_file.as_os_str().to_str().unwrap()).unwrap());
                     })
                     .unwrap();
 
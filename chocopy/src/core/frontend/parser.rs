@@ -1,14 +1,35 @@
+use super::lexer::lex_number;
 use super::token::*;
+use crate::common::diagnostic::*;
 use crate::common::location::*;
 use crate::common::node::*;
 use std::cmp::Ordering;
 use std::collections::vec_deque::VecDeque;
 
+// The keyword arguments of `print`, the only call that takes any
+const PRINT_KEYWORDS: [&str; 4] = ["sep", "end", "file", "flush"];
+
 fn unexpected(token: ComplexToken) -> CompilerError {
+    let (code, message) = match token.token {
+        Token::BadNumber(s) => (INVALID_INTEGER, format!("Invalid integer literal: {}", s)),
+        Token::NumberOutOfRange(s) => (
+            INTEGER_OUT_OF_RANGE,
+            format!(
+                "Integer literal out of range: {}; `int` values must be between {} and {}",
+                s,
+                i32::MIN,
+                i32::MAX
+            ),
+        ),
+        _ => (UNEXPECTED_TOKEN, "unexptected token".to_owned()),
+    };
     CompilerError {
         base: NodeBase::from_location(token.location),
-        message: "unexptected token".to_owned(),
+        message,
         syntax: true,
+        severity: Severity::Error,
+        flag: None,
+        code: Some(code.id.to_owned()),
     }
 }
 
@@ -75,6 +96,8 @@ struct Parser<F> {
     prev_pos_buf: VecDeque<Position>,
     eof: Option<ComplexToken>,
     errors: Vec<CompilerError>,
+    // Loop bodies enclosing the statements being parsed, for `break`
+    loops: u32,
 }
 
 impl<F: Iterator<Item = ComplexToken>> Parser<F> {
@@ -85,6 +108,7 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
             prev_pos_buf: VecDeque::new(),
             eof: None,
             errors: vec![],
+            loops: 0,
         }
     }
 
@@ -234,53 +258,177 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
             Token::LessEqual => BinaryOp::Le,
             Token::GreaterEqual => BinaryOp::Ge,
             Token::Is => BinaryOp::Is,
+            Token::In => BinaryOp::In,
+            Token::Not => {
+                let next = self.take();
+                if next.token != Token::In {
+                    self.push_back(next);
+                    self.push_back(token);
+                    return Some(left);
+                }
+                BinaryOp::In
+            }
             _ => {
                 self.push_back(token);
                 return Some(left);
             }
         };
 
+        // "a is not b" and "a not in b" are desugared into "not (a is b)" and "not (a in b)"
+        let mut negate = token.token == Token::Not;
+        if operator == BinaryOp::Is {
+            let token = self.take();
+            if token.token == Token::Not {
+                negate = true;
+            } else {
+                self.push_back(token);
+            }
+        }
+
         let right = self.parse_expr6()?;
         let end = self.prev_pos().unwrap_or(start);
 
-        Some(Expr::BinaryExpr(Box::new(BinaryExpr {
+        let expr = Expr::BinaryExpr(Box::new(BinaryExpr {
             base: NodeBase::from_positions(start, end),
             left,
             operator,
             right,
-        })))
+        }));
+
+        if negate {
+            Some(Expr::UnaryExpr(Box::new(UnaryExpr {
+                base: NodeBase::from_positions(start, end),
+                operator: UnaryOp::Not,
+                operand: expr,
+            })))
+        } else {
+            Some(expr)
+        }
     }
 
-    parse_expr_binary!(parse_expr6, parse_expr7,
+    parse_expr_binary!(parse_expr6, parse_expr7, Token::BitOr => BinaryOp::BitOr);
+    parse_expr_binary!(parse_expr7, parse_expr8, Token::BitXor => BinaryOp::BitXor);
+    parse_expr_binary!(parse_expr8, parse_expr9, Token::BitAnd => BinaryOp::BitAnd);
+
+    parse_expr_binary!(parse_expr9, parse_expr10,
+        Token::ShiftLeft => BinaryOp::Shl,
+        Token::ShiftRight => BinaryOp::Shr
+    );
+
+    parse_expr_binary!(parse_expr10, parse_expr11,
         Token::Plus => BinaryOp::Add,
         Token::Minus => BinaryOp::Sub
     );
 
-    parse_expr_binary!(parse_expr7, parse_expr8,
+    parse_expr_binary!(parse_expr11, parse_expr12,
         Token::Multiply => BinaryOp::Mul,
         Token::Divide => BinaryOp::Div,
         Token::Mod => BinaryOp::Mod
     );
 
-    parse_expr_unary!(parse_expr8, parse_expr9, Token::Minus => UnaryOp::Negative);
+    // Parse "- expr" and "~ expr", which can be nested as in "-~x"
+    fn parse_expr12(&mut self) -> Option<Expr> {
+        let start = self.next_pos();
+
+        let token = self.take();
+        let operator = match token.token {
+            Token::Minus => UnaryOp::Negative,
+            Token::BitNot => UnaryOp::Invert,
+            _ => {
+                self.push_back(token);
+                return self.parse_expr13();
+            }
+        };
+
+        // `-2147483648` is one literal, as its magnitude alone is out of range
+        let token = self.take();
+        if operator == UnaryOp::Negative
+            && let Token::NumberOutOfRange(s) = &token.token
+            && let Token::Number(value) = lex_number(s.clone(), true)
+        {
+            let base = NodeBase::from_positions(start, token.location.end);
+            return Some(Expr::IntegerLiteral(IntegerLiteral { base, value }));
+        }
+        self.push_back(token);
+
+        let operand = self.parse_expr12()?;
+        let end = self.prev_pos().unwrap_or(start);
+
+        Some(Expr::UnaryExpr(Box::new(UnaryExpr {
+            base: NodeBase::from_positions(start, end),
+            operator,
+            operand,
+        })))
+    }
+
+    // Parse "expr ** expr", which is right-associative and binds tighter than a
+    // unary operator on its left but not on its right: "-a ** -b" is "-(a ** (-b))"
+    fn parse_expr13(&mut self) -> Option<Expr> {
+        let start = self.next_pos();
+
+        let left = self.parse_expr14()?;
+
+        let token = self.take();
+        if token.token != Token::Power {
+            self.push_back(token);
+            return Some(left);
+        }
+
+        let right = self.parse_expr12()?;
+        let end = self.prev_pos().unwrap_or(start);
 
-    fn parse_expr9(&mut self) -> Option<Expr> {
+        Some(Expr::BinaryExpr(Box::new(BinaryExpr {
+            base: NodeBase::from_positions(start, end),
+            left,
+            operator: BinaryOp::Pow,
+            right,
+        })))
+    }
+
+    fn parse_expr14(&mut self) -> Option<Expr> {
         let start = self.next_pos();
 
-        // Parse "expr . id", "expr [ expr ]", "expr ( expr,* )"
-        let mut expr = self.parse_expr10()?;
+        // Parse "expr . id", "expr [ expr ]", "expr ( expr,* )", "id [ type,* ] ( expr,* )"
+        let mut expr = self.parse_expr15()?;
+        let mut type_args = None;
 
         loop {
             let token = self.take();
             match token.token {
+                Token::LeftSquare
+                    if type_args.is_none()
+                        && matches!(expr.content, ExprContent::Variable(_))
+                        && self.is_type_args_call() =>
+                {
+                    type_args = Some(self.parse_type_args()?);
+                }
                 Token::LeftPar => {
                     let mut args = vec![];
+                    let mut keywords = vec![];
+                    // Keyword arguments are only part of the syntax of `print`
+                    let print =
+                        matches!(&expr.content, ExprContent::Variable(v) if v.name == "print");
 
                     let token_head = self.take();
                     if token_head.token != Token::RightPar {
                         self.push_back(token_head);
                         loop {
-                            if let Some(arg) = self.parse_expr1() {
+                            let keyword = if print { self.parse_keyword_arg() } else { None };
+                            if let Some(keyword) = keyword {
+                                keywords.push(keyword?);
+                            } else if !keywords.is_empty() {
+                                let token = self.take();
+                                self.errors.push(CompilerError {
+                                    base: NodeBase::from_location(token.location),
+                                    message: "Positional argument follows keyword argument"
+                                        .to_owned(),
+                                    syntax: true,
+                                    severity: Severity::Error,
+                                    flag: None,
+                                    code: Some(POSITIONAL_AFTER_KEYWORD.id.to_owned()),
+                                });
+                                return None;
+                            } else if let Some(arg) = self.parse_expr1() {
                                 args.push(arg);
                             }
                             let token = self.take();
@@ -306,6 +454,8 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
                                 name: function.name,
                             },
                             args,
+                            type_args: type_args.take().unwrap_or_default(),
+                            keywords,
                         }),
                         ExprContent::MemberExpr(method) => {
                             Expr::MethodCallExpr(Box::new(MethodCallExpr {
@@ -326,15 +476,46 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
                     }
                 }
                 Token::LeftSquare => {
-                    let index = self.parse_expr1()?;
-                    self.eat(Token::RightSquare)?;
-                    let end = self.prev_pos().unwrap_or(start);
+                    // Parse "[ expr ]" or a slice "[ expr? : expr? ]"
+                    let token = self.take();
+                    let index = if token.token == Token::Colon {
+                        self.push_back(token);
+                        None
+                    } else {
+                        self.push_back(token);
+                        Some(self.parse_expr1()?)
+                    };
 
-                    expr = Expr::IndexExpr(Box::new(IndexExpr {
-                        base: NodeBase::from_positions(start, end),
-                        list: expr,
-                        index,
-                    }));
+                    let token = self.take();
+                    if token.token == Token::Colon {
+                        let token = self.take();
+                        let slice_end = if token.token == Token::RightSquare {
+                            self.push_back(token);
+                            None
+                        } else {
+                            self.push_back(token);
+                            Some(self.parse_expr1()?)
+                        };
+                        self.eat(Token::RightSquare)?;
+                        let end = self.prev_pos().unwrap_or(start);
+
+                        expr = Expr::SliceExpr(Box::new(SliceExpr {
+                            base: NodeBase::from_positions(start, end),
+                            list: expr,
+                            start: index,
+                            end: slice_end,
+                        }));
+                    } else {
+                        self.push_back(token);
+                        self.eat(Token::RightSquare)?;
+                        let end = self.prev_pos().unwrap_or(start);
+
+                        expr = Expr::IndexExpr(Box::new(IndexExpr {
+                            base: NodeBase::from_positions(start, end),
+                            list: expr,
+                            index: index?,
+                        }));
+                    }
                 }
                 Token::Dot => {
                     let member = self.take_id()?;
@@ -356,10 +537,10 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
         Some(expr)
     }
 
-    fn parse_expr10(&mut self) -> Option<Expr> {
+    fn parse_expr15(&mut self) -> Option<Expr> {
         let start = self.next_pos();
 
-        // Parse atomic expression, (), and []
+        // Parse atomic expression, (), [] and {}
         let token = self.take();
         let end = self.prev_pos().unwrap_or(start);
         let base = NodeBase::from_positions(start, end);
@@ -389,6 +570,11 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
                         }
                         let token = self.take();
                         match token.token {
+                            Token::For if elements.len() == 1 => {
+                                self.push_back(token);
+                                let element = elements.pop().unwrap();
+                                return self.parse_comprehension(start, element);
+                            }
                             Token::Comma => (),
                             Token::RightSquare => break,
                             _ => {
@@ -403,6 +589,26 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
                 let base = NodeBase::from_positions(start, end);
                 Expr::ListExpr(ListExpr { base, elements })
             }
+            Token::LeftBrace => {
+                // `{}` would be an empty dict, so sets have at least one element
+                let mut elements = vec![];
+                loop {
+                    elements.push(self.parse_expr1()?);
+                    let token = self.take();
+                    match token.token {
+                        Token::Comma => (),
+                        Token::RightBrace => break,
+                        _ => {
+                            self.errors.push(unexpected(token));
+                            return None;
+                        }
+                    }
+                }
+
+                let end = self.prev_pos().unwrap_or(start);
+                let base = NodeBase::from_positions(start, end);
+                Expr::SetExpr(SetExpr { base, elements })
+            }
             _ => {
                 self.errors.push(unexpected(token));
                 return None;
@@ -412,6 +618,64 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
         Some(expr)
     }
 
+    // Parse the "for ... in ... if ..." clauses of a list comprehension up to
+    // the closing bracket. Iterables and conditions can't be bare `if` expressions.
+    fn parse_comprehension(&mut self, start: Position, element: Expr) -> Option<Expr> {
+        let mut clauses = vec![];
+        loop {
+            let clause_start = self.next_pos();
+            let token = self.take();
+            match token.token {
+                Token::For => (),
+                Token::RightSquare if !clauses.is_empty() => break,
+                _ => {
+                    self.errors.push(unexpected(token));
+                    return None;
+                }
+            }
+
+            let token = self.take();
+            let identifier = if let Token::Identifier(name) = token.token {
+                ForTarget {
+                    inferred_type: None,
+                    base: NodeBase::from_location(token.location),
+                    name,
+                }
+            } else {
+                self.errors.push(unexpected(token));
+                return None;
+            };
+
+            self.eat(Token::In)?;
+            let iterable = self.parse_expr2()?;
+
+            let mut conditions = vec![];
+            loop {
+                let token = self.take();
+                if token.token != Token::If {
+                    self.push_back(token);
+                    break;
+                }
+                conditions.push(self.parse_expr2()?);
+            }
+
+            let end = self.prev_pos().unwrap_or(clause_start);
+            clauses.push(ComprehensionClause {
+                base: NodeBase::from_positions(clause_start, end),
+                identifier,
+                iterable,
+                conditions,
+            });
+        }
+
+        let end = self.prev_pos().unwrap_or(start);
+        Some(Expr::ListComprehension(Box::new(ListComprehension {
+            base: NodeBase::from_positions(start, end),
+            element,
+            clauses,
+        })))
+    }
+
     fn parse_assign_or_expr_stmt(&mut self) -> Option<Stmt> {
         let mut expr_list = vec![];
 
@@ -457,6 +721,25 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
         }
     }
 
+    fn parse_break(&mut self) -> Option<BreakStmt> {
+        let token = self.take();
+        if self.loops == 0 {
+            self.errors.push(CompilerError {
+                base: NodeBase::from_location(token.location),
+                message: "`break` outside of a loop".to_owned(),
+                syntax: true,
+                severity: Severity::Error,
+                flag: None,
+                code: Some(BREAK_OUTSIDE_LOOP.id.to_owned()),
+            });
+            return None;
+        }
+        self.eat(Token::NewLine)?;
+        Some(BreakStmt {
+            base: NodeBase::from_location(token.location),
+        })
+    }
+
     fn parse_return(&mut self) -> Option<ReturnStmt> {
         let start = self.next_pos();
 
@@ -481,6 +764,67 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
         })
     }
 
+    fn parse_yield(&mut self) -> Option<YieldStmt> {
+        let start = self.next_pos();
+
+        self.eat(Token::Yield)?;
+
+        let token = self.take();
+        let value = if token.token == Token::NewLine {
+            self.push_back(token);
+            None
+        } else {
+            self.push_back(token);
+            Some(self.parse_expr1()?)
+        };
+
+        let end = self.prev_pos().unwrap_or(start);
+
+        self.eat(Token::NewLine)?;
+
+        Some(YieldStmt {
+            base: NodeBase::from_positions(start, end),
+            value,
+        })
+    }
+
+    fn parse_del(&mut self) -> Option<DelStmt> {
+        let start = self.next_pos();
+
+        self.eat(Token::Del)?;
+
+        let mut targets = vec![];
+        let mut end;
+        loop {
+            targets.push(self.parse_expr1()?);
+
+            end = self.prev_pos().unwrap_or(start);
+            let token = self.take();
+            match targets.last().map(|e| &e.content) {
+                Some(ExprContent::Variable(_))
+                | Some(ExprContent::IndexExpr(_))
+                | Some(ExprContent::SliceExpr(_)) => (),
+                _ => {
+                    self.errors.push(unexpected(token));
+                    return None;
+                }
+            }
+            match token.token {
+                Token::Comma => (),
+                Token::NewLine => break,
+                _ => {
+                    self.errors.push(unexpected(token));
+                    return None;
+                }
+            }
+        }
+
+        Some(DelStmt {
+            base: NodeBase::from_positions(start, end),
+            targets,
+        })
+    }
+
     fn parse_block(&mut self) -> Option<Vec<Stmt>> {
         self.eat(Token::Colon)?;
         self.eat(Token::NewLine)?;
@@ -494,15 +838,36 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
         let start = self.next_pos();
         self.eat(Token::While)?;
         let condition = self.parse_expr1()?;
-        let body = self.parse_block()?;
+        let body = self.parse_loop_body()?;
+        let else_body = self.parse_loop_else()?;
         let end = self.prev_pos().unwrap_or(start);
         Some(WhileStmt {
             base: NodeBase::from_positions(start, end),
             condition,
             body,
+            else_body,
         })
     }
 
+    fn parse_loop_body(&mut self) -> Option<Vec<Stmt>> {
+        self.loops += 1;
+        let body = self.parse_block();
+        self.loops -= 1;
+        body
+    }
+
+    // Parse the optional `else` block following a loop. A `break` in it
+    // belongs to an enclosing loop.
+    fn parse_loop_else(&mut self) -> Option<Option<Vec<Stmt>>> {
+        let token = self.take();
+        if token.token == Token::Else {
+            Some(Some(self.parse_block()?))
+        } else {
+            self.push_back(token);
+            Some(None)
+        }
+    }
+
     fn parse_for(&mut self) -> Option<ForStmt> {
         let start = self.next_pos();
 
@@ -523,7 +888,8 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
         self.eat(Token::In)?;
 
         let iterable = self.parse_expr1()?;
-        let body = self.parse_block()?;
+        let body = self.parse_loop_body()?;
+        let else_body = self.parse_loop_else()?;
 
         let end = self.prev_pos().unwrap_or(start);
 
@@ -532,6 +898,7 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
             identifier,
             iterable,
             body,
+            else_body,
         })
     }
 
@@ -587,6 +954,14 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
                         self.skip_to_next_line();
                     }
                 }
+                Token::Break => {
+                    self.push_back(token);
+                    if let Some(break_stmt) = self.parse_break() {
+                        stmt_list.push(Stmt::BreakStmt(break_stmt));
+                    } else {
+                        self.skip_to_next_line();
+                    }
+                }
                 Token::Return => {
                     self.push_back(token);
                     if let Some(return_stmt) = self.parse_return() {
@@ -595,6 +970,22 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
                         self.skip_to_next_line();
                     }
                 }
+                Token::Yield => {
+                    self.push_back(token);
+                    if let Some(yield_stmt) = self.parse_yield() {
+                        stmt_list.push(Stmt::YieldStmt(yield_stmt));
+                    } else {
+                        self.skip_to_next_line();
+                    }
+                }
+                Token::Del => {
+                    self.push_back(token);
+                    if let Some(del_stmt) = self.parse_del() {
+                        stmt_list.push(Stmt::DelStmt(del_stmt));
+                    } else {
+                        self.skip_to_next_line();
+                    }
+                }
                 Token::While => {
                     self.push_back(token);
                     if let Some(while_stmt) = self.parse_while() {
@@ -675,11 +1066,28 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
     fn parse_class_def(&mut self) -> Option<ClassDef> {
         let start = self.next_pos();
 
-        // Parse "class ID ( ID ) : \n {"
+        // Parse "class ID ( ID ) : \n {", where the super class can be followed or
+        // replaced by "Generic [ ID,* ]"
         self.eat(Token::Class)?;
         let name = self.take_id()?;
         self.eat(Token::LeftPar)?;
-        let super_class = self.take_id()?;
+        let mut super_class = self.take_id()?;
+        let mut type_params = vec![];
+        let token = self.take();
+        if super_class.name == "Generic" && token.token == Token::LeftSquare {
+            type_params = self.parse_type_params()?;
+            super_class.name = "object".to_owned();
+        } else if token.token == Token::Comma {
+            let token = self.take();
+            if !matches!(&token.token, Token::Identifier(name) if name == "Generic") {
+                self.errors.push(unexpected(token));
+                return None;
+            }
+            self.eat(Token::LeftSquare)?;
+            type_params = self.parse_type_params()?;
+        } else {
+            self.push_back(token);
+        }
         self.eat(Token::RightPar)?;
         self.eat(Token::Colon)?;
         self.eat(Token::NewLine)?;
@@ -697,6 +1105,7 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
             base: NodeBase::from_positions(start, end),
             name,
             super_class,
+            type_params,
             declarations,
         })
     }
@@ -794,6 +1203,7 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
             Token::Colon => TypeAnnotation::ClassType(ClassType {
                 base: NodeBase::from_location(token.location),
                 class_name: "<None>".to_owned(),
+                type_args: vec![],
             }),
             Token::Arrow => {
                 let return_type = self.parse_type_annotation()?;
@@ -833,8 +1243,19 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
     fn parse_var_def(&mut self) -> Option<VarDef> {
         let start = self.next_pos();
 
-        // Parse "typed_var = literal \n"
-        let typed_var = self.parse_typed_var()?;
+        // Parse "typed_var = literal \n", where the type may be "Final [ type ]"
+        let identifier = self.take_id()?;
+        self.eat(Token::Colon)?;
+        let is_final = self.eat_final();
+        let type_ = self.parse_type_annotation()?;
+        if is_final {
+            self.eat(Token::RightSquare)?;
+        }
+        let typed_var = TypedVar {
+            base: NodeBase::from_positions(start, self.prev_pos().unwrap_or(start)),
+            identifier,
+            type_,
+        };
 
         self.eat(Token::Assign)?;
 
@@ -863,20 +1284,72 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
             base: NodeBase::from_positions(start, end),
             var: typed_var,
             value,
+            is_final,
         })
     }
 
+    // Take "Final [" if it comes next
+    fn eat_final(&mut self) -> bool {
+        let token = self.take();
+        if token.token == Token::Identifier("Final".to_owned()) {
+            let next = self.take();
+            if next.token == Token::LeftSquare {
+                return true;
+            }
+            self.push_back(next);
+        }
+        self.push_back(token);
+        false
+    }
+
     fn parse_type_annotation(&mut self) -> Option<TypeAnnotation> {
         let start = self.next_pos();
 
         let token = self.take();
         match token.token {
-            Token::Identifier(class_name) | Token::IdString(class_name) => {
+            // Parse "Optional [ type ]" and "ID [ type,* ]"
+            Token::Identifier(class_name) => {
+                let next = self.take();
+                if next.token != Token::LeftSquare {
+                    self.push_back(next);
+                    let end = self.prev_pos().unwrap_or(start);
+                    return Some(TypeAnnotation::ClassType(ClassType {
+                        base: NodeBase::from_positions(start, end),
+                        class_name,
+                        type_args: vec![],
+                    }));
+                }
+
+                if class_name == "Optional" {
+                    let element_type = self.parse_type_annotation()?;
+
+                    self.eat(Token::RightSquare)?;
+
+                    let end = self.prev_pos().unwrap_or(start);
+
+                    return Some(TypeAnnotation::OptionalType(Box::new(OptionalType {
+                        base: NodeBase::from_positions(start, end),
+                        element_type,
+                    })));
+                }
+
+                let type_args = self.parse_type_args()?;
+
+                let end = self.prev_pos().unwrap_or(start);
+
+                Some(TypeAnnotation::ClassType(ClassType {
+                    base: NodeBase::from_positions(start, end),
+                    class_name,
+                    type_args,
+                }))
+            }
+            Token::IdString(class_name) => {
                 let end = self.prev_pos().unwrap_or(start);
 
                 Some(TypeAnnotation::ClassType(ClassType {
                     base: NodeBase::from_positions(start, end),
                     class_name,
+                    type_args: vec![],
                 }))
             }
             Token::LeftSquare => {
@@ -898,6 +1371,111 @@ impl<F: Iterator<Item = ComplexToken>> Parser<F> {
         }
     }
 
+    // Parse "type,* ]" after "ID ["
+    fn parse_type_args(&mut self) -> Option<Vec<TypeAnnotation>> {
+        let mut type_args = vec![];
+        loop {
+            type_args.push(self.parse_type_annotation()?);
+            let token = self.take();
+            match token.token {
+                Token::Comma => (),
+                Token::RightSquare => break,
+                _ => {
+                    self.errors.push(unexpected(token));
+                    return None;
+                }
+            }
+        }
+        Some(type_args)
+    }
+
+    // Parse "ID,* ]" after "Generic ["
+    fn parse_type_params(&mut self) -> Option<Vec<Identifier>> {
+        let mut type_params = vec![];
+        loop {
+            type_params.push(self.take_id()?);
+            let token = self.take();
+            match token.token {
+                Token::Comma => (),
+                Token::RightSquare => break,
+                _ => {
+                    self.errors.push(unexpected(token));
+                    return None;
+                }
+            }
+        }
+        Some(type_params)
+    }
+
+    // Parse "ID = expr" in the arguments of `print`. Returns None, consuming
+    // nothing, if the next argument isn't a keyword argument of `print`.
+    fn parse_keyword_arg(&mut self) -> Option<Option<KeywordArg>> {
+        let token = self.take();
+        let name = if let Token::Identifier(name) = &token.token
+            && PRINT_KEYWORDS.contains(&name.as_str())
+        {
+            name.clone()
+        } else {
+            self.push_back(token);
+            return None;
+        };
+        let next = self.take();
+        if next.token != Token::Assign {
+            self.push_back(next);
+            self.push_back(token);
+            return None;
+        }
+
+        let start = token.location.start;
+        let name = Identifier {
+            base: NodeBase::from_location(token.location),
+            name,
+        };
+        let value = if let Some(value) = self.parse_expr1() {
+            value
+        } else {
+            return Some(None);
+        };
+        let end = self.prev_pos().unwrap_or(start);
+        Some(Some(KeywordArg {
+            base: NodeBase::from_positions(start, end),
+            name,
+            value,
+        }))
+    }
+
+    // After "ID [", look ahead for the matching "]" followed by "(", which makes
+    // the brackets type arguments of a constructor call instead of an index
+    fn is_type_args_call(&mut self) -> bool {
+        let prev_pos_buf = self.prev_pos_buf.clone();
+        let mut tokens = vec![];
+        let mut depth = 1;
+        let result = loop {
+            let token = self.take();
+            let token_kind = token.token.clone();
+            tokens.push(token);
+            match token_kind {
+                Token::LeftSquare => depth += 1,
+                Token::RightSquare => {
+                    depth -= 1;
+                    if depth == 0 {
+                        let next = self.take();
+                        let is_call = next.token == Token::LeftPar;
+                        tokens.push(next);
+                        break is_call;
+                    }
+                }
+                Token::NewLine | Token::Eof => break false,
+                _ => (),
+            }
+        };
+        for token in tokens.into_iter().rev() {
+            self.push_back(token);
+        }
+        self.prev_pos_buf = prev_pos_buf;
+        result
+    }
+
     fn parse_typed_var(&mut self) -> Option<TypedVar> {
         let start = self.next_pos();
 
@@ -9,7 +9,8 @@ pub enum Token {
     Dedent,
     Badent,
     Number(i32),
-    BadNumber,
+    BadNumber(String),
+    NumberOutOfRange(String),
     Identifier(String),
     IdString(String),
     StringLiteral(String),
@@ -55,6 +56,13 @@ pub enum Token {
     Multiply,
     Divide,
     Mod,
+    Power,
+    BitAnd,
+    BitOr,
+    BitXor,
+    BitNot,
+    ShiftLeft,
+    ShiftRight,
     Less,
     Greater,
     LessEqual,
@@ -66,6 +74,8 @@ pub enum Token {
     RightPar,
     LeftSquare,
     RightSquare,
+    LeftBrace,
+    RightBrace,
     Comma,
     Colon,
     Dot,
@@ -126,18 +136,35 @@ pub static OPERATORS: Lazy<HashMap<char, HashMap<char, Token>>> = Lazy::new(|| {
                 .into_iter()
                 .collect(),
         ),
-        ('*', vec![('\0', Token::Multiply)].into_iter().collect()),
+        (
+            '*',
+            vec![('\0', Token::Multiply), ('*', Token::Power)]
+                .into_iter()
+                .collect(),
+        ),
         ('/', vec![('/', Token::Divide)].into_iter().collect()),
         ('%', vec![('\0', Token::Mod)].into_iter().collect()),
+        ('&', vec![('\0', Token::BitAnd)].into_iter().collect()),
+        ('|', vec![('\0', Token::BitOr)].into_iter().collect()),
+        ('^', vec![('\0', Token::BitXor)].into_iter().collect()),
+        ('~', vec![('\0', Token::BitNot)].into_iter().collect()),
         (
             '<',
-            vec![('\0', Token::Less), ('=', Token::LessEqual)]
+            vec![
+                ('\0', Token::Less),
+                ('=', Token::LessEqual),
+                ('<', Token::ShiftLeft),
+            ]
                 .into_iter()
                 .collect(),
         ),
         (
             '>',
-            vec![('\0', Token::Greater), ('=', Token::GreaterEqual)]
+            vec![
+                ('\0', Token::Greater),
+                ('=', Token::GreaterEqual),
+                ('>', Token::ShiftRight),
+            ]
                 .into_iter()
                 .collect(),
         ),
@@ -152,6 +179,8 @@ pub static OPERATORS: Lazy<HashMap<char, HashMap<char, Token>>> = Lazy::new(|| {
         (')', vec![('\0', Token::RightPar)].into_iter().collect()),
         ('[', vec![('\0', Token::LeftSquare)].into_iter().collect()),
         (']', vec![('\0', Token::RightSquare)].into_iter().collect()),
+        ('{', vec![('\0', Token::LeftBrace)].into_iter().collect()),
+        ('}', vec![('\0', Token::RightBrace)].into_iter().collect()),
         (',', vec![('\0', Token::Comma)].into_iter().collect()),
         (':', vec![('\0', Token::Colon)].into_iter().collect()),
         ('.', vec![('\0', Token::Dot)].into_iter().collect()),
@@ -160,8 +189,24 @@ pub static OPERATORS: Lazy<HashMap<char, HashMap<char, Token>>> = Lazy::new(|| {
     .collect()
 });
 
+// Comments and blank lines, which the parser skips but the formatter keeps
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Trivia {
+    // A comment from `#` to the end of the line, either on a line of its own
+    // or after code
+    Comment {
+        text: String,
+        location: Location,
+        own_line: bool,
+    },
+    BlankLine(u32),
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct ComplexToken {
     pub token: Token,
     pub location: Location,
+    // The trivia before the token. A comment after code is attached to the
+    // line break ending it.
+    pub trivia: Vec<Trivia>,
 }
@@ -0,0 +1,1087 @@
+// An interpreter of type checked programs, for the REPL. It follows the
+// semantics of compiled programs, including the messages and exit codes of
+// runtime errors, but runs on the typed AST instead of generating code.
+
+mod set;
+mod value;
+
+use crate::common::node::*;
+use set::Set;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display, Formatter};
+use std::rc::Rc;
+pub use value::Value;
+use value::{Class, Object};
+
+// The errors that stop a program, like the traps of the runtime
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum RuntimeError {
+    InvalidArgument,
+    DivisionByZero,
+    IndexOutOfBounds,
+    NoneOperation,
+    GeneratorExhausted,
+    NegativeShift,
+    NegativeExponent,
+    MissingElement,
+}
+
+impl RuntimeError {
+    // The exit code of a compiled program stopped by the error
+    pub fn code(self) -> i32 {
+        match self {
+            RuntimeError::InvalidArgument => 1,
+            RuntimeError::DivisionByZero => 2,
+            RuntimeError::IndexOutOfBounds => 3,
+            RuntimeError::NoneOperation => 4,
+            RuntimeError::GeneratorExhausted => 5,
+            RuntimeError::NegativeShift => 6,
+            RuntimeError::NegativeExponent => 7,
+            RuntimeError::MissingElement => 8,
+        }
+    }
+}
+
+impl Display for RuntimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            RuntimeError::InvalidArgument => "Invalid argument",
+            RuntimeError::DivisionByZero => "Division by zero",
+            RuntimeError::IndexOutOfBounds => "Index out of bounds",
+            RuntimeError::NoneOperation => "Operation on None",
+            RuntimeError::GeneratorExhausted => "Generator exhausted",
+            RuntimeError::NegativeShift => "Negative shift count",
+            RuntimeError::NegativeExponent => "Negative exponent",
+            RuntimeError::MissingElement => "Element not in set",
+        };
+        write!(f, "{}", message)
+    }
+}
+
+// Where `print` writes and `input()` reads
+pub trait Console {
+    // Write to stdout, or to stderr if `error` is set
+    fn write(&mut self, text: &str, error: bool, flush: bool);
+    // Read a line without its line break, or `None` at the end of the input
+    fn read_line(&mut self) -> Option<String>;
+}
+
+// The standard streams of the process
+pub struct StdConsole;
+
+impl Console for StdConsole {
+    fn write(&mut self, text: &str, error: bool, flush: bool) {
+        use std::io::Write;
+        // Unlike compiled programs, the REPL keeps going if the output is closed
+        let _ = if error {
+            let mut stderr = std::io::stderr().lock();
+            stderr.write_all(text.as_bytes()).and_then(|_| stderr.flush())
+        } else {
+            let mut stdout = std::io::stdout().lock();
+            stdout
+                .write_all(text.as_bytes())
+                .and_then(|_| if flush { stdout.flush() } else { Ok(()) })
+        };
+    }
+
+    fn read_line(&mut self) -> Option<String> {
+        let mut line = String::new();
+        match std::io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim_end_matches(['\n', '\r']).to_owned()),
+        }
+    }
+}
+
+// A function or method, with its nested functions taken out of its declarations
+pub struct Function {
+    params: Vec<String>,
+    // Local variables with their initial values
+    variables: Vec<(String, Value)>,
+    statements: Vec<Stmt>,
+    nested: HashMap<String, Rc<Function>>,
+    // Parameters and local variables
+    locals: HashSet<String>,
+    // Names declared `global`
+    globals: HashSet<String>,
+    generator: bool,
+}
+
+impl Function {
+    fn new(def: &FuncDef) -> Rc<Function> {
+        let params: Vec<String> = def.params.iter().map(|p| p.identifier.name.clone()).collect();
+        let mut variables = vec![];
+        let mut nested = HashMap::new();
+        let mut globals = HashSet::new();
+        for declaration in &def.declarations {
+            match declaration {
+                Declaration::VarDef(v) => {
+                    variables.push((v.var.identifier.name.clone(), literal(&v.value)));
+                }
+                Declaration::FuncDef(f) => {
+                    nested.insert(f.name.name.clone(), Function::new(f));
+                }
+                Declaration::GlobalDecl(g) => {
+                    globals.insert(g.variable.name.clone());
+                }
+                _ => (),
+            }
+        }
+        let locals = params
+            .iter()
+            .chain(variables.iter().map(|(name, _)| name))
+            .cloned()
+            .collect();
+        Rc::new(Function {
+            params,
+            variables,
+            statements: def.statements.clone(),
+            nested,
+            locals,
+            globals,
+            generator: def.is_generator(),
+        })
+    }
+}
+
+fn literal(literal: &Literal) -> Value {
+    match &literal.content {
+        LiteralContent::IntegerLiteral(i) => Value::Int(i.value),
+        LiteralContent::BooleanLiteral(b) => Value::Bool(b.value),
+        LiteralContent::NoneLiteral(_) => Value::None,
+        LiteralContent::StringLiteral(s) => Value::str(s.value.as_bytes()),
+    }
+}
+
+// The variables of a function call, or the variable of a comprehension
+struct Frame {
+    function: Option<Rc<Function>>,
+    variables: HashMap<String, Value>,
+    // The frame of the enclosing function or comprehension, if any
+    parent: Scope,
+}
+
+// The innermost frame, or `None` at the top level
+type Scope = Option<Rc<RefCell<Frame>>>;
+
+pub struct Generator {
+    function: Rc<Function>,
+    frame: Rc<RefCell<Frame>>,
+    // Where the body stopped at the last `yield`, innermost first
+    points: Vec<Point>,
+    finished: bool,
+}
+
+// A position in the body of a suspended generator, recorded by each enclosing
+// statement on the way out of a `yield` and followed back in to resume it
+enum Point {
+    // The statement at this index of a block
+    Statement(usize),
+    Then,
+    Else,
+    WhileBody,
+    ForBody(Iteration),
+    LoopElse,
+}
+
+enum Iteration {
+    // A list or `str`, and the index of the next element. Sets are iterated
+    // over a list of their elements, so they can be modified in the loop.
+    Sequence(Value, usize),
+    Generator(Rc<RefCell<Generator>>),
+}
+
+enum Flow {
+    Next,
+    // Leaves the innermost loop, skipping its `else` clause
+    Break,
+    Return(Value),
+    Yield(Value),
+}
+
+// The element of a set, which cannot be None
+fn set_element(value: Value) -> Result<Value, RuntimeError> {
+    match value {
+        Value::None => Err(RuntimeError::NoneOperation),
+        value => Ok(value),
+    }
+}
+
+// `//` and `%`, rounding toward negative infinity
+fn divide(left: i32, right: i32, modulo: bool) -> Result<i32, RuntimeError> {
+    if right == 0 {
+        return Err(RuntimeError::DivisionByZero);
+    }
+    let (quotient, remainder) = (left.wrapping_div(right), left.wrapping_rem(right));
+    let adjust = remainder != 0 && (remainder < 0) != (right < 0);
+    Ok(match (modulo, adjust) {
+        (false, false) => quotient,
+        (false, true) => quotient.wrapping_sub(1),
+        (true, false) => remainder,
+        (true, true) => remainder.wrapping_add(right),
+    })
+}
+
+pub struct Interpreter {
+    globals: HashMap<String, Value>,
+    functions: HashMap<String, Rc<Function>>,
+    classes: HashMap<String, Rc<Class>>,
+    console: Box<dyn Console>,
+}
+
+impl Interpreter {
+    pub fn new(console: Box<dyn Console>) -> Interpreter {
+        let object = Class {
+            name: "object".to_owned(),
+            super_class: None,
+            attributes: vec![],
+            methods: HashMap::new(),
+        };
+        Interpreter {
+            globals: HashMap::new(),
+            functions: HashMap::new(),
+            classes: std::iter::once(("object".to_owned(), Rc::new(object))).collect(),
+            console,
+        }
+    }
+
+    pub fn console(&mut self) -> &mut dyn Console {
+        &mut *self.console
+    }
+
+    // Define the global variables, functions and classes of type checked declarations
+    pub fn declare(&mut self, declarations: &[Declaration]) {
+        for declaration in declarations {
+            match declaration {
+                Declaration::VarDef(v) => {
+                    self.globals.insert(v.var.identifier.name.clone(), literal(&v.value));
+                }
+                Declaration::FuncDef(f) => {
+                    self.functions.insert(f.name.name.clone(), Function::new(f));
+                }
+                Declaration::ClassDef(c) => self.declare_class(c),
+                _ => (),
+            }
+        }
+    }
+
+    fn declare_class(&mut self, c: &ClassDef) {
+        let super_class = self.classes[&c.super_class.name].clone();
+        let mut attributes = super_class.attributes.clone();
+        let mut methods = super_class.methods.clone();
+        for declaration in &c.declarations {
+            match declaration {
+                Declaration::VarDef(v) => {
+                    attributes.push((v.var.identifier.name.clone(), literal(&v.value)));
+                }
+                Declaration::FuncDef(f) => {
+                    methods.insert(f.name.name.clone(), Function::new(f));
+                }
+                _ => (),
+            }
+        }
+        let class = Class {
+            name: c.name.name.clone(),
+            super_class: Some(super_class),
+            attributes,
+            methods,
+        };
+        self.classes.insert(c.name.name.clone(), Rc::new(class));
+    }
+
+    // Run type checked top-level statements
+    pub fn execute(&mut self, statements: &[Stmt]) -> Result<(), RuntimeError> {
+        self.run_block(statements, &None, &mut vec![])?;
+        Ok(())
+    }
+
+    // Evaluate a type checked expression at the top level
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, RuntimeError> {
+        self.eval(expr, &None)
+    }
+
+    // The frame holding a variable, or `None` for a global one
+    fn frame_of(name: &str, scope: &Scope) -> Scope {
+        let mut scope = scope.clone();
+        while let Some(frame) = scope {
+            let parent = {
+                let f = frame.borrow();
+                match &f.function {
+                    Some(function) if function.locals.contains(name) => return Some(frame.clone()),
+                    Some(function) if function.globals.contains(name) => return None,
+                    None if f.variables.contains_key(name) => return Some(frame.clone()),
+                    _ => f.parent.clone(),
+                }
+            };
+            scope = parent;
+        }
+        None
+    }
+
+    fn get_variable(&self, name: &str, scope: &Scope) -> Value {
+        let value = match Self::frame_of(name, scope) {
+            Some(frame) => frame.borrow().variables.get(name).cloned(),
+            None => self.globals.get(name).cloned(),
+        };
+        // Deleted variables are not read, as checked by the type checker
+        value.unwrap_or(Value::None)
+    }
+
+    fn set_variable(&mut self, name: &str, value: Value, scope: &Scope) {
+        match Self::frame_of(name, scope) {
+            Some(frame) => frame.borrow_mut().variables.insert(name.to_owned(), value),
+            None => self.globals.insert(name.to_owned(), value),
+        };
+    }
+
+    // The function called by a name, with the frame it is nested in
+    fn function_of(&self, name: &str, scope: &Scope) -> Option<(Rc<Function>, Scope)> {
+        let mut scope = scope.clone();
+        while let Some(frame) = scope {
+            let parent = {
+                let f = frame.borrow();
+                if let Some(function) = f.function.as_ref().and_then(|f| f.nested.get(name)) {
+                    return Some((function.clone(), Some(frame.clone())));
+                }
+                f.parent.clone()
+            };
+            scope = parent;
+        }
+        self.functions.get(name).map(|function| (function.clone(), None))
+    }
+
+    // Run a block, or resume it where a generator stopped if there are points left
+    fn run_block(
+        &mut self,
+        block: &[Stmt],
+        scope: &Scope,
+        points: &mut Vec<Point>,
+    ) -> Result<Flow, RuntimeError> {
+        let (mut index, mut resuming) = match points.pop() {
+            Some(Point::Statement(index)) => (index, true),
+            Some(_) => unreachable!(),
+            None => (0, false),
+        };
+        while index < block.len() {
+            let flow = if resuming {
+                resuming = false;
+                self.resume(&block[index], scope, points)?
+            } else {
+                self.run(&block[index], scope, points)?
+            };
+            match flow {
+                Flow::Next => index += 1,
+                Flow::Yield(value) => {
+                    points.push(Point::Statement(index));
+                    return Ok(Flow::Yield(value));
+                }
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Next)
+    }
+
+    // Run the statements of a branch or a loop `else` clause, marked by `point`
+    fn run_branch(
+        &mut self,
+        block: &[Stmt],
+        point: Point,
+        scope: &Scope,
+        points: &mut Vec<Point>,
+    ) -> Result<Flow, RuntimeError> {
+        let flow = self.run_block(block, scope, points)?;
+        if let Flow::Yield(_) = flow {
+            points.push(point);
+        }
+        Ok(flow)
+    }
+
+    fn run(
+        &mut self,
+        statement: &Stmt,
+        scope: &Scope,
+        points: &mut Vec<Point>,
+    ) -> Result<Flow, RuntimeError> {
+        match statement {
+            Stmt::ExprStmt(s) => {
+                self.eval(&s.expr, scope)?;
+            }
+            Stmt::AssignStmt(s) => {
+                let value = self.eval(&s.value, scope)?;
+                for target in &s.targets {
+                    self.assign(target, value.clone(), scope)?;
+                }
+            }
+            Stmt::IfStmt(s) => {
+                return if self.eval(&s.condition, scope)?.as_bool() {
+                    self.run_branch(&s.then_body, Point::Then, scope, points)
+                } else {
+                    self.run_branch(&s.else_body, Point::Else, scope, points)
+                };
+            }
+            Stmt::WhileStmt(s) => return self.run_while(s, false, scope, points),
+            Stmt::ForStmt(s) => {
+                let iterable = self.eval(&s.iterable, scope)?;
+                let iteration = Self::iterate(iterable)?;
+                return self.run_for(s, iteration, false, scope, points);
+            }
+            Stmt::ReturnStmt(s) => {
+                let value = match &s.value {
+                    Some(value) => self.eval(value, scope)?,
+                    None => Value::None,
+                };
+                return Ok(Flow::Return(value));
+            }
+            Stmt::YieldStmt(s) => {
+                let value = match &s.value {
+                    Some(value) => self.eval(value, scope)?,
+                    None => Value::None,
+                };
+                return Ok(Flow::Yield(value));
+            }
+            Stmt::DelStmt(s) => {
+                for target in &s.targets {
+                    self.delete(target, scope)?;
+                }
+            }
+            Stmt::BreakStmt(_) => return Ok(Flow::Break),
+        }
+        Ok(Flow::Next)
+    }
+
+    // Continue a statement of a generator that stopped in it
+    fn resume(
+        &mut self,
+        statement: &Stmt,
+        scope: &Scope,
+        points: &mut Vec<Point>,
+    ) -> Result<Flow, RuntimeError> {
+        match (statement, points.pop()) {
+            (Stmt::YieldStmt(_), None) => Ok(Flow::Next),
+            (Stmt::IfStmt(s), Some(Point::Then)) => {
+                self.run_branch(&s.then_body, Point::Then, scope, points)
+            }
+            (Stmt::IfStmt(s), Some(Point::Else)) => {
+                self.run_branch(&s.else_body, Point::Else, scope, points)
+            }
+            (Stmt::WhileStmt(s), Some(Point::WhileBody)) => self.run_while(s, true, scope, points),
+            (Stmt::ForStmt(s), Some(Point::ForBody(iteration))) => {
+                self.run_for(s, iteration, true, scope, points)
+            }
+            (Stmt::WhileStmt(WhileStmt { else_body, .. }), Some(Point::LoopElse))
+            | (Stmt::ForStmt(ForStmt { else_body, .. }), Some(Point::LoopElse)) => {
+                let else_body = else_body.as_deref().unwrap_or_default();
+                self.run_branch(else_body, Point::LoopElse, scope, points)
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    // Run a `while` loop, starting in the body if a generator resumes there
+    fn run_while(
+        &mut self,
+        s: &WhileStmt,
+        mut resuming: bool,
+        scope: &Scope,
+        points: &mut Vec<Point>,
+    ) -> Result<Flow, RuntimeError> {
+        loop {
+            if !resuming && !self.eval(&s.condition, scope)?.as_bool() {
+                break;
+            }
+            resuming = false;
+            match self.run_block(&s.body, scope, points)? {
+                Flow::Next => (),
+                Flow::Break => return Ok(Flow::Next),
+                Flow::Yield(value) => {
+                    points.push(Point::WhileBody);
+                    return Ok(Flow::Yield(value));
+                }
+                flow => return Ok(flow),
+            }
+        }
+        self.run_branch(s.else_body(), Point::LoopElse, scope, points)
+    }
+
+    // Run a `for` loop, starting in the body if a generator resumes there
+    fn run_for(
+        &mut self,
+        s: &ForStmt,
+        mut iteration: Iteration,
+        mut resuming: bool,
+        scope: &Scope,
+        points: &mut Vec<Point>,
+    ) -> Result<Flow, RuntimeError> {
+        loop {
+            if !resuming {
+                let Some(element) = self.next_element(&mut iteration)? else {
+                    break;
+                };
+                self.set_variable(&s.identifier.name, element, scope);
+            }
+            resuming = false;
+            match self.run_block(&s.body, scope, points)? {
+                Flow::Next => (),
+                Flow::Break => return Ok(Flow::Next),
+                Flow::Yield(value) => {
+                    points.push(Point::ForBody(iteration));
+                    return Ok(Flow::Yield(value));
+                }
+                flow => return Ok(flow),
+            }
+        }
+        self.run_branch(s.else_body(), Point::LoopElse, scope, points)
+    }
+
+    fn iterate(iterable: Value) -> Result<Iteration, RuntimeError> {
+        Ok(match iterable {
+            Value::None => return Err(RuntimeError::NoneOperation),
+            Value::Set(set) => Iteration::Sequence(Value::list(set.borrow().elements()), 0),
+            Value::Generator(generator) => Iteration::Generator(generator),
+            value => Iteration::Sequence(value, 0),
+        })
+    }
+
+    // The next element of an iteration, or `None` at its end. Lists may change
+    // during the loop, so their length is checked each time.
+    fn next_element(&mut self, iteration: &mut Iteration) -> Result<Option<Value>, RuntimeError> {
+        let (sequence, index) = match iteration {
+            Iteration::Generator(generator) => return self.resume_generator(generator),
+            Iteration::Sequence(sequence, index) => (sequence, index),
+        };
+        let element = match sequence {
+            Value::List(list) => list.borrow().get(*index).cloned(),
+            Value::Str(s) => s.get(*index..*index + 1).map(Value::str),
+            _ => unreachable!(),
+        };
+        *index += 1;
+        Ok(element)
+    }
+
+    // Run a generator to its next `yield`, or return `None` when it is exhausted
+    fn resume_generator(
+        &mut self,
+        generator: &Rc<RefCell<Generator>>,
+    ) -> Result<Option<Value>, RuntimeError> {
+        let (function, frame, mut points) = {
+            let mut g = generator.borrow_mut();
+            if g.finished {
+                return Ok(None);
+            }
+            // A generator resumed from its own body is exhausted
+            g.finished = true;
+            (g.function.clone(), g.frame.clone(), std::mem::take(&mut g.points))
+        };
+        match self.run_block(&function.statements, &Some(frame), &mut points)? {
+            Flow::Yield(value) => {
+                let mut g = generator.borrow_mut();
+                g.finished = false;
+                g.points = points;
+                Ok(Some(value))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn assign(&mut self, target: &Expr, value: Value, scope: &Scope) -> Result<(), RuntimeError> {
+        match &target.content {
+            ExprContent::Variable(v) => self.set_variable(&v.name, value, scope),
+            ExprContent::IndexExpr(e) => {
+                let Value::List(list) = self.eval(&e.list, scope)? else {
+                    return Err(RuntimeError::NoneOperation);
+                };
+                let index = self.eval(&e.index, scope)?.as_int();
+                let mut list = list.borrow_mut();
+                let element = usize::try_from(index).ok().and_then(|i| list.get_mut(i));
+                *element.ok_or(RuntimeError::IndexOutOfBounds)? = value;
+            }
+            ExprContent::MemberExpr(e) => {
+                let Value::Object(object) = self.eval(&e.object, scope)? else {
+                    return Err(RuntimeError::NoneOperation);
+                };
+                object.attributes.borrow_mut().insert(e.member.name.clone(), value);
+            }
+            _ => unreachable!(),
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, target: &Expr, scope: &Scope) -> Result<(), RuntimeError> {
+        let (list, start, end) = match &target.content {
+            ExprContent::Variable(v) => {
+                if let Some(frame) = Self::frame_of(&v.name, scope) {
+                    frame.borrow_mut().variables.remove(&v.name);
+                }
+                return Ok(());
+            }
+            ExprContent::IndexExpr(e) => {
+                let list = self.eval(&e.list, scope)?;
+                let index = self.eval(&e.index, scope)?.as_int();
+                (list, Some(index), index.checked_add(1))
+            }
+            ExprContent::SliceExpr(e) => {
+                let list = self.eval(&e.list, scope)?;
+                let start = match &e.start {
+                    Some(start) => Some(self.eval(start, scope)?.as_int()),
+                    None => Some(0),
+                };
+                let end = match &e.end {
+                    Some(end) => Some(self.eval(end, scope)?.as_int()),
+                    None => None,
+                };
+                (list, start, end)
+            }
+            _ => unreachable!(),
+        };
+        let Value::List(list) = list else {
+            return Err(RuntimeError::NoneOperation);
+        };
+        let mut list = list.borrow_mut();
+        let len = list.len();
+        let bound = |bound: Option<i32>| match bound {
+            Some(bound) => usize::try_from(bound).ok().filter(|&bound| bound <= len),
+            None => Some(len),
+        };
+        let (Some(start), Some(end)) = (bound(start), bound(end)) else {
+            return Err(RuntimeError::IndexOutOfBounds);
+        };
+        if start < end {
+            list.drain(start..end);
+        }
+        Ok(())
+    }
+
+    fn eval_all(&mut self, exprs: &[Expr], scope: &Scope) -> Result<Vec<Value>, RuntimeError> {
+        exprs.iter().map(|expr| self.eval(expr, scope)).collect()
+    }
+
+    fn eval(&mut self, expr: &Expr, scope: &Scope) -> Result<Value, RuntimeError> {
+        Ok(match &expr.content {
+            ExprContent::IntegerLiteral(i) => Value::Int(i.value),
+            ExprContent::BooleanLiteral(b) => Value::Bool(b.value),
+            ExprContent::NoneLiteral(_) => Value::None,
+            ExprContent::StringLiteral(s) => Value::str(s.value.as_bytes()),
+            ExprContent::Variable(v) => self.get_variable(&v.name, scope),
+            ExprContent::UnaryExpr(e) => {
+                let operand = self.eval(&e.operand, scope)?;
+                match e.operator {
+                    UnaryOp::Negative => Value::Int(operand.as_int().wrapping_neg()),
+                    UnaryOp::Not => Value::Bool(!operand.as_bool()),
+                    UnaryOp::Invert => Value::Int(!operand.as_int()),
+                }
+            }
+            ExprContent::BinaryExpr(e) => return self.binary(e, scope),
+            ExprContent::IfExpr(e) => {
+                if self.eval(&e.condition, scope)?.as_bool() {
+                    self.eval(&e.then_expr, scope)?
+                } else {
+                    self.eval(&e.else_expr, scope)?
+                }
+            }
+            ExprContent::ListExpr(e) => Value::list(self.eval_all(&e.elements, scope)?),
+            ExprContent::SetExpr(e) => {
+                let mut set = Set::default();
+                for element in self.eval_all(&e.elements, scope)? {
+                    set.add(set_element(element)?);
+                }
+                Value::set(set)
+            }
+            ExprContent::ListComprehension(e) => {
+                let mut elements = vec![];
+                self.comprehend(&e.clauses, &e.element, scope, &mut elements)?;
+                Value::list(elements)
+            }
+            ExprContent::IndexExpr(e) => {
+                let list = self.eval(&e.list, scope)?;
+                if let Value::None = list {
+                    return Err(RuntimeError::NoneOperation);
+                }
+                let index = usize::try_from(self.eval(&e.index, scope)?.as_int()).ok();
+                let element = match &list {
+                    Value::List(list) => index.and_then(|i| list.borrow().get(i).cloned()),
+                    Value::Str(s) => index.and_then(|i| s.get(i..i + 1)).map(Value::str),
+                    _ => unreachable!(),
+                };
+                element.ok_or(RuntimeError::IndexOutOfBounds)?
+            }
+            ExprContent::MemberExpr(e) => {
+                let Value::Object(object) = self.eval(&e.object, scope)? else {
+                    return Err(RuntimeError::NoneOperation);
+                };
+                let attributes = object.attributes.borrow();
+                attributes[&e.member.name].clone()
+            }
+            ExprContent::CallExpr(e) => return self.call(e, scope),
+            ExprContent::MethodCallExpr(e) => return self.call_method(e, scope),
+            ExprContent::SliceExpr(_) => unreachable!("Slices are only allowed in `del`"),
+        })
+    }
+
+    fn binary(&mut self, e: &BinaryExpr, scope: &Scope) -> Result<Value, RuntimeError> {
+        let left = self.eval(&e.left, scope)?;
+        match e.operator {
+            BinaryOp::And if !left.as_bool() => return Ok(left),
+            BinaryOp::Or if left.as_bool() => return Ok(left),
+            BinaryOp::And | BinaryOp::Or => return self.eval(&e.right, scope),
+            _ => (),
+        }
+        let right = self.eval(&e.right, scope)?;
+
+        let int = |value: i32| Ok(Value::Int(value));
+        let bool = |value: bool| Ok(Value::Bool(value));
+        match (&e.operator, &left, &right) {
+            (BinaryOp::Eq, _, _) => bool(left.equal(&right)),
+            (BinaryOp::Ne, _, _) => bool(!left.equal(&right)),
+            (BinaryOp::Is, _, _) => bool(left.is(&right)),
+            (BinaryOp::Lt, _, _) => bool(left.compare(&right)?.is_lt()),
+            (BinaryOp::Le, _, _) => bool(left.compare(&right)?.is_le()),
+            (BinaryOp::Gt, _, _) => bool(left.compare(&right)?.is_gt()),
+            (BinaryOp::Ge, _, _) => bool(left.compare(&right)?.is_ge()),
+            (BinaryOp::Add, Value::Str(a), Value::Str(b)) => Ok(Value::str(&[&a[..], b].concat())),
+            (BinaryOp::Add, Value::List(a), Value::List(b)) => {
+                let elements = a.borrow().iter().chain(b.borrow().iter()).cloned().collect();
+                Ok(Value::list(elements))
+            }
+            (BinaryOp::BitOr, Value::Set(a), Value::Set(b)) => {
+                Ok(Value::set(a.borrow().union(&b.borrow())))
+            }
+            (BinaryOp::BitAnd, Value::Set(a), Value::Set(b)) => {
+                Ok(Value::set(a.borrow().intersection(&b.borrow())))
+            }
+            (BinaryOp::In, element, Value::Set(set)) => {
+                bool(set.borrow().contains(&set_element(element.clone())?))
+            }
+            (operator, Value::Int(a), Value::Int(b)) => {
+                let (a, b) = (*a, *b);
+                match operator {
+                    BinaryOp::Add => int(a.wrapping_add(b)),
+                    BinaryOp::Sub => int(a.wrapping_sub(b)),
+                    BinaryOp::Mul => int(a.wrapping_mul(b)),
+                    BinaryOp::Div => int(divide(a, b, false)?),
+                    BinaryOp::Mod => int(divide(a, b, true)?),
+                    BinaryOp::BitAnd => int(a & b),
+                    BinaryOp::BitOr => int(a | b),
+                    BinaryOp::BitXor => int(a ^ b),
+                    BinaryOp::Shl | BinaryOp::Shr if b < 0 => Err(RuntimeError::NegativeShift),
+                    // Shifting by 32 or more bits is well-defined for 32-bit `int`s
+                    BinaryOp::Shl => int(a.checked_shl(b as u32).unwrap_or(0)),
+                    BinaryOp::Shr => int(a >> b.min(31)),
+                    BinaryOp::Pow if b < 0 => Err(RuntimeError::NegativeExponent),
+                    BinaryOp::Pow => int(a.wrapping_pow(b as u32)),
+                    _ => unreachable!(),
+                }
+            }
+            // One of the operands of a list or set operation is None
+            _ => Err(RuntimeError::NoneOperation),
+        }
+    }
+
+    // Collect the elements of a comprehension, binding the variable of each
+    // clause in a frame of its own
+    fn comprehend(
+        &mut self,
+        clauses: &[ComprehensionClause],
+        element: &Expr,
+        scope: &Scope,
+        elements: &mut Vec<Value>,
+    ) -> Result<(), RuntimeError> {
+        let Some((clause, rest)) = clauses.split_first() else {
+            elements.push(self.eval(element, scope)?);
+            return Ok(());
+        };
+        let iterable = self.eval(&clause.iterable, scope)?;
+        let mut iteration = Self::iterate(iterable)?;
+        let frame = Frame {
+            function: None,
+            variables: HashMap::new(),
+            parent: scope.clone(),
+        };
+        let frame = Rc::new(RefCell::new(frame));
+        let inner = Some(frame.clone());
+        'elements: while let Some(value) = self.next_element(&mut iteration)? {
+            let name = clause.identifier.name.clone();
+            frame.borrow_mut().variables.insert(name, value);
+            for condition in &clause.conditions {
+                if !self.eval(condition, &inner)?.as_bool() {
+                    continue 'elements;
+                }
+            }
+            self.comprehend(rest, element, &inner, elements)?;
+        }
+        Ok(())
+    }
+
+    // Call a function, with the frame it is nested in as the parent of its own
+    fn invoke(
+        &mut self,
+        function: &Rc<Function>,
+        parent: Scope,
+        args: Vec<Value>,
+    ) -> Result<Value, RuntimeError> {
+        let variables = function
+            .params
+            .iter()
+            .cloned()
+            .zip(args)
+            .chain(function.variables.iter().cloned())
+            .collect();
+        let frame = Frame {
+            function: Some(function.clone()),
+            variables,
+            parent,
+        };
+        let frame = Rc::new(RefCell::new(frame));
+        if function.generator {
+            let generator = Generator {
+                function: function.clone(),
+                frame,
+                points: vec![],
+                finished: false,
+            };
+            return Ok(Value::Generator(Rc::new(RefCell::new(generator))));
+        }
+        match self.run_block(&function.statements, &Some(frame), &mut vec![])? {
+            Flow::Return(value) => Ok(value),
+            _ => Ok(Value::None),
+        }
+    }
+
+    // Create an object with the initial values of its attributes, and call its
+    // `__init__` method
+    fn construct(&mut self, class: Rc<Class>) -> Result<Value, RuntimeError> {
+        let object = Object {
+            attributes: RefCell::new(class.attributes.iter().cloned().collect()),
+            class: class.clone(),
+        };
+        let object = Value::Object(Rc::new(object));
+        if let Some(init) = class.methods.get("__init__") {
+            self.invoke(init, None, vec![object.clone()])?;
+        }
+        Ok(object)
+    }
+
+    fn call(&mut self, call: &CallExpr, scope: &Scope) -> Result<Value, RuntimeError> {
+        let name = call.function.name.as_str();
+        if let Some((function, parent)) = self.function_of(name, scope) {
+            let args = self.eval_all(&call.args, scope)?;
+            return self.invoke(&function, parent, args);
+        }
+        if let Some(class) = self.classes.get(name) {
+            return self.construct(class.clone());
+        }
+
+        Ok(match name {
+            "print" => return self.print(call, scope),
+            "input" => Value::str(self.console.read_line().unwrap_or_default().as_bytes()),
+            "len" => {
+                let len = match self.eval(&call.args[0], scope)? {
+                    Value::Str(s) => s.len(),
+                    Value::List(list) => list.borrow().len(),
+                    Value::Set(set) => set.borrow().len(),
+                    _ => return Err(RuntimeError::InvalidArgument),
+                };
+                Value::Int(len as i32)
+            }
+            "isinstance" => {
+                let object = self.eval(&call.args[0], scope)?;
+                let ExprContent::Variable(class) = &call.args[1].content else {
+                    unreachable!()
+                };
+                Value::Bool(object.is_instance(&class.name))
+            }
+            "next" => {
+                let Value::Generator(generator) = self.eval(&call.args[0], scope)? else {
+                    return Err(RuntimeError::NoneOperation);
+                };
+                let value = self.resume_generator(&generator)?;
+                value.ok_or(RuntimeError::GeneratorExhausted)?
+            }
+            "int" => Value::Int(0),
+            "bool" => Value::Bool(false),
+            "str" => Value::str(b""),
+            "set" => Value::set(Set::default()),
+            _ => unreachable!("Calls are type checked"),
+        })
+    }
+
+    // `print(*objects, sep=str, end=str, file=stdout|stderr, flush=bool)`
+    fn print(&mut self, call: &CallExpr, scope: &Scope) -> Result<Value, RuntimeError> {
+        let args = self.eval_all(&call.args, scope)?;
+        let (mut sep, mut end) = (" ".to_owned(), "\n".to_owned());
+        let (mut error, mut flush) = (false, false);
+        for keyword in &call.keywords {
+            if keyword.name.name == "file" {
+                let stream = &keyword.value.content;
+                error = matches!(stream, ExprContent::Variable(v) if v.name == "stderr");
+                continue;
+            }
+            let value = self.eval(&keyword.value, scope)?;
+            match keyword.name.name.as_str() {
+                "sep" => sep = value.print_text()?,
+                "end" => end = value.print_text()?,
+                _ => flush = value.as_bool(),
+            }
+        }
+
+        // Nothing is written if any object can't be printed
+        let texts = args.iter().map(Value::print_text).collect::<Result<Vec<_>, _>>()?;
+        let output = texts.join(&sep) + &end;
+        self.console.write(&output, error, flush);
+        Ok(Value::None)
+    }
+
+    fn call_method(&mut self, call: &MethodCallExpr, scope: &Scope) -> Result<Value, RuntimeError> {
+        let name = &call.method.member.name;
+        let object = &call.method.object;
+        // `super().method()` calls the method of the parent class on `self`
+        if let ExprContent::CallExpr(c) = &object.content
+            && c.function.name == "super"
+            && self.function_of("super", scope).is_none()
+        {
+            let ValueType::ClassValueType(parent) = object.get_type() else {
+                unreachable!()
+            };
+            let method = self.classes[&parent.class_name].methods.get(name).cloned();
+            let this = self.get_variable(&Self::method_self(scope), scope);
+            let args = self.eval_all(&call.args, scope)?;
+            return match method {
+                Some(method) => self.invoke(&method, None, [vec![this], args].concat()),
+                None => Ok(Value::None),
+            };
+        }
+
+        let object = self.eval(object, scope)?;
+        let args = self.eval_all(&call.args, scope)?;
+        match &object {
+            Value::None => Err(RuntimeError::NoneOperation),
+            Value::Object(o) => match o.class.methods.get(name).cloned() {
+                Some(method) => self.invoke(&method, None, [vec![object], args].concat()),
+                // `object.__init__`
+                None => Ok(Value::None),
+            },
+            Value::Set(set) => {
+                let element = set_element(args.into_iter().next().unwrap())?;
+                let mut set = set.borrow_mut();
+                match name.as_str() {
+                    "add" => set.add(element),
+                    "remove" if !set.remove(&element) => return Err(RuntimeError::MissingElement),
+                    _ => {
+                        set.remove(&element);
+                    }
+                }
+                Ok(Value::None)
+            }
+            // `__init__` of the other built-in classes
+            _ => Ok(Value::None),
+        }
+    }
+
+    // The name of the first parameter of the method enclosing the scope
+    fn method_self(scope: &Scope) -> String {
+        let mut scope = scope.clone();
+        while let Some(frame) = scope {
+            let parent = {
+                let f = frame.borrow();
+                if let Some(function) = &f.function {
+                    return function.params[0].clone();
+                }
+                f.parent.clone()
+            };
+            scope = parent;
+        }
+        unreachable!("`super()` is only allowed in methods")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::{frontend, typecheck};
+
+    // A console reading from a list of lines and writing stdout to a shared buffer
+    struct TestConsole {
+        input: std::vec::IntoIter<String>,
+        output: Rc<RefCell<String>>,
+    }
+
+    impl Console for TestConsole {
+        fn write(&mut self, text: &str, error: bool, _flush: bool) {
+            if !error {
+                self.output.borrow_mut().push_str(text);
+            }
+        }
+
+        fn read_line(&mut self) -> Option<String> {
+            self.input.next()
+        }
+    }
+
+    // The output of a program, ending like the one of a compiled program on errors
+    fn run(source: &str, input: Vec<String>) -> String {
+        let ast = typecheck::check(frontend::process_source(source), false);
+        assert!(ast.errors.errors.is_empty());
+        let output = Rc::new(RefCell::new(String::new()));
+        let console = TestConsole {
+            input: input.into_iter(),
+            output: output.clone(),
+        };
+        let mut interpreter = Interpreter::new(Box::new(console));
+        interpreter.declare(&ast.declarations);
+        if let Err(error) = interpreter.execute(&ast.statements) {
+            let code = error.code();
+            output.borrow_mut().push_str(&format!("{}\nExited with error code {}\n", error, code));
+        }
+        output.take()
+    }
+
+    // The cases at the end of a source file, each made of the lines of its input
+    // and its expected output, commented out between `#!` and `#<->#` markers
+    fn cases(source: &str) -> Vec<(Vec<String>, String)> {
+        let mut cases = vec![];
+        let mut lines = source.lines();
+        while lines.any(|line| line == "#!") {
+            let uncomment = |lines: &mut std::str::Lines| {
+                let section = lines.by_ref().take_while(|&line| line != "#<->#");
+                section.map(|line| line[1..].to_owned()).collect::<Vec<_>>()
+            };
+            let input = uncomment(&mut lines);
+            let output = uncomment(&mut lines).join("\n");
+            cases.push((input, output));
+        }
+        cases
+    }
+
+    #[test]
+    fn sample() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../test/pa3");
+        let mut files = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|f| f.unwrap().path())
+            .filter(|f| f.extension().is_some_and(|e| e == "py"))
+            .collect::<Vec<_>>();
+        files.sort();
+        for file in files {
+            let source = std::fs::read_to_string(&file).unwrap();
+            let mut cases = cases(&source);
+            let result = file.with_extension("py.ast.typed.s.result");
+            if cases.is_empty() {
+                let Ok(output) = std::fs::read_to_string(result) else {
+                    continue;
+                };
+                cases.push((vec![], output));
+            }
+            for (input, output) in cases {
+                // Deeply recursive programs need a larger stack than the test threads
+                let source = source.clone();
+                let result = std::thread::Builder::new()
+                    .stack_size(256 << 20)
+                    .spawn(move || run(&source, input))
+                    .unwrap()
+                    .join()
+                    .unwrap();
+                assert_eq!(result.trim_end(), output.trim_end(), "{}", file.display());
+            }
+        }
+    }
+}
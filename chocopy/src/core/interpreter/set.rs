@@ -0,0 +1,131 @@
+use super::value::Value;
+
+// Hash sets of int, bool or str elements, laid out like the ones of the runtime
+// (see `chocopy-stdlib/src/set.rs`) so that `for` loops visit the elements in
+// the same order as compiled programs.
+//
+// The elements live in an open addressing table with linear probing, which is
+// allocated on the first insertion and doubled when it is 3/4 full.
+
+const INITIAL_CAPACITY: usize = 8;
+
+#[derive(Default)]
+pub struct Set {
+    table: Vec<Option<Value>>,
+    len: usize,
+}
+
+fn hash(element: &Value) -> u64 {
+    match element {
+        // FNV-1a
+        Value::Str(s) => s.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        }),
+        Value::Int(i) => (*i as u32 as u64).wrapping_mul(0x9E3779B97F4A7C15) >> 32,
+        Value::Bool(b) => (*b as u64).wrapping_mul(0x9E3779B97F4A7C15) >> 32,
+        _ => unreachable!(),
+    }
+}
+
+impl Set {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    // The slot holding the element, or the empty slot where it would be inserted
+    fn find(&self, element: &Value) -> (usize, bool) {
+        let mask = self.table.len() - 1;
+        let mut index = hash(element) as usize & mask;
+        loop {
+            match &self.table[index] {
+                None => return (index, false),
+                Some(slot) if slot.equal(element) => return (index, true),
+                Some(_) => index = (index + 1) & mask,
+            }
+        }
+    }
+
+    pub fn contains(&self, element: &Value) -> bool {
+        self.len != 0 && self.find(element).1
+    }
+
+    pub fn add(&mut self, element: Value) {
+        if (self.len + 1) * 4 > self.table.len() * 3 {
+            self.grow();
+        }
+        let (index, found) = self.find(&element);
+        if !found {
+            self.table[index] = Some(element);
+            self.len += 1;
+        }
+    }
+
+    fn grow(&mut self) {
+        let capacity = std::cmp::max(INITIAL_CAPACITY, self.table.len() * 2);
+        let elements = self.elements();
+        self.table = vec![None; capacity];
+        for element in elements {
+            let (index, _) = self.find(&element);
+            self.table[index] = Some(element);
+        }
+    }
+
+    // Remove the element, shifting back the following elements of its probe sequence
+    pub fn remove(&mut self, element: &Value) -> bool {
+        if !self.contains(element) {
+            return false;
+        }
+        let mask = self.table.len() - 1;
+        let (mut hole, _) = self.find(element);
+        let mut index = hole;
+        loop {
+            index = (index + 1) & mask;
+            let Some(slot) = &self.table[index] else {
+                break;
+            };
+            // The element can fill the hole unless its home is cyclically in (hole, index]
+            let home = hash(slot) as usize & mask;
+            let stays = if hole <= index {
+                hole < home && home <= index
+            } else {
+                hole < home || home <= index
+            };
+            if !stays {
+                self.table[hole] = self.table[index].take();
+                hole = index;
+            }
+        }
+        self.table[hole] = None;
+        self.len -= 1;
+        true
+    }
+
+    // The elements in the order of the table
+    pub fn elements(&self) -> Vec<Value> {
+        self.table.iter().flatten().cloned().collect()
+    }
+
+    // Unions are built by adding both operands to an empty set
+    pub fn union(&self, other: &Set) -> Set {
+        let mut set = Set::default();
+        for element in self.elements().into_iter().chain(other.elements()) {
+            set.add(element);
+        }
+        set
+    }
+
+    // Intersections are built from the left operand, without the elements missing
+    // from the right one
+    pub fn intersection(&self, other: &Set) -> Set {
+        let mut set = Set::default();
+        for element in self.elements() {
+            set.add(element);
+        }
+        for element in set.elements() {
+            if !other.contains(&element) {
+                set.remove(&element);
+            }
+        }
+        set
+    }
+}
@@ -0,0 +1,222 @@
+use super::set::Set;
+use super::{Function, Generator, RuntimeError};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// A value of the interpreter. Like in compiled programs, `str` holds UTF-8 bytes,
+// and lists, sets and objects are shared by reference.
+#[derive(Clone)]
+pub enum Value {
+    None,
+    Int(i32),
+    Bool(bool),
+    Str(Rc<[u8]>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Set(Rc<RefCell<Set>>),
+    Object(Rc<Object>),
+    Generator(Rc<RefCell<Generator>>),
+}
+
+pub struct Class {
+    pub name: String,
+    pub super_class: Option<Rc<Class>>,
+    // Attributes with their initial values, inherited ones first
+    pub attributes: Vec<(String, Value)>,
+    // Methods, including inherited ones. `object.__init__` does nothing and is left out.
+    pub methods: HashMap<String, Rc<Function>>,
+}
+
+pub struct Object {
+    pub class: Rc<Class>,
+    pub attributes: RefCell<HashMap<String, Value>>,
+}
+
+impl Value {
+    pub fn str(bytes: &[u8]) -> Value {
+        Value::Str(bytes.into())
+    }
+
+    pub fn list(elements: Vec<Value>) -> Value {
+        Value::List(Rc::new(RefCell::new(elements)))
+    }
+
+    pub fn set(set: Set) -> Value {
+        Value::Set(Rc::new(RefCell::new(set)))
+    }
+
+    pub fn as_int(&self) -> i32 {
+        match self {
+            Value::Int(i) => *i,
+            _ => unreachable!("Type checked as int"),
+        }
+    }
+
+    pub fn as_bool(&self) -> bool {
+        match self {
+            Value::Bool(b) => *b,
+            _ => unreachable!("Type checked as bool"),
+        }
+    }
+
+    // Content equality of `int`, `bool`, `str` and lists, and identity otherwise
+    pub fn equal(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::List(a), Value::List(b)) => {
+                let (a, b) = (a.borrow(), b.borrow());
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.equal(b))
+            }
+            (a, b) => a.is(b),
+        }
+    }
+
+    // Lexicographic ordering of `int`, `str` and lists. Ordering a `None` list fails.
+    pub fn compare(&self, other: &Value) -> Result<Ordering, RuntimeError> {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => Ok(a.cmp(b)),
+            (Value::Str(a), Value::Str(b)) => Ok(a.cmp(b)),
+            (Value::List(a), Value::List(b)) => {
+                let (a, b) = (a.borrow(), b.borrow());
+                for (a, b) in a.iter().zip(b.iter()) {
+                    let ordering = a.compare(b)?;
+                    if ordering != Ordering::Equal {
+                        return Ok(ordering);
+                    }
+                }
+                Ok(a.len().cmp(&b.len()))
+            }
+            (Value::Bool(a), Value::Bool(b)) => Ok(a.cmp(b)),
+            _ => Err(RuntimeError::NoneOperation),
+        }
+    }
+
+    // Identity, as tested by `is`. Values of `int` and `bool` have none, and are
+    // compared by value.
+    pub fn is(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::None, Value::None) => true,
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => Rc::ptr_eq(a, b),
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Set(a), Value::Set(b)) => Rc::ptr_eq(a, b),
+            (Value::Object(a), Value::Object(b)) => Rc::ptr_eq(a, b),
+            (Value::Generator(a), Value::Generator(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    // Whether the value is an instance of the class, like `isinstance`
+    pub fn is_instance(&self, class_name: &str) -> bool {
+        let name = match self {
+            Value::None => return false,
+            Value::Int(_) => "int",
+            Value::Bool(_) => "bool",
+            Value::Str(_) => "str",
+            Value::Set(_) => "set",
+            Value::List(_) => "object",
+            Value::Generator(_) => "Iterator",
+            Value::Object(o) => {
+                let mut class = Some(&o.class);
+                while let Some(c) = class {
+                    if c.name == class_name {
+                        return true;
+                    }
+                    class = c.super_class.as_ref();
+                }
+                return class_name == "object";
+            }
+        };
+        class_name == name || class_name == "object"
+    }
+
+    // The text of the value for `print`, which only prints `int`, `bool` and `str`
+    pub fn print_text(&self) -> Result<String, RuntimeError> {
+        match self {
+            Value::Int(i) => Ok(i.to_string()),
+            Value::Bool(b) => Ok(if *b { "True" } else { "False" }.to_owned()),
+            Value::Str(s) => Ok(String::from_utf8_lossy(s).into_owned()),
+            _ => Err(RuntimeError::InvalidArgument),
+        }
+    }
+
+    // The text of the value as the REPL shows it, like Python's `repr`
+    pub fn repr(&self) -> String {
+        let mut text = String::new();
+        self.write_repr(&mut text, &mut vec![]);
+        text
+    }
+
+    // `lists` holds the lists being written, which are shown as `[...]` if they
+    // contain themselves
+    fn write_repr(&self, text: &mut String, lists: &mut Vec<*const RefCell<Vec<Value>>>) {
+        match self {
+            Value::None => text.push_str("None"),
+            Value::Int(i) => text.push_str(&i.to_string()),
+            Value::Bool(b) => text.push_str(if *b { "True" } else { "False" }),
+            Value::Str(s) => {
+                let s = String::from_utf8_lossy(s);
+                let quote = if s.contains('\'') && !s.contains('"') {
+                    '"'
+                } else {
+                    '\''
+                };
+                text.push(quote);
+                for c in s.chars() {
+                    match c {
+                        '\\' => text.push_str("\\\\"),
+                        '\n' => text.push_str("\\n"),
+                        '\t' => text.push_str("\\t"),
+                        '\r' => text.push_str("\\r"),
+                        c if c == quote => {
+                            text.push('\\');
+                            text.push(c);
+                        }
+                        c if c < ' ' || c == '\x7f' => {
+                            text.push_str(&format!("\\x{:02x}", c as u32));
+                        }
+                        c => text.push(c),
+                    }
+                }
+                text.push(quote);
+            }
+            Value::List(list) => {
+                if lists.contains(&Rc::as_ptr(list)) {
+                    text.push_str("[...]");
+                    return;
+                }
+                lists.push(Rc::as_ptr(list));
+                text.push('[');
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i != 0 {
+                        text.push_str(", ");
+                    }
+                    element.write_repr(text, lists);
+                }
+                text.push(']');
+                lists.pop();
+            }
+            Value::Set(set) => {
+                let elements = set.borrow().elements();
+                if elements.is_empty() {
+                    text.push_str("set()");
+                    return;
+                }
+                text.push('{');
+                for (i, element) in elements.iter().enumerate() {
+                    if i != 0 {
+                        text.push_str(", ");
+                    }
+                    element.write_repr(text, lists);
+                }
+                text.push('}');
+            }
+            Value::Object(o) => text.push_str(&format!("<{} object>", o.class.name)),
+            Value::Generator(_) => text.push_str("<generator object>"),
+        }
+    }
+}
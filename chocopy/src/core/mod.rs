@@ -1,3 +1,5 @@
 pub mod codegen;
 pub mod frontend;
+pub mod interpreter;
+pub mod repl;
 pub mod typecheck;
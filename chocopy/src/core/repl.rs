@@ -0,0 +1,258 @@
+// An interactive session, which type checks each input against the declarations
+// of the previous ones and runs it with the interpreter
+//
+// Inputs are parsed after as many empty lines as the session has read before,
+// so that diagnostics point into the transcript of the whole session.
+
+use crate::common::node::*;
+use crate::common::render;
+use crate::core::interpreter::{Console, Interpreter, Value};
+use crate::core::{frontend, typecheck};
+
+const FILE: &str = "<stdin>";
+
+pub struct Session {
+    // The declarations of the accepted inputs, type checked
+    declarations: Vec<Declaration>,
+    // Every input read so far, including rejected ones
+    transcript: String,
+    strict_none: bool,
+    interpreter: Interpreter,
+    // Show `>>>` and `...` prompts, for terminals
+    pub prompt: bool,
+    // Render diagnostics with ANSI colors
+    pub color: bool,
+}
+
+impl Session {
+    pub fn new(console: Box<dyn Console>, strict_none: bool) -> Session {
+        Session {
+            declarations: vec![],
+            transcript: String::new(),
+            strict_none,
+            interpreter: Interpreter::new(console),
+            prompt: false,
+            color: false,
+        }
+    }
+
+    // Read and run inputs until the end of the input or `:quit`
+    pub fn run(&mut self) {
+        while let Some(input) = self.read_input() {
+            if !self.input(&input) {
+                break;
+            }
+        }
+    }
+
+    // Read a line, or a compound statement up to an empty line if the line
+    // starts one
+    fn read_input(&mut self) -> Option<String> {
+        self.write_prompt(">>> ");
+        let mut input = self.interpreter.console().read_line()?;
+        if input.trim_end().ends_with(':') && !input.starts_with(':') {
+            loop {
+                self.write_prompt("... ");
+                match self.interpreter.console().read_line() {
+                    Some(line) if !line.trim().is_empty() => {
+                        input.push('\n');
+                        input.push_str(&line);
+                    }
+                    _ => break,
+                }
+            }
+        }
+        Some(input)
+    }
+
+    fn write_prompt(&mut self, prompt: &str) {
+        if self.prompt {
+            self.interpreter.console().write(prompt, false, true);
+        }
+    }
+
+    fn write(&mut self, text: &str, error: bool) {
+        self.interpreter.console().write(text, error, true);
+    }
+
+    // Handle an input or a command, and return whether to keep reading
+    pub fn input(&mut self, input: &str) -> bool {
+        if input.trim().is_empty() {
+            return true;
+        }
+        let (command, rest) = match input.strip_prefix(':') {
+            Some(command) => {
+                let (command, rest) = command.split_once([' ', '\n']).unwrap_or((command, ""));
+                (Some(command), rest)
+            }
+            None => (None, input),
+        };
+        match command {
+            None => self.execute(rest),
+            Some("type") => self.show_type(rest),
+            Some("ast") => self.show_ast(rest),
+            Some("quit") => return false,
+            Some(command) => {
+                let expected = "expected :type, :ast or :quit";
+                self.write(&format!("Unknown command :{}, {}\n", command, expected), true);
+            }
+        }
+        true
+    }
+
+    // Parse and type check an input with the accumulated declarations. Only
+    // the declarations and statements of the input are returned, and its
+    // diagnostics are reported.
+    fn check(&mut self, input: &str) -> Option<Program> {
+        let lines = self.transcript.lines().count();
+        self.transcript.push_str(input);
+        self.transcript.push('\n');
+
+        let mut ast = frontend::process_source(&("\n".repeat(lines) + input));
+        if ast.errors.errors.is_empty() {
+            let new = ast.declarations.len();
+            let mut declarations = self.declarations.clone();
+            declarations.append(&mut ast.declarations);
+            ast.declarations = declarations;
+            ast = typecheck::check(ast, self.strict_none);
+            ast.declarations.drain(..ast.declarations.len() - new);
+        }
+        let errors = &ast.errors.errors;
+        if errors.iter().any(|error| error.severity == Severity::Error) {
+            let text = render::render(FILE, &self.transcript, errors, self.color);
+            self.write(&text, true);
+            return None;
+        }
+        Some(ast)
+    }
+
+    // Run an input, echoing the value of a lone expression unless it is None
+    fn execute(&mut self, input: &str) {
+        let Some(ast) = self.check(input) else {
+            return;
+        };
+        self.interpreter.declare(&ast.declarations);
+        self.declarations.extend(ast.declarations);
+
+        let result = match &ast.statements[..] {
+            [Stmt::ExprStmt(s)] => self.interpreter.evaluate(&s.expr).map(|value| match value {
+                Value::None => (),
+                value => self.write(&(value.repr() + "\n"), false),
+            }),
+            statements => self.interpreter.execute(statements),
+        };
+        if let Err(error) = result {
+            self.write(&format!("{}\n", error), true);
+        }
+    }
+
+    // `:type expr` shows the inferred type of an expression without evaluating it
+    fn show_type(&mut self, input: &str) {
+        let Some(ast) = self.check(input) else {
+            return;
+        };
+        match (&ast.declarations[..], &ast.statements[..]) {
+            ([], [Stmt::ExprStmt(s)]) => self.write(&format!("{}\n", s.expr.get_type()), false),
+            _ => self.write(":type expects an expression\n", true),
+        }
+    }
+
+    // `:ast stmt` shows the typed AST of the input as JSON, like `--typed`,
+    // without running it
+    fn show_ast(&mut self, input: &str) {
+        if let Some(ast) = self.check(input) {
+            let json = serde_json::to_string_pretty(&ast).unwrap();
+            self.write(&(json + "\n"), false);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // A console writing stdout and stderr to one shared buffer, marking errors
+    struct TestConsole(Rc<RefCell<String>>);
+
+    impl Console for TestConsole {
+        fn write(&mut self, text: &str, error: bool, _flush: bool) {
+            let mut output = self.0.borrow_mut();
+            if error {
+                output.push_str("! ");
+            }
+            output.push_str(text);
+        }
+
+        fn read_line(&mut self) -> Option<String> {
+            None
+        }
+    }
+
+    // Run the inputs of a session and return its output
+    fn session(inputs: &[&str]) -> String {
+        let output = Rc::new(RefCell::new(String::new()));
+        let mut session = Session::new(Box::new(TestConsole(output.clone())), false);
+        for input in inputs {
+            assert!(session.input(input));
+        }
+        output.take()
+    }
+
+    #[test]
+    fn declarations_persist() {
+        let output = session(&[
+            "x: int = 1",
+            "def double(n: int) -> int:\n    return n * 2",
+            "class Counter(object):\n    n: int = 0\n    def inc(self: \"Counter\"):\n        \
+             self.n = self.n + 1",
+            "c: Counter = None",
+            "c = Counter()\nc.inc()\nc.inc()",
+            "double(x) + c.n",
+            "x = 20",
+            "print(double(x))",
+            "[x, x + 1]",
+            "\"a\" + \"'b\"",
+        ]);
+        assert_eq!(output, "4\n40\n[20, 21]\n\"a'b\"\n");
+    }
+
+    #[test]
+    fn commands() {
+        let output = session(&[":type [1, 2][0] > 1", ":type None", ":type pass"]);
+        assert_eq!(output, "bool\n<None>\n! :type expects an expression\n");
+
+        let output = session(&[":ast 1"]);
+        let ast: Program = serde_json::from_str(&output).unwrap();
+        let [Stmt::ExprStmt(s)] = &ast.statements[..] else {
+            panic!("{}", output)
+        };
+        assert_eq!(s.expr.get_type().to_string(), "int");
+    }
+
+    #[test]
+    fn errors() {
+        let output = session(&["y: int = True", "y", "1 // 0", "print(1)"]);
+        let errors: Vec<&str> = output.lines().filter(|line| line.starts_with("! ")).collect();
+        assert_eq!(errors.len(), 3, "{}", output);
+        assert!(errors[0].contains("error"), "{}", output);
+        // The rejected declaration is not kept, and the next input points past it
+        assert!(output.contains("<stdin>:2:1"), "{}", output);
+        assert!(output.ends_with("! Division by zero\n1\n"), "{}", output);
+    }
+
+    #[test]
+    fn generators() {
+        let output = session(&[
+            "def count(n: int) -> Iterator[int]:\n    i: int = 0\n    while i < n:\n        \
+             yield i\n        i = i + 1",
+            "g: Iterator[int] = None",
+            "g = count(2)",
+            "next(g)",
+            "next(g)",
+            "next(g)",
+        ]);
+        assert_eq!(output, "0\n1\n! Generator exhausted\n");
+    }
+}
@@ -1,11 +1,94 @@
 #![allow(clippy::ptr_arg)]
 use super::class_env::*;
 use super::error::*;
+use super::narrow::*;
+use super::suggest::suggest;
+use super::unbound::*;
+use super::warning::{for_each_subexpr, statement_exprs};
 use crate::common::local_env::*;
 use crate::common::node::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-type TypeLocalEnv = LocalEnv<FuncType, ValueType>;
+pub type TypeLocalEnv = LocalEnv<FuncType, ValueType>;
+
+// Hidden variable in the frame of a generator, holding the yielded type
+const YIELD_SLOT: &str = "$yield";
+
+// Hidden variable in the frame of a method, holding the type of the parent class
+const SUPER_SLOT: &str = "$super";
+
+// Marker in the global environment when `next` is not redefined by the program
+pub const BUILTIN_NEXT: &str = "$next";
+
+fn is_builtin_next(call: &CallExpr, o: &TypeLocalEnv) -> bool {
+    call.function.name == "next" && o.get(BUILTIN_NEXT).is_some() && !o.is_shadowed("next")
+}
+
+// `print` is reserved in the global scope, but may be redefined locally
+fn is_builtin_print(call: &CallExpr, o: &TypeLocalEnv) -> bool {
+    call.function.name == "print" && !o.is_shadowed("print")
+}
+
+// `super()` is not a reserved name, so it may be redefined by the program
+fn is_builtin_super(call: &CallExpr, o: &TypeLocalEnv) -> bool {
+    call.function.name == "super" && o.get("super").is_none()
+}
+
+// Built-in functions and classes, which run no code of the program when called
+const PURE_BUILTINS: [&str; 8] =
+    ["print", "input", "len", "isinstance", "int", "str", "bool", "object"];
+
+// Whether evaluating the expression itself may run code of the program, which
+// may assign global variables: a call of a function, method or constructor, or
+// a generator resumed by iterating over an iterator
+fn runs_code(e: &Expr, o: &TypeLocalEnv) -> bool {
+    match &e.content {
+        ExprContent::CallExpr(call) => !PURE_BUILTINS.contains(&call.function.name.as_str()),
+        ExprContent::MethodCallExpr(_) | ExprContent::ListComprehension(_) => true,
+        ExprContent::Variable(v) => {
+            matches!(o.get(&v.name), Some(EnvSlot::Var(t, _)) if iterator_element(t).is_some())
+        }
+        _ => false,
+    }
+}
+
+// Whether the statements, or their nested blocks, may run code of the program
+fn statements_run_code(statements: &[Stmt], o: &TypeLocalEnv) -> bool {
+    let mut exprs = vec![];
+    statement_exprs(statements, &mut exprs);
+    let mut found = false;
+    for expr in exprs {
+        for_each_subexpr(expr, &mut |e| found |= runs_code(e, o));
+    }
+    found
+}
+
+// The element type of `Iterator[T]`
+fn iterator_element(t: &ValueType) -> Option<&ValueType> {
+    match t {
+        ValueType::ClassValueType(c) if c.class_name == "Iterator" => c.type_args.first(),
+        _ => None,
+    }
+}
+
+// The element type of `set[T]`
+fn set_element(t: &ValueType) -> Option<&ValueType> {
+    match t {
+        ValueType::ClassValueType(c) if c.class_name == "set" => c.type_args.first(),
+        _ => None,
+    }
+}
+
+// The type of the elements produced by iterating over a `str`, list, set or iterator
+fn iteration_element(t: &ValueType) -> Option<&ValueType> {
+    if *t == *TYPE_STR {
+        Some(t)
+    } else if let ValueType::ListValueType(ListValueType { element_type }) = t {
+        Some(&**element_type)
+    } else {
+        iterator_element(t).or_else(|| set_element(t))
+    }
+}
 
 impl Expr {
     pub fn analyze(
@@ -22,18 +105,55 @@ impl Expr {
             ExprContent::Variable(s) => s.analyze(errors, o, m),
             ExprContent::IfExpr(s) => s.analyze(errors, o, m),
             ExprContent::IndexExpr(s) => s.analyze(errors, o, m),
+            ExprContent::ListComprehension(s) => s.analyze(errors, o, m),
             ExprContent::ListExpr(s) => s.analyze(errors, o, m),
             ExprContent::MemberExpr(s) => s.analyze(errors, o, m),
             ExprContent::MethodCallExpr(s) => s.analyze(errors, o, m),
             ExprContent::NoneLiteral(s) => s.analyze(errors, o, m),
+            ExprContent::SetExpr(s) => s.analyze(errors, o, m),
+            ExprContent::SliceExpr(s) => {
+                let t = s.analyze(errors, o, m);
+                if s.base().error_msg.is_none() {
+                    s.add_error(errors, error_slice());
+                }
+                t
+            }
             ExprContent::StringLiteral(s) => s.analyze(errors, o, m),
             ExprContent::UnaryExpr(s) => s.analyze(errors, o, m),
         };
+        if let ExprContent::CallExpr(_) | ExprContent::MethodCallExpr(_) = &self.content
+            && runs_code(self, o)
+        {
+            o.reset_globals();
+        }
         self.inferred_type = Some(inferred_type.clone());
         inferred_type
     }
 }
 
+impl Expr {
+    // Use a value of `Optional` type as the wrapped type. In strict mode, it must
+    // have been narrowed first.
+    fn unwrap_optional(
+        &mut self,
+        t: ValueType,
+        errors: &mut Vec<CompilerError>,
+        m: &ClassEnv,
+    ) -> ValueType {
+        if let ValueType::OptionalValueType(OptionalValueType { element_type }) = &t {
+            if m.strict_none() {
+                let msg = error_maybe_none(&t);
+                self.add_error(errors, msg);
+            } else {
+                self.inferred_type = Some((**element_type).clone());
+            }
+            (**element_type).clone()
+        } else {
+            t
+        }
+    }
+}
+
 impl Literal {
     pub fn analyze(
         &mut self,
@@ -60,10 +180,14 @@ impl Variable {
         o: &mut TypeLocalEnv,
         _m: &ClassEnv,
     ) -> ValueType {
+        o.resolve(&self.name, self.base.location);
         match o.get(&self.name) {
             None | Some(EnvSlot::Func(_)) => {
                 let msg = error_variable(&self.name);
                 self.add_error(errors, msg);
+                if let Some(name) = suggest(&self.name, o.variable_names()) {
+                    errors.push(CompilerError::note(self.base.location, note_did_you_mean(name)));
+                }
                 TYPE_OBJECT.clone()
             }
             Some(EnvSlot::Var(t, _)) => t.clone(),
@@ -81,23 +205,32 @@ impl AssignStmt {
     ) {
         let right: ValueType = self.value.analyze(errors, o, m);
 
+        // Assigned variables take their declared type, not a narrowed one
+        o.reset(self.targets.iter().filter_map(|target| match &target.content {
+            ExprContent::Variable(v) => Some(v.name.clone()),
+            _ => None,
+        }));
+
         // We don't do `for target in &mut self.targets` because of mut ref conflict
         for i in 0..self.targets.len() {
             let left: ValueType = self.targets[i].analyze(errors, o, m);
             match &self.targets[i].content {
                 ExprContent::Variable(Variable { name, .. }) => {
                     if let Some(EnvSlot::Var(_, Assignable(false))) = o.get(name) {
-                        let msg = error_nonlocal_assign(name);
+                        let msg = if o.is_constant(name) {
+                            error_final_assign(name)
+                        } else {
+                            error_nonlocal_assign(name)
+                        };
                         self.targets[i].add_error(errors, msg);
                     }
                 }
-                ExprContent::IndexExpr(index_expr) => {
+                ExprContent::IndexExpr(index_expr)
                     if index_expr.list.get_type() == &*TYPE_STR
-                        && self.targets[i].base().error_msg.is_none()
-                    {
-                        let msg = error_str_index_assign();
-                        self.targets[i].add_error(errors, msg);
-                    }
+                        && self.targets[i].base().error_msg.is_none() =>
+                {
+                    let msg = error_str_index_assign();
+                    self.targets[i].add_error(errors, msg);
                 }
                 _ => (),
             }
@@ -205,10 +338,44 @@ impl UnaryExpr {
                 }
                 TYPE_BOOL.clone()
             }
+            UnaryOp::Invert => {
+                if operand != *TYPE_INT {
+                    let msg = error_unary("~", &operand);
+                    self.add_error(errors, msg);
+                }
+                TYPE_INT.clone()
+            }
         }
     }
 }
 
+// Types whose values `==` and `!=` compare by content
+fn is_equatable(t: &ValueType) -> bool {
+    match t {
+        ValueType::ListValueType(l) => is_equatable(&l.element_type),
+        t => *t == *TYPE_INT || *t == *TYPE_BOOL || *t == *TYPE_STR || *t == *TYPE_EMPTY,
+    }
+}
+
+// Types whose values `<`, `<=`, `>` and `>=` order, lists lexicographically
+fn is_ordered(t: &ValueType) -> bool {
+    match t {
+        ValueType::ListValueType(l) => is_ordered(&l.element_type),
+        t => *t == *TYPE_INT || *t == *TYPE_STR || *t == *TYPE_EMPTY,
+    }
+}
+
+// Comparison operands must have the same type, except that `[]` matches any list
+fn same_comparison_type(left: &ValueType, right: &ValueType) -> bool {
+    let is_list = |t: &ValueType| matches!(t, ValueType::ListValueType(_)) || *t == *TYPE_EMPTY;
+    match (left, right) {
+        (ValueType::ListValueType(l), ValueType::ListValueType(r)) => {
+            same_comparison_type(&l.element_type, &r.element_type)
+        }
+        _ => left == right || (is_list(left) && is_list(right)),
+    }
+}
+
 impl BinaryExpr {
     pub fn analyze(
         &mut self,
@@ -217,11 +384,36 @@ impl BinaryExpr {
         m: &ClassEnv,
     ) -> ValueType {
         let left: ValueType = self.left.analyze(errors, o, m);
-        let right: ValueType = self.right.analyze(errors, o, m);
+        let right: ValueType = match self.operator {
+            // The right operand is only evaluated if the left one didn't short-circuit
+            BinaryOp::And | BinaryOp::Or => {
+                let positive = self.operator == BinaryOp::And;
+                let facts = narrow(&self.left, positive, o, m);
+                let mut handle = o.refine(facts);
+                self.right.analyze(errors, handle.inner(), m)
+            }
+            _ => self.right.analyze(errors, o, m),
+        };
 
         let mut error = false;
         let output = match self.operator {
-            BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => {
+            // Union and intersection of sets
+            BinaryOp::BitOr | BinaryOp::BitAnd if set_element(&left).is_some() => {
+                if left != right {
+                    error = true;
+                }
+                left.clone()
+            }
+            BinaryOp::Sub
+            | BinaryOp::Mul
+            | BinaryOp::Div
+            | BinaryOp::Mod
+            | BinaryOp::Pow
+            | BinaryOp::BitAnd
+            | BinaryOp::BitOr
+            | BinaryOp::BitXor
+            | BinaryOp::Shl
+            | BinaryOp::Shr => {
                 if left != *TYPE_INT || right != *TYPE_INT {
                     error = true;
                 }
@@ -234,7 +426,7 @@ impl BinaryExpr {
                 TYPE_BOOL.clone()
             }
             BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge => {
-                if left != *TYPE_INT || right != *TYPE_INT {
+                if !is_ordered(&left) || !same_comparison_type(&left, &right) {
                     error = true;
                 }
                 TYPE_BOOL.clone()
@@ -276,8 +468,14 @@ impl BinaryExpr {
                     TYPE_OBJECT.clone()
                 }
             }
+            BinaryOp::In => {
+                if !set_element(&right).is_some_and(|element| *element == left) {
+                    error = true;
+                }
+                TYPE_BOOL.clone()
+            }
             BinaryOp::Eq | BinaryOp::Ne => {
-                if (left != *TYPE_INT && left != *TYPE_STR && left != *TYPE_BOOL) || left != right {
+                if !is_equatable(&left) || !same_comparison_type(&left, &right) {
                     error = true
                 }
                 TYPE_BOOL.clone()
@@ -293,6 +491,12 @@ impl BinaryExpr {
                 BinaryOp::Mul => "*",
                 BinaryOp::Div => "//",
                 BinaryOp::Mod => "%",
+                BinaryOp::Pow => "**",
+                BinaryOp::BitAnd => "&",
+                BinaryOp::BitOr => "|",
+                BinaryOp::BitXor => "^",
+                BinaryOp::Shl => "<<",
+                BinaryOp::Shr => ">>",
                 BinaryOp::Eq => "==",
                 BinaryOp::Ne => "!=",
                 BinaryOp::Lt => "<",
@@ -300,6 +504,7 @@ impl BinaryExpr {
                 BinaryOp::Le => "<=",
                 BinaryOp::Ge => ">=",
                 BinaryOp::Is => "is",
+                BinaryOp::In => "in",
             };
             let msg = error_binary(op_name, &left, &right);
             self.add_error(errors, msg);
@@ -321,8 +526,16 @@ impl IfExpr {
             let msg = error_condition(&condition);
             self.add_error(errors, msg);
         }
-        let then_type = self.then_expr.analyze(errors, o, m);
-        let else_type = self.else_expr.analyze(errors, o, m);
+        let then_type = {
+            let facts = narrow(&self.condition, true, o, m);
+            let mut handle = o.refine(facts);
+            self.then_expr.analyze(errors, handle.inner(), m)
+        };
+        let else_type = {
+            let facts = narrow(&self.condition, false, o, m);
+            let mut handle = o.refine(facts);
+            self.else_expr.analyze(errors, handle.inner(), m)
+        };
         m.join(&then_type, &else_type)
     }
 }
@@ -347,6 +560,102 @@ impl ListExpr {
     }
 }
 
+impl SetExpr {
+    pub fn analyze(
+        &mut self,
+        errors: &mut Vec<CompilerError>,
+        o: &mut TypeLocalEnv,
+        m: &ClassEnv,
+    ) -> ValueType {
+        let element_type = self.elements[0].analyze(errors, o, m);
+        let mut error = false;
+        if !is_hashable(&element_type) {
+            let msg = error_set_element(&element_type);
+            self.elements[0].add_error(errors, msg);
+            error = true;
+        }
+        for element in self.elements.iter_mut().skip(1) {
+            let t = element.analyze(errors, o, m);
+            if !error && t != element_type {
+                let msg = error_set_mixed(&element_type, &t);
+                element.add_error(errors, msg);
+                error = true;
+            }
+        }
+
+        if error {
+            return TYPE_OBJECT.clone();
+        }
+        ValueType::ClassValueType(ClassValueType {
+            class_name: "set".to_owned(),
+            type_args: vec![element_type],
+        })
+    }
+}
+
+impl ListComprehension {
+    pub fn analyze(
+        &mut self,
+        errors: &mut Vec<CompilerError>,
+        o: &mut TypeLocalEnv,
+        m: &ClassEnv,
+    ) -> ValueType {
+        let element_type =
+            analyze_comprehension(&mut self.clauses, &mut self.element, errors, o, m);
+        let element_type = Box::new(element_type);
+        ValueType::ListValueType(ListValueType { element_type })
+    }
+}
+
+// Analyze the clauses of a comprehension in turn, each one binding its variable
+// for the following ones and the element. Returns the type of the element.
+fn analyze_comprehension(
+    clauses: &mut [ComprehensionClause],
+    element: &mut Expr,
+    errors: &mut Vec<CompilerError>,
+    o: &mut TypeLocalEnv,
+    m: &ClassEnv,
+) -> ValueType {
+    let (clause, rest) = if let Some(clauses) = clauses.split_first_mut() {
+        clauses
+    } else {
+        return element.analyze(errors, o, m);
+    };
+
+    let iterable = clause.iterable.analyze(errors, o, m);
+    let iterable = clause.iterable.unwrap_optional(iterable, errors, m);
+    if iterator_element(&iterable).is_some() {
+        o.reset_globals();
+    }
+    let variable_type = if let Some(element_type) = iteration_element(&iterable) {
+        element_type.clone()
+    } else {
+        let msg = error_iterable(&iterable);
+        clause.add_error(errors, msg);
+        TYPE_OBJECT.clone()
+    };
+    clause.identifier.inferred_type = Some(variable_type.clone());
+
+    let location = clause.identifier.base.location;
+    let mut scope = o.bind(clause.identifier.name.clone(), variable_type, location);
+    let o = scope.inner();
+
+    // Each condition narrows the ones after it, the following clauses and the element
+    let mut facts = vec![];
+    for condition in &mut clause.conditions {
+        let mut handle = o.refine(facts.clone());
+        let condition_type = condition.analyze(errors, handle.inner(), m);
+        if condition_type != *TYPE_BOOL {
+            let msg = error_condition(&condition_type);
+            condition.add_error(errors, msg);
+        }
+        facts.extend(narrow(condition, true, handle.inner(), m));
+    }
+
+    let mut handle = o.refine(facts);
+    analyze_comprehension(rest, element, errors, handle.inner(), m)
+}
+
 impl IndexExpr {
     pub fn analyze(
         &mut self,
@@ -355,6 +664,7 @@ impl IndexExpr {
         m: &ClassEnv,
     ) -> ValueType {
         let left = self.list.analyze(errors, o, m);
+        let left = self.list.unwrap_optional(left, errors, m);
         let element_type = if let ValueType::ListValueType(ListValueType { element_type }) = left {
             *element_type
         } else if left == *TYPE_STR {
@@ -375,6 +685,37 @@ impl IndexExpr {
     }
 }
 
+impl SliceExpr {
+    pub fn analyze(
+        &mut self,
+        errors: &mut Vec<CompilerError>,
+        o: &mut TypeLocalEnv,
+        m: &ClassEnv,
+    ) -> ValueType {
+        let left = self.list.analyze(errors, o, m);
+        let left = self.list.unwrap_optional(left, errors, m);
+        if left == *TYPE_STR {
+            let msg = error_str_index_assign();
+            self.add_error(errors, msg);
+        } else if !matches!(left, ValueType::ListValueType(_)) {
+            let msg = error_index_left(&left);
+            self.add_error(errors, msg);
+        }
+
+        let bounds = [&mut self.start, &mut self.end].map(|bound| {
+            bound.as_mut().map(|bound| bound.analyze(errors, o, m))
+        });
+        for index in bounds.into_iter().flatten() {
+            if index != *TYPE_INT && self.base.error_msg.is_none() {
+                let msg = error_index_right(&index);
+                self.add_error(errors, msg);
+            }
+        }
+
+        left
+    }
+}
+
 impl MemberExpr {
     pub fn analyze(
         &mut self,
@@ -383,8 +724,9 @@ impl MemberExpr {
         m: &ClassEnv,
     ) -> ValueType {
         let class = self.object.analyze(errors, o, m);
-        let class_name = if let ValueType::ClassValueType(ClassValueType { class_name }) = class {
-            class_name
+        let class = self.object.unwrap_optional(class, errors, m);
+        let class = if let ValueType::ClassValueType(class) = class {
+            class
         } else {
             let msg = error_member(&class);
             self.add_error(errors, msg);
@@ -392,11 +734,16 @@ impl MemberExpr {
         };
 
         let name = &self.member.name;
-        if let Some(member) = m.get_attribute(&class_name, name) {
-            member.clone()
+        let declaration = m.item_location(&class.class_name, name);
+        o.resolve_to(name, self.member.base.location, declaration);
+        if let Some(member) = m.get_attribute(&class.class_name, name) {
+            m.instantiate(member, &class)
         } else {
-            let msg = error_attribute(name, &class_name);
+            let msg = error_attribute(name, &class.class_name);
             self.add_error(errors, msg);
+            if let Some(name) = suggest(&self.member.name, m.attribute_names(&class.class_name)) {
+                errors.push(CompilerError::note(self.base.location, note_did_you_mean(name)));
+            }
             TYPE_OBJECT.clone()
         }
     }
@@ -409,6 +756,32 @@ impl CallExpr {
         o: &mut TypeLocalEnv,
         m: &ClassEnv,
     ) -> ValueType {
+        o.resolve(&self.function.name, self.function.base.location);
+        if is_builtin_print(self, o) {
+            return self.analyze_print(errors, o, m);
+        }
+        if !self.keywords.is_empty() {
+            for keyword in &mut self.keywords {
+                keyword.value.analyze(errors, o, m);
+            }
+            let msg = error_keyword_unsupported(&self.function.name);
+            self.keywords[0].add_error(errors, msg);
+        }
+        if is_builtin_isinstance(self, o) && self.args.len() == 2 {
+            return self.analyze_isinstance(errors, o, m);
+        }
+        if is_builtin_next(self, o) && self.args.len() == 1 {
+            return self.analyze_next(errors, o, m);
+        }
+        if is_builtin_super(self, o) {
+            for arg in &mut self.args {
+                arg.analyze(errors, o, m);
+            }
+            let msg = error_super();
+            self.add_error(errors, msg);
+            return TYPE_OBJECT.clone();
+        }
+
         let args: Vec<_> = self
             .args
             .iter_mut()
@@ -420,14 +793,41 @@ impl CallExpr {
         } else {
             let msg = error_function(&self.function.name);
             self.add_error(errors, msg);
+            if let Some(name) = suggest(&self.function.name, o.function_names()) {
+                errors.push(CompilerError::note(self.base.location, note_did_you_mean(name)));
+            }
             return TYPE_OBJECT.clone();
         };
 
         // Reference program: don't attach type to constructor
-        if !m.contains(&self.function.name) {
+        let is_constructor = m.contains(&self.function.name);
+        if !is_constructor {
             self.function.inferred_type = Some(function.clone());
         }
 
+        let mut return_type = function.return_type.clone();
+        let type_params = if is_constructor {
+            m.type_params(&self.function.name)
+        } else {
+            &[]
+        };
+        if self.type_args.len() != type_params.len() {
+            let msg = error_type_args(&self.function.name, type_params.len(), self.type_args.len());
+            self.add_error(errors, msg);
+        } else if !type_params.is_empty() {
+            for arg in &mut self.type_args {
+                m.check_annotation(arg, o.type_params(), errors);
+            }
+            m.check_plain_type_args(&self.function.name, &mut self.type_args, errors);
+            if self.function.name == "set" && self.type_args[0].base().error_msg.is_none() {
+                check_set_element(&mut self.type_args[0], errors);
+            }
+            return_type = ValueType::ClassValueType(ClassValueType {
+                class_name: self.function.name.clone(),
+                type_args: self.type_args.iter().map(ValueType::from_annotation).collect(),
+            });
+        }
+
         if function.parameters.len() != args.len() {
             let msg = error_call_count(function.parameters.len(), args.len());
             self.add_error(errors, msg);
@@ -441,7 +841,131 @@ impl CallExpr {
             }
         }
 
-        function.return_type.clone()
+        return_type
+    }
+
+    // `print(*objects, sep=str, end=str, file=stdout|stderr, flush=bool)`.
+    // The stream is a name rather than an expression, and isn't analyzed.
+    fn analyze_print(
+        &mut self,
+        errors: &mut Vec<CompilerError>,
+        o: &mut TypeLocalEnv,
+        m: &ClassEnv,
+    ) -> ValueType {
+        for arg in &mut self.args {
+            arg.analyze(errors, o, m);
+        }
+        if let Some(EnvSlot::Func(f)) = o.get(&self.function.name) {
+            self.function.inferred_type = Some(f.clone());
+        }
+
+        let mut seen = HashSet::new();
+        for keyword in &mut self.keywords {
+            let name = keyword.name.name.clone();
+            let expected = match name.as_str() {
+                "sep" | "end" => Some(&*TYPE_STR),
+                "flush" => Some(&*TYPE_BOOL),
+                _ => None,
+            };
+            if name == "file" {
+                let is_stream = matches!(
+                    &keyword.value.content,
+                    ExprContent::Variable(v) if v.name == "stdout" || v.name == "stderr"
+                );
+                if !is_stream {
+                    let msg = error_print_file();
+                    keyword.value.add_error(errors, msg);
+                }
+            } else {
+                let t = keyword.value.analyze(errors, o, m);
+                if let Some(expected) = expected {
+                    if !m.is_compatible(&t, expected) {
+                        let msg = error_keyword_type(&name, expected, &t);
+                        keyword.add_error(errors, msg);
+                    }
+                } else {
+                    let msg = error_keyword_unknown(&self.function.name, &name);
+                    keyword.add_error(errors, msg);
+                }
+            }
+            if !seen.insert(name) {
+                let msg = error_keyword_repeated(&keyword.name.name);
+                keyword.add_error(errors, msg);
+            }
+        }
+
+        TYPE_NONE.clone()
+    }
+
+    // `isinstance(x, C)`: the class name is not an expression and isn't analyzed
+    fn analyze_isinstance(
+        &mut self,
+        errors: &mut Vec<CompilerError>,
+        o: &mut TypeLocalEnv,
+        m: &ClassEnv,
+    ) -> ValueType {
+        self.args[0].analyze(errors, o, m);
+        if let Some(EnvSlot::Func(f)) = o.get(&self.function.name) {
+            self.function.inferred_type = Some(f.clone());
+        }
+
+        if let ExprContent::Variable(c) = &self.args[1].content {
+            o.resolve(&c.name, c.base.location);
+        }
+        let is_class = matches!(
+            &self.args[1].content,
+            ExprContent::Variable(c) if m.contains(&c.name)
+        );
+        if !is_class {
+            let msg = error_isinstance_class();
+            self.args[1].add_error(errors, msg);
+        }
+
+        TYPE_BOOL.clone()
+    }
+
+    // `next(it)`: the result has the element type of the iterator
+    fn analyze_next(
+        &mut self,
+        errors: &mut Vec<CompilerError>,
+        o: &mut TypeLocalEnv,
+        m: &ClassEnv,
+    ) -> ValueType {
+        let iterator = self.args[0].analyze(errors, o, m);
+        let iterator = self.args[0].unwrap_optional(iterator, errors, m);
+        if let Some(EnvSlot::Func(f)) = o.get(&self.function.name) {
+            self.function.inferred_type = Some(f.clone());
+        }
+
+        if let Some(element_type) = iterator_element(&iterator) {
+            element_type.clone()
+        } else {
+            let msg = error_next_iterator(&iterator);
+            self.add_error(errors, msg);
+            TYPE_OBJECT.clone()
+        }
+    }
+}
+
+impl CallExpr {
+    // `super()` as the object of a method call: the method is looked up in the
+    // parent class of the enclosing method, and called on `self`
+    fn analyze_super(
+        &mut self,
+        errors: &mut Vec<CompilerError>,
+        o: &TypeLocalEnv,
+    ) -> Option<ValueType> {
+        if let Some(parent) = o.get_local(SUPER_SLOT) {
+            self.function.inferred_type = Some(FuncType {
+                parameters: vec![],
+                return_type: parent.clone(),
+            });
+            Some(parent.clone())
+        } else {
+            let msg = error_super();
+            self.add_error(errors, msg);
+            None
+        }
     }
 }
 
@@ -459,9 +983,23 @@ impl MethodCallExpr {
             .collect();
 
         let member = &mut self.method;
-        let class = member.object.analyze(errors, o, m);
-        let class_name = if let ValueType::ClassValueType(ClassValueType { class_name }) = class {
-            class_name
+        let class = match &mut member.object.content {
+            ExprContent::CallExpr(call) if is_builtin_super(call, o) && call.args.is_empty() => {
+                let class = if let Some(class) = call.analyze_super(errors, o) {
+                    class
+                } else {
+                    return TYPE_OBJECT.clone();
+                };
+                member.object.inferred_type = Some(class.clone());
+                class
+            }
+            _ => {
+                let class = member.object.analyze(errors, o, m);
+                member.object.unwrap_optional(class, errors, m)
+            }
+        };
+        let class = if let ValueType::ClassValueType(class) = class {
+            class
         } else {
             let msg = error_member(&class);
             self.add_error(errors, msg);
@@ -469,16 +1007,25 @@ impl MethodCallExpr {
         };
 
         let method_name = &member.member.name;
+        let declaration = m.item_location(&class.class_name, method_name);
+        o.resolve_to(method_name, member.member.base.location, declaration);
 
-        let method = if let Some(method) = m.get_method(&class_name, method_name) {
+        let method = if let Some(method) = m.get_method(&class.class_name, method_name) {
             method
         } else {
-            let msg = error_method(method_name, &class_name);
+            let msg = error_method(method_name, &class.class_name);
             self.add_error(errors, msg);
+            let method_name = &self.method.member.name;
+            if let Some(name) = suggest(method_name, m.method_names(&class.class_name)) {
+                errors.push(CompilerError::note(self.base.location, note_did_you_mean(name)));
+            }
             return TYPE_OBJECT.clone();
         };
 
+        // The declared signature is attached, as code generation works on the
+        // type parameters of generic classes
         member.inferred_type = Some(method.clone());
+        let method = m.instantiate_method(method, &class);
 
         if method.parameters.len() - 1 != args.len() {
             let msg = error_call_count(method.parameters.len() - 1, args.len());
@@ -507,6 +1054,16 @@ impl ReturnStmt {
     ) {
         // Reference program: do not analyze the expression on top-level return
         if let Some(return_expected) = r {
+            // A generator can only stop, its declared type is that of the generator object
+            if o.get(YIELD_SLOT).is_some() {
+                if let Some(value) = &mut self.value {
+                    value.analyze(errors, o, m);
+                    let msg = error_generator_return_value();
+                    self.add_error(errors, msg);
+                }
+                return;
+            }
+
             let return_type = if let Some(value) = &mut self.value {
                 value.analyze(errors, o, m)
             } else {
@@ -542,8 +1099,8 @@ impl IfStmt {
             self.add_error(errors, msg);
         }
 
-        analyze_stmt(&mut self.then_body, errors, o, m, r);
-        analyze_stmt(&mut self.else_body, errors, o, m, r);
+        analyze_branch(&self.condition, true, &mut self.then_body, errors, o, m, r);
+        analyze_branch(&self.condition, false, &mut self.else_body, errors, o, m, r);
     }
 }
 
@@ -555,13 +1112,24 @@ impl WhileStmt {
         m: &ClassEnv,
         r: Option<&ValueType>,
     ) {
+        // Narrowing from outside doesn't hold for later iterations
+        let mut assigned = HashSet::new();
+        assigned_names(&self.body, &mut assigned);
+        o.reset(assigned);
+        if statements_run_code(&self.body, o) {
+            o.reset_globals();
+        }
+
         let condition = self.condition.analyze(errors, o, m);
         if condition != *TYPE_BOOL {
             let msg = error_condition(&condition);
             self.add_error(errors, msg);
         }
 
-        analyze_stmt(&mut self.body, errors, o, m, r);
+        analyze_branch(&self.condition, true, &mut self.body, errors, o, m, r);
+        if let Some(else_body) = &mut self.else_body {
+            analyze_branch(&self.condition, false, else_body, errors, o, m, r);
+        }
     }
 }
 
@@ -575,16 +1143,27 @@ impl ForStmt {
     ) {
         // Eh, the error handling is a mess in the reference program
 
+        // Narrowing from outside doesn't hold for later iterations
+        let mut assigned = HashSet::new();
+        assigned_names(&self.body, &mut assigned);
+        assigned.insert(self.identifier.name.clone());
+        o.reset(assigned);
+        if statements_run_code(&self.body, o) {
+            o.reset_globals();
+        }
+
         let iterable = self.iterable.analyze(errors, o, m);
-        let element_type = if iterable == *TYPE_STR {
-            Some(&iterable)
-        } else if let ValueType::ListValueType(ListValueType { element_type }) = &iterable {
-            Some(&**element_type)
-        } else {
+        let iterable = self.iterable.unwrap_optional(iterable, errors, m);
+        // Each iteration resumes a generator
+        if iterator_element(&iterable).is_some() {
+            o.reset_globals();
+        }
+        o.resolve(&self.identifier.name, self.identifier.base.location);
+        let element_type = iteration_element(&iterable);
+        if element_type.is_none() {
             let msg = error_iterable(&iterable);
             self.add_error(errors, msg);
-            None
-        };
+        }
 
         if let Some(element_type) = element_type {
             let variable = match o.get(&self.identifier.name) {
@@ -596,7 +1175,12 @@ impl ForStmt {
                 if m.is_compatible(element_type, &variable) {
                     self.identifier.inferred_type = Some(variable); // yes, we attach the type here
                     if !assignable {
-                        let msg = error_nonlocal_assign(&self.identifier.name);
+                        let name = &self.identifier.name;
+                        let msg = if o.is_constant(name) {
+                            error_final_assign(name)
+                        } else {
+                            error_nonlocal_assign(name)
+                        };
                         // and this error is attached to the identifier
                         self.identifier.add_error(errors, msg);
                     }
@@ -607,10 +1191,62 @@ impl ForStmt {
             } else {
                 let msg = error_variable(&self.identifier.name);
                 self.add_error(errors, msg);
+                if let Some(name) = suggest(&self.identifier.name, o.variable_names()) {
+                    let location = self.identifier.base.location;
+                    errors.push(CompilerError::note(location, note_did_you_mean(name)));
+                }
             }
         }
 
         analyze_stmt(&mut self.body, errors, o, m, r);
+        if let Some(else_body) = &mut self.else_body {
+            analyze_stmt(else_body, errors, o, m, r);
+        }
+    }
+}
+
+impl YieldStmt {
+    pub fn analyze(&mut self, errors: &mut Vec<CompilerError>, o: &mut TypeLocalEnv, m: &ClassEnv) {
+        let yield_expected = match o.get(YIELD_SLOT) {
+            Some(EnvSlot::Var(t, _)) => t.clone(),
+            _ => {
+                let msg = error_top_yield();
+                self.add_error(errors, msg);
+                return;
+            }
+        };
+
+        let yield_type = if let Some(value) = &mut self.value {
+            value.analyze(errors, o, m)
+        } else {
+            TYPE_NONE.clone()
+        };
+        if !m.is_compatible(&yield_type, &yield_expected) {
+            let msg = error_assign(&yield_expected, &yield_type);
+            self.add_error(errors, msg);
+        }
+    }
+}
+
+impl DelStmt {
+    pub fn analyze(&mut self, errors: &mut Vec<CompilerError>, o: &mut TypeLocalEnv, m: &ClassEnv) {
+        // Whether variables may be deleted is checked by `check_unbound`
+        for target in &mut self.targets {
+            if let ExprContent::SliceExpr(s) = &mut target.content {
+                let t = s.analyze(errors, o, m);
+                target.inferred_type = Some(t);
+                continue;
+            }
+
+            target.analyze(errors, o, m);
+            if let ExprContent::IndexExpr(index_expr) = &target.content
+                && index_expr.list.get_type() == &*TYPE_STR
+                && target.base().error_msg.is_none()
+            {
+                let msg = error_str_index_assign();
+                target.add_error(errors, msg);
+            }
+        }
     }
 }
 
@@ -629,10 +1265,34 @@ fn analyze_stmt(
             Stmt::ForStmt(s) => s.analyze(errors, o, m, r),
             Stmt::WhileStmt(s) => s.analyze(errors, o, m, r),
             Stmt::ReturnStmt(s) => s.analyze(errors, o, m, r),
+            Stmt::YieldStmt(s) => s.analyze(errors, o, m),
+            Stmt::DelStmt(s) => s.analyze(errors, o, m),
+            Stmt::BreakStmt(_) => (),
         }
+
+        // Narrowed types don't survive reassignment
+        let mut assigned = HashSet::new();
+        assigned_names(std::slice::from_ref(statement), &mut assigned);
+        o.reset(assigned);
     }
 }
 
+// Analyze statements guarded by `condition` evaluating to `positive`,
+// with local variable types narrowed accordingly
+fn analyze_branch(
+    condition: &Expr,
+    positive: bool,
+    statements: &mut [Stmt],
+    errors: &mut Vec<CompilerError>,
+    o: &mut TypeLocalEnv,
+    m: &ClassEnv,
+    r: Option<&ValueType>,
+) {
+    let facts = narrow(condition, positive, o, m);
+    let mut handle = o.refine(facts);
+    analyze_stmt(statements, errors, handle.inner(), m, r);
+}
+
 fn analyze_decl(
     declarations: &mut [Declaration],
     errors: &mut Vec<CompilerError>,
@@ -651,6 +1311,17 @@ fn analyze_decl(
 
 impl FuncDef {
     pub fn analyze(&mut self, errors: &mut Vec<CompilerError>, o: &mut TypeLocalEnv, m: &ClassEnv) {
+        self.analyze_in(errors, o, m, None);
+    }
+
+    // Analyze a function, or a method of a class derived from `super_class`
+    fn analyze_in(
+        &mut self,
+        errors: &mut Vec<CompilerError>,
+        o: &mut TypeLocalEnv,
+        m: &ClassEnv,
+        super_class: Option<&ValueType>,
+    ) {
         let frame: HashMap<String, LocalSlot<FuncType, ValueType>> = self
             .declarations
             .iter()
@@ -680,20 +1351,61 @@ impl FuncDef {
                     LocalSlot::Var(ValueType::from_annotation(&param.type_)),
                 )
             }))
+            .chain(self.is_generator().then(|| {
+                let return_type = ValueType::from_annotation(&self.return_type);
+                let yield_type = iterator_element(&return_type).unwrap_or(&TYPE_OBJECT);
+                (YIELD_SLOT.to_owned(), LocalSlot::Var(yield_type.clone()))
+            }))
+            .chain(super_class.map(|t| (SUPER_SLOT.to_owned(), LocalSlot::Var(t.clone()))))
             .collect();
 
+        let mut captured = HashSet::new();
+        captured_names(&self.declarations, &mut captured);
+
         let mut handle = o.push(frame);
+        for declaration in &self.declarations {
+            let name = declaration.name();
+            handle.inner().declare(name.name.clone(), name.base.location);
+        }
+        for param in &self.params {
+            let name = &param.identifier;
+            handle.inner().declare(name.name.clone(), name.base.location);
+        }
+        handle.inner().pin(captured);
         analyze_decl(&mut self.declarations, errors, handle.inner(), m);
 
         let return_type = ValueType::from_annotation(&self.return_type);
         let r = Some(&return_type);
         analyze_stmt(&mut self.statements, errors, handle.inner(), m, r);
+
+        let locals: HashSet<String> = self
+            .params
+            .iter()
+            .map(|param| param.identifier.name.clone())
+            .chain(self.declarations.iter().filter_map(|decl| match decl {
+                Declaration::VarDef(v) => Some(v.var.identifier.name.clone()),
+                _ => None,
+            }))
+            .collect();
+        check_unbound(&locals, &mut self.declarations, &mut self.statements, errors);
     }
 }
 
 impl ClassDef {
     pub fn analyze(&mut self, errors: &mut Vec<CompilerError>, o: &mut TypeLocalEnv, m: &ClassEnv) {
-        analyze_decl(&mut self.declarations, errors, o, m);
+        let super_class = ValueType::ClassValueType(ClassValueType {
+            class_name: self.super_class.name.clone(),
+            type_args: vec![],
+        });
+        o.set_type_params(m.type_params(&self.name.name));
+        for declaration in &mut self.declarations {
+            match declaration {
+                Declaration::FuncDef(s) => s.analyze_in(errors, o, m, Some(&super_class)),
+                Declaration::VarDef(s) => s.analyze(errors, o, m),
+                _ => (),
+            }
+        }
+        o.set_type_params(&[]);
     }
 }
 
@@ -701,5 +1413,6 @@ impl Program {
     pub fn analyze(&mut self, errors: &mut Vec<CompilerError>, o: &mut TypeLocalEnv, m: &ClassEnv) {
         analyze_decl(&mut self.declarations, errors, o, m);
         analyze_stmt(&mut self.statements, errors, o, m, None);
+        check_unbound(&HashSet::new(), &mut self.declarations, &mut self.statements, errors);
     }
 }
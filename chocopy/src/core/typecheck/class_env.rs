@@ -1,4 +1,6 @@
 use super::error::*;
+use super::suggest::suggest;
+use crate::common::location::Location;
 use crate::common::node::*;
 use std::collections::HashMap;
 use std::collections::HashSet;
@@ -11,39 +13,178 @@ enum Type {
 
 struct ClassInfo {
     super_class: String,
+    type_params: Vec<String>,
     items: HashMap<String, Type>,
+    // Where the items of user-defined classes are declared
+    locations: HashMap<String, Location>,
 }
 
-pub struct ClassEnv(HashMap<String, ClassInfo>);
+pub struct ClassEnv {
+    classes: HashMap<String, ClassInfo>,
+    // Type parameters of generic classes. They are stored in `classes` as
+    // subclasses of object without any items, and annotations only accept them
+    // in the scope of their class.
+    type_vars: HashSet<String>,
+    // In strict mode, only `Optional` types admit None
+    strict_none: bool,
+}
+
+// Inside a generic class, the bare class name stands for the class instantiated
+// with its own type parameters, e.g. `self: "Box"` means `Box[T]`
+fn fill_type_args(t: &mut TypeAnnotation, class_name: &str, type_params: &[String]) {
+    match t {
+        TypeAnnotation::ClassType(c) => {
+            if c.class_name == class_name && c.type_args.is_empty() {
+                c.type_args = type_params
+                    .iter()
+                    .map(|param| {
+                        TypeAnnotation::ClassType(ClassType {
+                            base: c.base.clone(),
+                            class_name: param.clone(),
+                            type_args: vec![],
+                        })
+                    })
+                    .collect();
+            } else {
+                for arg in &mut c.type_args {
+                    fill_type_args(arg, class_name, type_params);
+                }
+            }
+        }
+        TypeAnnotation::ListType(l) => fill_type_args(&mut l.element_type, class_name, type_params),
+        TypeAnnotation::OptionalType(o) => {
+            fill_type_args(&mut o.element_type, class_name, type_params)
+        }
+    }
+}
+
+fn fill_type_args_decl(decls: &mut [Declaration], class_name: &str, type_params: &[String]) {
+    for decl in decls {
+        match decl {
+            Declaration::VarDef(v) => fill_type_args(&mut v.var.type_, class_name, type_params),
+            Declaration::FuncDef(f) => {
+                for param in &mut f.params {
+                    fill_type_args(&mut param.type_, class_name, type_params);
+                }
+                fill_type_args(&mut f.return_type, class_name, type_params);
+                fill_type_args_decl(&mut f.declarations, class_name, type_params);
+            }
+            _ => (),
+        }
+    }
+}
+
+// Replace type parameters by type arguments
+fn substitute(t: &ValueType, params: &[String], args: &[ValueType]) -> ValueType {
+    match t {
+        ValueType::ClassValueType(c) => {
+            if let Some(i) = params.iter().position(|param| *param == c.class_name) {
+                args[i].clone()
+            } else {
+                ValueType::ClassValueType(ClassValueType {
+                    class_name: c.class_name.clone(),
+                    type_args: c
+                        .type_args
+                        .iter()
+                        .map(|arg| substitute(arg, params, args))
+                        .collect(),
+                })
+            }
+        }
+        ValueType::ListValueType(l) => ValueType::ListValueType(ListValueType {
+            element_type: Box::new(substitute(&l.element_type, params, args)),
+        }),
+        ValueType::OptionalValueType(o) => ValueType::OptionalValueType(OptionalValueType {
+            element_type: Box::new(substitute(&o.element_type, params, args)),
+        }),
+    }
+}
+
+// Whether values of the type can be elements of a set
+pub fn is_hashable(t: &ValueType) -> bool {
+    *t == *TYPE_INT || *t == *TYPE_BOOL || *t == *TYPE_STR
+}
+
+pub fn check_set_element(t: &mut TypeAnnotation, errors: &mut Vec<CompilerError>) {
+    let element_type = ValueType::from_annotation(t);
+    if !is_hashable(&element_type) {
+        let msg = error_set_element(&element_type);
+        t.add_error(errors, msg);
+    }
+}
 
 impl ClassEnv {
     fn add_basic_type(&mut self, name: &str) {
-        self.0.insert(
+        self.classes.insert(
             name.to_owned(),
             ClassInfo {
                 super_class: "object".to_owned(),
+                type_params: vec![],
                 items: std::iter::once((
                     "__init__".to_owned(),
                     Type::FuncType(FuncType {
                         parameters: vec![ValueType::ClassValueType(ClassValueType {
                             class_name: "object".to_owned(),
+                            type_args: vec![],
                         })],
                         return_type: TYPE_NONE.clone(),
                     }),
                 ))
                 .collect(),
+                locations: HashMap::new(),
             },
         );
     }
 
-    pub fn new() -> ClassEnv {
-        let mut class_env = ClassEnv(HashMap::new());
+    pub fn new(strict_none: bool) -> ClassEnv {
+        let mut class_env = ClassEnv {
+            classes: HashMap::new(),
+            type_vars: HashSet::new(),
+            strict_none,
+        };
         class_env.add_basic_type("object");
         class_env.add_basic_type("str");
         class_env.add_basic_type("int");
         class_env.add_basic_type("bool");
         class_env.add_basic_type("<None>");
         class_env.add_basic_type("<Empty>");
+
+        // Generator objects, yielding values of the type argument
+        class_env.classes.insert(
+            "Iterator".to_owned(),
+            ClassInfo {
+                super_class: "object".to_owned(),
+                type_params: vec!["T".to_owned()],
+                items: HashMap::new(),
+                locations: HashMap::new(),
+            },
+        );
+
+        // Hash sets of `int`, `bool` or `str`, implemented by the runtime
+        let element = ValueType::ClassValueType(ClassValueType {
+            class_name: "T".to_owned(),
+            type_args: vec![],
+        });
+        let set = ValueType::ClassValueType(ClassValueType {
+            class_name: "set".to_owned(),
+            type_args: vec![element.clone()],
+        });
+        let method = Type::FuncType(FuncType {
+            parameters: vec![set, element],
+            return_type: TYPE_NONE.clone(),
+        });
+        class_env.classes.insert(
+            "set".to_owned(),
+            ClassInfo {
+                super_class: "object".to_owned(),
+                type_params: vec!["T".to_owned()],
+                items: ["add", "remove", "discard"]
+                    .into_iter()
+                    .map(|name| (name.to_owned(), method.clone()))
+                    .collect(),
+                locations: HashMap::new(),
+            },
+        );
         class_env
     }
 
@@ -54,38 +195,95 @@ impl ClassEnv {
         id_set: &HashSet<String>,
     ) {
         let class_name = &class_def.name.name;
+
+        // Collect type parameters
+        let mut type_params = vec![];
+        for param in &mut class_def.type_params {
+            // Type parameters name types inside the class only, but they are
+            // stored with the classes, so they cannot shadow one
+            if self.contains(&param.name)
+                || param.name == *class_name
+                || type_params.contains(&param.name)
+            {
+                let msg = error_dup(&param.name);
+                param.add_error(errors, msg);
+                continue;
+            }
+            type_params.push(param.name.clone());
+            self.type_vars.insert(param.name.clone());
+            self.classes
+                .entry(param.name.clone())
+                .or_insert_with(|| ClassInfo {
+                    super_class: "object".to_owned(),
+                    type_params: vec![],
+                    items: HashMap::new(),
+                    locations: HashMap::new(),
+                });
+        }
+        if !type_params.is_empty() {
+            fill_type_args_decl(&mut class_def.declarations, class_name, &type_params);
+        }
+
         let super_name = &class_def.super_class.name;
         let super_class = if matches!(super_name.as_str(), "int" | "str" | "bool") {
             let msg = error_super_special(super_name);
             class_def.super_class.add_error(errors, msg);
-            self.0.get("object").unwrap()
-        } else if let Some(super_class) = self.0.get(super_name) {
-            super_class
+            self.classes.get("object").unwrap()
+        } else if let Some(super_class) = self
+            .classes
+            .get(super_name)
+            .filter(|_| !self.type_vars.contains(super_name))
+        {
+            if super_class.type_params.is_empty() {
+                super_class
+            } else {
+                let msg = error_super_generic(super_name);
+                class_def.super_class.add_error(errors, msg);
+                self.classes.get("object").unwrap()
+            }
         } else {
-            let msg = if id_set.contains(super_name) {
+            let msg = if id_set.contains(super_name) || self.type_vars.contains(super_name) {
                 error_super_not_class
             } else {
                 error_super_undef
             }(super_name);
             class_def.super_class.add_error(errors, msg);
-            self.0.get("object").unwrap()
+            self.classes.get("object").unwrap()
         };
 
         // Inherit items
         let mut items = super_class.items.clone();
+        let mut locations = super_class.locations.clone();
+
+        let self_type = ValueType::ClassValueType(ClassValueType {
+            class_name: class_name.clone(),
+            type_args: type_params
+                .iter()
+                .map(|param| {
+                    ValueType::ClassValueType(ClassValueType {
+                        class_name: param.clone(),
+                        type_args: vec![],
+                    })
+                })
+                .collect(),
+        });
 
         // Check and insert new items
-        let mut id_set = HashSet::new();
+        let mut id_set = HashMap::new();
         for item_decl in &mut class_def.declarations {
             let name_str = item_decl.name_mut().name.clone();
 
             // Class scope identifier collision check
-            if !id_set.insert(name_str.clone()) {
+            let location = item_decl.name_mut().base.location;
+            if let Some(&first) = id_set.get(&name_str) {
                 let msg = error_dup(&name_str);
                 let name = item_decl.name_mut();
                 name.add_error(errors, msg);
+                errors.push(CompilerError::note(first, note_first_declared()));
                 continue;
             }
+            id_set.insert(name_str.clone(), location);
+            let overridden = locations.insert(name_str.clone(), location);
 
             match item_decl {
                 Declaration::FuncDef(func) => {
@@ -99,11 +297,7 @@ impl ClassEnv {
                     let name = item_decl.name_mut();
 
                     // Self parameter check
-                    if parameters.first()
-                        != Some(&ValueType::ClassValueType(ClassValueType {
-                            class_name: class_name.clone(),
-                        }))
-                    {
+                    if parameters.first() != Some(&self_type) {
                         let msg = error_method_self(&name_str);
                         name.add_error(errors, msg);
                     }
@@ -117,12 +311,13 @@ impl ClassEnv {
                     match items.insert(name_str.clone(), item_type.clone()) {
                         None => (),
                         Some(Type::FuncType(mut old)) => {
-                            old.parameters[0] = ValueType::ClassValueType(ClassValueType {
-                                class_name: class_name.clone(),
-                            });
+                            old.parameters[0] = self_type.clone();
                             if Type::FuncType(old) != item_type {
                                 let msg = error_method_override(&name_str);
                                 name.add_error(errors, msg);
+                                if let Some(overridden) = overridden {
+                                    errors.push(CompilerError::note(overridden, note_overridden()));
+                                }
                             }
                         }
                         _ => {
@@ -148,24 +343,40 @@ impl ClassEnv {
                 _ => unreachable!(),
             }
         }
-        self.0.insert(
+        self.classes.insert(
             class_name.clone(),
             ClassInfo {
                 super_class: class_def.super_class.name.clone(),
+                type_params,
                 items,
+                locations,
             },
         );
     }
 
+    pub fn strict_none(&self) -> bool {
+        self.strict_none
+    }
+
     pub fn is_compatible(&self, sub_class: &ValueType, super_class: &ValueType) -> bool {
         if sub_class == super_class {
             return true;
         }
+        if let ValueType::OptionalValueType(OptionalValueType { element_type }) = super_class {
+            return *sub_class == *TYPE_NONE
+                || self.is_compatible(sub_class.non_optional(), element_type);
+        }
+        if let ValueType::OptionalValueType(OptionalValueType { element_type }) = sub_class {
+            return !self.strict_none && self.is_compatible(element_type, super_class);
+        }
+        if *sub_class == *TYPE_NONE && self.strict_none {
+            return false;
+        }
         if *super_class == *TYPE_OBJECT {
             return true;
         }
         if *sub_class == *TYPE_NONE {
-            if let ValueType::ClassValueType(ClassValueType { class_name }) = super_class {
+            if let ValueType::ClassValueType(ClassValueType { class_name, .. }) = super_class {
                 return class_name != "int" && class_name != "str" && class_name != "bool";
             } else {
                 return true;
@@ -190,28 +401,37 @@ impl ClassEnv {
             return false;
         }
 
-        let mut sub_name =
-            if let ValueType::ClassValueType(ClassValueType { class_name }) = sub_class {
-                class_name
-            } else {
-                return false;
-            };
+        let (mut sub_name, mut sub_args) = if let ValueType::ClassValueType(ClassValueType {
+            class_name,
+            type_args,
+        }) = sub_class
+        {
+            (class_name, &type_args[..])
+        } else {
+            return false;
+        };
 
-        let super_name =
-            if let ValueType::ClassValueType(ClassValueType { class_name }) = super_class {
-                class_name
-            } else {
-                return false;
-            };
+        let (super_name, super_args) = if let ValueType::ClassValueType(ClassValueType {
+            class_name,
+            type_args,
+        }) = super_class
+        {
+            (class_name, &type_args[..])
+        } else {
+            return false;
+        };
 
         loop {
             if sub_name == super_name {
-                return true;
+                // Generic classes are invariant in their type arguments
+                return sub_args == super_args;
             }
             if sub_name == "object" {
                 return false;
             }
-            sub_name = &self.0.get(sub_name).unwrap().super_class;
+            sub_name = &self.classes.get(sub_name).unwrap().super_class;
+            // Generic classes can't be extended, so super classes have no type arguments
+            sub_args = &[];
         }
     }
 
@@ -222,12 +442,35 @@ impl ClassEnv {
         if self.is_compatible(b, a) {
             return a.clone();
         }
+        let is_none_or_optional =
+            |t: &ValueType| matches!(t, ValueType::OptionalValueType(_)) || *t == *TYPE_NONE;
+        if (self.strict_none && (is_none_or_optional(a) || is_none_or_optional(b)))
+            || matches!(a, ValueType::OptionalValueType(_))
+            || matches!(b, ValueType::OptionalValueType(_))
+        {
+            let (a, b) = (a.non_optional(), b.non_optional());
+            let element_type = if *a == *TYPE_NONE {
+                b.clone()
+            } else if *b == *TYPE_NONE {
+                a.clone()
+            } else {
+                self.join(a, b)
+            };
+            // Unboxed values can't be None
+            if element_type == *TYPE_INT || element_type == *TYPE_BOOL {
+                return TYPE_OBJECT.clone();
+            }
+            let element_type = Box::new(element_type);
+            return ValueType::OptionalValueType(OptionalValueType { element_type });
+        }
         if let (
             ValueType::ClassValueType(ClassValueType {
                 class_name: a_class,
+                ..
             }),
             ValueType::ClassValueType(ClassValueType {
                 class_name: b_class,
+                ..
             }),
         ) = (a, b)
         {
@@ -239,10 +482,18 @@ impl ClassEnv {
                 return TYPE_OBJECT.clone();
             }
 
+            // Different instantiations of the same generic class
+            if a_class == b_class {
+                return ValueType::ClassValueType(ClassValueType {
+                    class_name: self.classes.get(a_class).unwrap().super_class.clone(),
+                    type_args: vec![],
+                });
+            }
+
             let gen_chain = |mut t| {
                 let mut v = vec![t];
                 while t != "object" {
-                    t = &self.0.get(t).unwrap().super_class;
+                    t = &self.classes.get(t).unwrap().super_class;
                     v.push(t);
                 }
                 v
@@ -257,6 +508,7 @@ impl ClassEnv {
                 if a_chain.last() != b_chain.last() {
                     return ValueType::ClassValueType(ClassValueType {
                         class_name: common.to_owned(),
+                        type_args: vec![],
                     });
                 }
             }
@@ -266,20 +518,215 @@ impl ClassEnv {
     }
 
     pub fn get_attribute(&self, class_name: &str, name: &str) -> Option<&ValueType> {
-        match self.0.get(class_name)?.items.get(name)? {
+        match self.classes.get(class_name)?.items.get(name)? {
             Type::ValueType(t) => Some(t),
             _ => None,
         }
     }
 
     pub fn get_method(&self, class_name: &str, name: &str) -> Option<&FuncType> {
-        match self.0.get(class_name)?.items.get(name)? {
+        match self.classes.get(class_name)?.items.get(name)? {
             Type::FuncType(t) => Some(t),
             _ => None,
         }
     }
 
+    // Where an item of a user-defined class is declared, which may be in a
+    // superclass
+    pub fn item_location(&self, class_name: &str, name: &str) -> Option<Location> {
+        self.classes.get(class_name)?.locations.get(name).copied()
+    }
+
+    // Names of the attributes of a class, including inherited ones
+    pub fn attribute_names(&self, class_name: &str) -> Vec<&str> {
+        self.item_names(class_name, |t| matches!(t, Type::ValueType(_)))
+    }
+
+    // Names of the methods of a class, including inherited ones
+    pub fn method_names(&self, class_name: &str) -> Vec<&str> {
+        self.item_names(class_name, |t| matches!(t, Type::FuncType(_)))
+    }
+
+    fn item_names(&self, class_name: &str, filter: fn(&Type) -> bool) -> Vec<&str> {
+        self.classes.get(class_name).map_or(vec![], |class| {
+            class
+                .items
+                .iter()
+                .filter(|(_, t)| filter(t))
+                .map(|(name, _)| name.as_str())
+                .collect()
+        })
+    }
+
+    // The type of an item declared in a generic class, as seen through an
+    // instantiation of the class
+    pub fn instantiate(&self, t: &ValueType, class: &ClassValueType) -> ValueType {
+        let params = self.type_params(&class.class_name);
+        if params.is_empty() || class.type_args.len() != params.len() {
+            return t.clone();
+        }
+        substitute(t, params, &class.type_args)
+    }
+
+    pub fn instantiate_method(&self, method: &FuncType, class: &ClassValueType) -> FuncType {
+        FuncType {
+            parameters: method
+                .parameters
+                .iter()
+                .map(|t| self.instantiate(t, class))
+                .collect(),
+            return_type: self.instantiate(&method.return_type, class),
+        }
+    }
+
+    // Lists of a type parameter hold references, like any list of objects, so
+    // `[T]` cannot become a list of plain values
+    pub fn check_plain_type_args(
+        &self,
+        class_name: &str,
+        type_args: &mut [TypeAnnotation],
+        errors: &mut Vec<CompilerError>,
+    ) {
+        let params = self.type_params(class_name);
+        for (arg, param) in type_args.iter_mut().zip(params) {
+            let arg_type = ValueType::from_annotation(arg);
+            if (arg_type == *TYPE_INT || arg_type == *TYPE_BOOL)
+                && arg.base().error_msg.is_none()
+                && self.param_in_list(class_name, param, &mut HashSet::new())
+            {
+                let msg = error_plain_type_arg(class_name, param, &arg_type);
+                arg.add_error(errors, msg);
+            }
+        }
+    }
+
+    // Whether a type parameter of a class is the element type of a list in the
+    // types of its attributes and methods, directly or through another generic
+    // class
+    fn param_in_list(
+        &self,
+        class_name: &str,
+        param: &str,
+        visited: &mut HashSet<(String, String)>,
+    ) -> bool {
+        if !visited.insert((class_name.to_owned(), param.to_owned())) {
+            return false;
+        }
+        let Some(class) = self.classes.get(class_name) else {
+            return false;
+        };
+        class.items.values().any(|item| match item {
+            Type::ValueType(t) => self.type_in_list(t, param, false, visited),
+            Type::FuncType(f) => f
+                .parameters
+                .iter()
+                .chain(std::iter::once(&f.return_type))
+                .any(|t| self.type_in_list(t, param, false, visited)),
+        })
+    }
+
+    fn type_in_list(
+        &self,
+        t: &ValueType,
+        param: &str,
+        in_list: bool,
+        visited: &mut HashSet<(String, String)>,
+    ) -> bool {
+        match t {
+            ValueType::ClassValueType(c) if c.class_name == param => in_list,
+            ValueType::ClassValueType(c) => {
+                let params = self.type_params(&c.class_name);
+                c.type_args.iter().zip(params).any(|(arg, inner)| match arg {
+                    ValueType::ClassValueType(a) if a.class_name == param => {
+                        self.param_in_list(&c.class_name, inner, visited)
+                    }
+                    _ => self.type_in_list(arg, param, false, visited),
+                })
+            }
+            ValueType::ListValueType(l) => self.type_in_list(&l.element_type, param, true, visited),
+            ValueType::OptionalValueType(o) => {
+                self.type_in_list(&o.element_type, param, false, visited)
+            }
+        }
+    }
+
+    pub fn type_params(&self, class_name: &str) -> &[String] {
+        self.classes
+            .get(class_name)
+            .map_or(&[], |class| &class.type_params)
+    }
+
+    pub fn is_type_var(&self, name: &str) -> bool {
+        self.type_vars.contains(name)
+    }
+
+    // Whether the name refers to a class, excluding type parameters
     pub fn contains(&self, class_name: &str) -> bool {
-        self.0.contains_key(class_name)
+        self.classes.contains_key(class_name) && !self.type_vars.contains(class_name)
+    }
+
+    // Check that an annotation only names existing classes and type parameters in
+    // `scope`, and that generic classes get the right number of type arguments
+    pub fn check_annotation(
+        &self,
+        t: &mut TypeAnnotation,
+        scope: &[String],
+        errors: &mut Vec<CompilerError>,
+    ) {
+        match t {
+            TypeAnnotation::ClassType(c) => {
+                let valid = if self.type_vars.contains(&c.class_name) {
+                    scope.contains(&c.class_name)
+                } else {
+                    self.classes.contains_key(&c.class_name)
+                };
+                if !valid {
+                    let msg = error_invalid_type(&c.class_name);
+                    c.add_error(errors, msg);
+                    // Classes written in annotations, and the type parameters in scope
+                    let names = self
+                        .classes
+                        .keys()
+                        .filter(|name| !name.starts_with('<') && self.contains(name))
+                        .chain(scope)
+                        .map(String::as_str);
+                    if let Some(name) = suggest(&c.class_name, names) {
+                        errors.push(CompilerError::note(c.base.location, note_did_you_mean(name)));
+                    }
+                    return;
+                }
+                let expected = self.type_params(&c.class_name).len();
+                if c.type_args.len() != expected {
+                    let msg = error_type_args(&c.class_name, expected, c.type_args.len());
+                    c.add_error(errors, msg);
+                    return;
+                }
+                for arg in &mut c.type_args {
+                    self.check_annotation(arg, scope, errors);
+                }
+                self.check_plain_type_args(&c.class_name, &mut c.type_args, errors);
+                if c.class_name == "set" && c.type_args[0].base().error_msg.is_none() {
+                    check_set_element(&mut c.type_args[0], errors);
+                }
+            }
+            TypeAnnotation::ListType(l) => self.check_annotation(&mut l.element_type, scope, errors),
+            TypeAnnotation::OptionalType(o) => {
+                // `Optional` can only wrap types that are represented by a reference
+                let wrapped = match &o.element_type {
+                    TypeAnnotation::ClassType(c) => {
+                        matches!(c.class_name.as_str(), "int" | "bool")
+                    }
+                    TypeAnnotation::OptionalType(_) => true,
+                    TypeAnnotation::ListType(_) => false,
+                };
+                if wrapped {
+                    let name = ValueType::from_annotation(&o.element_type).to_string();
+                    let msg = error_optional_type(&name);
+                    o.add_error(errors, msg);
+                } else {
+                    self.check_annotation(&mut o.element_type, scope, errors);
+                }
+            }
+        }
     }
 }
@@ -1,149 +1,382 @@
+use crate::common::diagnostic::*;
 use crate::common::node::*;
 
-pub fn error_dup(name: &str) -> String {
-    format!(
-        "Duplicate declaration of identifier in same scope: {}",
-        name
+pub fn error_dup(name: &str) -> Message {
+    Message::new(
+        DUPLICATE_DECLARATION,
+        format!("Duplicate declaration of identifier in same scope: {}", name),
     )
 }
 
-pub fn error_super_undef(name: &str) -> String {
-    format!("Super-class not defined: {}", name)
+pub fn error_super_undef(name: &str) -> Message {
+    Message::new(UNDEFINED_SUPERCLASS, format!("Super-class not defined: {}", name))
 }
 
-pub fn error_super_not_class(name: &str) -> String {
-    format!("Super-class must be a class: {}", name)
+pub fn error_super_not_class(name: &str) -> Message {
+    Message::new(SUPERCLASS_NOT_CLASS, format!("Super-class must be a class: {}", name))
 }
 
-pub fn error_super_special(name: &str) -> String {
-    format!("Cannot extend special class: {}", name)
+pub fn error_super_special(name: &str) -> Message {
+    Message::new(SPECIAL_SUPERCLASS, format!("Cannot extend special class: {}", name))
 }
 
-pub fn error_method_self(name: &str) -> String {
-    format!(
-        "First parameter of the following method must be of the enclosing class: {}",
-        name
+pub fn error_method_self(name: &str) -> Message {
+    Message::new(
+        METHOD_SELF,
+        format!("First parameter of the following method must be of the enclosing class: {}", name),
     )
 }
 
-pub fn error_method_override(name: &str) -> String {
-    format!("Method overridden with different type signature: {}", name)
+pub fn error_method_override(name: &str) -> Message {
+    Message::new(
+        METHOD_OVERRIDE,
+        format!("Method overridden with different type signature: {}", name),
+    )
+}
+
+pub fn error_attribute_redefine(name: &str) -> Message {
+    Message::new(ATTRIBUTE_REDEFINITION, format!("Cannot re-define attribute: {}", name))
+}
+
+pub fn error_invalid_type(name: &str) -> Message {
+    Message::new(
+        INVALID_TYPE,
+        format!("Invalid type annotation; there is no class named: {}", name),
+    )
+}
+
+pub fn error_shadow(name: &str) -> Message {
+    Message::new(CLASS_NAME_SHADOWED, format!("Cannot shadow class name: {}", name))
+}
+
+pub fn error_nonlocal(name: &str) -> Message {
+    Message::new(NOT_NONLOCAL, format!("Not a nonlocal variable: {}", name))
+}
+
+pub fn error_global(name: &str) -> Message {
+    Message::new(NOT_GLOBAL, format!("Not a global variable: {}", name))
+}
+
+pub fn error_return(name: &str) -> Message {
+    Message::new(
+        MISSING_RETURN,
+        format!("All paths in this function/method must have a return statement: {}", name),
+    )
+}
+
+pub fn error_variable(name: &str) -> Message {
+    Message::new(UNDEFINED_VARIABLE, format!("Not a variable: {}", name))
+}
+
+pub fn error_assign(left: &ValueType, right: &ValueType) -> Message {
+    Message::new(TYPE_MISMATCH, format!("Expected type `{}`; got type `{}`", &left, &right))
+}
+
+pub fn error_nonlocal_assign(name: &str) -> Message {
+    Message::new(
+        ASSIGN_UNDECLARED,
+        format!(
+            "Cannot assign to variable that is not explicitly declared in this scope: {}",
+            name
+        ),
+    )
+}
+
+pub fn error_final_assign(name: &str) -> Message {
+    Message::new(FINAL_ASSIGN, format!("Cannot assign to `Final` variable: {}", name))
+}
+
+pub fn error_final_local(name: &str) -> Message {
+    Message::new(FINAL_LOCAL, format!("`Final` is only allowed for global variables: {}", name))
+}
+
+pub fn error_unary(operator: &str, operand: &ValueType) -> Message {
+    Message::new(
+        UNARY_OPERAND,
+        format!("Cannot apply operator `{}` on type `{}`", operator, operand),
+    )
+}
+
+pub fn error_binary(operator: &str, left: &ValueType, right: &ValueType) -> Message {
+    Message::new(
+        BINARY_OPERANDS,
+        format!("Cannot apply operator `{}` on types `{}` and `{}`", operator, left, right),
+    )
+}
+
+pub fn error_condition(condition: &ValueType) -> Message {
+    Message::new(CONDITION_TYPE, format!("Condition expression cannot be of type `{}`", condition))
+}
+
+pub fn error_member(t: &ValueType) -> Message {
+    Message::new(MEMBER_NON_CLASS, format!("Cannot access member of non-class type `{}`", t))
+}
+
+pub fn error_call_count(expected: usize, got: usize) -> Message {
+    Message::new(ARGUMENT_COUNT, format!("Expected {} arguments; got {}", expected, got))
+}
+
+pub fn error_call_type(location: usize, expected: &ValueType, got: &ValueType) -> Message {
+    Message::new(
+        ARGUMENT_TYPE,
+        format!("Expected type `{}`; got type `{}` in parameter {}", expected, got, location),
+    )
+}
+
+pub fn error_index_left(left: &ValueType) -> Message {
+    Message::new(NOT_INDEXABLE, format!("Cannot index into type `{}`", &left))
+}
+
+pub fn error_index_right(index: &ValueType) -> Message {
+    Message::new(INDEX_NOT_INT, format!("Index is of non-integer type `{}`", &index))
+}
+
+pub fn error_attribute(name: &str, class_name: &str) -> Message {
+    Message::new(
+        UNDEFINED_ATTRIBUTE,
+        format!("There is no attribute named `{}` in class `{}`", name, class_name),
+    )
+}
+
+pub fn error_function(name: &str) -> Message {
+    Message::new(NOT_CALLABLE, format!("Not a function or class: {}", name))
+}
+
+pub fn error_method(method_name: &str, class_name: &str) -> Message {
+    Message::new(
+        UNDEFINED_METHOD,
+        format!("There is no method named `{}` in class `{}`", method_name, class_name),
+    )
+}
+
+pub fn error_none_return(return_expected: &ValueType) -> Message {
+    Message::new(NONE_RETURN, format!("Expected type `{}`; got `None`", &return_expected))
+}
+
+pub fn error_iterable(iterable: &ValueType) -> Message {
+    Message::new(NOT_ITERABLE, format!("Cannot iterate over value of type `{}`", &iterable))
+}
+
+pub fn error_multi_assign() -> Message {
+    Message::new(
+        MULTI_ASSIGN_NONE,
+        "Right-hand side of multiple assignment may not be [<None>]".to_owned(),
+    )
+}
+
+pub fn error_top_return() -> Message {
+    Message::new(TOP_LEVEL_RETURN, "Return statement cannot appear at the top level".to_owned())
+}
+
+pub fn error_str_index_assign() -> Message {
+    Message::new(STR_INDEX_ASSIGN, "`str` is not a list type".to_owned())
+}
+
+pub fn error_isinstance_class() -> Message {
+    Message::new(
+        ISINSTANCE_CLASS,
+        "Second argument of `isinstance` must be a class name".to_owned(),
+    )
+}
+
+pub fn error_optional_type(name: &str) -> Message {
+    Message::new(OPTIONAL_TYPE, format!("`Optional` cannot wrap type: {}", name))
 }
 
-pub fn error_attribute_redefine(name: &str) -> String {
-    format!("Cannot re-define attribute: {}", name)
+pub fn error_maybe_none(t: &ValueType) -> Message {
+    Message::new(MAYBE_NONE, format!("Value of type `{}` may be None", t))
 }
 
-pub fn error_invalid_type(name: &str) -> String {
-    format!("Invalid type annotation; there is no class named: {}", name)
+pub fn error_super_generic(name: &str) -> Message {
+    Message::new(GENERIC_SUPERCLASS, format!("Cannot extend generic class: {}", name))
+}
+
+pub fn error_plain_type_arg(class: &str, param: &str, arg: &ValueType) -> Message {
+    Message::new(
+        PLAIN_TYPE_ARGUMENT,
+        format!(
+            "Class {} stores type parameter {} in lists, so it cannot be `{}`",
+            class, param, arg
+        ),
+    )
+}
+
+pub fn error_type_args(name: &str, expected: usize, got: usize) -> Message {
+    Message::new(
+        TYPE_ARGUMENT_COUNT,
+        format!("Class {} expects {} type argument(s); got {}", name, expected, got),
+    )
+}
+
+pub fn error_generator_return(name: &str) -> Message {
+    Message::new(
+        GENERATOR_RETURN_TYPE,
+        format!("Generator must be declared to return an `Iterator`: {}", name),
+    )
 }
 
-pub fn error_shadow(name: &str) -> String {
-    format!("Cannot shadow class name: {}", name)
+pub fn error_generator_nested(name: &str) -> Message {
+    Message::new(
+        GENERATOR_NESTED,
+        format!("Generators cannot be nested or contain nested functions: {}", name),
+    )
 }
 
-pub fn error_nonlocal(name: &str) -> String {
-    format!("Not a nonlocal variable: {}", name)
+pub fn error_generator_return_value() -> Message {
+    Message::new(
+        GENERATOR_RETURN_VALUE,
+        "Return statement in a generator cannot have a value".to_owned(),
+    )
 }
 
-pub fn error_global(name: &str) -> String {
-    format!("Not a global variable: {}", name)
+pub fn error_top_yield() -> Message {
+    Message::new(TOP_LEVEL_YIELD, "Yield statement cannot appear at the top level".to_owned())
 }
 
-pub fn error_return(name: &str) -> String {
-    format!(
-        "All paths in this function/method must have a return statement: {}",
-        name
+pub fn error_next_iterator(t: &ValueType) -> Message {
+    Message::new(
+        NEXT_NON_ITERATOR,
+        format!("Argument of `next` must be an `Iterator`; got type `{}`", t),
     )
 }
 
-pub fn error_variable(name: &str) -> String {
-    format!("Not a variable: {}", name)
+pub fn error_slice() -> Message {
+    Message::new(SLICE_OUTSIDE_DEL, "Slices are only supported as `del` targets".to_owned())
 }
 
-pub fn error_assign(left: &ValueType, right: &ValueType) -> String {
-    format!("Expected type `{}`; got type `{}`", &left, &right)
+pub fn error_del_local(name: &str) -> Message {
+    Message::new(DEL_NON_LOCAL, format!("Only local variables can be deleted: {}", name))
 }
 
-pub fn error_nonlocal_assign(name: &str) -> String {
-    format!(
-        "Cannot assign to variable that is not explicitly declared in this scope: {}",
-        name
+pub fn error_del_captured(name: &str) -> Message {
+    Message::new(
+        DEL_CAPTURED,
+        format!("Cannot delete variable used by a nested function: {}", name),
     )
 }
 
-pub fn error_unary(operator: &str, operand: &ValueType) -> String {
-    format!("Cannot apply operator `{}` on type `{}`", operator, operand)
+pub fn error_unbound(name: &str) -> Message {
+    Message::new(POSSIBLY_UNBOUND, format!("Variable may be unbound after `del`: {}", name))
 }
 
-pub fn error_binary(operator: &str, left: &ValueType, right: &ValueType) -> String {
-    format!(
-        "Cannot apply operator `{}` on types `{}` and `{}`",
-        operator, left, right
+pub fn error_super() -> Message {
+    Message::new(
+        SUPER_OUTSIDE_METHOD,
+        "`super()` can only be used to call a parent class method from a method".to_owned(),
     )
 }
 
-pub fn error_condition(condition: &ValueType) -> String {
-    format!("Condition expression cannot be of type `{}`", condition)
+pub fn error_keyword_unsupported(name: &str) -> Message {
+    Message::new(KEYWORD_UNSUPPORTED, format!("Keyword arguments are not supported by `{}`", name))
 }
 
-pub fn error_member(t: &ValueType) -> String {
-    format!("Cannot access member of non-class type `{}`", t)
+pub fn error_keyword_unknown(function: &str, name: &str) -> Message {
+    Message::new(
+        KEYWORD_UNKNOWN,
+        format!("Unexpected keyword argument for `{}`: {}", function, name),
+    )
 }
 
-pub fn error_call_count(expected: usize, got: usize) -> String {
-    format!("Expected {} arguments; got {}", expected, got)
+pub fn error_keyword_repeated(name: &str) -> Message {
+    Message::new(KEYWORD_REPEATED, format!("Keyword argument repeated: {}", name))
 }
 
-pub fn error_call_type(location: usize, expected: &ValueType, got: &ValueType) -> String {
-    format!(
-        "Expected type `{}`; got type `{}` in parameter {}",
-        expected, got, location,
+pub fn error_keyword_type(name: &str, expected: &ValueType, got: &ValueType) -> Message {
+    Message::new(
+        KEYWORD_TYPE,
+        format!("Expected type `{}` for keyword argument `{}`; got type `{}`", expected, name, got),
     )
 }
 
-pub fn error_index_left(left: &ValueType) -> String {
-    format!("Cannot index into type `{}`", &left)
+pub fn error_print_file() -> Message {
+    Message::new(PRINT_FILE, "Keyword argument `file` must be `stdout` or `stderr`".to_owned())
 }
 
-pub fn error_index_right(index: &ValueType) -> String {
-    format!("Index is of non-integer type `{}`", &index)
+pub fn error_set_element(t: &ValueType) -> Message {
+    Message::new(
+        SET_ELEMENT_TYPE,
+        format!("Set elements must be `int`, `bool` or `str`; got type `{}`", t),
+    )
 }
 
-pub fn error_attribute(name: &str, class_name: &str) -> String {
-    format!(
-        "There is no attribute named `{}` in class `{}`",
-        name, class_name
+pub fn error_set_mixed(expected: &ValueType, got: &ValueType) -> Message {
+    Message::new(
+        SET_MIXED_ELEMENTS,
+        format!("Expected set element of type `{}`; got type `{}`", expected, got),
     )
 }
 
-pub fn error_function(name: &str) -> String {
-    format!("Not a function or class: {}", name)
+pub fn error_missing_type() -> Message {
+    Message::new(MISSING_TYPE, "Expression has no inferred type in the typed AST".to_owned())
+}
+
+pub fn warning_unused_variable(name: &str) -> Message {
+    Message::new(UNUSED_VARIABLE, format!("Local variable is never used: {}", name))
+}
+
+pub fn warning_unused_parameter(name: &str) -> Message {
+    Message::new(UNUSED_PARAMETER, format!("Parameter is never used: {}", name))
+}
+
+pub fn warning_unused_function(name: &str) -> Message {
+    Message::new(UNUSED_FUNCTION, format!("Function is never used: {}", name))
+}
+
+pub fn warning_unreachable(exit: &str) -> Message {
+    Message::new(UNREACHABLE, format!("Unreachable statement after `{}`", exit))
+}
+
+pub fn warning_shadow(name: &str) -> Message {
+    Message::new(SHADOW, format!("Local name shadows a global: {}", name))
+}
+
+pub fn note_first_declared() -> String {
+    "First declared here".to_owned()
+}
+
+pub fn note_overridden() -> String {
+    "Overridden method declared here".to_owned()
+}
+
+pub fn note_did_you_mean(name: &str) -> String {
+    format!("Did you mean `{}`?", name)
 }
 
-pub fn error_method(method_name: &str, class_name: &str) -> String {
-    format!(
-        "There is no method named `{}` in class `{}`",
-        method_name, class_name
+pub fn note_global(name: &str) -> String {
+    format!("Global `{}` is declared here", name)
+}
+
+pub fn warning_always_true(name: &str, operator: &str) -> Message {
+    Message::new(
+        ALWAYS_TRUE,
+        format!("Comparison is always true: `{} {} {}`", name, operator, name),
     )
 }
 
-pub fn error_none_return(return_expected: &ValueType) -> String {
-    format!("Expected type `{}`; got `None`", &return_expected)
+pub fn warning_possibly_none(name: &str) -> Message {
+    Message::new(POSSIBLY_NONE, format!("Variable may be `None` here: {}", name))
+}
+
+pub fn note_none_initialized(name: &str) -> String {
+    format!("`{}` is initialized to `None` here", name)
+}
+
+pub fn note_none_assigned(name: &str) -> String {
+    format!("`{}` is assigned `None` here", name)
 }
 
-pub fn error_iterable(iterable: &ValueType) -> String {
-    format!("Cannot iterate over value of type `{}`", &iterable)
+pub fn note_none_copied(name: &str, from: &str) -> String {
+    format!("`{}` is assigned `{}` here", name, from)
 }
 
-pub fn error_multi_assign() -> String {
-    "Right-hand side of multiple assignment may not be [<None>]".to_owned()
+pub fn note_none_tested(name: &str) -> String {
+    format!("`{}` is `None` when this is true", name)
 }
 
-pub fn error_top_return() -> String {
-    "Return statement cannot appear at the top level".to_owned()
+pub fn note_none_branch(name: &str, positive: bool) -> String {
+    format!("`{}` may be `None` when this is {}", name, if positive { "true" } else { "false" })
 }
 
-pub fn error_str_index_assign() -> String {
-    "`str` is not a list type".to_owned()
+pub fn note_none_loop(name: &str) -> String {
+    format!("`{}` may be `None` if the loop body never runs", name)
 }
@@ -1,20 +1,23 @@
 mod analyze;
 mod class_env;
 mod error;
+mod narrow;
+mod possibly_none;
+mod suggest;
+mod symbols;
+mod unbound;
+mod warning;
 
 use crate::common::local_env::*;
+use crate::common::location::Location;
 use crate::common::node::*;
-use class_env::*;
+use analyze::BUILTIN_NEXT;
 use error::*;
 use std::collections::{HashMap, HashSet};
-
-fn check_var_def(v: &mut VarDef, errors: &mut Vec<CompilerError>, classes: &ClassEnv) {
-    let core_type = v.var.type_.core_type_mut();
-    if !classes.contains(&core_type.class_name) {
-        let msg = error_invalid_type(&core_type.class_name);
-        core_type.add_error(errors, msg);
-    }
-}
+pub use class_env::ClassEnv;
+pub use symbols::{Reference, Symbol, SymbolKind, SymbolTable, SymbolType, expression_types};
+pub use warning::{WarningConfig, warnings};
+use warning::{for_each_subexpr, statement_exprs};
 
 fn always_return(statements: &[Stmt]) -> bool {
     for statement in statements {
@@ -24,74 +27,88 @@ fn always_return(statements: &[Stmt]) -> bool {
                 then_body,
                 else_body,
                 ..
-            }) => {
-                if always_return(then_body) && always_return(else_body) {
-                    return true;
-                }
-            }
+            }) if always_return(then_body) && always_return(else_body) => return true,
+            // The `else` clause runs whenever the loop finishes without a `break`
+            Stmt::WhileStmt(s) if !breaks(&s.body) && always_return(s.else_body()) => return true,
+            Stmt::ForStmt(s) if !breaks(&s.body) && always_return(s.else_body()) => return true,
             _ => (),
         }
     }
     false
 }
 
+// Whether the statements may break out of the loop whose body they are. The
+// `else` clause of a nested loop breaks out of the enclosing one.
+fn breaks(statements: &[Stmt]) -> bool {
+    statements.iter().any(|statement| match statement {
+        Stmt::BreakStmt(_) => true,
+        Stmt::IfStmt(s) => breaks(&s.then_body) || breaks(&s.else_body),
+        Stmt::WhileStmt(s) => breaks(s.else_body()),
+        Stmt::ForStmt(s) => breaks(s.else_body()),
+        _ => false,
+    })
+}
+
 fn check_func(
     f: &mut FuncDef,
     errors: &mut Vec<CompilerError>,
     classes: &ClassEnv,
     globals: &HashSet<String>,
     nonlocals: &HashSet<String>,
+    type_params: &[String],
 ) {
     let mut locals = HashSet::new();
-    let mut id_set = HashSet::new();
+    // Names declared in the function, with their first declaration
+    let mut id_set = HashMap::new();
+    let generator = f.is_generator();
     // Check parameter type, collision and shadowing
     // semantic rule: 1(param), 2(param), 11(param)
     for param in &mut f.params {
-        let core_type = param.type_.core_type_mut();
-        if !classes.contains(&core_type.class_name) {
-            let msg = error_invalid_type(&core_type.class_name);
-            core_type.add_error(errors, msg);
-        }
+        classes.check_annotation(&mut param.type_, type_params, errors);
 
         let id = &mut param.identifier;
         if classes.contains(&id.name) {
             let msg = error_shadow(&id.name);
             id.add_error(errors, msg);
         }
-        if id_set.contains(&id.name) {
+        if let Some(&first) = id_set.get(&id.name) {
             let msg = error_dup(&id.name);
             id.add_error(errors, msg);
+            errors.push(CompilerError::note(first, note_first_declared()));
         }
         locals.insert(id.name.clone());
-        id_set.insert(id.name.clone());
+        id_set.entry(id.name.clone()).or_insert(id.base.location);
     }
 
     // Check return type
     // semantic rule: 11(return)
-    let core_type = f.return_type.core_type_mut();
-    if !classes.contains(&core_type.class_name) {
-        let msg = error_invalid_type(&core_type.class_name);
-        core_type.add_error(errors, msg);
+    classes.check_annotation(&mut f.return_type, type_params, errors);
+    if generator
+        && !matches!(&f.return_type, TypeAnnotation::ClassType(c) if c.class_name == "Iterator")
+    {
+        let msg = error_generator_return(&f.name.name);
+        f.name.add_error(errors, msg);
     }
 
     let mut nonlocal_remove = HashSet::new();
     // semantic rule: 1, 2(local/function), 3, 11(local)
     for decl in &mut f.declarations {
         let name = decl.name_mut();
-        if id_set.contains(&name.name) {
+        if let Some(&first) = id_set.get(&name.name) {
             let msg = error_dup(&name.name);
             name.add_error(errors, msg);
+            errors.push(CompilerError::note(first, note_first_declared()));
         }
-        id_set.insert(name.name.clone());
+        id_set.entry(name.name.clone()).or_insert(name.base.location);
 
         match decl {
             Declaration::VarDef(v) => {
-                let var = &mut v.var;
-                let core_type = var.type_.core_type_mut();
-                if !classes.contains(&core_type.class_name) {
-                    let msg = error_invalid_type(&core_type.class_name);
-                    core_type.add_error(errors, msg);
+                if v.is_final {
+                    let msg = error_final_local(&v.var.identifier.name);
+                    v.var.identifier.add_error(errors, msg);
                 }
+                let var = &mut v.var;
+                classes.check_annotation(&mut var.type_, type_params, errors);
 
                 let id = &mut var.identifier;
                 if classes.contains(&id.name) {
@@ -101,11 +118,17 @@ fn check_func(
                 locals.insert(id.name.clone());
             }
             Declaration::FuncDef(f) => {
+                // Generator frames live on the heap and cannot be captured
+                let nested_generator = f.is_generator();
                 let id = &mut f.name;
                 if classes.contains(&id.name) {
                     let msg = error_shadow(&id.name);
                     id.add_error(errors, msg);
                 }
+                if generator || nested_generator {
+                    let msg = error_generator_nested(&id.name);
+                    id.add_error(errors, msg);
+                }
                 nonlocal_remove.insert(id.name.clone());
             }
             Declaration::NonLocalDecl(v) => {
@@ -128,12 +151,19 @@ fn check_func(
     }
 
     // semantic rule: 9
-    if let TypeAnnotation::ClassType(c) = &f.return_type {
-        if matches!(c.class_name.as_str(), "int" | "str" | "bool") && !always_return(&f.statements)
-        {
-            let msg = error_return(&f.name.name);
-            f.name.add_error(errors, msg);
+    // In strict mode, falling off the end would return None for any non-optional type
+    let must_return = match &f.return_type {
+        TypeAnnotation::ClassType(c) => {
+            matches!(c.class_name.as_str(), "int" | "str" | "bool")
+                || (classes.strict_none() && c.class_name != "<None>")
         }
+        TypeAnnotation::ListType(_) => classes.strict_none(),
+        TypeAnnotation::OptionalType(_) => false,
+    };
+    // Generators only end by exhausting
+    if must_return && !generator && !always_return(&f.statements) {
+        let msg = error_return(&f.name.name);
+        f.name.add_error(errors, msg);
     }
 
     // recursion
@@ -144,12 +174,40 @@ fn check_func(
         .collect();
     for decl in &mut f.declarations {
         if let Declaration::FuncDef(f) = decl {
-            check_func(f, errors, classes, globals, &nonlocals);
+            check_func(f, errors, classes, globals, &nonlocals, type_params);
         }
     }
 }
 
-pub fn check(mut ast: Program) -> Program {
+pub fn check(ast: Program, strict_none: bool) -> Program {
+    check_resolving(ast, strict_none).0
+}
+
+// A type checked program with what the checker learned about its names, for
+// tools such as the language server
+pub struct Analysis {
+    pub ast: Program,
+    pub symbols: SymbolTable,
+    pub classes: ClassEnv,
+}
+
+// Type check a program, and build its symbol table
+pub fn analyze(ast: Program, strict_none: bool) -> Analysis {
+    let (ast, resolutions, classes) = check_resolving(ast, strict_none);
+    let symbols = symbols::build(&ast, resolutions);
+    Analysis {
+        ast,
+        symbols,
+        classes,
+    }
+}
+
+// Type check a program, and return the names resolved along the way with the
+// classes. Names are only resolved when there are no declaration errors.
+fn check_resolving(
+    mut ast: Program,
+    strict_none: bool,
+) -> (Program, Vec<Resolution>, ClassEnv) {
     let mut errors = vec![];
 
     let mut id_set = HashSet::new();
@@ -160,19 +218,30 @@ pub fn check(mut ast: Program) -> Program {
     id_set.insert("print".to_owned());
     id_set.insert("input".to_owned());
     id_set.insert("len".to_owned());
+    id_set.insert("isinstance".to_owned());
+    id_set.insert("Iterator".to_owned());
+    id_set.insert("set".to_owned());
 
-    let mut classes = ClassEnv::new();
+    let mut classes = ClassEnv::new(strict_none);
 
     // Pass A
     // semantic rule: 1(global/class), 4, 5, 6, 7
     // collects class info
+    let mut first_declared = HashMap::new();
     for decl in &mut ast.declarations {
         // Global identifier collision check
+        // Type parameters are only in scope in their class, but a class cannot
+        // take the name of one
+        let is_class = matches!(decl, Declaration::ClassDef(_));
         let name = decl.name_mut();
-        if !id_set.insert(name.name.clone()) {
+        if !id_set.insert(name.name.clone()) || is_class && classes.is_type_var(&name.name) {
             let msg = error_dup(&name.name);
             name.add_error(&mut errors, msg);
+            if let Some(&first) = first_declared.get(&name.name) {
+                errors.push(CompilerError::note(first, note_first_declared()));
+            }
         }
+        first_declared.entry(name.name.clone()).or_insert(name.base.location);
 
         if let Declaration::ClassDef(class_def) = decl {
             classes.add_class(class_def, &mut errors, &id_set);
@@ -183,17 +252,26 @@ pub fn check(mut ast: Program) -> Program {
     // semantic rules: 11(global/class variable)
     // collects global variables
     let mut globals = HashSet::new();
+    let mut constants = HashSet::new();
     for decl in &mut ast.declarations {
         match decl {
             Declaration::VarDef(v) => {
-                check_var_def(v, &mut errors, &classes);
+                classes.check_annotation(&mut v.var.type_, &[], &mut errors);
                 let name = &v.var.identifier.name;
                 globals.insert(name.clone());
+                if v.is_final {
+                    constants.insert(name.clone());
+                }
             }
             Declaration::ClassDef(c) => {
+                let type_params = classes.type_params(&c.name.name);
                 for decl in &mut c.declarations {
                     if let Declaration::VarDef(v) = decl {
-                        check_var_def(v, &mut errors, &classes);
+                        if v.is_final {
+                            let msg = error_final_local(&v.var.identifier.name);
+                            v.var.identifier.add_error(&mut errors, msg);
+                        }
+                        classes.check_annotation(&mut v.var.type_, type_params, &mut errors);
                     }
                 }
             }
@@ -224,12 +302,34 @@ pub fn check(mut ast: Program) -> Program {
         }),
     );
 
+    // The second argument is a class name, checked specially during analysis
+    global_env.insert(
+        "isinstance".to_owned(),
+        LocalSlot::Func(FuncType {
+            parameters: vec![TYPE_OBJECT.clone(), TYPE_OBJECT.clone()],
+            return_type: TYPE_BOOL.clone(),
+        }),
+    );
+
+    // `next` was a valid identifier before generators, so a global declaration
+    // replaces the built-in one. The marker tells whether it is still in effect.
+    // The return type is the type argument of the iterator, set during analysis.
+    if !id_set.contains("next") {
+        let next = FuncType {
+            parameters: vec![TYPE_OBJECT.clone()],
+            return_type: TYPE_OBJECT.clone(),
+        };
+        global_env.insert("next".to_owned(), LocalSlot::Func(next.clone()));
+        global_env.insert(BUILTIN_NEXT.to_owned(), LocalSlot::Func(next));
+    }
+
     global_env.insert(
         "int".to_owned(),
         LocalSlot::Func(FuncType {
             parameters: vec![],
             return_type: ValueType::ClassValueType(ClassValueType {
                 class_name: "int".to_owned(),
+                type_args: vec![],
             }),
         }),
     );
@@ -240,6 +340,7 @@ pub fn check(mut ast: Program) -> Program {
             parameters: vec![],
             return_type: ValueType::ClassValueType(ClassValueType {
                 class_name: "bool".to_owned(),
+                type_args: vec![],
             }),
         }),
     );
@@ -250,6 +351,19 @@ pub fn check(mut ast: Program) -> Program {
             parameters: vec![],
             return_type: ValueType::ClassValueType(ClassValueType {
                 class_name: "str".to_owned(),
+                type_args: vec![],
+            }),
+        }),
+    );
+
+    // `set[T]()` creates an empty set
+    global_env.insert(
+        "set".to_owned(),
+        LocalSlot::Func(FuncType {
+            parameters: vec![],
+            return_type: ValueType::ClassValueType(ClassValueType {
+                class_name: "set".to_owned(),
+                type_args: vec![],
             }),
         }),
     );
@@ -260,6 +374,7 @@ pub fn check(mut ast: Program) -> Program {
             parameters: vec![],
             return_type: ValueType::ClassValueType(ClassValueType {
                 class_name: "object".to_owned(),
+                type_args: vec![],
             }),
         }),
     );
@@ -270,7 +385,7 @@ pub fn check(mut ast: Program) -> Program {
     for decl in &mut ast.declarations {
         match decl {
             Declaration::FuncDef(f) => {
-                check_func(f, &mut errors, &classes, &globals, &HashSet::new());
+                check_func(f, &mut errors, &classes, &globals, &HashSet::new(), &[]);
                 global_env.insert(
                     f.name.name.clone(),
                     LocalSlot::Func(FuncType {
@@ -284,9 +399,11 @@ pub fn check(mut ast: Program) -> Program {
                 );
             }
             Declaration::ClassDef(c) => {
+                let type_params = classes.type_params(&c.name.name);
                 for decl in &mut c.declarations {
                     if let Declaration::FuncDef(f) = decl {
-                        check_func(f, &mut errors, &classes, &globals, &HashSet::new())
+                        let nonlocals = HashSet::new();
+                        check_func(f, &mut errors, &classes, &globals, &nonlocals, type_params)
                     }
                 }
                 let name = &c.name.name;
@@ -296,6 +413,7 @@ pub fn check(mut ast: Program) -> Program {
                         parameters: vec![],
                         return_type: ValueType::ClassValueType(ClassValueType {
                             class_name: name.clone(),
+                            type_args: vec![],
                         }),
                     }),
                 );
@@ -314,9 +432,16 @@ pub fn check(mut ast: Program) -> Program {
     // Pass D
     // semantic rules: 8, 10
     // and type checking
+    let mut resolutions = vec![];
     if errors.is_empty() {
         let mut env = LocalEnv::new(global_env);
+        env.set_constants(constants);
+        for declaration in &ast.declarations {
+            let name = declaration.name();
+            env.declare(name.name.clone(), name.base.location);
+        }
         ast.analyze(&mut errors, &mut env, &classes);
+        resolutions = env.take_resolutions();
     }
 
     ast.errors = Errors {
@@ -324,48 +449,132 @@ pub fn check(mut ast: Program) -> Program {
         errors,
     };
     ast.errors.sort();
-    ast
+    (ast, resolutions, classes)
+}
+
+// Find the first expression without a type in the declarations and statements
+fn find_untyped(declarations: &[Declaration], statements: &[Stmt]) -> Option<Location> {
+    for declaration in declarations {
+        let untyped = match declaration {
+            Declaration::VarDef(v) if v.value.inferred_type.is_none() => {
+                Some(v.value.base().location)
+            }
+            Declaration::FuncDef(f) => find_untyped(&f.declarations, &f.statements),
+            Declaration::ClassDef(c) => find_untyped(&c.declarations, &[]),
+            _ => None,
+        };
+        if untyped.is_some() {
+            return untyped;
+        }
+    }
+    let mut exprs = vec![];
+    statement_exprs(statements, &mut exprs);
+    // The class of `isinstance` and the stream of `file=` are names rather
+    // than expressions, and have no type
+    let mut names: HashSet<*const Expr> = HashSet::new();
+    let mut untyped = None;
+    for expr in exprs {
+        for_each_subexpr(expr, &mut |e| {
+            if let ExprContent::CallExpr(call) = &e.content {
+                if call.function.name == "isinstance" && call.args.len() == 2 {
+                    names.insert(&call.args[1]);
+                }
+                for keyword in call.keywords.iter().filter(|k| k.name.name == "file") {
+                    names.insert(&keyword.value);
+                }
+            }
+            if untyped.is_none() && e.inferred_type.is_none() && !names.contains(&(e as *const _)) {
+                untyped = Some(e.base().location);
+            }
+        });
+    }
+    untyped
+}
+
+// Check that a typed AST read from JSON with `--no-recheck` has a type on
+// every expression, which code generation relies on. Only the first missing
+// one is reported. Undeclared names and inconsistent types are left to `check`.
+pub fn validate_typed(ast: &Program) -> Vec<CompilerError> {
+    match find_untyped(&ast.declarations, &ast.statements) {
+        Some(location) => {
+            let message = error_missing_type();
+            vec![CompilerError {
+                base: NodeBase::from_location(location),
+                message: message.text,
+                syntax: false,
+                severity: Severity::Error,
+                flag: None,
+                code: Some(message.code.id.to_owned()),
+            }]
+        }
+        None => vec![],
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::{Write, stdout};
+    use crate::common::test_files::check_files;
+
     #[test]
     fn sample() {
-        let mut passed = true;
-        let test_dirs = ["test/pa2", "test/original/pa2"];
-        for dir in &test_dirs {
-            println!("Testing Directory {}", dir);
-            let mut files = std::fs::read_dir(dir)
-                .unwrap()
-                .map(|f| f.unwrap())
-                .filter(|f| f.file_name().to_str().unwrap().ends_with(".ast"))
-                .map(|f| f.path())
-                .collect::<Vec<_>>();
-            files.sort();
-
-            for ast_file in files {
-                let mut typed_file = ast_file.clone();
-                let mut file_name = ast_file.file_name().unwrap().to_owned();
-                print!("Testing {} ---- ", file_name.to_str().unwrap());
-                stdout().flush().unwrap();
-                file_name.push(".typed");
-                typed_file.set_file_name(file_name);
-                let ast_string = String::from_utf8(std::fs::read(ast_file).unwrap()).unwrap();
-                let typed_string = String::from_utf8(std::fs::read(typed_file).unwrap()).unwrap();
-                let ast = serde_json::from_str::<Program>(&ast_string).unwrap();
-                let mut typed = serde_json::from_str::<Program>(&typed_string).unwrap();
-                let result = check(ast);
-                typed.errors.sort();
-                if result == typed {
-                    println!("\x1b[32mOK\x1b[0m");
-                } else {
-                    println!("\x1b[31mError\x1b[0m");
-                    passed = false;
-                }
-            }
+        for dir in ["pa2", "original/pa2"] {
+            check_files(dir, ".ast", ".ast.typed", |ast, _| check(ast, false));
         }
-        assert!(passed);
+    }
+
+    // Notes are not part of the typed AST, so the suggestions are checked here
+    #[test]
+    fn suggestions() {
+        let ast_file = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../test/pa2/bad_suggest.py.ast");
+        let ast_string = std::fs::read_to_string(ast_file).unwrap();
+        let ast = serde_json::from_str::<Program>(&ast_string).unwrap();
+        let notes: Vec<_> = check(ast, false)
+            .errors
+            .errors
+            .into_iter()
+            .filter(|error| error.severity == Severity::Note)
+            .map(|note| (note.base.location.start.row, note.base.location.start.col, note.message))
+            .collect();
+        let expected = [
+            (12, 7, "Did you mean `length`?"),
+            (13, 7, "Did you mean `grow`?"),
+            (14, 7, "Did you mean `total`?"),
+            (16, 7, "Did you mean `double`?"),
+            (17, 5, "Did you mean `total`?"),
+        ];
+        let expected: Vec<_> = expected.map(|(row, col, m)| (row, col, m.to_owned())).into();
+        assert_eq!(notes, expected);
+    }
+
+    #[test]
+    fn warnings() {
+        let mut config = WarningConfig::new();
+        config.apply("all").unwrap();
+        check_files("warnings", ".ast", ".ast.typed", |ast, _| {
+            let mut result = check(ast, false);
+            assert!(result.errors.errors.is_empty());
+            result.errors.errors = super::warnings(&result, &config);
+            result.errors.sort();
+            serde_json::to_value(result).unwrap()
+        });
+    }
+
+    // The programs in test/strict are checked with `--strict-none`, and all of
+    // them are valid without it
+    #[test]
+    fn strict_none() {
+        check_files("strict", ".ast", ".ast.typed", |ast, path| {
+            assert!(check(ast.clone(), false).errors.errors.is_empty(), "{}", path.display());
+            serde_json::to_value(check(ast, true)).unwrap()
+        });
+    }
+
+    #[test]
+    fn symbols() {
+        check_files("symbols", ".ast", ".symbols", |ast, _| {
+            serde_json::to_value(analyze(ast, false).symbols).unwrap()
+        });
     }
 }
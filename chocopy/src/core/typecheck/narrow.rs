@@ -0,0 +1,162 @@
+// Flow-sensitive narrowing of local variable types from branch conditions
+
+use super::analyze::TypeLocalEnv;
+use super::class_env::*;
+use crate::common::local_env::*;
+use crate::common::node::*;
+use std::collections::HashSet;
+
+enum Fact {
+    IsInstance(String, String), // variable, class name
+    NotNone(String),
+}
+
+// Whether `call` is the built-in `isinstance` rather than a user function shadowing it
+pub fn is_builtin_isinstance(call: &CallExpr, o: &TypeLocalEnv) -> bool {
+    call.function.name == "isinstance" && !o.is_shadowed("isinstance")
+}
+
+// Collect facts that hold when `condition` evaluates to `positive`
+fn collect_facts(condition: &Expr, positive: bool, o: &TypeLocalEnv, facts: &mut Vec<Fact>) {
+    match &condition.content {
+        ExprContent::UnaryExpr(e) if e.operator == UnaryOp::Not => {
+            collect_facts(&e.operand, !positive, o, facts)
+        }
+        ExprContent::BinaryExpr(e)
+            if (e.operator == BinaryOp::And && positive)
+                || (e.operator == BinaryOp::Or && !positive) =>
+        {
+            collect_facts(&e.left, positive, o, facts);
+            collect_facts(&e.right, positive, o, facts);
+        }
+        ExprContent::BinaryExpr(e) if e.operator == BinaryOp::Is && !positive => {
+            match (&e.left.content, &e.right.content) {
+                (ExprContent::Variable(v), ExprContent::NoneLiteral(_))
+                | (ExprContent::NoneLiteral(_), ExprContent::Variable(v)) => {
+                    facts.push(Fact::NotNone(v.name.clone()))
+                }
+                _ => (),
+            }
+        }
+        ExprContent::CallExpr(e) if positive && is_builtin_isinstance(e, o) => {
+            if let [
+                Expr {
+                    content: ExprContent::Variable(v),
+                    ..
+                },
+                Expr {
+                    content: ExprContent::Variable(c),
+                    ..
+                },
+            ] = &e.args[..]
+            {
+                facts.push(Fact::IsInstance(v.name.clone(), c.name.clone()));
+            }
+        }
+        _ => (),
+    }
+}
+
+// Collect variables that may be assigned by the statements
+pub fn assigned_names(statements: &[Stmt], names: &mut HashSet<String>) {
+    for statement in statements {
+        match statement {
+            Stmt::AssignStmt(s) => {
+                for target in &s.targets {
+                    if let ExprContent::Variable(v) = &target.content {
+                        names.insert(v.name.clone());
+                    }
+                }
+            }
+            Stmt::ForStmt(s) => {
+                names.insert(s.identifier.name.clone());
+                assigned_names(&s.body, names);
+                assigned_names(s.else_body(), names);
+            }
+            Stmt::IfStmt(s) => {
+                assigned_names(&s.then_body, names);
+                assigned_names(&s.else_body, names);
+            }
+            Stmt::WhileStmt(s) => {
+                assigned_names(&s.body, names);
+                assigned_names(s.else_body(), names);
+            }
+            Stmt::DelStmt(s) => {
+                for target in &s.targets {
+                    if let ExprContent::Variable(v) = &target.content {
+                        names.insert(v.name.clone());
+                    }
+                }
+            }
+            Stmt::ExprStmt(_) | Stmt::ReturnStmt(_) | Stmt::YieldStmt(_) | Stmt::BreakStmt(_) => ()
+        }
+    }
+}
+
+// Collect variables declared `nonlocal` by nested functions, at any depth
+pub fn captured_names(declarations: &[Declaration], names: &mut HashSet<String>) {
+    for declaration in declarations {
+        match declaration {
+            Declaration::NonLocalDecl(v) => {
+                names.insert(v.variable.name.clone());
+            }
+            Declaration::FuncDef(f) => captured_names(&f.declarations, names),
+            _ => (),
+        }
+    }
+}
+
+// Compute the refined types of local variables in a region guarded by `condition`
+// evaluating to `positive`. The refinements must be reset where the variables
+// are reassigned.
+pub fn narrow(
+    condition: &Expr,
+    positive: bool,
+    o: &TypeLocalEnv,
+    m: &ClassEnv,
+) -> Vec<(String, ValueType)> {
+    let mut facts = vec![];
+    collect_facts(condition, positive, o, &mut facts);
+
+    let mut refinements = vec![];
+    for fact in facts {
+        let name = match &fact {
+            Fact::IsInstance(name, _) | Fact::NotNone(name) => name,
+        };
+        if !o.is_refinable(name) {
+            continue;
+        }
+        let current = if let Some(EnvSlot::Var(t, _)) = o.get(name) {
+            t
+        } else {
+            continue;
+        };
+        match fact {
+            Fact::IsInstance(name, class_name) => {
+                // Only narrow to a strict subtype. Plain types are stored unboxed and
+                // cannot be reinterpreted from an object reference. The type arguments
+                // of a generic class are unknown.
+                if !m.contains(&class_name) || !m.type_params(&class_name).is_empty() {
+                    continue;
+                }
+                let class = ValueType::ClassValueType(ClassValueType {
+                    class_name,
+                    type_args: vec![],
+                });
+                if class != *current
+                    && class != *TYPE_INT
+                    && class != *TYPE_BOOL
+                    && m.is_compatible(&class, current)
+                {
+                    refinements.push((name, class));
+                }
+            }
+            Fact::NotNone(name) => {
+                if let ValueType::OptionalValueType(OptionalValueType { element_type }) = current {
+                    refinements.push((name, (**element_type).clone()));
+                }
+            }
+        }
+    }
+    refinements
+}
@@ -0,0 +1,483 @@
+// Dataflow analysis of variables that may be `None`: a member access, method
+// call or index on such a variable is reported, with notes explaining the path
+// along which it may be `None`. Only variables of the analyzed scope that no
+// other function can assign are tracked.
+
+use super::error::*;
+use super::narrow::captured_names;
+use crate::common::location::Location;
+use crate::common::node::*;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+
+// The notes explaining how a variable may be `None`, in program order
+type Path = Vec<(Location, String)>;
+
+#[derive(Clone)]
+struct State {
+    // Nothing after a return is reachable
+    reachable: bool,
+    // Variables that may be `None`, with how they became `None`
+    none: HashMap<String, Path>,
+}
+
+impl State {
+    fn unreachable() -> State {
+        State {
+            reachable: false,
+            none: HashMap::new(),
+        }
+    }
+
+    // Merge the state of another path into this program point
+    fn join(&mut self, other: State) {
+        if !other.reachable {
+            return;
+        }
+        if !self.reachable {
+            *self = other;
+            return;
+        }
+        for (name, path) in other.none {
+            self.none.entry(name).or_insert(path);
+        }
+    }
+
+    // Whether merging `other` would make more variables possibly `None`
+    fn grows(&self, other: &State) -> bool {
+        other.reachable
+            && (!self.reachable || other.none.keys().any(|name| !self.none.contains_key(name)))
+    }
+}
+
+fn extend(mut path: Path, location: Location, note: String) -> Path {
+    if !path.iter().any(|(l, n)| *l == location && *n == note) {
+        path.push((location, note));
+    }
+    path
+}
+
+// A dereference of a variable that may be `None`
+pub struct PossiblyNone {
+    pub location: Location,
+    pub name: String,
+    pub notes: Path,
+}
+
+struct Analyzer<'a> {
+    tracked: &'a HashSet<String>,
+    // Dereferences are only reported once a loop body has reached its fixpoint
+    report: bool,
+    found: Vec<PossiblyNone>,
+    // States at the `break` statements of each enclosing loop
+    breaks: Vec<State>,
+}
+
+impl Analyzer<'_> {
+    // A variable that was dereferenced is not `None` afterwards, or the
+    // program would have stopped
+    fn deref(&mut self, object: &Expr, state: &mut State) {
+        if let ExprContent::Variable(v) = &object.content
+            && let Some(notes) = state.none.remove(&v.name)
+            && self.report
+        {
+            self.found.push(PossiblyNone {
+                location: v.base.location,
+                name: v.name.clone(),
+                notes,
+            });
+        }
+    }
+
+    // Refine the state with what holds when `condition` evaluates to `positive`
+    fn narrow(&self, condition: &Expr, positive: bool, state: &mut State) {
+        match &condition.content {
+            ExprContent::UnaryExpr(e) if e.operator == UnaryOp::Not => {
+                self.narrow(&e.operand, !positive, state)
+            }
+            ExprContent::BinaryExpr(e)
+                if (e.operator == BinaryOp::And && positive)
+                    || (e.operator == BinaryOp::Or && !positive) =>
+            {
+                self.narrow(&e.left, positive, state);
+                self.narrow(&e.right, positive, state);
+            }
+            ExprContent::BinaryExpr(e) if e.operator == BinaryOp::Is => {
+                let v = match (&e.left.content, &e.right.content) {
+                    (ExprContent::Variable(v), ExprContent::NoneLiteral(_))
+                    | (ExprContent::NoneLiteral(_), ExprContent::Variable(v)) => v,
+                    _ => return,
+                };
+                if !self.tracked.contains(&v.name) || !state.reachable {
+                    return;
+                }
+                if positive {
+                    let note = note_none_tested(&v.name);
+                    state.none.insert(v.name.clone(), vec![(e.base.location, note)]);
+                } else {
+                    state.none.remove(&v.name);
+                }
+            }
+            _ => (),
+        }
+    }
+
+    // How assigning `value` to `name` may make it `None`
+    fn value(&self, name: &str, value: &Expr, state: &State) -> Option<Path> {
+        let location = value.base().location;
+        match &value.content {
+            ExprContent::Variable(v) => {
+                let path = state.none.get(&v.name)?.clone();
+                Some(extend(path, location, note_none_copied(name, &v.name)))
+            }
+            ExprContent::IfExpr(e) => {
+                let mut then_state = state.clone();
+                let mut else_state = state.clone();
+                self.narrow(&e.condition, true, &mut then_state);
+                self.narrow(&e.condition, false, &mut else_state);
+                self.value(name, &e.then_expr, &then_state)
+                    .or_else(|| self.value(name, &e.else_expr, &else_state))
+            }
+            _ if matches!(value.content, ExprContent::NoneLiteral(_))
+                || value.inferred_type.as_ref() == Some(&*TYPE_NONE) =>
+            {
+                Some(vec![(location, note_none_assigned(name))])
+            }
+            _ => None,
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr, state: &mut State) {
+        match &expr.content {
+            ExprContent::MemberExpr(e) => {
+                self.expr(&e.object, state);
+                self.deref(&e.object, state);
+            }
+            ExprContent::MethodCallExpr(e) => {
+                self.expr(&e.method.object, state);
+                self.deref(&e.method.object, state);
+                for arg in &e.args {
+                    self.expr(arg, state);
+                }
+            }
+            ExprContent::IndexExpr(e) => {
+                self.expr(&e.list, state);
+                self.deref(&e.list, state);
+                self.expr(&e.index, state);
+            }
+            // The right operand only runs when the left one does not decide the result
+            ExprContent::BinaryExpr(e) if matches!(e.operator, BinaryOp::And | BinaryOp::Or) => {
+                self.expr(&e.left, state);
+                let mut right = state.clone();
+                self.narrow(&e.left, e.operator == BinaryOp::And, &mut right);
+                self.expr(&e.right, &mut right);
+            }
+            ExprContent::BinaryExpr(e) => {
+                self.expr(&e.left, state);
+                self.expr(&e.right, state);
+            }
+            ExprContent::UnaryExpr(e) => self.expr(&e.operand, state),
+            ExprContent::IfExpr(e) => {
+                self.expr(&e.condition, state);
+                let mut else_state = state.clone();
+                self.narrow(&e.condition, true, state);
+                self.narrow(&e.condition, false, &mut else_state);
+                self.expr(&e.then_expr, state);
+                self.expr(&e.else_expr, &mut else_state);
+                state.join(else_state);
+            }
+            ExprContent::CallExpr(e) => {
+                for arg in &e.args {
+                    self.expr(arg, state);
+                }
+                for keyword in &e.keywords {
+                    self.expr(&keyword.value, state);
+                }
+            }
+            ExprContent::SetExpr(SetExpr { elements, .. })
+            | ExprContent::ListExpr(ListExpr { elements, .. }) => {
+                for element in elements {
+                    self.expr(element, state);
+                }
+            }
+            ExprContent::SliceExpr(e) => {
+                self.expr(&e.list, state);
+                for bound in e.start.iter().chain(&e.end) {
+                    self.expr(bound, state);
+                }
+            }
+            ExprContent::ListComprehension(e) => {
+                // The first iterable is evaluated outside of the comprehension
+                // scope, where the clause variables shadow tracked ones
+                self.expr(&e.clauses[0].iterable, state);
+                let mut inner = state.clone();
+                for clause in &e.clauses {
+                    inner.none.remove(&clause.identifier.name);
+                }
+                for (i, clause) in e.clauses.iter().enumerate() {
+                    if i > 0 {
+                        self.expr(&clause.iterable, &mut inner);
+                    }
+                    for condition in &clause.conditions {
+                        self.expr(condition, &mut inner);
+                        self.narrow(condition, true, &mut inner);
+                    }
+                }
+                self.expr(&e.element, &mut inner);
+            }
+            ExprContent::Variable(_)
+            | ExprContent::IntegerLiteral(_)
+            | ExprContent::BooleanLiteral(_)
+            | ExprContent::NoneLiteral(_)
+            | ExprContent::StringLiteral(_) => (),
+        }
+    }
+
+    fn stmts(&mut self, statements: &[Stmt], state: &mut State) {
+        for statement in statements {
+            match statement {
+                Stmt::ExprStmt(s) => self.expr(&s.expr, state),
+                Stmt::AssignStmt(s) => {
+                    self.expr(&s.value, state);
+                    for target in &s.targets {
+                        if !matches!(target.content, ExprContent::Variable(_)) {
+                            self.expr(target, state);
+                        }
+                    }
+                    for target in &s.targets {
+                        if let ExprContent::Variable(v) = &target.content
+                            && self.tracked.contains(&v.name)
+                        {
+                            match self.value(&v.name, &s.value, state) {
+                                Some(path) if state.reachable => {
+                                    state.none.insert(v.name.clone(), path);
+                                }
+                                _ => {
+                                    state.none.remove(&v.name);
+                                }
+                            }
+                        }
+                    }
+                }
+                Stmt::IfStmt(s) => {
+                    self.expr(&s.condition, state);
+                    let mut else_state = state.clone();
+                    self.narrow(&s.condition, true, state);
+                    self.narrow(&s.condition, false, &mut else_state);
+                    self.stmts(&s.then_body, state);
+                    self.stmts(&s.else_body, &mut else_state);
+                    self.join_branches(&s.condition, state, else_state);
+                }
+                Stmt::WhileStmt(s) => {
+                    self.breaks.push(State::unreachable());
+                    self.while_loop(s, state);
+                    let breaks = self.breaks.pop().unwrap();
+                    if let Some(else_body) = &s.else_body {
+                        self.stmts(else_body, state);
+                    }
+                    state.join(breaks);
+                }
+                Stmt::ForStmt(s) => {
+                    self.expr(&s.iterable, state);
+                    self.breaks.push(State::unreachable());
+                    self.for_loop(s, state);
+                    let breaks = self.breaks.pop().unwrap();
+                    if let Some(else_body) = &s.else_body {
+                        self.stmts(else_body, state);
+                    }
+                    state.join(breaks);
+                }
+                Stmt::BreakStmt(_) => {
+                    let exit = std::mem::replace(state, State::unreachable());
+                    self.breaks.last_mut().unwrap().join(exit);
+                }
+                Stmt::ReturnStmt(s) => {
+                    if let Some(value) = &s.value {
+                        self.expr(value, state);
+                    }
+                    *state = State::unreachable();
+                }
+                Stmt::YieldStmt(s) => {
+                    if let Some(value) = &s.value {
+                        self.expr(value, state);
+                    }
+                }
+                Stmt::DelStmt(s) => {
+                    for target in &s.targets {
+                        if let ExprContent::Variable(v) = &target.content {
+                            state.none.remove(&v.name);
+                        } else {
+                            self.expr(target, state);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Merge the states after the branches of an `if`, noting which branch
+    // leaves a variable `None` when only one of them does
+    fn join_branches(&self, condition: &Expr, state: &mut State, else_state: State) {
+        if !state.reachable || !else_state.reachable {
+            state.join(else_state);
+            return;
+        }
+        let location = condition.base().location;
+        for (name, path) in state.none.iter_mut() {
+            if !else_state.none.contains_key(name) {
+                branch_note(path, location, name, true);
+            }
+        }
+        for (name, mut path) in else_state.none {
+            if let Entry::Vacant(entry) = state.none.entry(name) {
+                branch_note(&mut path, location, entry.key(), false);
+                entry.insert(path);
+            }
+        }
+    }
+
+    // Analyze a loop, which may run any number of times, until the state at its
+    // head stops growing, then once more to report. `body` runs one iteration
+    // from the head state and returns the state when the loop exits instead.
+    // Returns the states at the exit and at the end of the body.
+    fn fixpoint(
+        &mut self,
+        state: &State,
+        body: &mut dyn FnMut(&mut Self, &mut State) -> State,
+    ) -> (State, State) {
+        let mut head = state.clone();
+        let report = self.report;
+        self.report = false;
+        loop {
+            let mut body_state = head.clone();
+            body(self, &mut body_state);
+            if !head.grows(&body_state) {
+                break;
+            }
+            head.join(body_state);
+        }
+        self.report = report;
+
+        let exit = body(self, &mut head);
+        (exit, head)
+    }
+
+    fn while_loop(&mut self, s: &WhileStmt, state: &mut State) {
+        let (mut exit, end) = self.fixpoint(state, &mut |this, state| {
+            this.expr(&s.condition, state);
+            let mut exit = state.clone();
+            this.narrow(&s.condition, false, &mut exit);
+            this.narrow(&s.condition, true, state);
+            this.stmts(&s.body, state);
+            exit
+        });
+        note_skipped(s.condition.base().location, &mut exit, &end);
+        *state = exit;
+    }
+
+    fn for_loop(&mut self, s: &ForStmt, state: &mut State) {
+        let (mut exit, end) = self.fixpoint(state, &mut |this, state| {
+            let exit = state.clone();
+            state.none.remove(&s.identifier.name);
+            this.stmts(&s.body, state);
+            exit
+        });
+        note_skipped(s.iterable.base().location, &mut exit, &end);
+        *state = exit;
+    }
+}
+
+// Note the branch of a condition that leaves a variable `None`, unless the
+// condition itself is what tested the variable
+fn branch_note(path: &mut Path, location: Location, name: &str, positive: bool) {
+    let tested = path
+        .last()
+        .is_some_and(|(l, _)| l.start >= location.start && l.end <= location.end);
+    if !tested {
+        *path = extend(std::mem::take(path), location, note_none_branch(name, positive));
+    }
+}
+
+// Variables that are only `None` after a loop because its body may not run get
+// a note at `location`
+fn note_skipped(location: Location, exit: &mut State, end: &State) {
+    for (name, path) in exit.none.iter_mut() {
+        if !end.none.contains_key(name) {
+            *path = extend(std::mem::take(path), location, note_none_loop(name));
+        }
+    }
+}
+
+fn analyze(
+    tracked: &HashSet<String>,
+    declarations: &[Declaration],
+    statements: &[Stmt],
+) -> Vec<PossiblyNone> {
+    let mut state = State {
+        reachable: true,
+        none: HashMap::new(),
+    };
+    for declaration in declarations {
+        if let Declaration::VarDef(v) = declaration
+            && let LiteralContent::NoneLiteral(_) = &v.value.content
+            && tracked.contains(&v.var.identifier.name)
+        {
+            let name = &v.var.identifier.name;
+            let note = note_none_initialized(name);
+            state.none.insert(name.clone(), vec![(v.value.base().location, note)]);
+        }
+    }
+    let mut analyzer = Analyzer {
+        tracked,
+        report: true,
+        found: vec![],
+        breaks: vec![],
+    };
+    analyzer.stmts(statements, &mut state);
+    analyzer.found
+}
+
+// Find dereferences of parameters and local variables that may be `None` in a
+// function body. Variables that nested functions assign are not tracked.
+pub fn check_function(f: &FuncDef) -> Vec<PossiblyNone> {
+    let mut captured = HashSet::new();
+    captured_names(&f.declarations, &mut captured);
+    let mut tracked: HashSet<String> = f.params.iter().map(|p| p.identifier.name.clone()).collect();
+    for declaration in &f.declarations {
+        if let Declaration::VarDef(v) = declaration {
+            tracked.insert(v.var.identifier.name.clone());
+        }
+    }
+    tracked.retain(|name| !captured.contains(name));
+    analyze(&tracked, &f.declarations, &f.statements)
+}
+
+// Collect the names declared `global` by functions, at any depth
+fn global_names(declarations: &[Declaration], names: &mut HashSet<String>) {
+    for declaration in declarations {
+        match declaration {
+            Declaration::GlobalDecl(v) => {
+                names.insert(v.variable.name.clone());
+            }
+            Declaration::FuncDef(f) => global_names(&f.declarations, names),
+            Declaration::ClassDef(c) => global_names(&c.declarations, names),
+            _ => (),
+        }
+    }
+}
+
+// Find dereferences of global variables that may be `None` in the statements of
+// a program. Variables that functions assign are not tracked.
+pub fn check_program(ast: &Program) -> Vec<PossiblyNone> {
+    let mut assigned = HashSet::new();
+    global_names(&ast.declarations, &mut assigned);
+    let mut tracked = HashSet::new();
+    for declaration in &ast.declarations {
+        if let Declaration::VarDef(v) = declaration
+            && !assigned.contains(&v.var.identifier.name)
+        {
+            tracked.insert(v.var.identifier.name.clone());
+        }
+    }
+    analyze(&tracked, &ast.declarations, &ast.statements)
+}
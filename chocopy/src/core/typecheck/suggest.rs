@@ -0,0 +1,61 @@
+// "Did you mean" suggestions for misspelled names
+
+// Optimal string alignment distance: the number of insertions, deletions,
+// substitutions and transpositions of adjacent characters between the strings
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // Rows i - 2, i - 1 and i of the distance matrix
+    let mut previous2 = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = (a[i - 1] != b[j - 1]) as usize;
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(previous2[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut previous2, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+// The candidate closest to the name, if it is close enough to be a likely typo:
+// one edit for every three characters. Names shorter than that get no
+// suggestion, as any other short name would be as close. Ties are broken
+// alphabetically, so that suggestions are deterministic.
+pub fn suggest<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = name.chars().count() / 3;
+    if limit == 0 {
+        return None;
+    }
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(d, _)| *d <= limit)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggestions() {
+        assert_eq!(distance("lenght", "length"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(suggest("lenght", ["length", "width"]), Some("length"));
+        assert_eq!(suggest("x", ["y", "z"]), None);
+        assert_eq!(suggest("fo", ["foo"]), None);
+        assert_eq!(suggest("fooo", ["foo", "x"]), Some("foo"));
+        assert_eq!(suggest("count", ["total", "size"]), None);
+    }
+}
@@ -0,0 +1,332 @@
+// The symbol table of a program: every declaration with its type and enclosing
+// scope, and every use of a name with the declaration it resolves to. Uses in
+// expressions are resolved during type checking, and class names in type
+// annotations here.
+
+use super::warning::{for_each_subexpr, statement_exprs};
+use crate::common::local_env::Resolution;
+use crate::common::location::Location;
+use crate::common::node::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum SymbolKind {
+    Global,
+    Class,
+    TypeParameter,
+    Attribute,
+    Method,
+    Function,
+    Parameter,
+    Local,
+    // `global` and `nonlocal` declarations, which alias a variable of another scope
+    GlobalAlias,
+    NonlocalAlias,
+    ComprehensionVariable,
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[serde(untagged)]
+pub enum SymbolType {
+    Value(ValueType),
+    Func(FuncType),
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct Symbol {
+    pub id: usize,
+    pub name: String,
+    pub kind: SymbolKind,
+    pub location: Location,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub type_: Option<SymbolType>,
+    // The function, method or class declaring the symbol, or `None` at the top level
+    pub scope: Option<usize>,
+    // The variable a `global` or `nonlocal` declaration refers to
+    #[serde(rename = "aliasOf", skip_serializing_if = "Option::is_none")]
+    pub alias_of: Option<usize>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct Reference {
+    pub name: String,
+    pub location: Location,
+    // `None` for built-in names
+    pub declaration: Option<usize>,
+}
+
+#[derive(Serialize, Clone, PartialEq, Eq, Debug, Default)]
+pub struct SymbolTable {
+    pub declarations: Vec<Symbol>,
+    pub references: Vec<Reference>,
+}
+
+fn func_type(f: &FuncDef) -> SymbolType {
+    SymbolType::Func(FuncType {
+        parameters: f.params.iter().map(|p| ValueType::from_annotation(&p.type_)).collect(),
+        return_type: ValueType::from_annotation(&f.return_type),
+    })
+}
+
+fn var_type(annotation: &TypeAnnotation) -> Option<SymbolType> {
+    Some(SymbolType::Value(ValueType::from_annotation(annotation)))
+}
+
+struct Builder {
+    table: SymbolTable,
+    // Names visible in each enclosing function, outermost (global) first
+    frames: Vec<HashMap<String, usize>>,
+    // Class names, and the type parameters of the class being visited
+    classes: HashMap<String, usize>,
+    type_params: HashMap<String, usize>,
+}
+
+impl Builder {
+    fn add(
+        &mut self,
+        name: &Identifier,
+        kind: SymbolKind,
+        type_: Option<SymbolType>,
+        scope: Option<usize>,
+    ) -> usize {
+        let id = self.table.declarations.len();
+        self.table.declarations.push(Symbol {
+            id,
+            name: name.name.clone(),
+            kind,
+            location: name.base.location,
+            type_,
+            scope,
+            alias_of: None,
+        });
+        id
+    }
+
+    // The innermost variable of an enclosing function, skipping the current one
+    fn enclosing(&self, name: &str) -> Option<usize> {
+        let frames = &self.frames[1..self.frames.len() - 1];
+        frames.iter().rev().find_map(|frame| frame.get(name).copied())
+    }
+
+    fn annotation(&mut self, annotation: &TypeAnnotation) {
+        match annotation {
+            TypeAnnotation::ClassType(c) => {
+                let declaration = self
+                    .type_params
+                    .get(&c.class_name)
+                    .or_else(|| self.classes.get(&c.class_name))
+                    .copied();
+                self.table.references.push(Reference {
+                    name: c.class_name.clone(),
+                    location: c.base.location,
+                    declaration,
+                });
+                for arg in &c.type_args {
+                    self.annotation(arg);
+                }
+            }
+            TypeAnnotation::ListType(l) => self.annotation(&l.element_type),
+            TypeAnnotation::OptionalType(o) => self.annotation(&o.element_type),
+        }
+    }
+
+    fn comprehensions(&mut self, statements: &[Stmt], scope: Option<usize>) {
+        let mut exprs = vec![];
+        statement_exprs(statements, &mut exprs);
+        let mut variables = vec![];
+        for expr in exprs {
+            for_each_subexpr(expr, &mut |e| {
+                if let ExprContent::ListComprehension(c) = &e.content {
+                    variables.extend(c.clauses.iter().map(|clause| clause.identifier.clone()));
+                }
+            });
+        }
+        for variable in variables {
+            let id = self.table.declarations.len();
+            self.table.declarations.push(Symbol {
+                id,
+                name: variable.name,
+                kind: SymbolKind::ComprehensionVariable,
+                location: variable.base.location,
+                type_: variable.inferred_type.map(SymbolType::Value),
+                scope,
+                alias_of: None,
+            });
+        }
+    }
+
+    fn function(&mut self, f: &FuncDef, kind: SymbolKind, scope: Option<usize>) {
+        let id = self.add(&f.name, kind, Some(func_type(f)), scope);
+        let scope = Some(id);
+        for param in &f.params {
+            self.annotation(&param.type_);
+        }
+        self.annotation(&f.return_type);
+
+        self.frames.push(HashMap::new());
+        for param in &f.params {
+            let param_id = self.add(
+                &param.identifier,
+                SymbolKind::Parameter,
+                var_type(&param.type_),
+                scope,
+            );
+            self.frames.last_mut().unwrap().insert(param.identifier.name.clone(), param_id);
+        }
+        for declaration in &f.declarations {
+            let name = declaration.name();
+            let (kind, type_, alias_of) = match declaration {
+                Declaration::VarDef(v) => {
+                    self.annotation(&v.var.type_);
+                    (SymbolKind::Local, var_type(&v.var.type_), None)
+                }
+                Declaration::FuncDef(_) => continue,
+                Declaration::GlobalDecl(_) => {
+                    let target = self.frames[0].get(&name.name).copied();
+                    (SymbolKind::GlobalAlias, None, target)
+                }
+                Declaration::NonLocalDecl(_) => {
+                    (SymbolKind::NonlocalAlias, None, self.enclosing(&name.name))
+                }
+                Declaration::ClassDef(_) => unreachable!(),
+            };
+            let type_ = type_.or_else(|| {
+                alias_of.and_then(|target| self.table.declarations[target].type_.clone())
+            });
+            let id = self.add(name, kind, type_, scope);
+            self.table.declarations[id].alias_of = alias_of;
+            self.frames.last_mut().unwrap().insert(name.name.clone(), id);
+        }
+        // Nested functions may refer to variables declared after them
+        for declaration in &f.declarations {
+            if let Declaration::FuncDef(g) = declaration {
+                self.function(g, SymbolKind::Function, scope);
+            }
+        }
+        self.comprehensions(&f.statements, scope);
+        self.frames.pop();
+    }
+
+    fn class(&mut self, c: &ClassDef, id: usize) {
+        self.table.references.push(Reference {
+            name: c.super_class.name.clone(),
+            location: c.super_class.base.location,
+            declaration: self.classes.get(&c.super_class.name).copied(),
+        });
+        let scope = Some(id);
+        for param in &c.type_params {
+            let param_id = self.add(param, SymbolKind::TypeParameter, None, scope);
+            self.type_params.insert(param.name.clone(), param_id);
+        }
+        for declaration in &c.declarations {
+            match declaration {
+                Declaration::VarDef(v) => {
+                    self.annotation(&v.var.type_);
+                    let type_ = var_type(&v.var.type_);
+                    self.add(&v.var.identifier, SymbolKind::Attribute, type_, scope);
+                }
+                Declaration::FuncDef(f) => self.function(f, SymbolKind::Method, scope),
+                _ => (),
+            }
+        }
+        self.type_params.clear();
+    }
+}
+
+// Build the symbol table of a type checked program from the names resolved
+// while checking it
+pub fn build(ast: &Program, resolutions: Vec<Resolution>) -> SymbolTable {
+    let mut builder = Builder {
+        table: SymbolTable::default(),
+        frames: vec![HashMap::new()],
+        classes: HashMap::new(),
+        type_params: HashMap::new(),
+    };
+
+    // Global variables and classes first, as functions and classes may refer
+    // to later ones
+    for declaration in &ast.declarations {
+        match declaration {
+            Declaration::VarDef(v) => {
+                let type_ = var_type(&v.var.type_);
+                let id = builder.add(&v.var.identifier, SymbolKind::Global, type_, None);
+                builder.frames[0].insert(v.var.identifier.name.clone(), id);
+            }
+            Declaration::ClassDef(c) => {
+                let type_ = SymbolType::Value(ValueType::ClassValueType(ClassValueType {
+                    class_name: c.name.name.clone(),
+                    type_args: vec![],
+                }));
+                let id = builder.add(&c.name, SymbolKind::Class, Some(type_), None);
+                builder.classes.insert(c.name.name.clone(), id);
+            }
+            _ => (),
+        }
+    }
+    for declaration in &ast.declarations {
+        match declaration {
+            Declaration::VarDef(v) => builder.annotation(&v.var.type_),
+            Declaration::ClassDef(c) => builder.class(c, builder.classes[&c.name.name]),
+            Declaration::FuncDef(f) => builder.function(f, SymbolKind::Function, None),
+            _ => (),
+        }
+    }
+    builder.comprehensions(&ast.statements, None);
+
+    let by_location: HashMap<Location, usize> = builder
+        .table
+        .declarations
+        .iter()
+        .map(|symbol| (symbol.location, symbol.id))
+        .collect();
+    let references = &mut builder.table.references;
+    references.extend(resolutions.into_iter().map(|resolution| Reference {
+        name: resolution.name,
+        location: resolution.location,
+        declaration: resolution
+            .declaration
+            .and_then(|location| by_location.get(&location).copied()),
+    }));
+    // Loop bodies may be checked more than once
+    references.sort_by_key(|reference| reference.location);
+    references.dedup_by_key(|reference| reference.location);
+    builder.table
+}
+
+fn collect_types(
+    declarations: &[Declaration],
+    statements: &[Stmt],
+    types: &mut Vec<(Location, ValueType)>,
+) {
+    for declaration in declarations {
+        match declaration {
+            Declaration::VarDef(v) => {
+                if let Some(t) = &v.value.inferred_type {
+                    types.push((v.value.base().location, t.clone()));
+                }
+            }
+            Declaration::FuncDef(f) => collect_types(&f.declarations, &f.statements, types),
+            Declaration::ClassDef(c) => collect_types(&c.declarations, &[], types),
+            _ => (),
+        }
+    }
+    let mut exprs = vec![];
+    statement_exprs(statements, &mut exprs);
+    for expr in exprs {
+        for_each_subexpr(expr, &mut |e| {
+            if let Some(t) = &e.inferred_type {
+                types.push((e.base().location, t.clone()));
+            }
+        });
+    }
+}
+
+// The inferred types of the expressions of a type checked program, for tools
+// to show
+pub fn expression_types(ast: &Program) -> Vec<(Location, ValueType)> {
+    let mut types = vec![];
+    collect_types(&ast.declarations, &ast.statements, &mut types);
+    types
+}
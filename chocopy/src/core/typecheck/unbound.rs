@@ -0,0 +1,310 @@
+// Definite-assignment analysis for `del`: a deleted local variable is unbound
+// until it is assigned again, and must not be used in between
+
+use super::error::*;
+use crate::common::diagnostic::Message;
+use crate::common::node::*;
+use std::collections::HashSet;
+
+// Names that may be unbound at a program point
+type State = HashSet<String>;
+
+struct Checker<'a> {
+    locals: &'a HashSet<String>,
+    captured: &'a HashSet<String>,
+    errors: &'a mut Vec<CompilerError>,
+    // Errors are only reported once a loop body has reached its fixpoint
+    report: bool,
+    // States at the `break` statements of each enclosing loop
+    breaks: Vec<State>,
+}
+
+impl Checker<'_> {
+    fn error(&mut self, node: &mut impl Node, message: Message) {
+        if self.report && node.base().error_msg.is_none() {
+            node.add_error(self.errors, message);
+        }
+    }
+
+    fn expr(&mut self, expr: &mut Expr, state: &State) {
+        for_each_variable(expr, &mut |v| {
+            if state.contains(&v.name) {
+                self.error(v, error_unbound(&v.name));
+            }
+        });
+    }
+
+    fn stmts(&mut self, statements: &mut [Stmt], state: &mut State) {
+        for statement in statements {
+            match statement {
+                Stmt::ExprStmt(s) => self.expr(&mut s.expr, state),
+                Stmt::AssignStmt(s) => {
+                    self.expr(&mut s.value, state);
+                    for target in &mut s.targets {
+                        if !matches!(target.content, ExprContent::Variable(_)) {
+                            self.expr(target, state);
+                        }
+                    }
+                    for target in &s.targets {
+                        if let ExprContent::Variable(v) = &target.content {
+                            state.remove(&v.name);
+                        }
+                    }
+                }
+                Stmt::IfStmt(s) => {
+                    self.expr(&mut s.condition, state);
+                    let mut else_state = state.clone();
+                    self.stmts(&mut s.then_body, state);
+                    self.stmts(&mut s.else_body, &mut else_state);
+                    state.extend(else_state);
+                }
+                Stmt::WhileStmt(s) => {
+                    self.breaks.push(State::new());
+                    self.fixpoint(None, &mut s.body, state);
+                    self.expr(&mut s.condition, state);
+                    let breaks = self.breaks.pop().unwrap();
+                    if let Some(else_body) = &mut s.else_body {
+                        self.stmts(else_body, state);
+                    }
+                    state.extend(breaks);
+                }
+                Stmt::ForStmt(s) => {
+                    self.expr(&mut s.iterable, state);
+                    self.breaks.push(State::new());
+                    self.fixpoint(Some(&s.identifier.name), &mut s.body, state);
+                    let breaks = self.breaks.pop().unwrap();
+                    if let Some(else_body) = &mut s.else_body {
+                        self.stmts(else_body, state);
+                    }
+                    state.extend(breaks);
+                }
+                Stmt::BreakStmt(_) => {
+                    // The loop exits with the state here, and nothing after is reachable
+                    self.breaks.last_mut().unwrap().extend(state.drain());
+                }
+                Stmt::ReturnStmt(s) => {
+                    if let Some(value) = &mut s.value {
+                        self.expr(value, state);
+                    }
+                    // Nothing after a return is reachable
+                    state.clear();
+                }
+                Stmt::YieldStmt(s) => {
+                    if let Some(value) = &mut s.value {
+                        self.expr(value, state);
+                    }
+                }
+                Stmt::DelStmt(s) => {
+                    for target in &mut s.targets {
+                        if let ExprContent::Variable(v) = &mut target.content {
+                            if !self.locals.contains(&v.name) {
+                                self.error(v, error_del_local(&v.name));
+                            } else if self.captured.contains(&v.name) {
+                                self.error(v, error_del_captured(&v.name));
+                            } else if state.contains(&v.name) {
+                                self.error(v, error_unbound(&v.name));
+                            } else {
+                                state.insert(v.name.clone());
+                            }
+                        } else {
+                            self.expr(target, state);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Analyze a loop body, which may run any number of times. `state` becomes the
+    // state at the loop head, which is also the state after the loop.
+    fn fixpoint(&mut self, target: Option<&str>, body: &mut [Stmt], state: &mut State) {
+        let report = self.report;
+        self.report = false;
+        loop {
+            let mut body_state = state.clone();
+            if let Some(target) = target {
+                body_state.remove(target);
+            }
+            self.stmts(body, &mut body_state);
+            if body_state.is_subset(state) {
+                break;
+            }
+            state.extend(body_state);
+        }
+        self.report = report;
+
+        let mut body_state = state.clone();
+        if let Some(target) = target {
+            body_state.remove(target);
+        }
+        self.stmts(body, &mut body_state);
+    }
+}
+
+// Visit every variable read by the expression
+fn for_each_variable(expr: &mut Expr, f: &mut dyn FnMut(&mut Variable)) {
+    match &mut expr.content {
+        ExprContent::Variable(v) => f(v),
+        ExprContent::BinaryExpr(e) => {
+            for_each_variable(&mut e.left, f);
+            for_each_variable(&mut e.right, f);
+        }
+        ExprContent::UnaryExpr(e) => for_each_variable(&mut e.operand, f),
+        ExprContent::IfExpr(e) => {
+            for_each_variable(&mut e.condition, f);
+            for_each_variable(&mut e.then_expr, f);
+            for_each_variable(&mut e.else_expr, f);
+        }
+        ExprContent::CallExpr(e) => {
+            for arg in &mut e.args {
+                for_each_variable(arg, f);
+            }
+            for keyword in &mut e.keywords {
+                for_each_variable(&mut keyword.value, f);
+            }
+        }
+        ExprContent::MethodCallExpr(e) => {
+            for_each_variable(&mut e.method.object, f);
+            for arg in &mut e.args {
+                for_each_variable(arg, f);
+            }
+        }
+        ExprContent::SetExpr(e) => {
+            for element in &mut e.elements {
+                for_each_variable(element, f);
+            }
+        }
+        ExprContent::MemberExpr(e) => for_each_variable(&mut e.object, f),
+        ExprContent::IndexExpr(e) => {
+            for_each_variable(&mut e.list, f);
+            for_each_variable(&mut e.index, f);
+        }
+        ExprContent::SliceExpr(e) => {
+            for_each_variable(&mut e.list, f);
+            if let Some(start) = &mut e.start {
+                for_each_variable(start, f);
+            }
+            if let Some(end) = &mut e.end {
+                for_each_variable(end, f);
+            }
+        }
+        ExprContent::ListComprehension(e) => {
+            // The first iterable is evaluated outside of the comprehension scope
+            let (first, rest) = e.clauses.split_first_mut().unwrap();
+            for_each_variable(&mut first.iterable, f);
+            let bound: HashSet<String> =
+                std::iter::once(&*first).chain(&*rest).map(|c| c.identifier.name.clone()).collect();
+            let mut inner = |v: &mut Variable| {
+                if !bound.contains(&v.name) {
+                    f(v)
+                }
+            };
+            for condition in &mut first.conditions {
+                for_each_variable(condition, &mut inner);
+            }
+            for clause in rest {
+                for_each_variable(&mut clause.iterable, &mut inner);
+                for condition in &mut clause.conditions {
+                    for_each_variable(condition, &mut inner);
+                }
+            }
+            for_each_variable(&mut e.element, &mut inner);
+        }
+        ExprContent::ListExpr(e) => {
+            for element in &mut e.elements {
+                for_each_variable(element, f);
+            }
+        }
+        ExprContent::IntegerLiteral(_)
+        | ExprContent::BooleanLiteral(_)
+        | ExprContent::NoneLiteral(_)
+        | ExprContent::StringLiteral(_) => (),
+    }
+}
+
+// Collect every name mentioned by the statements
+fn mentioned_names(statements: &mut [Stmt], names: &mut HashSet<String>) {
+    let mut visit = |expr: &mut Expr| {
+        for_each_variable(expr, &mut |v| {
+            names.insert(v.name.clone());
+        })
+    };
+    let mut stack: Vec<&mut Stmt> = statements.iter_mut().collect();
+    while let Some(statement) = stack.pop() {
+        match statement {
+            Stmt::ExprStmt(s) => visit(&mut s.expr),
+            Stmt::AssignStmt(s) => {
+                visit(&mut s.value);
+                s.targets.iter_mut().for_each(&mut visit);
+            }
+            Stmt::IfStmt(s) => {
+                visit(&mut s.condition);
+                stack.extend(s.then_body.iter_mut().chain(s.else_body.iter_mut()));
+            }
+            Stmt::WhileStmt(s) => {
+                visit(&mut s.condition);
+                stack.extend(s.body.iter_mut().chain(s.else_body.iter_mut().flatten()));
+            }
+            Stmt::ForStmt(s) => {
+                visit(&mut s.iterable);
+                stack.extend(s.body.iter_mut().chain(s.else_body.iter_mut().flatten()));
+            }
+            Stmt::ReturnStmt(ReturnStmt { value, .. }) | Stmt::YieldStmt(YieldStmt { value, .. }) => {
+                value.iter_mut().for_each(&mut visit)
+            }
+            Stmt::DelStmt(s) => s.targets.iter_mut().for_each(&mut visit),
+            Stmt::BreakStmt(_) => (),
+        }
+    }
+}
+
+// Collect the names a nested function may refer to in enclosing scopes
+fn free_names(f: &mut FuncDef, names: &mut HashSet<String>) {
+    let mut inner = HashSet::new();
+    mentioned_names(&mut f.statements, &mut inner);
+    let mut bound: HashSet<String> = f.params.iter().map(|p| p.identifier.name.clone()).collect();
+    for declaration in &mut f.declarations {
+        match declaration {
+            Declaration::FuncDef(g) => {
+                free_names(g, &mut inner);
+                bound.insert(g.name.name.clone());
+            }
+            Declaration::NonLocalDecl(v) => {
+                inner.insert(v.variable.name.clone());
+            }
+            Declaration::VarDef(v) => {
+                bound.insert(v.var.identifier.name.clone());
+            }
+            Declaration::GlobalDecl(v) => {
+                bound.insert(v.variable.name.clone());
+            }
+            Declaration::ClassDef(_) => (),
+        }
+    }
+    names.extend(inner.into_iter().filter(|name| !bound.contains(name)));
+}
+
+// Check `del` statements of a function body, where `locals` are the parameters
+// and local variables that can be deleted
+pub fn check_unbound(
+    locals: &HashSet<String>,
+    declarations: &mut [Declaration],
+    statements: &mut [Stmt],
+    errors: &mut Vec<CompilerError>,
+) {
+    let mut captured = HashSet::new();
+    for declaration in declarations {
+        if let Declaration::FuncDef(f) = declaration {
+            free_names(f, &mut captured);
+        }
+    }
+
+    let mut checker = Checker {
+        locals,
+        captured: &captured,
+        errors,
+        report: true,
+        breaks: vec![],
+    };
+    checker.stmts(statements, &mut HashSet::new());
+}
@@ -0,0 +1,450 @@
+// Warnings for valid programs that are likely mistakes. They are reported on
+// the typed AST of a program without errors, and never stop code generation
+// unless `-Werror` is given.
+
+use super::always_return;
+use super::error::*;
+use super::possibly_none::{self, PossiblyNone};
+use crate::common::diagnostic::Message;
+use crate::common::location::Location;
+use crate::common::node::*;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Warning {
+    UnusedVariable,
+    UnusedParameter,
+    UnusedFunction,
+    Unreachable,
+    Shadow,
+    AlwaysTrue,
+    PossiblyNone,
+}
+
+impl Warning {
+    const ALL: [Warning; 7] = [
+        Warning::UnusedVariable,
+        Warning::UnusedParameter,
+        Warning::UnusedFunction,
+        Warning::Unreachable,
+        Warning::Shadow,
+        Warning::AlwaysTrue,
+        Warning::PossiblyNone,
+    ];
+
+    // The name used by `-W` options
+    pub fn name(self) -> &'static str {
+        match self {
+            Warning::UnusedVariable => "unused-variable",
+            Warning::UnusedParameter => "unused-parameter",
+            Warning::UnusedFunction => "unused-function",
+            Warning::Unreachable => "unreachable",
+            Warning::Shadow => "shadow",
+            Warning::AlwaysTrue => "always-true",
+            Warning::PossiblyNone => "possibly-none",
+        }
+    }
+
+    // Methods often ignore parameters required by an overridden method, and
+    // shadowing is an ordinary language feature, so these are opt-in
+    fn enabled_by_default(self) -> bool {
+        !matches!(self, Warning::UnusedParameter | Warning::Shadow)
+    }
+}
+
+pub struct WarningConfig {
+    enabled: HashSet<Warning>,
+    // Report warnings as errors
+    error: bool,
+}
+
+impl Default for WarningConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WarningConfig {
+    pub fn new() -> WarningConfig {
+        WarningConfig {
+            enabled: Warning::ALL
+                .into_iter()
+                .filter(|w| w.enabled_by_default())
+                .collect(),
+            error: false,
+        }
+    }
+
+    // Apply the argument of a `-W` option: `NAME` or `no-NAME` to enable or
+    // disable one warning, `all` or `no-all` for every warning, and `error`
+    // or `no-error` to turn warnings into errors
+    pub fn apply(&mut self, option: &str) -> Result<(), String> {
+        let (enable, name) = match option.strip_prefix("no-") {
+            Some(name) => (false, name),
+            None => (true, option),
+        };
+        let warnings = match name {
+            "error" => {
+                self.error = enable;
+                return Ok(());
+            }
+            "all" => Warning::ALL.to_vec(),
+            _ => match Warning::ALL.into_iter().find(|w| w.name() == name) {
+                Some(warning) => vec![warning],
+                None => return Err(format!("Unknown warning: {}", name)),
+            },
+        };
+        for warning in warnings {
+            if enable {
+                self.enabled.insert(warning);
+            } else {
+                self.enabled.remove(&warning);
+            }
+        }
+        Ok(())
+    }
+}
+
+// Collect the expressions of the statements, including those of nested blocks.
+// Variables assigned to are not collected, as they are not read.
+pub(super) fn statement_exprs<'a>(statements: &'a [Stmt], exprs: &mut Vec<&'a Expr>) {
+    for statement in statements {
+        match statement {
+            Stmt::ExprStmt(s) => exprs.push(&s.expr),
+            Stmt::AssignStmt(s) => {
+                exprs.push(&s.value);
+                exprs.extend(
+                    s.targets
+                        .iter()
+                        .filter(|target| !matches!(target.content, ExprContent::Variable(_))),
+                );
+            }
+            Stmt::IfStmt(s) => {
+                exprs.push(&s.condition);
+                statement_exprs(&s.then_body, exprs);
+                statement_exprs(&s.else_body, exprs);
+            }
+            Stmt::WhileStmt(s) => {
+                exprs.push(&s.condition);
+                statement_exprs(&s.body, exprs);
+                statement_exprs(s.else_body(), exprs);
+            }
+            Stmt::ForStmt(s) => {
+                exprs.push(&s.iterable);
+                statement_exprs(&s.body, exprs);
+                statement_exprs(s.else_body(), exprs);
+            }
+            Stmt::ReturnStmt(ReturnStmt { value, .. }) | Stmt::YieldStmt(YieldStmt { value, .. }) => {
+                exprs.extend(value)
+            }
+            Stmt::DelStmt(s) => exprs.extend(&s.targets),
+            Stmt::BreakStmt(_) => (),
+        }
+    }
+}
+
+// Visit the expression and all of its subexpressions
+pub(super) fn for_each_subexpr(expr: &Expr, f: &mut dyn FnMut(&Expr)) {
+    f(expr);
+    match &expr.content {
+        ExprContent::BinaryExpr(e) => {
+            for_each_subexpr(&e.left, f);
+            for_each_subexpr(&e.right, f);
+        }
+        ExprContent::UnaryExpr(e) => for_each_subexpr(&e.operand, f),
+        ExprContent::IfExpr(e) => {
+            for_each_subexpr(&e.condition, f);
+            for_each_subexpr(&e.then_expr, f);
+            for_each_subexpr(&e.else_expr, f);
+        }
+        ExprContent::CallExpr(e) => {
+            for arg in &e.args {
+                for_each_subexpr(arg, f);
+            }
+            for keyword in &e.keywords {
+                for_each_subexpr(&keyword.value, f);
+            }
+        }
+        ExprContent::MethodCallExpr(e) => {
+            for_each_subexpr(&e.method.object, f);
+            for arg in &e.args {
+                for_each_subexpr(arg, f);
+            }
+        }
+        ExprContent::SetExpr(e) => {
+            for element in &e.elements {
+                for_each_subexpr(element, f);
+            }
+        }
+        ExprContent::MemberExpr(e) => for_each_subexpr(&e.object, f),
+        ExprContent::IndexExpr(e) => {
+            for_each_subexpr(&e.list, f);
+            for_each_subexpr(&e.index, f);
+        }
+        ExprContent::SliceExpr(e) => {
+            for_each_subexpr(&e.list, f);
+            if let Some(start) = &e.start {
+                for_each_subexpr(start, f);
+            }
+            if let Some(end) = &e.end {
+                for_each_subexpr(end, f);
+            }
+        }
+        ExprContent::ListComprehension(e) => {
+            for clause in &e.clauses {
+                for_each_subexpr(&clause.iterable, f);
+                for condition in &clause.conditions {
+                    for_each_subexpr(condition, f);
+                }
+            }
+            for_each_subexpr(&e.element, f);
+        }
+        ExprContent::ListExpr(e) => {
+            for element in &e.elements {
+                for_each_subexpr(element, f);
+            }
+        }
+        ExprContent::Variable(_)
+        | ExprContent::IntegerLiteral(_)
+        | ExprContent::BooleanLiteral(_)
+        | ExprContent::NoneLiteral(_)
+        | ExprContent::StringLiteral(_) => (),
+    }
+}
+
+// Collect the variables and functions the statements refer to
+fn statement_reads(statements: &[Stmt], reads: &mut HashSet<String>) {
+    let mut exprs = vec![];
+    statement_exprs(statements, &mut exprs);
+    for expr in exprs {
+        for_each_subexpr(expr, &mut |e| match &e.content {
+            ExprContent::Variable(v) => {
+                reads.insert(v.name.clone());
+            }
+            ExprContent::CallExpr(c) => {
+                reads.insert(c.function.name.clone());
+            }
+            _ => (),
+        });
+    }
+}
+
+// Collect the names a function body refers to, including from nested functions.
+// Names are not resolved, so a nested function reading its own local counts
+// as a use of an outer variable with the same name.
+fn function_reads(f: &FuncDef) -> HashSet<String> {
+    let mut reads = HashSet::new();
+    statement_reads(&f.statements, &mut reads);
+    for declaration in &f.declarations {
+        match declaration {
+            Declaration::FuncDef(g) => reads.extend(function_reads(g)),
+            Declaration::NonLocalDecl(v) => {
+                reads.insert(v.variable.name.clone());
+            }
+            _ => (),
+        }
+    }
+    reads
+}
+
+struct Checker<'a> {
+    config: &'a WarningConfig,
+    // Global variables and functions, which local names may shadow
+    globals: HashMap<&'a str, Location>,
+    warnings: Vec<CompilerError>,
+}
+
+impl Checker<'_> {
+    fn warn(&mut self, warning: Warning, location: Location, message: Message) -> bool {
+        if !self.config.enabled.contains(&warning) {
+            return false;
+        }
+        self.warnings.push(CompilerError {
+            base: NodeBase::from_location(location),
+            message: message.text,
+            syntax: false,
+            severity: if self.config.error {
+                Severity::Error
+            } else {
+                Severity::Warning
+            },
+            flag: Some(warning.name().to_owned()),
+            code: Some(message.code.id.to_owned()),
+        });
+        true
+    }
+
+    fn shadow(&mut self, id: &Identifier) {
+        if let Some(&global) = self.globals.get(id.name.as_str())
+            && self.warn(Warning::Shadow, id.base.location, warning_shadow(&id.name))
+        {
+            self.warnings.push(CompilerError::note(global, note_global(&id.name)));
+        }
+    }
+
+    fn possibly_none(&mut self, found: Vec<PossiblyNone>) {
+        for PossiblyNone { location, name, notes } in found {
+            if self.warn(Warning::PossiblyNone, location, warning_possibly_none(&name)) {
+                for (location, note) in notes {
+                    self.warnings.push(CompilerError::note(location, note));
+                }
+            }
+        }
+    }
+
+    // `self` is never reported as unused in methods
+    fn function(&mut self, f: &FuncDef, method: bool) {
+        let reads = function_reads(f);
+        for param in f.params.iter().skip(method as usize) {
+            let id = &param.identifier;
+            self.shadow(id);
+            if !reads.contains(&id.name) {
+                let message = warning_unused_parameter(&id.name);
+                self.warn(Warning::UnusedParameter, id.base.location, message);
+            }
+        }
+        for declaration in &f.declarations {
+            match declaration {
+                Declaration::VarDef(v) => {
+                    let id = &v.var.identifier;
+                    self.shadow(id);
+                    if !reads.contains(&id.name) {
+                        let message = warning_unused_variable(&id.name);
+                        self.warn(Warning::UnusedVariable, id.base.location, message);
+                    }
+                }
+                Declaration::FuncDef(g) => {
+                    self.shadow(&g.name);
+                    self.function(g, false);
+                }
+                _ => (),
+            }
+        }
+        self.body(&f.statements);
+        self.possibly_none(possibly_none::check_function(f));
+    }
+
+    fn body(&mut self, statements: &[Stmt]) {
+        let mut exprs = vec![];
+        statement_exprs(statements, &mut exprs);
+        for expr in exprs {
+            for_each_subexpr(expr, &mut |e| {
+                if let ExprContent::BinaryExpr(b) = &e.content
+                    && let (ExprContent::Variable(left), ExprContent::Variable(right)) =
+                        (&b.left.content, &b.right.content)
+                    && left.name == right.name
+                {
+                    let operator = match b.operator {
+                        BinaryOp::Is => "is",
+                        BinaryOp::Eq => "==",
+                        BinaryOp::Le => "<=",
+                        BinaryOp::Ge => ">=",
+                        _ => return,
+                    };
+                    let message = warning_always_true(&left.name, operator);
+                    self.warn(Warning::AlwaysTrue, b.base.location, message);
+                }
+            });
+        }
+        self.unreachable(statements);
+    }
+
+    // Report the first statement after one that always returns or breaks, in
+    // each block
+    fn unreachable(&mut self, statements: &[Stmt]) {
+        let exits =
+            |s: &Stmt| matches!(s, Stmt::BreakStmt(_)) || always_return(std::slice::from_ref(s));
+        if let Some(i) = statements.iter().position(exits)
+            && let Some(next) = statements.get(i + 1)
+        {
+            let exit = match statements[i] {
+                Stmt::BreakStmt(_) => "break",
+                _ => "return",
+            };
+            self.warn(Warning::Unreachable, next.base().location, warning_unreachable(exit));
+        }
+        for statement in statements {
+            match statement {
+                Stmt::IfStmt(s) => {
+                    self.unreachable(&s.then_body);
+                    self.unreachable(&s.else_body);
+                }
+                Stmt::WhileStmt(s) => {
+                    self.unreachable(&s.body);
+                    self.unreachable(s.else_body());
+                }
+                Stmt::ForStmt(s) => {
+                    self.unreachable(&s.body);
+                    self.unreachable(s.else_body());
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+// Find the warnings of a program that type checked without errors
+pub fn warnings(ast: &Program, config: &WarningConfig) -> Vec<CompilerError> {
+    let mut globals = HashMap::new();
+    for declaration in &ast.declarations {
+        match declaration {
+            Declaration::VarDef(v) => {
+                let id = &v.var.identifier;
+                globals.insert(id.name.as_str(), id.base.location);
+            }
+            Declaration::FuncDef(f) => {
+                globals.insert(f.name.name.as_str(), f.name.base.location);
+            }
+            _ => (),
+        }
+    }
+
+    // Functions are used if anything but their own body refers to them
+    let mut used = HashSet::new();
+    statement_reads(&ast.statements, &mut used);
+    for declaration in &ast.declarations {
+        match declaration {
+            Declaration::FuncDef(f) => {
+                let mut reads = function_reads(f);
+                reads.remove(&f.name.name);
+                used.extend(reads);
+            }
+            Declaration::ClassDef(c) => {
+                for method in &c.declarations {
+                    if let Declaration::FuncDef(m) = method {
+                        used.extend(function_reads(m));
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut checker = Checker {
+        config,
+        globals,
+        warnings: vec![],
+    };
+    for declaration in &ast.declarations {
+        match declaration {
+            Declaration::FuncDef(f) => {
+                if !used.contains(&f.name.name) {
+                    let message = warning_unused_function(&f.name.name);
+                    checker.warn(Warning::UnusedFunction, f.name.base.location, message);
+                }
+                checker.function(f, false);
+            }
+            Declaration::ClassDef(c) => {
+                for method in &c.declarations {
+                    if let Declaration::FuncDef(m) = method {
+                        checker.function(m, true);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    checker.body(&ast.statements);
+    checker.possibly_none(possibly_none::check_program(ast));
+    checker.warnings
+}
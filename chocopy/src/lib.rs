@@ -0,0 +1,4 @@
+// The compiler as a library, shared by the `chocopy` command and the language
+// server
+pub mod common;
+pub mod core;
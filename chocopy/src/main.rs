@@ -1,15 +1,14 @@
-mod common;
-mod core;
-
-use common::location::Location;
-use common::node::Program;
-use core::codegen;
-use core::codegen::Platform;
-use core::frontend;
-use core::typecheck;
+use chocopy::common::diagnostic;
+use chocopy::common::node::{CompilerError, Severity};
+use chocopy::common::render;
+use chocopy::core::codegen;
+use chocopy::core::codegen::Platform;
+use chocopy::core::frontend;
+use chocopy::core::interpreter::StdConsole;
+use chocopy::core::repl::Session;
+use chocopy::core::typecheck;
 use getopts::Options;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::IsTerminal;
 
 #[cfg(target_os = "windows")]
 const PLATFORM: Platform = Platform::Windows;
@@ -25,40 +24,37 @@ fn print_usage(program: &str, opts: Options) {
     print!("{}", opts.usage(&brief));
 }
 
-fn check_error(file: &str, ast: &Program) -> bool {
-    let errors = &ast.errors.errors;
-    if errors.is_empty() {
-        true
-    } else {
-        let file = File::open(file).unwrap();
-        let mut lines = BufReader::new(file)
-            .lines()
-            .take_while(|l| l.is_ok())
-            .map(|l| l.unwrap());
-        let mut current_row = 1;
-        let mut line = lines.next();
-        for error in errors {
-            let Location { start, .. } = error.base.location;
-            let row = start.row;
-            if row > current_row {
-                for _ in 0..row - current_row - 1 {
-                    lines.next();
-                }
-                line = lines.next().map(|s| s.replace('\t', " "));
-                current_row = row;
-            }
-            eprintln!("{}, {}: {}", start.row, start.col, error.message);
-            if let Some(line) = &line {
-                eprintln!("    | {}", line);
-                eprint!("    | ");
-                for _ in 0..std::cmp::max(start.col as i64 - 1, 0) {
-                    eprint!(" ");
-                }
-                eprintln!("^");
-            }
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DiagnosticsFormat {
+    Human,
+    Json,
+    Sarif,
+}
+
+// Report the diagnostics, and return whether none is an error. Text goes to
+// stderr, while JSON and SARIF go to stdout, apart from other messages. The
+// source is empty when the input is an AST, so no source lines are shown.
+fn check_error(
+    file: &str,
+    source: &str,
+    format: DiagnosticsFormat,
+    errors: &[CompilerError],
+) -> bool {
+    match format {
+        DiagnosticsFormat::Human => {
+            let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+            eprint!("{}", render::render(file, source, errors, color));
+        }
+        DiagnosticsFormat::Json => {
+            let json = diagnostic::to_json(errors);
+            println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        }
+        DiagnosticsFormat::Sarif => {
+            let sarif = diagnostic::to_sarif(file, errors);
+            println!("{}", serde_json::to_string_pretty(&sarif).unwrap());
         }
-        false
     }
+    errors.iter().all(|error| error.severity != Severity::Error)
 }
 
 #[derive(Debug)]
@@ -83,14 +79,89 @@ impl std::fmt::Display for CodeError {
 
 impl std::error::Error for CodeError {}
 
+// Format source files in place, or with `--check` only report the files that
+// are not formatted
+fn fmt(program: &str, args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut opts = Options::new();
+    opts.optflag("", "check", "Report unformatted files instead of formatting them");
+    let matches = match opts.parse(args) {
+        Ok(m) if !m.free.is_empty() => m,
+        _ => {
+            let brief = format!("Usage: {} fmt FILE... [OPTIONS]", program);
+            print!("{}", opts.usage(&brief));
+            return Err(ArgumentError.into());
+        }
+    };
+
+    let mut formatted = true;
+    for file in &matches.free {
+        let source = std::fs::read_to_string(file)?;
+        match frontend::format_source(&source) {
+            Err(errors) => {
+                check_error(file, &source, DiagnosticsFormat::Human, &errors);
+                formatted = false;
+            }
+            Ok(output) if output != source => {
+                if matches.opt_present("check") {
+                    eprintln!("{} is not formatted", file);
+                    formatted = false;
+                } else {
+                    std::fs::write(file, output)?;
+                }
+            }
+            Ok(_) => (),
+        }
+    }
+    if !formatted {
+        return Err(CodeError.into());
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = std::env::args().collect();
     let program = args[0].clone();
+    if args.get(1).map(String::as_str) == Some("fmt") {
+        return fmt(&program, &args[2..]);
+    }
 
     let mut opts = Options::new();
     opts.optflag("a", "ast", "Print bare AST");
     opts.optflag("t", "typed", "Print typed AST");
+    opts.optflag("", "symbols", "Print the declarations and references of names");
+    opts.optflag("", "repl", "Read and run declarations and statements interactively");
     opts.optflag("o", "obj", "Output object file without linking");
+    opts.optflag("s", "static", "Prefer static linking of the C runtime");
+    opts.optflag(
+        "",
+        "strict-none",
+        "Only allow None in Optional types, and skip None checks elsewhere",
+    );
+    opts.optmulti(
+        "W",
+        "",
+        "Enable warning NAME, or disable it with no-NAME; `all` enables every warning \
+         and `error` turns warnings into errors",
+        "NAME",
+    );
+    opts.optflag("", "typed-warnings", "Include warnings in the typed AST");
+    opts.optflag("", "from-ast", "Read INPUT as an AST in JSON, like the output of --ast");
+    opts.optflag(
+        "",
+        "from-typed",
+        "Read INPUT as a typed AST in JSON, like the output of --typed",
+    );
+    opts.optflag(
+        "",
+        "no-recheck",
+        "Compile the input of --from-typed with its own types, without type checking it again",
+    );
+    opts.optopt(
+        "",
+        "diagnostics-format",
+        "Report errors and warnings as text (default), JSON or SARIF",
+        "human|json|sarif",
+    );
 
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
@@ -101,6 +172,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     };
 
+    let strict_none = matches.opt_present("strict-none");
+    if matches.opt_present("repl") {
+        // Deeply recursive programs need a larger stack than the main thread's
+        let session = std::thread::Builder::new().stack_size(256 << 20).spawn(move || {
+            let mut session = Session::new(Box::new(StdConsole), strict_none);
+            session.prompt = std::io::stdin().is_terminal();
+            session.color =
+                std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+            session.run();
+        })?;
+        session.join().unwrap();
+        return Ok(());
+    }
+
     let input = if let Some(input) = matches.free.first() {
         input
     } else {
@@ -108,25 +193,89 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         return Err(ArgumentError.into());
     };
 
-    let ast = frontend::process(input)?;
+    let mut warning_config = typecheck::WarningConfig::new();
+    for option in matches.opt_strs("W") {
+        if let Err(message) = warning_config.apply(&option) {
+            eprintln!("{}", message);
+            return Err(ArgumentError.into());
+        }
+    }
+
+    let format = match matches.opt_str("diagnostics-format").as_deref() {
+        None | Some("human") => DiagnosticsFormat::Human,
+        Some("json") => DiagnosticsFormat::Json,
+        Some("sarif") => DiagnosticsFormat::Sarif,
+        Some(format) => {
+            eprintln!("Unknown diagnostics format: {}", format);
+            return Err(ArgumentError.into());
+        }
+    };
+
+    let from_ast = matches.opt_present("from-ast");
+    let from_typed = matches.opt_present("from-typed");
+    if from_ast && from_typed {
+        eprintln!("Only one of --from-ast and --from-typed can be given");
+        return Err(ArgumentError.into());
+    }
+
+    let (ast, source) = if from_ast || from_typed {
+        match frontend::read_json(input) {
+            Ok(ast) => (ast, String::new()),
+            Err(e) => {
+                eprintln!("Failed to read the AST: {}", e);
+                return Err(CodeError.into());
+            }
+        }
+    } else {
+        let ast = frontend::process(input)?;
+        (ast, std::fs::read_to_string(input).unwrap_or_default())
+    };
 
     if matches.opt_present("ast") {
         println!("{}", serde_json::to_string_pretty(&ast).unwrap());
         return Ok(());
     }
 
-    if !check_error(input, &ast) {
+    // Syntax errors are reported alone, as the program is not type checked.
+    // So are the errors recorded in an AST read from JSON.
+    if !ast.errors.errors.is_empty() {
+        check_error(input, &source, format, &ast.errors.errors);
         return Err(CodeError.into());
     }
 
-    let ast = typecheck::check(ast);
+    if matches.opt_present("symbols") {
+        let symbols = typecheck::analyze(ast, strict_none).symbols;
+        println!("{}", serde_json::to_string_pretty(&symbols).unwrap());
+        return Ok(());
+    }
+
+    // A typed AST from JSON is checked again unless asked not to, as code
+    // generation assumes a well-typed program and panics on anything else
+    let mut ast = if from_typed && matches.opt_present("no-recheck") {
+        let errors = typecheck::validate_typed(&ast);
+        if !errors.is_empty() {
+            check_error(input, &source, format, &errors);
+            return Err(CodeError.into());
+        }
+        ast
+    } else {
+        typecheck::check(ast, strict_none)
+    };
+    let mut diagnostics = ast.errors.clone();
+    if diagnostics.errors.is_empty() {
+        diagnostics.errors = typecheck::warnings(&ast, &warning_config);
+        diagnostics.sort();
+    }
 
     if matches.opt_present("typed") {
+        if matches.opt_present("typed-warnings") {
+            ast.errors = diagnostics;
+        }
         println!("{}", serde_json::to_string_pretty(&ast).unwrap());
         return Ok(());
     }
 
-    if !check_error(input, &ast) {
+    if !check_error(input, &source, format, &diagnostics.errors) {
         return Err(CodeError.into());
     }
 
@@ -139,7 +288,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let no_link = matches.opt_present("o");
     let static_lib = matches.opt_present("s");
-    codegen::codegen(input, ast, output, no_link, static_lib, PLATFORM)?;
+    codegen::codegen(input, ast, output, no_link, static_lib, strict_none, PLATFORM)?;
 
     Ok(())
 }
@@ -0,0 +1,13 @@
+def f(n: int) -> int:
+    def g() -> int:
+        break
+    while n > 0:
+        break
+    break
+    return n
+
+break
+for n in [1]:
+    pass
+else:
+    break
@@ -0,0 +1,2 @@
+print(sep="", 1)
+f(x, y=1)
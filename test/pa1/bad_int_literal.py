@@ -0,0 +1,6 @@
+x: int = 0x7FFFFFFF
+y: int = 0
+y = 1_000 + 0b1
+y = 0x80000000
+y = -2147483648
+y = -2147483649
@@ -0,0 +1,5 @@
+a | b ^ c & d << e + f
+-a ** -b ** c
+~-a >> 2 * b
+a << b >> c
+x = a == b | c
@@ -0,0 +1,4 @@
+X: Final[int] = 1
+Y: Final[[str]] = None
+Z: Final = 2
+Final: int = 0
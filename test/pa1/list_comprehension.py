@@ -0,0 +1,5 @@
+[x for x in xs]
+[x * 2 for x in xs if x > 0]
+[x + y if a else b for x in xs if x if not y for y in f(x) if y < x or z]
+[[y for y in x] for x in [[1], [2, 3]]]
+print([c + "!" for c in "abc"][0])
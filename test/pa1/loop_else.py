@@ -0,0 +1,12 @@
+while x:
+    x = x - 1
+else:
+    pass
+for y in z:
+    pass
+else:
+    print(y)
+    if y:
+        pass
+    else:
+        pass
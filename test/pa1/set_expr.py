@@ -0,0 +1,4 @@
+s: set[int] = None
+s = {1, 2 + 3, x}
+print(1 in s, 2 not in s or s)
+s = a | {1} & b
@@ -0,0 +1,7 @@
+print(True & 1)
+print("a" | "b")
+print(~True)
+print(1 << False)
+print([1] ** 2)
+print(None ^ 1)
+print(1 >> "x")
@@ -0,0 +1,13 @@
+a:[int] = None
+f:[bool] = None
+o:[object] = None
+
+True < False
+"a" < 1
+a < ["a"]
+a == [True]
+f < [True]
+f == a
+o == o
+[None] < [None]
+a < None
@@ -0,0 +1,31 @@
+g: int = 0
+def f(x: int, ys: [int]) -> int:
+    z: int = 0
+    w: int = 0
+    def h() -> int:
+        return w
+    del x
+    print(x)
+    x = 1
+    print(x)
+    del z
+    if x > 0:
+        z = 1
+    print(z)
+    del g
+    del w
+    while x > 0:
+        del x
+    del ys[True]
+    del ys["a":]
+    del "abc"[0]
+    del "abc"[0:1]
+    print(ys[1:2])
+    return 0
+
+def k(y: int) -> int:
+    for y in [1, 2]:
+        del y
+    return y
+
+del g
@@ -0,0 +1,22 @@
+X: Final[int] = 1
+Y: Final[str] = "a"
+Z: Final[int] = "a"
+
+def f() -> int:
+    global X
+    X = 2
+    return X
+
+def g() -> object:
+    global Y
+    for Y in ["a"]:
+        pass
+    return Y
+
+def h() -> int:
+    X: int = 0
+    X = 3
+    return X
+
+X = 3
+Y = X = 4
@@ -0,0 +1,9 @@
+X: Final[int] = 1
+Y: Final[str] = "a"
+
+class C(object):
+    a: Final[int] = 1
+
+def f() -> int:
+    b: Final[int] = 2
+    return b
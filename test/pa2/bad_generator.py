@@ -0,0 +1,26 @@
+def shadow(next: int) -> int:
+    return next(1)
+
+def count(n: int) -> Iterator[int]:
+    i: int = 0
+    while i < n:
+        yield True
+        i = i + 1
+    return i
+
+def nothing() -> Iterator[str]:
+    yield
+    yield 1
+    return
+
+g: Iterator[int] = None
+h: Iterator[object] = None
+x: int = 0
+s: str = ""
+g = count(3)
+h = g
+s = next(g)
+x = next(x)
+for s in g:
+    pass
+yield 1
@@ -0,0 +1,19 @@
+def count(n: int) -> [int]:
+    yield n
+
+def outer() -> Iterator[int]:
+    def helper() -> int:
+        return 1
+    yield helper()
+
+def wrapper() -> int:
+    def inner() -> Iterator[int]:
+        yield 1
+    return 0
+
+class Gen(Iterator):
+    pass
+
+def plain() -> Iterator:
+    yield 1
+
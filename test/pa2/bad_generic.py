@@ -0,0 +1,30 @@
+class Plain(object):
+    pass
+
+class Bag(Generic[T]):
+    items: [T] = None
+
+class Box(Generic[T]):
+    value: T = None
+
+    def set(self: "Box", v: T) -> object:
+        self.value = 1
+        self.value = v
+
+b: Box[int] = None
+c: Box[str] = None
+o: object = None
+b = Box[int]()
+c = Box[int]()
+b.set("s")
+b.value = "s"
+c = b
+o = Box[[int]]()
+o = Box()
+o = Box[int, str]()
+o = Box[T]()
+o = Bag[bool]()
+o = b if True else c
+o.value
+print(b.value + 1)
+print(isinstance(b, T))
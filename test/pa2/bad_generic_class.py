@@ -0,0 +1,47 @@
+class Box(Generic[T]):
+    value: T = None
+
+    def set(self: "Box", v: T) -> object:
+        self.value = 1
+        self.value = v
+
+    def get(self: Box[int]) -> T:
+        return self.value
+
+class Sub(Box):
+    pass
+
+class Bad(T):
+    pass
+
+class Twice(Generic[U, U]):
+    pass
+
+class Plain(object):
+    pass
+
+class Bag(Generic[T]):
+    items: [T] = None
+
+class Wrap(Generic[T]):
+    bag: Bag[T] = None
+
+class Shadow(Generic[Plain]):
+    pass
+
+class Self(Generic[Self]):
+    pass
+
+class T(object):
+    pass
+
+x: Box = None
+y: Box[int, str] = None
+z: T = None
+p: Plain[int] = None
+b: Box[int] = None
+c: Box[str] = None
+l: Bag[int] = None
+m: Wrap[bool] = None
+n: Bag[str] = None
+U: int = 0
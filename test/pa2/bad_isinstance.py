@@ -0,0 +1,30 @@
+class Animal(object):
+    pass
+
+class Dog(Animal):
+    def fetch(self: "Dog") -> int:
+        return 1
+
+def reassigned(a: Animal) -> int:
+    if isinstance(a, Dog):
+        a = Animal()
+        return a.fetch()
+    return 0
+
+def captured(a: Animal) -> int:
+    def reset() -> object:
+        nonlocal a
+        a = Animal()
+    if isinstance(a, Dog):
+        return a.fetch()
+    return 0
+
+x: Animal = None
+
+if isinstance(x, Dog):
+    x.fetch()
+    reassigned(x)
+    x.fetch()
+
+isinstance(x, 3)
+isinstance(x)
@@ -0,0 +1,20 @@
+class A(object):
+    n: int = 1
+
+def f(xs: [int]) -> [int]:
+    y: int = 0
+    del y
+    return [y for y in xs] + [y]
+
+xs: [int] = None
+ys: [int] = None
+zs: [object] = None
+xs = [1, 2]
+ys = [x for x in 5]
+ys = [x for x in xs if x]
+ys = [x for x in xs if x > 0 + True]
+ys = [y for x in xs]
+ys = ["a" for x in xs]
+zs = [[] for x in xs]
+print(x)
+
@@ -0,0 +1,18 @@
+def f(xs: [int]) -> int:
+    x: int = 0
+    for x in xs:
+        return x
+    else:
+        pass
+
+def g(n: int) -> int:
+    while n > 0:
+        return n
+
+def h(xs: [int]) -> int:
+    x: int = 0
+    for x in xs:
+        if x > 0:
+            break
+    else:
+        return 0
@@ -0,0 +1,11 @@
+x: [int] = None
+y: int = 0
+while x is None:
+    x = []
+else:
+    x = None
+    y = len(x)
+for y in [1]:
+    pass
+else:
+    y = False
@@ -0,0 +1,10 @@
+class A(object):
+    x: int = 0
+
+def f(a: Optional[int]) -> int:
+    return 0
+
+def g(a: Optional[A]) -> Optional[Optional[A]]:
+    return a
+
+y: Optional[bool] = None
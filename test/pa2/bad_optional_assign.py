@@ -0,0 +1,9 @@
+class A(object):
+    x: int = 0
+
+z: Optional[A] = None
+w: A = None
+z = A()
+z = None
+z = 1
+w = z
@@ -0,0 +1,9 @@
+x:int = 1
+
+print(x, sep=1)
+print(x, end=None)
+print(x, flush=1)
+print(x, file=x)
+print(x, file="stderr")
+print(x, sep=",", sep=";")
+print(undefined, end=undefined)
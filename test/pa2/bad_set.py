@@ -0,0 +1,9 @@
+a: set[int] = None
+c: set[str] = None
+a = {1, "x"}
+a = {[1]}
+c = {"a"}
+print(1 in c)
+print(a | c)
+a.add("x")
+a = set[object]()
@@ -0,0 +1,19 @@
+class Node(object):
+    length: int = 0
+    def grow(self: "Node") -> int:
+        return 1
+
+def double(x: int) -> int:
+    return x * 2
+
+n: Node = None
+total: int = 0
+n = Node()
+print(n.lenght)
+print(n.grwo())
+print(totl)
+print(n.size)
+print(doubel(total))
+for totl in [1, 2]:
+    pass
+print(x)
@@ -0,0 +1,5 @@
+class Node(object):
+    pass
+
+m: Nod = None
+k: [Noed] = None
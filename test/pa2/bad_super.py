@@ -0,0 +1,26 @@
+class A(object):
+    def __init__(self: "A"):
+        pass
+
+    def f(self: "A") -> int:
+        return 1
+
+class B(A):
+    def f(self: "B") -> int:
+        def inner() -> int:
+            return super().f()
+        return super().g()
+
+    def g(self: "B") -> object:
+        return super()
+
+    def h(self: "B") -> int:
+        return super().f(1)
+
+    def k(self: "B") -> int:
+        return super(B, self).f()
+
+def f() -> int:
+    return super().f()
+
+super().__init__()
@@ -0,0 +1,12 @@
+def hash(s: str) -> int:
+    h: int = 5381
+    c: str = ""
+    for c in s:
+        h = ((h << 5) + h) ^ len(c)
+    return h & 2147483647
+
+def mod_pow(b: int, e: int, m: int) -> int:
+    return b ** e % m
+
+print(hash("abc") | ~0 >> 1)
+print(mod_pow(3, 4, 5) ** 2)
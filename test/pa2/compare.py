@@ -0,0 +1,19 @@
+a:[int] = None
+b:[[int]] = None
+s:[str] = None
+t:str = "a"
+a = [1, 2]
+b = [a, []]
+s = ["x"]
+
+t < "b"
+t >= t
+a == [1, 2]
+a != []
+[] == a
+[] < []
+a <= [3]
+b > [[1], [2]]
+b == [[], a]
+s < ["y"]
+[[True]] == [[False]]
@@ -0,0 +1,21 @@
+xs: [int] = None
+
+def f(ys: [str], n: int) -> int:
+    o: object = None
+    i: int = 0
+    del ys[0], ys[n:], ys[:n], ys[:]
+    o = ys
+    del o
+    if n > 0:
+        del n
+        n = 1
+    else:
+        n = 2
+    for i in [1, 2]:
+        del i
+    return n
+
+xs = [1, 2, 3]
+del xs[0]
+del xs[1:2]
+del xs[len(xs) - 1:]
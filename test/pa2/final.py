@@ -0,0 +1,9 @@
+N: Final[int] = 3
+ON: Final[bool] = True
+S: Final[str] = "s"
+
+def f(x: int) -> int:
+    global N
+    return x * N
+
+print(f(N) if ON else 0, S)
@@ -0,0 +1,47 @@
+class Tree(object):
+    value: int = 0
+    left: Tree = None
+    right: Tree = None
+
+    def walk(self: "Tree") -> Iterator[int]:
+        x: int = 0
+        if self.left is not None:
+            for x in self.left.walk():
+                yield x
+        yield self.value
+        if self.right is not None:
+            for x in self.right.walk():
+                yield x
+
+def count(n: int) -> Iterator[int]:
+    i: int = 0
+    while i < n:
+        yield i
+        i = i + 1
+
+def words(s: str) -> Iterator[str]:
+    c: str = ""
+    for c in s:
+        if c == " ":
+            return
+        yield c
+
+def anything() -> Iterator[object]:
+    yield 1
+    yield "two"
+    yield None
+    yield
+
+g: Iterator[int] = None
+x: int = 0
+o: object = None
+g = count(3)
+x = next(g)
+for x in g:
+    print(x)
+for o in anything():
+    print(o)
+for x in Tree().walk():
+    print(x)
+print(next(words("ab")) + "c")
+print(isinstance(g, Iterator))
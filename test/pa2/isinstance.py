@@ -0,0 +1,27 @@
+class Animal(object):
+    pass
+
+class Dog(Animal):
+    def fetch(self: "Dog") -> int:
+        return 1
+
+def narrow_if(a: Animal) -> int:
+    if isinstance(a, Dog):
+        return a.fetch()
+    elif a is not None:
+        return 0
+    return -1
+
+def narrow_else(a: Animal) -> int:
+    if not isinstance(a, Dog):
+        return 0
+    else:
+        return a.fetch()
+
+def narrow_expr(a: Animal, x: object) -> int:
+    if isinstance(x, str) and len(x) > 0:
+        return a.fetch() if isinstance(a, Dog) else len(x)
+    return 0
+
+isinstance(Dog(), Animal)
+isinstance(1, object)
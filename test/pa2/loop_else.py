@@ -0,0 +1,18 @@
+def f(xs: [int]) -> int:
+    x: int = 0
+    for x in xs:
+        pass
+    else:
+        return x
+
+def g(s: str) -> str:
+    while len(s) < 3:
+        s = s + "a"
+    else:
+        return s
+
+x: [int] = None
+while x is None:
+    x = []
+else:
+    print(len(x))
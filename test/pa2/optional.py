@@ -0,0 +1,42 @@
+class Node(object):
+    value: int = 0
+    next: Optional[Node] = None
+    def get(self: "Node") -> int:
+        return self.value
+
+def make(n: int) -> Optional[Node]:
+    head: Optional[Node] = None
+    node: Optional[Node] = None
+    i: int = 0
+    while i < n:
+        node = Node()
+        if node is not None:
+            node.value = i
+            node.next = head
+        head = node
+        i = i + 1
+    return head
+
+def total(n: Optional[Node]) -> int:
+    s: int = 0
+    cur: Optional[Node] = None
+    cur = n
+    while cur is not None:
+        s = s + cur.get() + cur.value
+        cur = cur.next
+    return s
+
+def pick(a: Optional[Node]) -> int:
+    return a.value if a is not None else -1
+
+def first(a: Optional[Node], b: Node) -> Node:
+    if a is None:
+        return b
+    else:
+        return a
+
+print(total(make(5)))
+print(pick(None))
+print(pick(make(3)))
+print(first(None, Node()).value)
+print(first(make(4), Node()).value)
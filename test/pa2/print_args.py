@@ -0,0 +1,14 @@
+x:int = 1
+s:str = "a"
+
+def show(x: int) -> object:
+    print("x", x, sep=" = ", end="\n", flush=False)
+    return None
+
+print()
+print(x, s, True, None, [1])
+print(x, sep="")
+print(s, end=s + "\n", sep=", ")
+print("err", file=stderr)
+print("out", file=stdout, flush=x > 0)
+show(x)
@@ -0,0 +1,15 @@
+a: set[int] = None
+b: set[str] = None
+c: set[bool] = None
+x: int = 0
+a = {1, 2, x}
+b = set[str]()
+c = {True}
+a.add(3)
+b.remove("x")
+c.discard(False)
+print(x in a, "y" not in b, len(c))
+a = a | {4} & a
+for x in a:
+    pass
+print([y for y in b])
@@ -0,0 +1,23 @@
+x: int = 0
+print(6 & 3)
+print(6 | 3)
+print(6 ^ 3)
+print(~5)
+print(1 << 4)
+print(1 << 31)
+print(1 << 32)
+print(-16 >> 2)
+print(-16 >> 40)
+print(16 >> 40)
+print(2 ** 10)
+print(2 ** 3 ** 2)
+print(-2 ** 2)
+print((-2) ** 3)
+print(3 ** 0)
+print(7 ** 100)
+print(1 + 2 << 1 & 7 | 8 ^ 1)
+print(2 * 3 ** 2)
+print(~-1 == 0)
+x = 3
+print(-x ** 2)
+print(2 ** x ** 2 % 100)
@@ -0,0 +1,49 @@
+words:[str] = None
+grid:[[int]] = None
+flags:[bool] = None
+empty:[int] = None
+i:int = 0
+j:int = 0
+w:str = ""
+
+def smallest(xs: [str]) -> str:
+    best:str = ""
+    x:str = ""
+    best = xs[0]
+    for x in xs:
+        if x < best:
+            best = x
+    return best
+
+words = ["pear", "apple", "peach", "app", "b", ""]
+print(smallest(words))
+print("apple" < "apply", "app" < "apple", "b" > "apple", "a" <= "a", "a" >= "b")
+print("abc" == "abc", "abc" != "abd", "" == "")
+
+# Insertion sort on strings
+i = 1
+while i < len(words):
+    w = words[i]
+    j = i - 1
+    while j >= 0 and words[j] > w:
+        words[j + 1] = words[j]
+        j = j - 1
+    words[j + 1] = w
+    i = i + 1
+print(words == ["", "app", "apple", "b", "peach", "pear"])
+for w in words:
+    print(w, end=",")
+print()
+
+grid = [[1, 2], [1, 2, 3], [0, 9]]
+print(grid[0] < grid[1], grid[1] < grid[0], grid[2] < grid[0], grid[0] <= [1, 2])
+print(grid == [[1, 2], [1, 2, 3], [0, 9]], grid != [[1, 2], [1, 2, 3], [0, 9]])
+print(grid < [[1, 2], [1, 2, 4]], grid > [[1, 2], [1]], [[]] < grid, [] < grid)
+print([1, 2] + [3] == [1, 2, 3], [] == [], [] != [], [] >= [])
+
+flags = [True, False]
+print(flags == [True, False], flags != [True, True], [flags] == [[True, False]])
+print([["a"], ["b", "c"]] < [["a"], ["b", "d"]], [["a"]] == [["a"]])
+print(empty is None)
+print(empty == [])
+print(empty < [])
@@ -0,0 +1,34 @@
+ys: [bool] = None
+class A(object):
+    x: int = 1
+
+def f(n: int) -> int:
+    xs: [int] = None
+    s: [str] = None
+    a: A = None
+    i: int = 0
+    xs = [1, 2, 3, 4, 5, 6]
+    del xs[0]
+    print(len(xs))
+    del xs[1:3]
+    for i in xs:
+        print(i)
+    del xs[:]
+    print(len(xs))
+    s = ["a", "b", "c"]
+    del s[1], s[0]
+    print(s[0])
+    a = A()
+    del a
+    a = A()
+    print(a.x)
+    xs = [1, 2, 3]
+    del xs[2:1]
+    print(len(xs))
+    return n
+
+print(f(3))
+ys = [True, False, True]
+del ys[1:]
+print(len(ys))
+print(ys[0])
@@ -0,0 +1,25 @@
+class Box(object):
+    v: int = 0
+
+def run() -> int:
+    xs: [Box] = None
+    b: Box = None
+    i: int = 0
+    j: int = 0
+    total: int = 0
+    while j < 200:
+        xs = []
+        i = 0
+        while i < 50:
+            b = Box()
+            b.v = i
+            xs = xs + [b]
+            i = i + 1
+        del xs[10:40]
+        del xs[0]
+        for b in xs:
+            total = total + b.v
+        j = j + 1
+    return total
+
+print(run())
@@ -0,0 +1,5 @@
+xs: [int] = None
+xs = [1, 2, 3]
+del xs[1:3]
+print(len(xs))
+del xs[1]
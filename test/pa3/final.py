@@ -0,0 +1,28 @@
+SIZE: Final[int] = 10
+DEBUG: Final[bool] = False
+NAME: Final[str] = "chocopy"
+LIMIT: Final[object] = 5
+count: int = 0
+
+def area(n: int) -> int:
+    global SIZE
+    return SIZE * n
+
+def shadow(SIZE: int) -> int:
+    return SIZE + 1
+
+def local() -> int:
+    DEBUG: int = 3
+    return DEBUG
+
+print(SIZE, DEBUG, NAME, LIMIT)
+print(area(3), shadow(1), local())
+print(SIZE * 2 + 1, -SIZE // 3, -SIZE % 3, SIZE % -3, 7 // -2)
+print(1 << 40, -8 >> 100, 3 ** 5, ~SIZE, SIZE ^ 3 | 64 & 127)
+print(not DEBUG, DEBUG or SIZE > 5, SIZE == 10 and not DEBUG)
+print(2147483647 + SIZE, [x + SIZE for x in [1, 2]][1])
+count = SIZE
+while count > 0:
+    count = count - 3
+print(count)
+print(SIZE // (SIZE - 10))
@@ -0,0 +1,63 @@
+class Box(object):
+    items: [int] = None
+    def __init__(self: "Box"):
+        self.items = [1, 2, 3]
+    def each(self: "Box") -> Iterator[int]:
+        x: int = 0
+        for x in self.items:
+            yield x * 10
+
+def count(n: int) -> Iterator[int]:
+    i: int = 0
+    while i < n:
+        yield i
+        i = i + 1
+
+def words(s: str, stop: str) -> Iterator[str]:
+    w: str = ""
+    c: str = ""
+    for c in s:
+        if c == " ":
+            if w == stop:
+                return
+            yield w
+            w = ""
+        else:
+            w = w + c
+    yield w
+
+def flags() -> Iterator[bool]:
+    yield True
+    yield False
+
+def boxes(n: int) -> Iterator[Box]:
+    while n > 0:
+        yield Box()
+        n = n - 1
+
+g: Iterator[int] = None
+x: int = 0
+s: str = ""
+b: bool = False
+o: object = None
+bx: Box = None
+
+for x in count(5):
+    print(x)
+for s in words("hello big world", "nope"):
+    print(s)
+for s in words("a b stop c d", "stop"):
+    print(s)
+for b in flags():
+    print(b)
+g = count(3)
+print(next(g))
+print(next(g) + next(g))
+print(isinstance(g, Iterator))
+print(isinstance(g, object))
+for x in Box().each():
+    print(x)
+for bx in boxes(2):
+    print(bx.items[1])
+for o in count(2):
+    print(o)
@@ -0,0 +1,9 @@
+def two() -> Iterator[int]:
+    yield 1
+    yield 2
+
+g: Iterator[int] = None
+g = two()
+print(next(g))
+print(next(g))
+print(next(g))
@@ -0,0 +1,59 @@
+class Node(object):
+    value: int = 0
+    next: Node = None
+
+def chain(n: int) -> Iterator[Node]:
+    head: Node = None
+    node: Node = None
+    i: int = 0
+    while i < n:
+        node = Node()
+        node.value = i
+        node.next = head
+        head = node
+        yield head
+        i = i + 1
+
+def walk(head: Node) -> int:
+    s: int = 0
+    while head is not None:
+        s = s + head.value
+        head = head.next
+    return s
+
+def strs(n: int) -> Iterator[str]:
+    i: int = 0
+    t: [str] = None
+    t = ["x", "y"]
+    while i < n:
+        yield t[i % 2] + t[i % len(t)]
+        t = t + ["z"]
+        i = i + 1
+
+def gens(n: int) -> Iterator[Iterator[int]]:
+    while n > 0:
+        yield inner(n)
+        n = n - 1
+
+def inner(n: int) -> Iterator[int]:
+    l: [int] = None
+    l = [n, n + 1, n + 2]
+    for n in l:
+        yield n
+
+last: Node = None
+s: str = ""
+total: int = 0
+k: int = 0
+g: Iterator[int] = None
+for last in chain(3000):
+    pass
+print(walk(last))
+for s in strs(2000):
+    total = total + len(s)
+print(total)
+total = 0
+for g in gens(2000):
+    for k in g:
+        total = total + k
+print(total)
@@ -0,0 +1,133 @@
+class Node(Generic[T]):
+    value: T = None
+    next: Node[T] = None
+
+class Stack(Generic[T]):
+    top: Node[T] = None
+    size: int = 0
+
+    def push(self: "Stack", item: T) -> object:
+        node: Node[T] = None
+        node = Node[T]()
+        node.value = item
+        node.next = self.top
+        self.top = node
+        self.size = self.size + 1
+
+    def pop(self: "Stack") -> T:
+        item: T = None
+        item = self.top.value
+        self.top = self.top.next
+        self.size = self.size - 1
+        return item
+
+    def each(self: "Stack") -> Iterator[T]:
+        node: Node[T] = None
+        node = self.top
+        while node is not None:
+            yield node.value
+            node = node.next
+
+    def empty(self: "Stack") -> bool:
+        return self.size == 0
+
+class Bag(Generic[T]):
+    items: [T] = None
+
+    def __init__(self: "Bag"):
+        self.items = []
+
+    def add(self: "Bag", item: T) -> object:
+        self.items = self.items + [item]
+
+    def first(self: "Bag", n: int) -> [T]:
+        result: [T] = None
+        i: int = 0
+        result = []
+        while i < n:
+            result = result + [self.items[i]]
+            i = i + 1
+        return result
+
+class Pair(Generic[K, V]):
+    key: K = None
+    value: V = None
+
+    def swap(self: "Pair") -> Pair[V, K]:
+        p: Pair[V, K] = None
+        p = Pair[V, K]()
+        p.key = self.value
+        p.value = self.key
+        return p
+
+class Animal(object):
+    name: str = "animal"
+
+def total(s: Stack[int]) -> int:
+    n: int = 0
+    while not s.empty():
+        n = n + s.pop()
+    return n
+
+T: int = 10
+ints: Stack[int] = None
+strs: Stack[str] = None
+flags: Stack[bool] = None
+nested: Stack[Stack[int]] = None
+words: Bag[str] = None
+animals: Bag[Animal] = None
+p: Pair[int, str] = None
+q: Pair[str, int] = None
+x: int = 0
+w: str = ""
+
+ints = Stack[int]()
+ints.push(1)
+ints.push(20)
+ints.push(300)
+print(ints.size)
+print(total(ints))
+
+strs = Stack[str]()
+strs.push("a")
+strs.push("bc")
+print(strs.pop() + strs.pop())
+
+flags = Stack[bool]()
+flags.push(True)
+flags.push(not flags.pop())
+print(flags.pop())
+
+nested = Stack[Stack[int]]()
+nested.push(Stack[int]())
+nested.push(ints)
+ints.push(42)
+print(nested.pop().pop())
+
+ints.push(5)
+ints.push(6)
+for x in ints.each():
+    print(x + T)
+
+words = Bag[str]()
+words.add("one")
+words.add("two")
+words.add("three")
+w = words.items[1]
+print(w)
+for w in words.first(2):
+    print(w + "!")
+print(len(words.items))
+
+animals = Bag[Animal]()
+animals.add(Animal())
+print(animals.items[0].name)
+
+p = Pair[int, str]()
+p.key = 7
+p.value = "seven"
+q = p.swap()
+print(q.key)
+print(q.value + 1)
+print(isinstance(ints, Stack))
+print(p.key if p.key > 3 else 0)
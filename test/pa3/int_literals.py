@@ -0,0 +1,9 @@
+print(0x7FFF_FFFF)
+print(0o17 + 0b1010_1010 + 0XfF + 0B1 + 0O7)
+print(1_000_000)
+print(0x_1F)
+print(007)
+print(2147483647)
+print(-2147483648)
+print(-0x8000_0000)
+print(--2147483647 - 1)
@@ -0,0 +1,42 @@
+class Animal(object):
+    name: str = "animal"
+    def speak(self: "Animal") -> str:
+        return "..."
+
+class Dog(Animal):
+    tricks: int = 3
+    def speak(self: "Dog") -> str:
+        return "woof"
+    def fetch(self: "Dog") -> int:
+        return self.tricks
+
+class Puppy(Dog):
+    pass
+
+def describe(a: Animal) -> int:
+    if isinstance(a, Dog):
+        return a.fetch() + a.tricks
+    elif a is not None:
+        return 0
+    return -1
+
+def check(x: object) -> str:
+    if isinstance(x, str) and len(x) > 0:
+        return x
+    return "?"
+
+print(describe(Dog()))
+print(describe(Puppy()))
+print(describe(Animal()))
+print(describe(None))
+print(isinstance(Puppy(), Animal))
+print(isinstance(Animal(), Dog))
+print(isinstance(1, int))
+print(isinstance(1, object))
+print(isinstance(True, int))
+print(isinstance(None, object))
+print(isinstance("s", object))
+print(isinstance([1], object))
+print(check("hi"))
+print(check(3))
+print(not (None is not None))
@@ -0,0 +1,62 @@
+class Point(object):
+    x: int = 0
+    y: int = 0
+
+def make(x: int, y: int) -> Point:
+    p: Point = None
+    p = Point()
+    p.x = x
+    p.y = y
+    return p
+
+def count(n: int) -> Iterator[int]:
+    i: int = 0
+    while i < n:
+        yield i
+        i = i + 1
+
+def squares(xs: [int]) -> [int]:
+    return [x * x for x in xs]
+
+def show(xs: [int]):
+    s: str = ""
+    x: int = 0
+    for x in xs:
+        s = s + " " + str_of(x)
+    print(s)
+
+def str_of(n: int) -> str:
+    digits: str = "0123456789"
+    s: str = ""
+    if n == 0:
+        return "0"
+    if n < 0:
+        return "-" + str_of(-n)
+    while n > 0:
+        s = digits[n % 10] + s
+        n = n // 10
+    return s
+
+x: int = 100
+xs: [int] = None
+ps: [Point] = None
+flags: [bool] = None
+words: [str] = None
+xs = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10]
+show(squares(xs))
+show([x for x in xs if x % 2 == 0])
+show([x + y for x in [1, 2, 3] for y in [10, 20]])
+show([x * y for x in xs if x > 3 if x < 7 for y in xs if y == x])
+print(x)
+show([i for i in count(20)])
+show([i * 2 for i in count(5) if i != 2])
+ps = [make(i, i * i) for i in xs]
+show([p.y for p in ps if p.x > 5])
+flags = [x > 5 for x in xs]
+print(flags[4])
+print(flags[5])
+words = [c + c for c in "abc"]
+print(words[0] + words[1] + words[2])
+print(len([c for c in "hello" if c != "l"]))
+print(len([x for x in xs if x > 100]))
+show([len(w) for w in [[1], [1, 2], []]])
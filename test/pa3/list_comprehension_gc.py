@@ -0,0 +1,25 @@
+class Box(object):
+    v: int = 0
+
+def box(v: int) -> Box:
+    b: Box = None
+    b = Box()
+    b.v = v
+    return b
+
+def total(bs: [Box]) -> int:
+    t: int = 0
+    b: Box = None
+    for b in bs:
+        t = t + b.v
+    return t
+
+i: int = 0
+t: int = 0
+n: [int] = None
+n = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9]
+while i < 200:
+    t = t + total([box(a * b) for a in n for b in n if (a + b) % 3 != 0])
+    t = t + total([box(a) for a in n])
+    i = i + 1
+print(t)
@@ -0,0 +1,51 @@
+def find(items: [int], target: int) -> int:
+    i: int = 0
+    for i in items:
+        if i == target:
+            break
+    else:
+        return -1
+    return i
+
+def first_even(n: int) -> int:
+    i: int = 0
+    while i < n:
+        i = i + 1
+        if i % 2 == 0:
+            break
+        print(i)
+    else:
+        print("none")
+    return i
+
+def gen(n: int) -> Iterator[int]:
+    i: int = 0
+    while True:
+        if i == n:
+            break
+        yield i
+        i = i + 1
+
+x: int = 0
+s: str = ""
+print(find([1, 2, 3], 2))
+print(find([1, 2, 3], 5))
+print(first_even(5))
+print(first_even(1))
+for x in gen(3):
+    print(x)
+for x in [1, 2, 3]:
+    while True:
+        break
+    else:
+        print("never")
+    if x == 2:
+        break
+    print(x)
+for s in "abc":
+    for x in [1]:
+        pass
+    else:
+        if s == "b":
+            break
+    print(s)
@@ -0,0 +1,44 @@
+def find(xs: [int], target: int) -> int:
+    i: int = 0
+    for i in xs:
+        if i == target:
+            return i * 10
+    else:
+        return -1
+
+def countdown(n: int) -> int:
+    while n > 0:
+        n = n - 1
+    else:
+        return n + 100
+
+def gen(n: int) -> Iterator[int]:
+    while n > 0:
+        yield n
+        n = n - 1
+    else:
+        yield 42
+
+def maybe(s: str) -> str:
+    x: [int] = None
+    while x is None:
+        x = [1]
+    else:
+        return s + "!"
+
+x: int = 0
+print(find([1, 2, 3], 2), find([1, 2, 3], 5), find([], 0))
+print(countdown(3))
+for x in gen(2):
+    print(x)
+else:
+    print("done")
+while x < 0:
+    print("never")
+else:
+    print("else", x)
+for x in [y for y in [1] if y > 1]:
+    print("never")
+else:
+    print("empty")
+print(maybe("hi"))
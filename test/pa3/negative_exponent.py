@@ -0,0 +1,3 @@
+x: int = 2
+print(x ** 3)
+print(x ** (x - 3))
@@ -0,0 +1,24 @@
+def count(n: int, s: str) -> str:
+    print("count", n, sep=":", end=" ")
+    return s
+
+x:int = 42
+xs:[int] = None
+i:int = 0
+
+print()
+print(x, True, "text")
+print(1, 2, 3, sep=", ")
+print("no", "space", sep="")
+print("same", end=" ")
+print("line")
+print(count(1, "a"), count(2, "b"), sep=count(3, "-"), end=count(4, "!") + "\n")
+print("hidden", file=stderr)
+print("flushed", file=stdout, flush=x > 0)
+
+xs = [1, 2, 3]
+while i < len(xs):
+    print(xs[i], end="")
+    i = i + 1
+print("", end="\n", sep="unused")
+print(x)
@@ -0,0 +1,2 @@
+print("a", end="")
+print("b", "c", sep="-", end="")
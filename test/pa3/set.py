@@ -0,0 +1,50 @@
+def total(s: set[int]) -> int:
+    t: int = 0
+    x: int = 0
+    for x in s:
+        t = t + x
+    return t
+
+a: set[int] = None
+b: set[int] = None
+c: set[str] = None
+d: set[bool] = None
+w: str = ""
+i: int = 0
+a = {1, 2, 3, 2}
+b = set[int]()
+print(len(a), len(b))
+b.add(3)
+b.add(4)
+b.add(-5)
+print(3 in a, 4 in a, -5 in b, 0 in b)
+print(len(a | b), total(a | b))
+print(len(a & b), total(a & b))
+a.remove(2)
+a.discard(2)
+a.discard(100)
+print(len(a), 2 in a, 1 in a)
+c = {"apple", "pear"}
+c.add("ban" + "ana")
+print("banana" in c, "kiwi" in c, len(c))
+for w in c | c:
+    if w == "pear":
+        c.discard(w)
+print(len(c), "pear" in c)
+d = {True}
+print(True in d, False in d)
+d.add(False)
+print(len(d))
+while i < 2000:
+    a.add(i * 7)
+    c.add("x" + ("y" if i % 2 == 0 else "z"))
+    i = i + 1
+print(len(a), total(a))
+i = 0
+while i < 2000:
+    a.discard(i * 7)
+    i = i + 1
+print(len(a), total(a))
+print(isinstance(a, set), isinstance(c, object))
+print(len([x * 2 for x in {1, 2}]), [x * 2 for x in {1, 2}][0] + [x * 2 for x in {1, 2}][1])
+a.remove(42)
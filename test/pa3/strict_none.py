@@ -0,0 +1,22 @@
+class Node(object):
+    value: int = 0
+    next: Optional["Node"] = None
+
+def init(n: Node, value: int, next: Optional[Node]) -> Node:
+    n.value = value
+    n.next = next
+    return n
+
+def make(value: int, next: Optional[Node]) -> Node:
+    return init(Node(), value, next)
+
+def total(n: Optional[Node]) -> int:
+    if n is None:
+        return 0
+    else:
+        return n.value + total(n.next)
+
+head: Optional[Node] = None
+head = make(1, make(2, make(3, None)))
+print(total(head))
+print(make(4, head).value)
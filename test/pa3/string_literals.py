@@ -0,0 +1,18 @@
+a: str = 'it\'s'
+b: str = """multi
+line "quoted" ""
+end"""
+c: str = "\x41\u00e9\U0001F600"
+d: str = r"raw\n\"x"
+e: str = ''
+f: str = 'Node'
+g: str = R'\d'
+print(a)
+print(b)
+print(c)
+print(d)
+print(len(e))
+print(f)
+print(g)
+print("a\
+b")
@@ -0,0 +1,50 @@
+class A(object):
+    x: int = 0
+
+    def __init__(self: "A"):
+        self.x = 1
+        print("A.__init__")
+
+    def describe(self: "A") -> str:
+        return "A"
+
+    def twice(self: "A", n: int) -> int:
+        return n * 2
+
+class B(A):
+    y: int = 0
+
+    def __init__(self: "B"):
+        super().__init__()
+        self.y = 2
+        print("B.__init__")
+
+    def describe(self: "B") -> str:
+        return "B(" + super().describe() + ")"
+
+    def twice(self: "B", n: int) -> int:
+        return super().twice(n) + 1
+
+class C(B):
+    def __init__(self: "C"):
+        super().__init__()
+        print("C.__init__")
+
+    def describe(self: "C") -> str:
+        return "C(" + super().describe() + ")"
+
+class D(object):
+    def __init__(self: "D"):
+        super().__init__()
+        print("D.__init__")
+
+c: C = None
+a: A = None
+c = C()
+print(c.x)
+print(c.y)
+print(c.describe())
+print(c.twice(5))
+a = c
+print(a.describe())
+D()
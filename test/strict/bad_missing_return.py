@@ -0,0 +1,11 @@
+class A(object):
+    x: int = 0
+
+
+def k(a: Optional[A]) -> A:
+    if a is not None:
+        return a
+
+
+def m() -> [int]:
+    pass
@@ -0,0 +1,32 @@
+class A(object):
+    x: int = 0
+
+    def get(self: "A") -> int:
+        return self.x
+
+
+b: Optional[A] = None
+
+
+def f(a: Optional[A]) -> int:
+    return a.x
+
+
+def g(a: Optional[A]) -> int:
+    if a is not None:
+        return a.get()
+    return a.get()
+
+
+def h(a: A) -> A:
+    a = None
+    return a
+
+
+print(f(b))
+print(b.x)
+b = A()
+if b is not None:
+    print(b.x)
+    print(f(b))
+    print(b.x)
@@ -0,0 +1,8 @@
+class A(object):
+    x: int = 0
+
+
+a: A = None
+o: object = None
+l: [int] = None
+b: Optional[A] = None
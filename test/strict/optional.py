@@ -0,0 +1,26 @@
+class Node(object):
+    value: int = 0
+    next: Optional["Node"] = None
+
+
+def value(n: Optional[Node]) -> int:
+    if n is None:
+        return -1
+    else:
+        return n.value
+
+
+def first(a: Optional[Node], b: Node) -> Node:
+    if a is not None:
+        return a
+    return b
+
+
+def make(v: int) -> Node:
+    return Node()
+
+
+head: Optional[Node] = None
+head = make(1)
+print(value(head))
+print(first(None, make(2)).value)
@@ -0,0 +1,24 @@
+class Node(object):
+    value: int = 0
+    next: Optional["Node"] = None
+
+    def init(self: "Node", value: int, next: Optional["Node"]) -> "Node":
+        self.value = value
+        self.next = next
+        return self
+
+
+head: Optional[Node] = None
+n: Optional[Node] = None
+head = Node().init(1, None)
+if head is not None:
+    head = Node().init(3, Node().init(2, head))
+n = head
+while n is not None:
+    print(n.value)
+    n = n.next
+if head is not None and len("ab") == 2:
+    print(head.value + len([head.value]))
+    n = head.next
+    if n is not None:
+        print(n.value)
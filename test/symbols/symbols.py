@@ -0,0 +1,30 @@
+class Box(Generic[T]):
+    item: T = None
+
+    def get(self: "Box") -> T:
+        return self.item
+
+class A(object):
+    x: int = 0
+
+    def f(self: "A", y: int) -> int:
+        z: int = 1
+        def g() -> int:
+            nonlocal z
+            z = z + y
+            return z
+        return g() + self.x
+
+a: A = None
+n: int = 0
+
+def bump() -> int:
+    global n
+    n = n + 1
+    return n
+
+a = A()
+print(a.f(bump()))
+print([i * 2 for i in [1, 2] if i > n])
+for n in [1]:
+    print(len([n]))
@@ -0,0 +1,65 @@
+class Node(object):
+    value: int = 0
+    next: "Node" = None
+
+    def get(self: "Node") -> int:
+        return self.value
+
+def last(n: Node) -> int:
+    while not (n.next is None):
+        n = n.next
+    return n.value
+
+def first(xs: [Node]) -> int:
+    found: Node = None
+    for found in xs:
+        print(found.value)
+    return found.value
+
+def branch(b: bool) -> int:
+    n: Node = None
+    if b:
+        n = Node()
+    return n.get()
+
+def tested(n: Node) -> int:
+    if n is None:
+        print("none")
+    return n.value
+
+def guarded(n: Node) -> int:
+    if n is None:
+        return 0
+    if not (n is None) and n.value > 0:
+        return n.value
+    return n.get()
+
+def copied() -> int:
+    a: Node = None
+    b: Node = None
+    b = a if a is None else Node()
+    return b.value + b.value
+
+def unset():
+    print("unset")
+
+def captured() -> int:
+    n: Node = None
+    def init():
+        nonlocal n
+        n = Node()
+    init()
+    return n.value
+
+head: Node = None
+items: [int] = None
+i: int = 0
+while i < 3:
+    head = Node()
+    i = i + 1
+print(head.value)
+print(items[0])
+head = unset()
+print(head.get())
+print(last(head) + first([head]) + branch(True) + tested(head))
+print(guarded(head) + copied() + captured())
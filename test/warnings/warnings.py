@@ -0,0 +1,46 @@
+x:int = 1
+
+def unused_fn(a: int) -> int:
+    y:int = 0
+    x:int = 2
+    return a
+    print("never")
+
+def helper(b: int, c: int) -> int:
+    if b <= b:
+        return c
+    else:
+        return b
+    b = 3
+
+def outer() -> int:
+    count:int = 0
+    def inner() -> int:
+        nonlocal count
+        count = count + 1
+        return count
+    return inner()
+
+def loop(n: int) -> int:
+    while n > 0:
+        return n
+    else:
+        return 0
+    n = 1
+
+def search(xs: [int]) -> int:
+    x:int = 0
+    for x in xs:
+        break
+        print(x)
+    return x
+
+class A(object):
+    def m(self: "A", z: int) -> int:
+        return 1
+
+print(helper(x, 2))
+print(x == x)
+print(outer())
+print(loop(x))
+print(search([x]))